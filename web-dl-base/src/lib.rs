@@ -6,6 +6,7 @@ pub mod media;
 pub mod progress;
 pub mod storable;
 pub mod util {
+    pub mod http;
     pub mod serde {
         pub mod byte_array;
         pub mod bytes;
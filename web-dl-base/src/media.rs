@@ -1,12 +1,17 @@
 use crate::{id, progress, util};
 use mime2ext::mime2ext;
 use mime_classifier::{ApacheBugFlag, LoadContext, MimeClassifier, NoSniffFlag};
-use reqwest::{Client, Url};
+use reqwest::{Client, StatusCode, Url};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::Display,
     fs, io,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 use thiserror::Error;
 
@@ -17,6 +22,9 @@ pub enum FsErrorOp {
     ReadFile,
     Canonicalize,
     HeadLinkTo(PathBuf),
+    RemoveFile,
+    RenameTo(PathBuf),
+    ReadDir,
 }
 impl Display for FsErrorOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -26,6 +34,9 @@ impl Display for FsErrorOp {
             Self::ReadFile => f.write_str("read file"),
             Self::Canonicalize => f.write_str("canonicalize"),
             Self::HeadLinkTo(p) => write!(f, "hard link to {} from", p.display()),
+            Self::RemoveFile => f.write_str("remove file"),
+            Self::RenameTo(p) => write!(f, "rename to {} from", p.display()),
+            Self::ReadDir => f.write_str("read directory"),
         }
     }
 }
@@ -38,6 +49,12 @@ pub enum Error {
         #[source]
         source: io::Error,
     },
+    #[error("hash mismatch of {}: expected {expected}, found {actual}", path.display())]
+    HashMismatch {
+        expected: HashDigest,
+        actual: HashDigest,
+        path: PathBuf,
+    },
     #[error("failed to process {field}")]
     Chained {
         field: String,
@@ -47,23 +64,148 @@ pub enum Error {
 }
 
 pub trait StoreImage {
-    fn load_images<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error>;
-    fn migrate<S, P>(&self, image_store: S, path: P) -> Result<(), Error>
+    fn load_images<B, P>(&mut self, backend: &B, path: P) -> Result<(), Error>
     where
-        S: AsRef<Path>,
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>;
+    fn migrate<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
         P: AsRef<Path>;
     fn store_extension(&self) -> Option<&str> {
         None
     }
-    fn store_images<P: AsRef<Path>>(&self, path: P) -> Result<(), Error>;
+    /// write images into `backend` and link them to `path`
+    fn store_images<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>;
     fn drop_images(&mut self);
+    /// call `f` on every image reference
+    fn visit_images(&self, f: &mut dyn FnMut(&ImageRef));
 }
 pub use web_dl_derive::StoreImage;
 
+/// content addressed storage of image data
+pub trait ImageBackend: Send + Sync {
+    fn put(&self, hash: &HashDigest, extension: &str, data: &[u8]) -> Result<(), Error>;
+    fn get(&self, hash: &HashDigest, extension: &str) -> Result<Vec<u8>, Error>;
+    fn exists(&self, hash: &HashDigest, extension: &str) -> Result<bool, Error>;
+    /// file names and sizes of stored images, names are those of [`ImageRef`]s
+    fn list(&self) -> Result<Vec<(String, u64)>, Error>;
+    /// remove the image named `name` in [`ImageBackend::list`], removing an absent one is a no-op
+    fn remove(&self, name: &str) -> Result<(), Error>;
+    /// make image available at `dest` in the item directory, copies the data by default
+    fn link(&self, hash: &HashDigest, extension: &str, dest: &Path) -> Result<(), Error> {
+        let data = self.get(hash, extension)?;
+        fs::write(dest, data).map_err(|e| Error::Fs {
+            op: FsErrorOp::WriteFile,
+            path: dest.to_path_buf(),
+            source: e,
+        })
+    }
+    /// the filesystem store behind this backend, fetched images are streamed into it
+    fn as_fs(&self) -> Option<&FsBackend> {
+        None
+    }
+}
+
+/// stores images as `<root>/<algo>-<hash>.<ext>` and hard links them into items
+#[derive(Debug, Clone)]
+pub struct FsBackend {
+    root: PathBuf,
+}
+impl FsBackend {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+    pub fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+    pub fn path(&self, hash: &HashDigest, extension: &str) -> PathBuf {
+        hash.store_path(&self.root, extension)
+    }
+}
+impl ImageBackend for FsBackend {
+    fn put(&self, hash: &HashDigest, extension: &str, data: &[u8]) -> Result<(), Error> {
+        let path = self.path(hash, extension);
+        fs::write(&path, data).map_err(|e| Error::Fs {
+            op: FsErrorOp::WriteFile,
+            path,
+            source: e,
+        })
+    }
+    fn get(&self, hash: &HashDigest, extension: &str) -> Result<Vec<u8>, Error> {
+        let path = self.path(hash, extension);
+        fs::read(&path).map_err(|e| Error::Fs {
+            op: FsErrorOp::ReadFile,
+            path,
+            source: e,
+        })
+    }
+    fn exists(&self, hash: &HashDigest, extension: &str) -> Result<bool, Error> {
+        Ok(self.path(hash, extension).exists())
+    }
+    /// temporary files of ongoing downloads are skipped
+    fn list(&self) -> Result<Vec<(String, u64)>, Error> {
+        let read_err = |e| Error::Fs {
+            op: FsErrorOp::ReadDir,
+            path: self.root.clone(),
+            source: e,
+        };
+        let mut ret = Vec::new();
+        for ent in fs::read_dir(&self.root).map_err(read_err)? {
+            let ent = ent.map_err(read_err)?;
+            let meta = ent.metadata().map_err(read_err)?;
+            let name = ent.file_name().to_string_lossy().into_owned();
+            if meta.is_file() && !name.starts_with('.') {
+                ret.push((name, meta.len()));
+            }
+        }
+        Ok(ret)
+    }
+    fn remove(&self, name: &str) -> Result<(), Error> {
+        let path = self.root.join(name);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::Fs {
+                op: FsErrorOp::RemoveFile,
+                path,
+                source: e,
+            }),
+            _ => Ok(()),
+        }
+    }
+    fn link(&self, hash: &HashDigest, extension: &str, dest: &Path) -> Result<(), Error> {
+        let sp = {
+            let sp = self.path(hash, extension);
+            sp.as_path().canonicalize().map_err(|e| Error::Fs {
+                op: FsErrorOp::Canonicalize,
+                path: sp,
+                source: e,
+            })?
+        };
+        if dest.exists() {
+            fs::remove_file(dest).map_err(|e| Error::Fs {
+                op: FsErrorOp::RemoveFile,
+                path: dest.to_path_buf(),
+                source: e,
+            })?;
+        }
+        fs::hard_link(sp.as_path(), dest).map_err(|e| Error::Fs {
+            op: FsErrorOp::HeadLinkTo(sp),
+            path: dest.to_path_buf(),
+            source: e,
+        })
+    }
+    fn as_fs(&self) -> Option<&FsBackend> {
+        Some(self)
+    }
+}
+
 #[doc(hidden)]
 /// private module, for derive macro only
 pub mod macro_export {
-    use super::{Error, StoreImage};
+    use super::{Error, ImageBackend, StoreImage};
     pub use std::{convert::AsRef, option::Option, path::Path, result::Result, string::String};
     use std::{fmt::Display, path::PathBuf};
 
@@ -79,15 +221,24 @@ pub mod macro_export {
             Ok(())
         }
     }
-    pub fn load_img_chained<I: StoreImage, P: AsRef<Path>, C: Display>(
+    pub fn load_img_chained<I, B, P, C>(
         field: &mut I,
+        backend: &B,
         path: P,
         context: C,
-    ) -> Result<(), Error> {
-        field.load_images(path).map_err(|e| Error::Chained {
-            field: context.to_string(),
-            source: Box::new(e),
-        })
+    ) -> Result<(), Error>
+    where
+        I: StoreImage,
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+        C: Display,
+    {
+        field
+            .load_images(backend, path)
+            .map_err(|e| Error::Chained {
+                field: context.to_string(),
+                source: Box::new(e),
+            })
     }
     pub fn with_extension<I: StoreImage>(field: &I, path: &Path, name: &str) -> PathBuf {
         let mut path = path.join(name);
@@ -96,35 +247,42 @@ pub mod macro_export {
         }
         path
     }
-    pub fn migrate_img_chained<I, S, P, C>(
+    pub fn migrate_img_chained<I, B, P, C>(
         field: &I,
-        image_store: S,
+        backend: &B,
         path: P,
         context: C,
     ) -> Result<(), Error>
     where
         I: StoreImage,
-        S: AsRef<Path>,
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+        C: Display,
+    {
+        field.migrate(backend, path).map_err(|e| Error::Chained {
+            field: context.to_string(),
+            source: Box::new(e),
+        })
+    }
+    pub fn store_img_chained<I, B, P, C>(
+        field: &I,
+        backend: &B,
+        path: P,
+        context: C,
+    ) -> Result<(), Error>
+    where
+        I: StoreImage,
+        B: ImageBackend + ?Sized,
         P: AsRef<Path>,
         C: Display,
     {
         field
-            .migrate(image_store, path)
+            .store_images(backend, path)
             .map_err(|e| Error::Chained {
                 field: context.to_string(),
                 source: Box::new(e),
             })
     }
-    pub fn store_img_chained<I: StoreImage, P: AsRef<Path>, C: Display>(
-        field: &I,
-        path: P,
-        context: C,
-    ) -> Result<(), Error> {
-        field.store_images(path).map_err(|e| Error::Chained {
-            field: context.to_string(),
-            source: Box::new(e),
-        })
-    }
 }
 use macro_export::create_dir_missing;
 
@@ -132,19 +290,23 @@ impl<I: StoreImage> StoreImage for Option<I> {
     fn store_extension(&self) -> Option<&str> {
         self.as_ref().and_then(|v| v.store_extension())
     }
-    fn load_images<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+    fn load_images<B, P>(&mut self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+    {
         match self {
-            Some(i) => i.load_images(path),
+            Some(i) => i.load_images(backend, path),
             None => Ok(()),
         }
     }
-    fn migrate<S, P>(&self, image_store: S, path: P) -> Result<(), Error>
+    fn migrate<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
     where
-        S: AsRef<Path>,
+        B: ImageBackend + ?Sized,
         P: AsRef<Path>,
     {
         match self {
-            Some(i) => i.migrate(image_store, path),
+            Some(i) => i.migrate(backend, path),
             None => Ok(()),
         }
     }
@@ -153,62 +315,123 @@ impl<I: StoreImage> StoreImage for Option<I> {
             i.drop_images()
         }
     }
-    fn store_images<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    fn visit_images(&self, f: &mut dyn FnMut(&ImageRef)) {
+        if let Some(i) = self {
+            i.visit_images(f)
+        }
+    }
+    fn store_images<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+    {
         match self {
-            Some(i) => i.store_images(path),
+            Some(i) => i.store_images(backend, path),
             None => Ok(()),
         }
     }
 }
-impl<I: id::HasId + StoreImage> StoreImage for Vec<I> {
-    fn load_images<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+/// call `f` on every element, in parallel with the `parallel` feature,
+/// the error of the first failed element is returned
+#[cfg(not(feature = "parallel"))]
+fn try_each<I, F>(v: &[I], f: F) -> Result<(), Error>
+where
+    I: Sync,
+    F: Fn(&I) -> Result<(), Error> + Send + Sync,
+{
+    v.iter().try_for_each(f)
+}
+#[cfg(feature = "parallel")]
+fn try_each<I, F>(v: &[I], f: F) -> Result<(), Error>
+where
+    I: Sync,
+    F: Fn(&I) -> Result<(), Error> + Send + Sync,
+{
+    use rayon::prelude::*;
+    v.par_iter()
+        .map(f)
+        .find_first(Result::is_err)
+        .unwrap_or(Ok(()))
+}
+#[cfg(not(feature = "parallel"))]
+fn try_each_mut<I, F>(v: &mut [I], f: F) -> Result<(), Error>
+where
+    I: Send,
+    F: Fn(&mut I) -> Result<(), Error> + Send + Sync,
+{
+    v.iter_mut().try_for_each(f)
+}
+#[cfg(feature = "parallel")]
+fn try_each_mut<I, F>(v: &mut [I], f: F) -> Result<(), Error>
+where
+    I: Send,
+    F: Fn(&mut I) -> Result<(), Error> + Send + Sync,
+{
+    use rayon::prelude::*;
+    v.par_iter_mut()
+        .map(f)
+        .find_first(Result::is_err)
+        .unwrap_or(Ok(()))
+}
+/// images of elements are processed in parallel with the `parallel` feature
+impl<I: id::HasId + StoreImage + Send + Sync> StoreImage for Vec<I> {
+    fn load_images<B, P>(&mut self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+    {
         let path = path.as_ref();
-        for i in self.iter_mut() {
+        try_each_mut(self, |i| {
             let id_str = i.id().to_string();
-            i.load_images(path.join(id_str.as_str()))
+            i.load_images(backend, path.join(id_str.as_str()))
                 .map_err(|e| Error::Chained {
                     field: id_str,
                     source: Box::new(e),
-                })?;
-        }
-        Ok(())
+                })
+        })
     }
-    fn migrate<S, P>(&self, image_store: S, path: P) -> Result<(), Error>
+    fn migrate<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
     where
-        S: AsRef<Path>,
+        B: ImageBackend + ?Sized,
         P: AsRef<Path>,
     {
         let path = path.as_ref();
         create_dir_missing(path)?;
-        let image_store = image_store.as_ref();
-        for i in self.iter() {
+        try_each(self, |i| {
             let id_str = i.id().to_string();
-            i.migrate(image_store, path.join(id_str.as_str()))
+            i.migrate(backend, path.join(id_str.as_str()))
                 .map_err(|e| Error::Chained {
                     field: id_str,
                     source: Box::new(e),
-                })?;
-        }
-        Ok(())
+                })
+        })
     }
-    fn store_images<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    fn store_images<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+    {
         let path = path.as_ref();
         create_dir_missing(path)?;
-        for i in self.iter() {
+        try_each(self, |i| {
             let id_str = i.id().to_string();
-            i.store_images(path.join(id_str.as_str()))
+            i.store_images(backend, path.join(id_str.as_str()))
                 .map_err(|e| Error::Chained {
                     field: id_str,
                     source: Box::new(e),
-                })?;
-        }
-        Ok(())
+                })
+        })
     }
     fn drop_images(&mut self) {
         for i in self.iter_mut() {
             i.drop_images()
         }
     }
+    fn visit_images(&self, f: &mut dyn FnMut(&ImageRef)) {
+        for i in self.iter() {
+            i.visit_images(f)
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Hash, Serialize, Deserialize)]
@@ -216,31 +439,95 @@ impl<I: id::HasId + StoreImage> StoreImage for Vec<I> {
 pub enum HashDigest {
     #[serde(rename = "sha256")]
     Sha256(#[serde(with = "util::serde::byte_array::hex")] [u8; 32]),
+    #[serde(rename = "blake3")]
+    Blake3(#[serde(with = "util::serde::byte_array::hex")] [u8; 32]),
 }
 impl HashDigest {
     fn store_path(&self, parent: &Path, extension: &str) -> PathBuf {
         let mut ret = parent.to_path_buf();
-        ret.push(match self {
-            Self::Sha256(h) => format!("sha256-{}", hex::encode(h)),
-        });
+        ret.push(self.to_string());
         ret.set_extension(extension);
         ret
     }
+    pub fn algo(&self) -> HashAlgo {
+        match self {
+            Self::Sha256(_) => HashAlgo::Sha256,
+            Self::Blake3(_) => HashAlgo::Blake3,
+        }
+    }
+}
+impl Display for HashDigest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sha256(h) => write!(f, "sha256-{}", hex::encode(h)),
+            Self::Blake3(h) => write!(f, "blake3-{}", hex::encode(h)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgo {
+    #[default]
+    Sha256,
+    Blake3,
+}
+impl HashAlgo {
+    fn hasher(self) -> Hasher {
+        use sha2::Digest;
+        match self {
+            Self::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            Self::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+    pub fn digest(self, data: &[u8]) -> HashDigest {
+        let mut h = self.hasher();
+        h.update(data);
+        h.finalize()
+    }
+}
+enum Hasher {
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        use sha2::Digest;
+        match self {
+            Self::Sha256(h) => h.update(data),
+            Self::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+    fn finalize(self) -> HashDigest {
+        use sha2::Digest;
+        match self {
+            Self::Sha256(h) => HashDigest::Sha256(h.finalize().into()),
+            Self::Blake3(h) => HashDigest::Blake3(h.finalize().into()),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ImageRef {
     pub url: String,
     pub hash: HashDigest,
     pub extension: String,
+    /// hash of the fetched image before transcoding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_hash: Option<HashDigest>,
+    /// the fetched image is an animated gif or webp
+    #[serde(default)]
+    pub animated: bool,
+    /// content type of the stored image, from the response header or sniffed from data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
     #[serde(default, with = "util::serde::bytes::if_readable")]
     pub data: Option<Box<[u8]>>,
 }
 impl Display for ImageRef {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.hash {
-            HashDigest::Sha256(s) => write!(f, "sha256-{}.{}", hex::encode(s), self.extension),
-        }
+        write!(f, "{}.{}", self.hash, self.extension)
     }
 }
 impl id::HasId for ImageRef {
@@ -250,118 +537,771 @@ impl id::HasId for ImageRef {
         self
     }
 }
+impl ImageRef {
+    /// load image data without verifying its hash
+    pub fn load_images_unchecked<B: ImageBackend + ?Sized>(
+        &mut self,
+        backend: &B,
+    ) -> Result<(), Error> {
+        self.data = Some(backend.get(&self.hash, &self.extension)?.into_boxed_slice());
+        Ok(())
+    }
+}
 impl StoreImage for ImageRef {
     fn store_extension(&self) -> Option<&str> {
         Some(self.extension.as_str())
     }
-    fn load_images<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
-        self.data = Some(
-            fs::read(path.as_ref())
-                .map_err(|e| Error::Fs {
-                    op: FsErrorOp::ReadFile,
-                    path: path.as_ref().to_path_buf(),
-                    source: e,
-                })?
-                .into_boxed_slice(),
-        );
+    fn load_images<B, P>(&mut self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+    {
+        if let Err(e) = self.load_images_unchecked(backend) {
+            // stores written before images moved into the backend keep them in item directories
+            match fs::read(path.as_ref()) {
+                Ok(d) => self.data = Some(d.into_boxed_slice()),
+                Err(_) => return Err(e),
+            }
+        }
+        let actual = self.hash.algo().digest(self.data.as_deref().unwrap());
+        if actual != self.hash {
+            self.data = None;
+            return Err(Error::HashMismatch {
+                expected: self.hash.clone(),
+                actual,
+                path: path.as_ref().to_path_buf(),
+            });
+        }
         Ok(())
     }
-    fn migrate<S, P>(&self, image_store: S, path: P) -> Result<(), Error>
+    fn migrate<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
     where
-        S: AsRef<Path>,
+        B: ImageBackend + ?Sized,
         P: AsRef<Path>,
     {
-        let sp = {
-            let sp = self.hash.store_path(image_store.as_ref(), &self.extension);
-            sp.as_path().canonicalize().map_err(|e| Error::Fs {
-                op: FsErrorOp::Canonicalize,
-                path: sp,
-                source: e,
-            })?
+        backend.link(&self.hash, &self.extension, path.as_ref())
+    }
+    fn store_images<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+    {
+        if !backend.exists(&self.hash, &self.extension)? {
+            match &self.data {
+                Some(d) => backend.put(&self.hash, &self.extension, d)?,
+                None => return Ok(()),
+            }
+        }
+        backend.link(&self.hash, &self.extension, path.as_ref())
+    }
+    fn drop_images(&mut self) {
+        self.data = None;
+    }
+    fn visit_images(&self, f: &mut dyn FnMut(&ImageRef)) {
+        f(self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX))
+            .min(self.max_delay)
+    }
+}
+
+/// hosts images are fetched from, a pattern is a host name, `*.domain` matching the domain
+/// and its subdomains, or `*` matching every host
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HostFilter {
+    /// every host not blocked is allowed when empty
+    pub allow: Vec<String>,
+    pub block: Vec<String>,
+}
+impl HostFilter {
+    fn matches(pattern: &str, host: &str) -> bool {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_prefix("*.") {
+            _ if pattern == "*" => true,
+            Some(d) => host == d || host.strip_suffix(d).is_some_and(|h| h.ends_with('.')),
+            None => host == pattern,
+        }
+    }
+    pub fn allows(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
         };
-        fs::hard_link(sp.as_path(), path.as_ref()).map_err(|e| Error::Fs {
-            op: FsErrorOp::HeadLinkTo(sp),
-            path: path.as_ref().to_path_buf(),
-            source: e,
+        let host = host.to_ascii_lowercase();
+        !self.block.iter().any(|p| Self::matches(p, &host))
+            && (self.allow.is_empty() || self.allow.iter().any(|p| Self::matches(p, &host)))
+    }
+}
+
+/// count of received bytes shared by its clones
+#[derive(Debug, Default, Clone)]
+pub struct ByteCounter(Arc<AtomicU64>);
+impl ByteCounter {
+    pub fn add(&self, bytes: u64) {
+        self.0.fetch_add(bytes, Ordering::Relaxed);
+    }
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FetchOpt {
+    pub retry: RetryPolicy,
+    pub hash: HashAlgo,
+    /// remove EXIF/XMP/ICC metadata before hashing, requires the `strip-metadata` feature
+    pub strip_metadata: bool,
+    /// keep only the first frame of animated images as png, requires the `first-frame` feature
+    pub first_frame: bool,
+    pub hosts: HostFilter,
+    /// bytes of image responses received, failed attempts included
+    pub transferred: ByteCounter,
+    /// timeout of a single image request overriding the one of the client,
+    /// timed out requests are retried
+    pub timeout: Option<Duration>,
+    /// stream fetched images into this store instead of buffering them,
+    /// unless they are processed in memory, see [`fetch_image_to_store`]
+    pub stream_to: Option<FsBackend>,
+}
+impl FetchOpt {
+    /// store images are streamed into, `None` when they have to be stripped or reduced to
+    /// their first frame, webp is transcoded after streaming, see [`fetch_image_to_store`]
+    fn stream_target(&self) -> Option<&FsBackend> {
+        if self.strip_metadata || self.first_frame {
+            None
+        } else {
+            self.stream_to.as_ref()
+        }
+    }
+}
+/// inline `data:` images and those on disallowed hosts are not fetched
+fn should_fetch(url: &Url, opt: &FetchOpt) -> bool {
+    if url.scheme() == "data" {
+        return false;
+    }
+    if !opt.hosts.allows(url) {
+        log::info!("skipped image {} on disallowed host", url);
+        return false;
+    }
+    true
+}
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("failed to fetch image")]
+    Request(#[from] reqwest::Error),
+    #[error("failed to save image")]
+    Store(#[from] Error),
+}
+
+enum Attempt {
+    Retry(reqwest::Error, Option<Duration>),
+    Fail(FetchError),
+}
+impl From<reqwest::Error> for Attempt {
+    fn from(value: reqwest::Error) -> Self {
+        if value.is_connect() || value.is_timeout() || value.is_request() || value.is_body() {
+            Self::Retry(value, None)
+        } else {
+            Self::Fail(FetchError::Request(value))
+        }
+    }
+}
+impl From<Error> for Attempt {
+    fn from(value: Error) -> Self {
+        Self::Fail(FetchError::Store(value))
+    }
+}
+
+/// destination of fetched image bytes, cleared before each attempt
+trait Sink {
+    fn reset(&mut self, size: Option<u64>) -> Result<(), Error>;
+    fn write(&mut self, data: &[u8]) -> Result<(), Error>;
+}
+impl Sink for Vec<u8> {
+    fn reset(&mut self, size: Option<u64>) -> Result<(), Error> {
+        self.clear();
+        if let Some(sz) = size {
+            self.reserve(sz as usize);
+        }
+        Ok(())
+    }
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+/// bytes kept in memory for mime sniffing when streaming to disk
+const SNIFF_LEN: usize = 512;
+
+struct TempFile {
+    path: PathBuf,
+    file: fs::File,
+    head: Vec<u8>,
+    /// frames of gif are beyond the sniffed head
+    gif: GifFrames,
+    len: u64,
+    persisted: bool,
+}
+impl TempFile {
+    fn create(dir: &Path) -> Result<Self, Error> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = dir.join(format!(
+            ".download-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        Ok(Self {
+            file: fs::File::create(&path).map_err(|e| Error::Fs {
+                op: FsErrorOp::WriteFile,
+                path: path.clone(),
+                source: e,
+            })?,
+            path,
+            head: Vec::with_capacity(SNIFF_LEN),
+            gif: GifFrames::default(),
+            len: 0,
+            persisted: false,
         })
     }
-    fn store_images<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
-        match &self.data {
-            Some(d) => fs::write(path.as_ref(), d).map_err(|e| Error::Fs {
+    fn persist(mut self, dest: &Path) -> Result<(), Error> {
+        fs::rename(&self.path, dest).map_err(|e| Error::Fs {
+            op: FsErrorOp::RenameTo(dest.to_path_buf()),
+            path: self.path.clone(),
+            source: e,
+        })?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+impl Sink for TempFile {
+    fn reset(&mut self, _: Option<u64>) -> Result<(), Error> {
+        use io::Seek;
+        self.head.clear();
+        self.gif = GifFrames::default();
+        self.len = 0;
+        self.file
+            .set_len(0)
+            .and_then(|_| self.file.rewind())
+            .map_err(|e| Error::Fs {
                 op: FsErrorOp::WriteFile,
-                path: path.as_ref().to_path_buf(),
+                path: self.path.clone(),
                 source: e,
-            }),
-            None => Ok(()),
-        }
+            })
     }
-    fn drop_images(&mut self) {
-        self.data = None;
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        use io::Write;
+        let n = (SNIFF_LEN - self.head.len()).min(data.len());
+        self.head.extend_from_slice(&data[..n]);
+        self.gif.update(data);
+        self.len += data.len() as u64;
+        self.file.write_all(data).map_err(|e| Error::Fs {
+            op: FsErrorOp::WriteFile,
+            path: self.path.clone(),
+            source: e,
+        })
+    }
+}
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.path);
+        }
     }
 }
 
-pub async fn fetch_image<P: progress::ImageProg>(
+async fn try_fetch_image<P: progress::ImageProg, S: Sink>(
     client: &Client,
     image_prog: &mut P,
-    url: Url,
-) -> reqwest::Result<ImageRef> {
-    use sha2::Digest;
-    let url_str = url.to_string();
-    log::debug!("fetching image {}", &url_str);
-    let mut resp = client.get(url).send().await?;
+    url: &Url,
+    opt: &FetchOpt,
+    sink: &mut S,
+) -> Result<(HashDigest, Option<String>), Attempt> {
+    let mut request = client.get(url.clone());
+    if let Some(t) = opt.timeout {
+        request = request.timeout(t);
+    }
+    let resp = request.send().await?;
+    let status = resp.status();
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        let after = util::http::retry_after(resp.headers());
+        return Err(Attempt::Retry(resp.error_for_status().unwrap_err(), after));
+    }
+    let mut resp = resp
+        .error_for_status()
+        .map_err(|e| Attempt::Fail(e.into()))?;
+    let mime = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
     image_prog.set_size(resp.content_length());
-    let mut ret = match resp.content_length() {
-        Some(sz) => Vec::with_capacity(sz as usize),
-        None => Vec::new(),
-    };
-    let mut dig = sha2::Sha256::new();
+    sink.reset(resp.content_length())?;
+    let mut dig = opt.hash.hasher();
     while let Some(s) = resp.chunk().await? {
         image_prog.inc(s.len() as u64);
-        ret.extend_from_slice(&s);
+        opt.transferred.add(s.len() as u64);
+        sink.write(&s)?;
         dig.update(&s);
     }
-    let hsh = dig.finalize().into();
-    log::debug!("fetched image {}, sha256: {}", url_str, hex::encode(hsh));
-    Ok(ImageRef {
-        url: url_str,
-        hash: HashDigest::Sha256(hsh),
-        extension: mime2ext(MimeClassifier::new().classify(
+    Ok((dig.finalize(), mime))
+}
+
+async fn fetch_retry<P: progress::ImageProg, S: Sink>(
+    client: &Client,
+    image_prog: &mut P,
+    url: &Url,
+    opt: &FetchOpt,
+    sink: &mut S,
+) -> Result<(HashDigest, Option<String>), FetchError> {
+    let retry = &opt.retry;
+    let mut attempt = 0;
+    loop {
+        match try_fetch_image(client, image_prog, url, opt, sink).await {
+            Ok(v) => return Ok(v),
+            Err(Attempt::Retry(e, after)) if attempt < retry.max_retries => {
+                let delay =
+                    after.map_or_else(|| retry.backoff(attempt), |d| d.min(retry.max_delay));
+                attempt += 1;
+                log::warn!(
+                    "failed to fetch image {} ({}), retry {}/{} in {:?}",
+                    url,
+                    e,
+                    attempt,
+                    retry.max_retries,
+                    delay
+                );
+                image_prog.retry(attempt);
+                image_prog.sleep(delay).await;
+            }
+            Err(Attempt::Retry(e, _)) => return Err(e.into()),
+            Err(Attempt::Fail(e)) => return Err(e),
+        }
+    }
+}
+
+fn sniff_mime(data: &[u8]) -> String {
+    MimeClassifier::new()
+        .classify(
             LoadContext::Image,
             NoSniffFlag::On,
             ApacheBugFlag::On,
             &None,
-            &ret,
-        ))
-        .unwrap_or("unknown")
-        .to_owned(),
+            data,
+        )
+        .to_string()
+}
+
+fn image_extension(mime: &str) -> &'static str {
+    mime2ext(mime).unwrap_or("unknown")
+}
+
+pub async fn fetch_image<P: progress::ImageProg>(
+    client: &Client,
+    image_prog: &mut P,
+    url: Url,
+    opt: &FetchOpt,
+) -> Result<ImageRef, FetchError> {
+    log::debug!("fetching image {}", url);
+    let mut ret = Vec::new();
+    let (fetched, mime) = fetch_retry(client, image_prog, &url, opt, &mut ret).await?;
+    let url_str = url.to_string();
+    log::debug!("fetched image {}, {}", url_str, fetched);
+    let sniffed = sniff_mime(&ret);
+    #[allow(unused_mut)]
+    let (mut hsh, mut extension, mut mime) = (
+        fetched.clone(),
+        image_extension(&sniffed),
+        mime.unwrap_or(sniffed),
+    );
+    if opt.strip_metadata {
+        #[cfg(feature = "strip-metadata")]
+        match strip_metadata(&ret, extension) {
+            Ok(Some(d)) => {
+                ret = d;
+                hsh = opt.hash.digest(&ret);
+                log::debug!("stripped metadata of image {}, {}", url_str, hsh);
+            }
+            Ok(None) => (),
+            Err(e) => log::warn!("failed to strip metadata of image {}: {}", url_str, e),
+        }
+        #[cfg(not(feature = "strip-metadata"))]
+        log::warn!(
+            "metadata of image {} is kept, stripping requires the strip-metadata feature",
+            url_str
+        );
+    }
+    let animated = is_animated(&ret, extension);
+    if animated && opt.first_frame {
+        #[cfg(feature = "first-frame")]
+        match to_png(&ret) {
+            Ok(png) => {
+                ret = png;
+                hsh = opt.hash.digest(&ret);
+                extension = "png";
+                mime = "image/png".to_owned();
+                log::debug!("extracted first frame of image {}, {}", url_str, hsh);
+            }
+            Err(e) => log::warn!("failed to extract first frame of {}: {}", url_str, e),
+        }
+        #[cfg(not(feature = "first-frame"))]
+        log::warn!(
+            "image {} is kept animated, extracting first frame requires the first-frame feature",
+            url_str
+        );
+    }
+    #[cfg(feature = "transcode-webp")]
+    if extension == "webp" {
+        if let Some((png, h)) = transcode_webp(&ret, &url_str, opt.hash) {
+            ret = png;
+            hsh = h;
+            extension = "png";
+            mime = "image/png".to_owned();
+        }
+    }
+    Ok(ImageRef {
+        url: url_str,
+        original_hash: if hsh != fetched { Some(fetched) } else { None },
+        hash: hsh,
+        extension: extension.to_owned(),
+        animated,
+        mime: Some(mime),
         data: Some(ret.into_boxed_slice()),
     })
 }
-pub async fn fetch_images_iter<I, P>(client: &Client, images_prog: &mut P, imgs: I) -> Vec<ImageRef>
+
+/// png data and its hash, `None` when the image is kept as webp
+#[cfg(feature = "transcode-webp")]
+fn transcode_webp(data: &[u8], url: &str, hash: HashAlgo) -> Option<(Vec<u8>, HashDigest)> {
+    match to_png(data) {
+        Ok(png) => {
+            let hsh = hash.digest(&png);
+            log::debug!("transcoded image {} to png, {}", url, hsh);
+            Some((png, hsh))
+        }
+        Err(e) => {
+            log::warn!("failed to transcode webp image {}: {}", url, e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "strip-metadata")]
+fn strip_metadata(data: &[u8], extension: &str) -> img_parts::Result<Option<Vec<u8>>> {
+    use img_parts::{
+        jpeg::{markers, Jpeg},
+        png::Png,
+        webp::WebP,
+        Bytes, ImageEXIF, ImageICC,
+    };
+    let data = Bytes::copy_from_slice(data);
+    let ret = match extension {
+        "jpg" | "jpeg" => {
+            let mut img = Jpeg::from_bytes(data)?;
+            img.remove_segments_by_marker(markers::APP1);
+            img.remove_segments_by_marker(markers::APP2);
+            img.encoder().bytes()
+        }
+        "png" => {
+            let mut img = Png::from_bytes(data)?;
+            img.chunks_mut().retain(|c| match &c.kind() {
+                b"eXIf" | b"iCCP" => false,
+                b"iTXt" => !c.contents().starts_with(b"XML:com.adobe.xmp\0"),
+                _ => true,
+            });
+            img.encoder().bytes()
+        }
+        "webp" => {
+            let mut img = WebP::from_bytes(data)?;
+            img.remove_chunks_by_id(*b"XMP ");
+            img.set_icc_profile(None);
+            img.set_exif(None);
+            img.encoder().bytes()
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(ret.to_vec()))
+}
+
+/// fetch image straight into the filesystem image store without buffering it in memory,
+/// the returned image has no data loaded and is not stripped of metadata,
+/// only webp images are read back to be transcoded with the `transcode-webp` feature
+pub async fn fetch_image_to_store<P: progress::ImageProg>(
+    client: &Client,
+    image_prog: &mut P,
+    url: Url,
+    opt: &FetchOpt,
+    backend: &FsBackend,
+) -> Result<ImageRef, FetchError> {
+    fetch_to_store(client, image_prog, url, opt, backend)
+        .await
+        .map(|v| v.0)
+}
+async fn fetch_to_store<P: progress::ImageProg>(
+    client: &Client,
+    image_prog: &mut P,
+    url: Url,
+    opt: &FetchOpt,
+    backend: &FsBackend,
+) -> Result<(ImageRef, u64), FetchError> {
+    log::debug!("fetching image {} to store", url);
+    let mut tmp = TempFile::create(backend.root())?;
+    let (hsh, mime) = fetch_retry(client, image_prog, &url, opt, &mut tmp).await?;
+    let sniffed = sniff_mime(&tmp.head);
+    let extension = image_extension(&sniffed);
+    let animated = match extension {
+        "gif" => tmp.gif.animated(),
+        _ => is_animated(&tmp.head, extension),
+    };
+    let len = tmp.len;
+    #[cfg(feature = "transcode-webp")]
+    if extension == "webp" {
+        let data = fs::read(&tmp.path).map_err(|e| Error::Fs {
+            op: FsErrorOp::ReadFile,
+            path: tmp.path.clone(),
+            source: e,
+        })?;
+        if let Some((png, png_hash)) = transcode_webp(&data, url.as_str(), opt.hash) {
+            backend.put(&png_hash, "png", &png)?;
+            return Ok((
+                ImageRef {
+                    url: url.to_string(),
+                    hash: png_hash,
+                    extension: "png".to_owned(),
+                    original_hash: Some(hsh),
+                    animated,
+                    mime: Some("image/png".to_owned()),
+                    data: None,
+                },
+                len,
+            ));
+        }
+    }
+    let dest = backend.path(&hsh, extension);
+    tmp.persist(&dest)?;
+    log::debug!("fetched image {} to {}", url, dest.display());
+    Ok((
+        ImageRef {
+            url: url.to_string(),
+            hash: hsh,
+            extension: extension.to_owned(),
+            original_hash: None,
+            animated,
+            mime: Some(mime.unwrap_or(sniffed)),
+            data: None,
+        },
+        len,
+    ))
+}
+/// stream the image into the store of `opt` when possible, returns the image and its size
+async fn fetch_with<P: progress::ImageProg>(
+    client: &Client,
+    image_prog: &mut P,
+    url: Url,
+    opt: &FetchOpt,
+) -> Result<(ImageRef, u64), FetchError> {
+    match opt.stream_target() {
+        Some(backend) => fetch_to_store(client, image_prog, url, opt, backend).await,
+        None => fetch_image(client, image_prog, url, opt).await.map(|i| {
+            let len = i.data.as_ref().map_or(0, |d| d.len() as u64);
+            (i, len)
+        }),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum GifState {
+    /// header and logical screen descriptor, bytes read
+    Header(usize),
+    /// skip bytes, then read sub-blocks or the next block
+    Skip(usize, bool),
+    Block,
+    /// image descriptor, bytes read
+    Descriptor(usize),
+    SubBlock,
+    Done,
+}
+/// counts image descriptors of gif data fed in chunks, so that animation is known without
+/// holding the whole image
+#[derive(Debug, Clone, Copy)]
+struct GifFrames {
+    state: GifState,
+    flags: u8,
+    frames: usize,
+}
+impl Default for GifFrames {
+    fn default() -> Self {
+        Self {
+            state: GifState::Header(0),
+            flags: 0,
+            frames: 0,
+        }
+    }
+}
+impl GifFrames {
+    fn color_table(flags: u8) -> usize {
+        if flags & 0x80 != 0 {
+            3 << ((flags & 0x07) + 1)
+        } else {
+            0
+        }
+    }
+    fn update(&mut self, mut data: &[u8]) {
+        while let Some(&b) = data.first() {
+            let mut consumed = 1;
+            self.state = match self.state {
+                GifState::Header(n) if n < 4 && b != b"GIF8"[n] => GifState::Done,
+                GifState::Header(n) => {
+                    if n == 10 {
+                        self.flags = b;
+                    }
+                    if n == 12 {
+                        GifState::Skip(Self::color_table(self.flags), false)
+                    } else {
+                        GifState::Header(n + 1)
+                    }
+                }
+                GifState::Skip(k, sub_block) => {
+                    consumed = k.min(data.len());
+                    match k - consumed {
+                        0 if sub_block => GifState::SubBlock,
+                        0 => GifState::Block,
+                        k => GifState::Skip(k, sub_block),
+                    }
+                }
+                // extension, skip its label
+                GifState::Block if b == 0x21 => GifState::Skip(1, true),
+                GifState::Block if b == 0x2c => {
+                    self.frames += 1;
+                    GifState::Descriptor(0)
+                }
+                GifState::Descriptor(8) => {
+                    // local color table and lzw minimum code size
+                    GifState::Skip(Self::color_table(b) + 1, true)
+                }
+                GifState::Descriptor(n) => GifState::Descriptor(n + 1),
+                GifState::SubBlock if b == 0 => GifState::Block,
+                GifState::SubBlock => GifState::Skip(b as usize, true),
+                GifState::Block | GifState::Done => GifState::Done,
+            };
+            if matches!(self.state, GifState::Done) || self.animated() {
+                self.state = GifState::Done;
+                return;
+            }
+            data = &data[consumed..];
+        }
+    }
+    fn animated(&self) -> bool {
+        self.frames > 1
+    }
+}
+
+/// animated gif has more than one image descriptor, animated webp sets the animation flag of `VP8X`
+fn is_animated(data: &[u8], extension: &str) -> bool {
+    match extension {
+        "gif" => {
+            let mut frames = GifFrames::default();
+            frames.update(data);
+            frames.animated()
+        }
+        "webp" => data.len() > 20 && &data[12..16] == b"VP8X" && data[20] & 0x02 != 0,
+        _ => false,
+    }
+}
+
+#[cfg(any(feature = "transcode-webp", feature = "first-frame"))]
+fn to_png(data: &[u8]) -> image::ImageResult<Vec<u8>> {
+    let img = image::load_from_memory(data)?;
+    let mut ret = io::Cursor::new(Vec::new());
+    img.write_to(&mut ret, image::ImageFormat::Png)?;
+    Ok(ret.into_inner())
+}
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Default)]
+pub struct FetchReport {
+    /// sorted by hash
+    pub images: Vec<ImageRef>,
+    pub fetched: usize,
+    /// size of fetched images
+    pub bytes: u64,
+    pub skipped: usize,
+    pub failed: Vec<(Url, FetchError)>,
+}
+impl FetchReport {
+    pub fn total(&self) -> usize {
+        self.fetched + self.skipped + self.failed.len()
+    }
+}
+
+pub async fn fetch_images_iter<I, P>(
+    client: &Client,
+    images_prog: &P,
+    imgs: I,
+    concurrency: usize,
+    opt: &FetchOpt,
+) -> FetchReport
 where
     I: Iterator<Item = Url>,
     P: progress::ImagesProg,
 {
-    let mut ret = Vec::new();
-    for url in imgs {
-        let mut prog = images_prog.start_image(&url);
-        if url.scheme() == "data" {
-            continue;
-        }
-        match fetch_image(client, &mut prog, url).await {
-            Ok(re) => {
-                ret.push(re);
+    use futures::stream::{self, StreamExt};
+    let results: Vec<_> = stream::iter(imgs)
+        .map(|url| async move {
+            let mut prog = images_prog.start_image(&url);
+            if !should_fetch(&url, opt) {
+                return Ok(None);
+            }
+            match fetch_with(client, &mut prog, url.clone(), opt).await {
+                Ok(re) => Ok(Some(re)),
+                Err(e) => {
+                    log::warn!("failed to fetch image {}: {:?}", url, e);
+                    Err((url, e))
+                }
             }
-            Err(e) => {
-                log::warn!("failed to fetch image: {:?}", anyhow::Error::new(e));
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    let mut ret = FetchReport::default();
+    for r in results {
+        match r {
+            Ok(Some((i, len))) => {
+                ret.fetched += 1;
+                ret.bytes += len;
+                ret.images.push(i);
             }
+            Ok(None) => ret.skipped += 1,
+            Err(e) => ret.failed.push(e),
         }
     }
-    ret.sort_by(|a: &ImageRef, b: &ImageRef| a.hash.cmp(&b.hash));
+    ret.images
+        .sort_by(|a: &ImageRef, b: &ImageRef| a.hash.cmp(&b.hash));
     ret
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Image {
     #[serde(rename = "url")]
     Url(String),
@@ -375,19 +1315,23 @@ impl StoreImage for Image {
             Self::Url(_) => None,
         }
     }
-    fn load_images<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+    fn load_images<B, P>(&mut self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+    {
         match self {
-            Self::Ref(r) => r.load_images(path),
+            Self::Ref(r) => r.load_images(backend, path),
             Self::Url(_) => Ok(()),
         }
     }
-    fn migrate<S, P>(&self, image_store: S, path: P) -> Result<(), Error>
+    fn migrate<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
     where
-        S: AsRef<Path>,
+        B: ImageBackend + ?Sized,
         P: AsRef<Path>,
     {
         match self {
-            Self::Ref(r) => r.migrate(image_store, path),
+            Self::Ref(r) => r.migrate(backend, path),
             Self::Url(_) => Ok(()),
         }
     }
@@ -396,9 +1340,18 @@ impl StoreImage for Image {
             r.drop_images()
         }
     }
-    fn store_images<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    fn visit_images(&self, f: &mut dyn FnMut(&ImageRef)) {
+        if let Self::Ref(r) = self {
+            r.visit_images(f)
+        }
+    }
+    fn store_images<B, P>(&self, backend: &B, path: P) -> Result<(), Error>
+    where
+        B: ImageBackend + ?Sized,
+        P: AsRef<Path>,
+    {
         match self {
-            Self::Ref(r) => r.store_images(path),
+            Self::Ref(r) => r.store_images(backend, path),
             Self::Url(_) => Ok(()),
         }
     }
@@ -409,6 +1362,7 @@ impl Image {
         &mut self,
         client: &Client,
         images_prog: &mut P,
+        opt: &FetchOpt,
     ) -> bool {
         match self {
             Image::Url(u) => {
@@ -420,9 +1374,13 @@ impl Image {
                         return false;
                     }
                 };
+                if !should_fetch(&url, opt) {
+                    images_prog.skip();
+                    return false;
+                }
                 let mut prog = images_prog.start_image(&url);
-                match fetch_image(client, &mut prog, url).await {
-                    Ok(r) => *self = Self::Ref(r),
+                match fetch_with(client, &mut prog, url, opt).await {
+                    Ok((r, _)) => *self = Self::Ref(r),
                     Err(e) => log::warn!("failed to fetch image {:?}", anyhow::Error::new(e)),
                 }
                 true
@@ -434,3 +1392,320 @@ impl Image {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        fetch_image, fetch_image_to_store, fetch_images_iter, is_animated, Error, FetchOpt,
+        FsBackend, GifFrames, HashAlgo, HostFilter, ImageRef, StoreImage,
+    };
+    use crate::progress::{ImageProg, ImagesProg, Progress};
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        os::unix::fs::MetadataExt,
+        path::PathBuf,
+        time::Duration,
+    };
+
+    struct NoProg;
+    impl Progress for NoProg {
+        async fn sleep(&self, _: Duration) {}
+    }
+    impl ImageProg for NoProg {
+        fn set_size(&mut self, _: Option<u64>) {}
+        fn inc(&mut self, _: u64) {}
+        fn retry(&mut self, _: u32) {}
+    }
+    impl ImagesProg for NoProg {
+        type ImageRep<'a> = NoProg;
+        fn start_image<I: std::fmt::Display>(&self, _: I) -> Self::ImageRep<'_> {
+            NoProg
+        }
+        fn skip(&self) {}
+    }
+
+    fn serve(body: &'static [u8], count: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for s in listener.incoming().take(count) {
+                let mut s = s.unwrap();
+                let mut buf = [0; 1024];
+                let _ = s.read(&mut buf).unwrap();
+                write!(
+                    s,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                s.write_all(body).unwrap();
+            }
+        });
+        format!("http://{}/image.png", addr)
+    }
+    fn temp_dir(name: &str) -> PathBuf {
+        let ret = std::env::temp_dir().join(format!("web-dl-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&ret);
+        std::fs::create_dir_all(&ret).unwrap();
+        ret
+    }
+
+    #[tokio::test]
+    async fn store_dedup() {
+        const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let url = serve(PNG, 2);
+        let root = temp_dir("store-dedup");
+        let image_store = root.join("images");
+        std::fs::create_dir(&image_store).unwrap();
+        let backend = FsBackend::new(&image_store);
+        let client = reqwest::Client::new();
+        let mut paths = Vec::new();
+        for item in ["a", "b"] {
+            let img = fetch_image(
+                &client,
+                &mut NoProg,
+                url.parse().unwrap(),
+                &FetchOpt::default(),
+            )
+            .await
+            .unwrap();
+            let item = root.join(item);
+            std::fs::create_dir(&item).unwrap();
+            let path = item.join(img.to_string());
+            img.store_images(&backend, &path).unwrap();
+            paths.push(path);
+        }
+        assert_eq!(std::fs::read_dir(&image_store).unwrap().count(), 1);
+        let ino: Vec<_> = paths
+            .iter()
+            .map(|p| std::fs::metadata(p).unwrap().ino())
+            .collect();
+        assert_eq!(ino[0], ino[1]);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stream_to_store() {
+        const GIF: &[u8] = b"GIF89a\x01\0\x01\0\0\0\0\
+            \x2c\0\0\0\0\x01\0\x01\0\0\x02\x02\x44\x01\0\
+            \x2c\0\0\0\0\x01\0\x01\0\0\x02\x02\x44\x01\0\x3b";
+        let url = serve(GIF, 2);
+        let root = temp_dir("stream-to-store");
+        let backend = FsBackend::new(&root);
+        let client = reqwest::Client::new();
+        let img = fetch_image_to_store(
+            &client,
+            &mut NoProg,
+            url.parse().unwrap(),
+            &FetchOpt::default(),
+            &backend,
+        )
+        .await
+        .unwrap();
+        assert_eq!(img.extension, "gif");
+        assert!(img.animated && img.data.is_none());
+        assert_eq!(std::fs::read(backend.path(&img.hash, "gif")).unwrap(), GIF);
+
+        let opt = FetchOpt {
+            stream_to: Some(backend.clone()),
+            ..Default::default()
+        };
+        let report = fetch_images_iter(
+            &client,
+            &NoProg,
+            [url.parse().unwrap()].into_iter(),
+            1,
+            &opt,
+        )
+        .await;
+        assert_eq!((report.fetched, report.bytes), (1, GIF.len() as u64));
+        assert_eq!(report.images[0].hash, img.hash);
+        assert_eq!(
+            report.images[0].data.is_none(),
+            opt.stream_target().is_some()
+        );
+        // temporary files are renamed into place
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 1);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn retry_after_capped() {
+        use std::sync::Mutex;
+        struct Sleeps(Mutex<Vec<Duration>>);
+        impl Progress for Sleeps {
+            async fn sleep(&self, duration: Duration) {
+                self.0.lock().unwrap().push(duration);
+            }
+        }
+        impl ImageProg for Sleeps {
+            fn set_size(&mut self, _: Option<u64>) {}
+            fn inc(&mut self, _: u64) {}
+            fn retry(&mut self, _: u32) {}
+        }
+        const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut incoming = listener.incoming();
+            let mut s = incoming.next().unwrap().unwrap();
+            let _ = s.read(&mut [0; 1024]).unwrap();
+            s.write_all(
+                b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 3600\r\n\
+                  Content-Length: 0\r\nConnection: close\r\n\r\n",
+            )
+            .unwrap();
+            drop(s);
+            let mut s = incoming.next().unwrap().unwrap();
+            let _ = s.read(&mut [0; 1024]).unwrap();
+            write!(
+                s,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                PNG.len()
+            )
+            .unwrap();
+            s.write_all(PNG).unwrap();
+        });
+        let opt = FetchOpt::default();
+        let mut prog = Sleeps(Mutex::new(Vec::new()));
+        let url = format!("http://{}/image.png", addr).parse().unwrap();
+        let img = fetch_image(&reqwest::Client::new(), &mut prog, url, &opt)
+            .await
+            .unwrap();
+        assert_eq!(img.hash, HashAlgo::default().digest(PNG));
+        assert_eq!(prog.0.into_inner().unwrap(), [opt.retry.max_delay]);
+    }
+
+    #[test]
+    fn hash_store_path() {
+        let parent = std::path::Path::new("images");
+        let sha = HashAlgo::Sha256.digest(b"abc").store_path(parent, "png");
+        let blake = HashAlgo::Blake3.digest(b"abc").store_path(parent, "png");
+        assert_eq!(
+            sha,
+            parent.join(
+                "sha256-ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad.png"
+            )
+        );
+        assert_eq!(
+            blake,
+            parent.join(
+                "blake3-6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85.png"
+            )
+        );
+        assert_ne!(sha, blake);
+    }
+
+    #[test]
+    fn load_hash_mismatch() {
+        let root = temp_dir("load-mismatch");
+        let data: &[u8] = b"image data";
+        let mut img = ImageRef {
+            url: String::new(),
+            hash: HashAlgo::Sha256.digest(data),
+            extension: "png".to_owned(),
+            original_hash: None,
+            animated: false,
+            mime: None,
+            data: Some(data.into()),
+        };
+        let path = root.join("image");
+        let backend = FsBackend::new(&root);
+        img.store_images(&backend, &path).unwrap();
+        img.drop_images();
+        img.load_images(&backend, &path).unwrap();
+        assert_eq!(img.data.as_deref(), Some(data));
+
+        std::fs::write(&path, b"corrupted").unwrap();
+        match img.load_images(&backend, &path) {
+            Err(Error::HashMismatch {
+                expected, actual, ..
+            }) => {
+                assert_eq!(expected, img.hash);
+                assert_eq!(actual, HashAlgo::Sha256.digest(b"corrupted"));
+            }
+            r => panic!("expect hash mismatch, got {:?}", r),
+        }
+        img.load_images_unchecked(&backend).unwrap();
+        assert_eq!(img.data.as_deref(), Some(&b"corrupted"[..]));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn vec_migrate() {
+        let root = temp_dir("vec-migrate");
+        let image_store = root.join("images");
+        std::fs::create_dir(&image_store).unwrap();
+        let backend = FsBackend::new(&image_store);
+        let imgs: Vec<ImageRef> = (0..1000)
+            .map(|i| {
+                let data = format!("image {}", i).into_bytes();
+                ImageRef {
+                    url: String::new(),
+                    hash: HashAlgo::Sha256.digest(&data),
+                    extension: "png".to_owned(),
+                    original_hash: None,
+                    animated: false,
+                    mime: None,
+                    data: Some(data.into_boxed_slice()),
+                }
+            })
+            .collect();
+        imgs.store_images(&backend, root.join("a")).unwrap();
+        imgs.migrate(&backend, root.join("b")).unwrap();
+        for i in imgs.iter() {
+            let p = root.join("b").join(i.to_string());
+            assert_eq!(
+                std::fs::read(p).unwrap().as_slice(),
+                i.data.as_deref().unwrap()
+            );
+        }
+
+        for i in [10, 500] {
+            std::fs::remove_file(backend.path(&imgs[i].hash, "png")).unwrap();
+        }
+        match imgs.migrate(&backend, root.join("c")) {
+            Err(Error::Chained { field, .. }) => assert_eq!(field, imgs[10].to_string()),
+            r => panic!("expect chained error, got {:?}", r),
+        }
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn gif_animated() {
+        const HEADER: &[u8] = b"GIF89a\x01\0\x01\0\0\0\0";
+        const FRAME: &[u8] = b"\x2c\0\0\0\0\x01\0\x01\0\0\x02\x02\x44\x01\0";
+        let gif = |frames: usize| {
+            let mut ret = HEADER.to_vec();
+            for _ in 0..frames {
+                ret.extend_from_slice(FRAME);
+            }
+            ret.push(0x3b);
+            ret
+        };
+        assert!(!is_animated(&gif(1), "gif"));
+        assert!(is_animated(&gif(2), "gif"));
+        assert!(!is_animated(&gif(2)[..20], "gif"));
+        // fed in chunks while streaming
+        let mut frames = GifFrames::default();
+        gif(2).chunks(1).for_each(|c| frames.update(c));
+        assert!(frames.animated());
+    }
+
+    #[test]
+    fn host_filter() {
+        let filter = HostFilter {
+            allow: vec!["*.zhimg.com".to_owned(), "zhihu.com".to_owned()],
+            block: vec!["tracker.zhimg.com".to_owned()],
+        };
+        let allows = |u: &str| filter.allows(&u.parse().unwrap());
+        assert!(allows("https://pic1.zhimg.com/a.jpg"));
+        assert!(allows("https://zhimg.com/a.jpg"));
+        assert!(allows("https://ZHIHU.com/a.jpg"));
+        assert!(!allows("https://www.zhihu.com/a.jpg"));
+        assert!(!allows("https://evilzhimg.com/a.jpg"));
+        assert!(!allows("https://tracker.zhimg.com/a.gif"));
+        assert!(HostFilter::default().allows(&"https://example.com/a.png".parse().unwrap()));
+    }
+}
@@ -7,11 +7,15 @@ pub trait Progress {
 pub trait ImageProg: Progress {
     fn set_size(&mut self, size: Option<u64>);
     fn inc(&mut self, delta: u64);
+    /// discard bytes reported by the failed attempt
+    fn retry(&mut self, attempt: u32);
 }
 pub trait ImagesProg: Progress {
     type ImageRep<'a>: ImageProg
     where
         Self: 'a;
-    fn start_image<I: Display>(&mut self, url: I) -> Self::ImageRep<'_>;
-    fn skip(&mut self);
+    /// may be called again before previous image reporters are dropped,
+    /// when images are fetched concurrently
+    fn start_image<I: Display>(&self, url: I) -> Self::ImageRep<'_>;
+    fn skip(&self);
 }
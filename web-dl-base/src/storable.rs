@@ -77,15 +77,30 @@ pub struct LoadOpt {
     pub load_raw: bool,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct StoreOpt {
+    /// store fields marked as raw data, they are removed from `path` otherwise
+    pub store_raw: bool,
+}
+impl Default for StoreOpt {
+    fn default() -> Self {
+        Self { store_raw: true }
+    }
+}
+
 pub trait Storable: Sized + serde::Serialize + serde::de::DeserializeOwned {
     fn load<P: AsRef<Path>>(path: P, load_opt: LoadOpt) -> Result<Self, Error>;
     fn store<P: AsRef<Path>>(&self, path: P) -> Result<(), Error>;
+    fn store_with<P: AsRef<Path>>(&self, path: P, store_opt: StoreOpt) -> Result<(), Error> {
+        let _ = store_opt;
+        self.store(path)
+    }
 }
 
 #[doc(hidden)]
 /// private module, for derive macro only
 pub mod macro_export {
-    use super::{Error, IoErrorOp, LoadOpt, Storable};
+    use super::{Error, IoErrorOp, LoadOpt, Storable, StoreOpt};
     pub use std::{
         self, convert::AsRef, default::Default, path::Path, result::Result, string::String,
     };
@@ -122,6 +137,29 @@ pub mod macro_export {
             source: Box::new(e),
         })
     }
+    /// store a raw data field, or remove what was stored at `path` when raw data is skipped
+    pub fn store_raw_chained<S: Storable, P: AsRef<Path>, C: Display>(
+        value: &S,
+        path: P,
+        store_opt: StoreOpt,
+        context: C,
+    ) -> Result<(), Error> {
+        if store_opt.store_raw {
+            return store_chained(value, path, context);
+        }
+        let path = path.as_ref();
+        match fs::symlink_metadata(path) {
+            Ok(m) if m.is_dir() => fs::remove_dir_all(path),
+            Ok(_) => fs::remove_file(path),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+        .map_err(|e| Error::Io {
+            op: IoErrorOp::Other("remove"),
+            path: path.to_path_buf(),
+            source: e,
+        })
+    }
 
     pub fn create_file<P: AsRef<Path>>(path: P) -> Result<fs::File, Error> {
         fs::File::create(path.as_ref()).map_err(|e| Error::Io {
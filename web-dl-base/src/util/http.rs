@@ -0,0 +1,42 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::Duration;
+
+/// delay given by the `Retry-After` header, either delta seconds or an http date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => chrono::DateTime::parse_from_rfc2822(value)
+            .ok()
+            .and_then(|t| {
+                (t.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                    .to_std()
+                    .ok()
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn retry_after() {
+        let headers =
+            |v: &str| HeaderMap::from_iter([(RETRY_AFTER, HeaderValue::from_str(v).unwrap())]);
+        assert_eq!(
+            super::retry_after(&headers(" 120")),
+            Some(Duration::from_secs(120))
+        );
+        let at = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        let d = super::retry_after(&headers(at.as_str())).unwrap();
+        assert!(d > Duration::from_secs(50) && d <= Duration::from_secs(60));
+        assert_eq!(
+            super::retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")),
+            None
+        );
+        assert_eq!(super::retry_after(&headers("soon")), None);
+        assert_eq!(super::retry_after(&HeaderMap::new()), None);
+    }
+}
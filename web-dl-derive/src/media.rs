@@ -94,6 +94,17 @@ fn gen_drops(info: &[FieldInfo]) -> TokenStream {
     }
     ret
 }
+fn gen_visits(info: &[FieldInfo]) -> TokenStream {
+    if info.is_empty() {
+        return quote!(let _ = f;);
+    }
+    let mut ret = TokenStream::new();
+    for i in info {
+        let expr = &i.expr;
+        ret.extend(quote! { #expr.visit_images(f); });
+    }
+    ret
+}
 
 fn gen_impl(
     name: Ident,
@@ -101,10 +112,13 @@ fn gen_impl(
     migrate_impl: TokenStream,
     store_impl: TokenStream,
     drop_impl: TokenStream,
+    visit_impl: TokenStream,
 ) -> proc_macro::TokenStream {
     let t_name = exported!(StoreImage);
     let res = support!(Result);
     let err = exported!(Error);
+    let backend = exported!(ImageBackend);
+    let image_ref = exported!(ImageRef);
     let path = {
         let as_ref = support!(AsRef);
         let path = support!(Path);
@@ -112,21 +126,23 @@ fn gen_impl(
     };
     quote! {
         impl #t_name for #name {
-            fn load_images<P>(&mut self, path: P) -> #res<(), #err>
+            fn load_images<B, P>(&mut self, backend: &B, path: P) -> #res<(), #err>
             where
+                B: #backend + ?Sized,
                 P: #path
             {
                 #load_impl
             }
-            fn store_images<P>(&self, path: P) -> #res<(), #err>
+            fn store_images<B, P>(&self, backend: &B, path: P) -> #res<(), #err>
             where
-                P:#path
+                B: #backend + ?Sized,
+                P: #path
             {
                 #store_impl
             }
-            fn migrate<S, P>(&self, image_store: S, path: P) -> #res<(), #err>
+            fn migrate<B, P>(&self, backend: &B, path: P) -> #res<(), #err>
             where
-                S: #path,
+                B: #backend + ?Sized,
                 P: #path
             {
                 #migrate_impl
@@ -134,6 +150,9 @@ fn gen_impl(
             fn drop_images(&mut self) {
                 #drop_impl
             }
+            fn visit_images(&self, f: &mut dyn FnMut(&#image_ref)) {
+                #visit_impl
+            }
         }
     }
     .into()
@@ -177,7 +196,7 @@ pub fn derive_store_image(input: proc_macro::TokenStream) -> proc_macro::TokenSt
                 {
                     let stmt = gen_stmts(
                         &s,
-                        |name, expr, path| quote!(#load_chained(&mut #expr, #path, #name)),
+                        |name, expr, path| quote!(#load_chained(&mut #expr, backend, #path, #name)),
                     );
                     quote! {
                         let path = path.as_ref();
@@ -187,11 +206,10 @@ pub fn derive_store_image(input: proc_macro::TokenStream) -> proc_macro::TokenSt
                 {
                     let stmt = gen_stmts(
                         &s,
-                        |name, expr, path| quote!(#migrate_chained(&#expr, image_store, #path, #name)),
+                        |name, expr, path| quote!(#migrate_chained(&#expr, backend, #path, #name)),
                     );
                     quote! {
                         let path = path.as_ref();
-                        let image_store = image_store.as_ref();
                         #create_dirs(path)?;
                         #stmt
                     }
@@ -199,7 +217,7 @@ pub fn derive_store_image(input: proc_macro::TokenStream) -> proc_macro::TokenSt
                 {
                     let stmt = gen_stmts(
                         &s,
-                        |name, expr, path| quote!(#store_chained(&#expr, #path,#name)),
+                        |name, expr, path| quote!(#store_chained(&#expr, backend, #path, #name)),
                     );
                     quote! {
                         let path = path.as_ref();
@@ -208,6 +226,7 @@ pub fn derive_store_image(input: proc_macro::TokenStream) -> proc_macro::TokenSt
                     }
                 },
                 gen_drops(&s),
+                gen_visits(&s),
             )
         }
         Data::Enum(_) => panic!("derive StoreImage for enum is not supported"),
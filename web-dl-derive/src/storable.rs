@@ -57,13 +57,26 @@ macro_rules! exported {
     };
 }
 
-fn gen_impl(name: Ident, load: TokenStream, store: TokenStream) -> proc_macro::TokenStream {
+fn gen_impl(
+    name: Ident,
+    load: TokenStream,
+    store: TokenStream,
+    store_with: Option<TokenStream>,
+) -> proc_macro::TokenStream {
     let p = support!(Path);
     let as_ref = support!(AsRef);
     let res = support!(Result);
     let t_name = exported!(Storable);
     let opt = exported!(LoadOpt);
+    let store_opt = exported!(StoreOpt);
     let err = exported!(Error);
+    let store_with = store_with.map(|s| {
+        quote! {
+            fn store_with<P:#as_ref<#p>>(&self, path: P, __store_opt: #store_opt) -> #res<(), #err> {
+                #s
+            }
+        }
+    });
     quote! {
         impl #t_name for #name {
             fn load<P:#as_ref<#p>>(path: P, __load_opt: #opt) -> #res<Self, #err> {
@@ -72,18 +85,23 @@ fn gen_impl(name: Ident, load: TokenStream, store: TokenStream) -> proc_macro::T
             fn store<P:#as_ref<#p>>(&self, path: P) -> #res<(), #err> {
                 #store
             }
+            #store_with
         }
     }
     .into()
 }
 
 pub fn derive_storable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = InputRecv::from_derive_input(&parse_macro_input!(input as DeriveInput)).unwrap();
+    let input = match InputRecv::from_derive_input(&parse_macro_input!(input as DeriveInput)) {
+        Ok(v) => v,
+        Err(e) => return e.write_errors().into(),
+    };
     match input.format {
         StoreFormat::Directory => {
             let res = support!(Result);
             let load_chain = support!(load_chained);
             let store_chain = support!(store_chained);
+            let store_raw_chain = support!(store_raw_chained);
             let mut load_fields: Punctuated<FieldValue, Comma> = Punctuated::new();
             let mut store_fields = Vec::new();
             for i in input.data.take_struct().unwrap() {
@@ -106,10 +124,11 @@ pub fn derive_storable(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
                         attrs: Vec::new(),
                         member: Member::Named(id.clone()),
                         colon_token: Some(Colon::default()),
+                        // raw data is optional, it's `None` when absent
                         expr: if i.raw_data.is_present() {
                             parse_quote! {
                                 if __load_opt.load_raw {
-                                    Some(#load_expr)
+                                    #load_expr
                                 } else {
                                     None
                                 }
@@ -119,18 +138,29 @@ pub fn derive_storable(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
                         },
                     }
                 });
-                store_fields.push(quote! {
-                    #store_chain(&self.#id, #path, #id_str)
+                store_fields.push(if i.raw_data.is_present() {
+                    quote! {
+                        #store_raw_chain(&self.#id, #path, __store_opt, #id_str)
+                    }
+                } else {
+                    quote! {
+                        #store_chain(&self.#id, #path, #id_str)
+                    }
                 });
             }
             let create_dir = support!(create_dir_missing);
+            let default = support!(Default);
+            let t_name = exported!(Storable);
             gen_impl(
                 input.ident,
                 quote! {
                     let path = path.as_ref();
                     #res::Ok(Self { #load_fields })
                 },
-                {
+                quote! {
+                    #t_name::store_with(self, path, #default::default())
+                },
+                Some({
                     if store_fields.is_empty() {
                         quote! {#res::Ok(())}
                     } else {
@@ -146,7 +176,7 @@ pub fn derive_storable(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
                             return #last;
                         }
                     }
-                },
+                }),
             )
         }
         StoreFormat::Yaml => {
@@ -156,6 +186,7 @@ pub fn derive_storable(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
                 input.ident,
                 quote! {#load(path)},
                 quote! {#store(self, path)},
+                None,
             )
         }
         StoreFormat::Json => {
@@ -165,6 +196,7 @@ pub fn derive_storable(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
                 input.ident,
                 quote! {#load(path)},
                 quote! {#store(self, path)},
+                None,
             )
         }
         StoreFormat::Ron => {
@@ -174,6 +206,7 @@ pub fn derive_storable(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
                 input.ident,
                 quote! {#load(path)},
                 quote! {#store(self, path)},
+                None,
             )
         }
     }
@@ -0,0 +1,128 @@
+use rustyline::{
+    completion::Completer, highlight::Highlighter, hint::Hinter, validate::Validator, Context,
+    Helper,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+use zhihu_dl::store::ObjectInfo;
+
+fn keys<K: Display, V>(map: &BTreeMap<K, V>) -> Vec<String> {
+    map.keys().map(|k| k.to_string()).collect()
+}
+/// ids of stored objects by type name used in commands
+pub fn stored_ids(objects: &ObjectInfo) -> HashMap<&'static str, Vec<String>> {
+    HashMap::from([
+        ("answer", keys(&objects.answer)),
+        ("article", keys(&objects.article)),
+        ("collection", keys(&objects.collection)),
+        ("column", keys(&objects.column)),
+        ("comment", keys(&objects.comment)),
+        ("pin", keys(&objects.pin)),
+        ("question", keys(&objects.question)),
+        ("topic", keys(&objects.topic)),
+        ("user", keys(&objects.user)),
+    ])
+}
+
+/// completes subcommands and flags of repl commands, and `--id` of items and containers
+/// from ids in store
+pub struct ReplHelper {
+    command: clap::Command,
+    ids: HashMap<&'static str, Vec<String>>,
+}
+impl ReplHelper {
+    pub fn new(command: clap::Command) -> Self {
+        Self {
+            command,
+            ids: HashMap::new(),
+        }
+    }
+    pub fn set_ids(&mut self, ids: HashMap<&'static str, Vec<String>>) {
+        self.ids = ids;
+    }
+    /// candidates of the last word of `words`
+    fn candidates(&self, words: &[&str]) -> Vec<String> {
+        let Some((word, prev)) = words.split_last() else {
+            return Vec::new();
+        };
+        let mut cmd = &self.command;
+        let mut kind = None;
+        for (idx, w) in prev.iter().enumerate() {
+            if let Some(c) = cmd.find_subcommand(w) {
+                cmd = c;
+                // type of the object named by `--id`, like `answer` in `item answer get`
+                if idx == 1 && matches!(prev[0], "item" | "container") {
+                    kind = Some(c.get_name());
+                }
+            }
+        }
+        let mut ret: Vec<String> = match (prev.last(), kind) {
+            (Some(&"--id"), Some(k)) => self.ids.get(k).cloned().unwrap_or_default(),
+            _ if word.starts_with('-') => cmd
+                .get_arguments()
+                .filter(|a| !a.is_hide_set())
+                .filter_map(|a| a.get_long())
+                .map(|l| format!("--{}", l))
+                .collect(),
+            _ => cmd
+                .get_subcommands()
+                .filter(|c| !c.is_hide_set())
+                .map(|c| c.get_name().to_owned())
+                .collect(),
+        };
+        ret.retain(|c| c.starts_with(word));
+        ret.sort();
+        ret
+    }
+}
+impl Completer for ReplHelper {
+    type Candidate = String;
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
+        let line = &line[..pos];
+        let mut words: Vec<_> = line.split_whitespace().collect();
+        if line.is_empty() || line.ends_with(char::is_whitespace) {
+            words.push("");
+        }
+        let start = pos - words.last().map_or(0, |w| w.len());
+        Ok((start, self.candidates(&words)))
+    }
+}
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+impl Highlighter for ReplHelper {}
+impl Validator for ReplHelper {}
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates() {
+        let mut helper = ReplHelper::new(crate::Command::repl());
+        helper.set_ids(HashMap::from([(
+            "answer",
+            vec!["12".to_owned(), "34".to_owned()],
+        )]));
+        assert_eq!(helper.candidates(&["ite"]), ["item"]);
+        assert!(helper
+            .candidates(&["item", "answer", ""])
+            .contains(&"download".to_owned()));
+        assert_eq!(
+            helper.candidates(&["item", "answer", "get", "--i"]),
+            ["--id"]
+        );
+        assert_eq!(
+            helper.candidates(&["item", "answer", "get", "--id", "3"]),
+            ["34"]
+        );
+    }
+}
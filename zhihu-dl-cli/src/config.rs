@@ -0,0 +1,97 @@
+use crate::Verbosity;
+use anyhow::Context;
+use serde::{Deserialize, Deserializer};
+use std::{fs, io, path::PathBuf};
+use zhihu_dl::element::content::convertor::ConvertFormat;
+
+/// defaults of command line options, options given on command line take precedence
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub store_path: Option<String>,
+    pub verbosity: Option<Verbosity>,
+    pub rate_limit: Option<f64>,
+    /// ron request profile file, see `--profile`
+    pub profile: Option<String>,
+    pub user_agent: Option<String>,
+    /// proxy of every request, see `--proxy`
+    pub proxy: Option<String>,
+    /// request timeouts in seconds, see `--timeout`
+    pub timeout: Option<u64>,
+    pub connect_timeout: Option<u64>,
+    pub image_timeout: Option<u64>,
+    /// store raw api responses with items, see `--no-raw-data`
+    pub keep_raw: Option<bool>,
+    /// hosts images are fetched from, see `--image-host`
+    pub image_hosts: Vec<String>,
+    pub blocked_image_hosts: Vec<String>,
+    /// format of the convert command
+    #[serde(deserialize_with = "parse_format")]
+    pub format: Option<ConvertFormat>,
+}
+fn parse_format<'de, D: Deserializer<'de>>(d: D) -> Result<Option<ConvertFormat>, D::Error> {
+    Option::<String>::deserialize(d)?
+        .map(|s| s.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// `zhihu-dl/config.yaml` in `$XDG_CONFIG_HOME`, or `~/.config` when it's unset
+fn default_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map(|d| d.join("zhihu-dl").join("config.yaml"))
+}
+
+impl Config {
+    fn parse(text: &str) -> Result<Self, serde_yaml::Error> {
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_yaml::from_str(text)
+    }
+    /// load `path`, or the default config file which may be absent
+    pub fn load(path: Option<&str>) -> anyhow::Result<Self> {
+        let (path, required) = match path {
+            Some(p) => (PathBuf::from(p), true),
+            None => match default_path() {
+                Some(p) => (p, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(t) => t,
+            Err(e) if !required && e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to read config {}", path.display()))
+            }
+        };
+        Self::parse(text.as_str()).with_context(|| format!("malformed config {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let config =
+            Config::parse("store_path: /data/zhihu\nverbosity: info\nformat: html\n").unwrap();
+        assert_eq!(config.store_path.as_deref(), Some("/data/zhihu"));
+        assert!(matches!(config.verbosity, Some(Verbosity::Info)));
+        assert_eq!(config.format, Some(ConvertFormat::Html));
+        assert!(
+            Config::parse("image_hosts: ['*.zhimg.com', pic.example.com]\n")
+                .unwrap()
+                .image_hosts
+                .contains(&"pic.example.com".to_owned())
+        );
+        assert!(Config::parse("").unwrap().store_path.is_none());
+        assert!(Config::parse("format: docx\n").is_err());
+        assert!(Config::parse("store: /data\n").is_err());
+    }
+}
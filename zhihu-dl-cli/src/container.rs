@@ -9,10 +9,11 @@ use zhihu_dl::{
     item::{
         any::Any,
         column::{self, Column},
+        search::{self, Search},
         user::{self, User},
-        Answer, Article, Collection, Comment, Item, ItemContainer, Pin, Question, VoidOpt,
+        Answer, Article, Collection, Comment, Item, ItemContainer, Pin, Question, Topic, VoidOpt,
     },
-    progress::progress_bar::ProgressReporter,
+    progress::Reporter,
     store,
 };
 
@@ -21,23 +22,33 @@ pub enum ContainerOper<Id: Args> {
     Get {
         #[command(flatten)]
         id: Id,
+        #[command(flatten)]
+        range: DateRange,
     },
+    /// fetch items newer than those stored with the container
     Update {
         #[command(flatten)]
         id: Id,
+        #[arg(long)]
+        /// fetch every item, marking stored items no longer listed as missing
+        full: bool,
+        #[command(flatten)]
+        range: DateRange,
     },
     Download {
         #[command(flatten)]
         id: Id,
         #[command(flatten)]
         link_opt: LinkOpt,
+        #[command(flatten)]
+        range: DateRange,
     },
 }
 impl<Id: Args> ContainerOper<Id> {
     async fn run<IC, I, O>(
         self,
         driver: &mut Driver,
-        prog: &ProgressReporter,
+        prog: &impl Reporter,
     ) -> Result<(), anyhow::Error>
     where
         I: Item + store::StoreItem,
@@ -47,6 +58,26 @@ impl<Id: Args> ContainerOper<Id> {
         if !driver.is_initialized() {
             anyhow::bail!("client is not initialized");
         }
+        let range = match &self {
+            Self::Get { range, .. } | Self::Update { range, .. } | Self::Download { range, .. } => {
+                range
+            }
+        };
+        driver.set_date_filter(range.to_filter());
+        let ret = self.run_filtered::<IC, I, O>(driver, prog).await;
+        driver.set_date_filter(Default::default());
+        ret
+    }
+    async fn run_filtered<IC, I, O>(
+        self,
+        driver: &mut Driver,
+        prog: &impl Reporter,
+    ) -> Result<(), anyhow::Error>
+    where
+        I: Item + store::StoreItem,
+        Id: OwnedId<IC>,
+        IC: ItemContainer<O, I>,
+    {
         fn error_msg<I: Item, O, IC: ItemContainer<O, I>>(
             oper: &str,
             id: IC::Id<'_>,
@@ -65,7 +96,7 @@ impl<Id: Args> ContainerOper<Id> {
             )
         }
         match self {
-            Self::Get { id } => {
+            Self::Get { id, .. } => {
                 let id = id.to_id();
                 driver
                     .get_container::<IC, I, O, _>(prog, id)
@@ -74,7 +105,7 @@ impl<Id: Args> ContainerOper<Id> {
                         error_msg::<I, O, IC>("get", id, format_args!(""), format_args!(""))
                     })?;
             }
-            Self::Download { id, link_opt } => {
+            Self::Download { id, link_opt, .. } => {
                 let id = id.to_id();
                 driver
                     .download_container::<IC, I, O, _, _>(
@@ -101,10 +132,10 @@ impl<Id: Args> ContainerOper<Id> {
                         )
                     })?;
             }
-            Self::Update { id } => {
+            Self::Update { id, full, .. } => {
                 let id = id.to_id();
                 driver
-                    .update_container::<IC, I, O, _>(prog, id)
+                    .update_container::<IC, I, O, _>(prog, id, full)
                     .await
                     .with_context(|| {
                         error_msg::<I, O, IC>("update", id, format_args!(""), format_args!(""))
@@ -123,7 +154,7 @@ pub enum CommentEntry {
     },
 }
 impl CommentEntry {
-    async fn run<IC>(self, driver: &mut Driver, prog: &ProgressReporter) -> anyhow::Result<()>
+    async fn run<IC>(self, driver: &mut Driver, prog: &impl Reporter) -> anyhow::Result<()>
     where
         IC: ItemContainer<VoidOpt, Comment>,
         NumId: OwnedId<IC>,
@@ -170,6 +201,15 @@ pub enum QuestionEntry {
     },
 }
 
+#[derive(Debug, Subcommand)]
+pub enum TopicEntry {
+    /// essence feed of the topic
+    Item {
+        #[command(subcommand)]
+        operation: ContainerOper<NumId>,
+    },
+}
+
 #[derive(Debug, Subcommand)]
 pub enum UserCollection {
     Created {
@@ -234,17 +274,22 @@ pub enum ContainerCmd {
         #[command(subcommand)]
         operation: QuestionEntry,
     },
+    /// answers and articles found by keywords
+    Search {
+        #[command(subcommand)]
+        operation: ContainerOper<SearchQuery>,
+    },
+    Topic {
+        #[command(subcommand)]
+        operation: TopicEntry,
+    },
     User {
         #[command(subcommand)]
         operation: UserEntry,
     },
 }
 impl ContainerCmd {
-    pub async fn run(
-        self,
-        driver: &mut Driver,
-        prog: &ProgressReporter,
-    ) -> Result<(), anyhow::Error> {
+    pub async fn run(self, driver: &mut Driver, prog: &impl Reporter) -> Result<(), anyhow::Error> {
         match self {
             Self::Answer { operation } => operation.run::<Answer>(driver, prog).await,
             Self::Article { operation } => operation.run::<Article>(driver, prog).await,
@@ -286,6 +331,16 @@ impl ContainerCmd {
                         .await
                 }
             },
+            Self::Search { operation } => {
+                operation
+                    .run::<Search, Any, search::SearchOpt>(driver, prog)
+                    .await
+            }
+            Self::Topic { operation } => match operation {
+                TopicEntry::Item { operation } => {
+                    operation.run::<Topic, Any, VoidOpt>(driver, prog).await
+                }
+            },
             Self::User { operation } => match operation {
                 UserEntry::Answer { operation } => {
                     operation.run::<User, Answer, VoidOpt>(driver, prog).await
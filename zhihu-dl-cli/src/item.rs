@@ -5,11 +5,17 @@ use std::{
     fmt::{self, Display},
     path::PathBuf,
 };
-use web_dl_base::{id::OwnedId, media, storable};
+use web_dl_base::{
+    id::{HasId, OwnedId},
+    media, storable,
+};
 use zhihu_dl::{
     driver::Driver,
-    item::{Answer, Article, Collection, Column, Fetchable, Item, Pin, Question, User},
-    progress::{progress_bar::ProgressReporter, ItemJob, Reporter},
+    element::content::convertor::ConvertFormat,
+    item::{
+        Answer, Article, Collection, Column, Fetchable, Item, Pin, Question, Topic, User, VoidOpt,
+    },
+    progress::{ItemJob, OtherJob, Reporter},
     store::{self, StoreItem},
 };
 
@@ -39,6 +45,15 @@ pub enum ItemOper<Id: Args> {
         id: Id,
         #[command(flatten)]
         link_opt: LinkOpt,
+        /// copy the item and its images to dest instead of linking it
+        #[arg(long)]
+        standalone: bool,
+        /// copy files instead of hard linking them, implies --standalone
+        #[arg(long)]
+        copy: bool,
+        /// also render the item as markdown, html or epub next to dest
+        #[arg(long)]
+        format: Option<ConvertFormat>,
     },
     Update {
         #[command(flatten)]
@@ -48,6 +63,14 @@ pub enum ItemOper<Id: Args> {
         #[command(flatten)]
         id: Id,
     },
+    /// remove from store
+    Delete {
+        #[command(flatten)]
+        id: Id,
+        #[arg(long)]
+        /// also remove images no longer referenced
+        remove_images: bool,
+    },
 }
 
 fn error_msg<I: Item, Id: Display>(oper: &str, id: Id, opt: fmt::Arguments<'_>) -> String {
@@ -61,7 +84,7 @@ fn error_msg<I: Item, Id: Display>(oper: &str, id: Id, opt: fmt::Arguments<'_>)
 }
 async fn add_raw<I>(
     driver: &mut Driver,
-    prog: &ProgressReporter,
+    prog: &impl Reporter,
     on_server: bool,
     path: &String,
 ) -> anyhow::Result<()>
@@ -90,7 +113,17 @@ fn check_driver(driver: &Driver) -> Result<(), anyhow::Error> {
     Ok(())
 }
 impl<Id: Args> ItemOper<Id> {
-    async fn run<I>(self, driver: &mut Driver, prog: &ProgressReporter) -> Result<(), anyhow::Error>
+    fn id(&self) -> Option<&Id> {
+        match self {
+            Self::Get { id }
+            | Self::Download { id, .. }
+            | Self::Update { id }
+            | Self::ConvertHtml { id }
+            | Self::Delete { id, .. } => Some(id),
+            Self::AddRaw { .. } => None,
+        }
+    }
+    async fn run<I>(self, driver: &mut Driver, prog: &impl Reporter) -> Result<(), anyhow::Error>
     where
         I: Fetchable + Item + media::StoreImage + store::BasicStoreItem,
         Id: OwnedId<I>,
@@ -111,32 +144,65 @@ impl<Id: Args> ItemOper<Id> {
                         error_msg::<I, _>("add raw data of", path, format_args!(""))
                     })?;
             }
-            ItemOper::Download { id, link_opt } => {
+            ItemOper::Download {
+                id,
+                link_opt,
+                standalone,
+                copy,
+                format,
+            } if standalone || copy => {
                 check_driver(driver)?;
                 let id = id.to_id();
-                driver
+                driver.set_output_format(format);
+                let ret = driver
+                    .export_item::<I, _, _>(prog, id, copy, PathBuf::from(link_opt.dest.as_str()))
+                    .await;
+                driver.set_output_format(None);
+                ret.with_context(|| {
+                    error_msg::<I, _>(
+                        "export",
+                        id,
+                        format_args!(
+                            "to {}[{}]",
+                            link_opt.dest,
+                            if copy { "copy" } else { "hard link" }
+                        ),
+                    )
+                })?;
+            }
+            ItemOper::Download {
+                id,
+                link_opt,
+                format,
+                ..
+            } => {
+                check_driver(driver)?;
+                let id = id.to_id();
+                driver.set_output_format(format);
+                let ret = driver
                     .download_item::<I, _, _>(
                         prog,
                         id,
                         !link_opt.link_absolute,
                         PathBuf::from(link_opt.dest.as_str()),
                     )
-                    .await
-                    .with_context(|| {
-                        error_msg::<I, _>(
-                            "download",
-                            id,
-                            format_args!(
-                                "to {}[{}]",
-                                link_opt.dest,
-                                if link_opt.link_absolute {
-                                    "link absolute"
-                                } else {
-                                    "link relative"
-                                }
-                            ),
-                        )
-                    })?;
+                    .await;
+                driver.set_output_format(None);
+                ret.with_context(|| {
+                    error_msg::<I, _>(
+                        "download",
+                        id,
+                        format_args!(
+                            "to {}[{}]",
+                            link_opt.dest,
+                            if link_opt.link_absolute {
+                                "link absolute"
+                            } else {
+                                "link relative"
+                            }
+                        ),
+                    )
+                })?;
             }
             ItemOper::Update { id } => {
                 check_driver(driver)?;
@@ -149,12 +215,13 @@ impl<Id: Args> ItemOper<Id> {
             ItemOper::ConvertHtml { id } => {
                 let id = id.to_id();
                 let p = prog.start_item::<&str, _>("Converting", "raw html of ", I::TYPE, id, None);
+                let convert_opt = *driver.convert_opt();
                 driver
                     .store
                     .get_object::<I>(id, storable::LoadOpt::default())
                     .context("failed to load object")
                     .and_then(|mut o| {
-                        o.convert_html();
+                        o.convert_html(&convert_opt);
                         driver
                             .store
                             .add_object(<I as StoreItem>::in_store(id, &driver.store).on_server, &o)
@@ -163,6 +230,15 @@ impl<Id: Args> ItemOper<Id> {
                     .with_context(|| error_msg::<I, _>("convert raw html", id, format_args!("")))?;
                 p.finish("Converted", id);
             }
+            ItemOper::Delete { id, remove_images } => {
+                let id = id.to_id();
+                let p = prog.start_item::<&str, _>("Deleting", "", I::TYPE, id, None);
+                let deleted = driver
+                    .store
+                    .delete_object::<I>(id, remove_images)
+                    .with_context(|| error_msg::<I, _>("delete", id, format_args!("")))?;
+                p.finish(if deleted { "Deleted" } else { "Absent" }, id);
+            }
         }
         Ok(())
     }
@@ -172,16 +248,20 @@ impl<Id: Args> ItemOper<Id> {
 pub enum ItemCmd {
     Answer {
         #[command(subcommand)]
-        operation: ItemOper<NumId>,
+        operation: ItemOper<AnswerArg>,
     },
     Article {
         #[command(subcommand)]
         operation: ItemOper<NumId>,
     },
+    /// collection title, description and creator, items are fetched by
+    /// `container collection item`
     Collection {
         #[command(subcommand)]
         operation: ItemOper<NumId>,
     },
+    /// column title, intro, image and author, items are fetched by
+    /// `container column item` and `container column pinned-item`
     Column {
         #[command(subcommand)]
         operation: ItemOper<StrId>,
@@ -191,6 +271,16 @@ pub enum ItemCmd {
         operation: ItemOper<NumId>,
     },
     Question {
+        /// also fetch all answers, download links the question and its answers to
+        /// question and answer under dest
+        #[arg(long)]
+        with_answers: bool,
+        #[command(subcommand)]
+        operation: ItemOper<NumId>,
+    },
+    /// topic name, introduction and avatar, items are fetched by
+    /// `container topic item`
+    Topic {
         #[command(subcommand)]
         operation: ItemOper<NumId>,
     },
@@ -199,16 +289,103 @@ pub enum ItemCmd {
         operation: ItemOper<UserSpec>,
     },
 }
+/// get or download a question, then all answers in it
+async fn question_with_answers(
+    operation: ItemOper<NumId>,
+    driver: &mut Driver,
+    prog: &impl Reporter,
+) -> anyhow::Result<()> {
+    check_driver(driver)?;
+    let (id, link_opt) = match operation {
+        ItemOper::Get { id } => (id, None),
+        ItemOper::Download {
+            id,
+            link_opt,
+            standalone: false,
+            copy: false,
+            ..
+        } => (id, Some(link_opt)),
+        _ => anyhow::bail!("--with-answers only applies to get and linking download"),
+    };
+    let id = OwnedId::<Question>::to_id(&id);
+    let job = prog.start_job("Fetching", format_args!("question {} with answers", id));
+    let answers = match &link_opt {
+        None => {
+            driver
+                .get_item::<Question, _>(prog, id)
+                .await
+                .with_context(|| error_msg::<Question, _>("get", id, format_args!("")))?;
+            driver
+                .get_container::<Question, Answer, VoidOpt, _>(prog, id)
+                .await
+        }
+        Some(link_opt) => {
+            let dest = PathBuf::from(link_opt.dest.as_str());
+            driver
+                .download_item::<Question, _, _>(
+                    prog,
+                    id,
+                    !link_opt.link_absolute,
+                    dest.join(Question::TYPE),
+                )
+                .await
+                .with_context(|| {
+                    error_msg::<Question, _>("download", id, format_args!("to {}", link_opt.dest))
+                })?;
+            driver
+                .download_container::<Question, Answer, VoidOpt, _, _>(
+                    prog,
+                    id,
+                    !link_opt.link_absolute,
+                    dest.join(Answer::TYPE),
+                )
+                .await
+        }
+    }
+    .with_context(|| error_msg::<Question, _>("fetch answers of", id, format_args!("")))?;
+    match answers {
+        Some(a) => job.finish(
+            "Fetched",
+            format_args!("question {} with {} answers", id, a.len()),
+        ),
+        None => job.finish(
+            "Fetched",
+            format_args!("question {} with stored answers", id),
+        ),
+    }
+    Ok(())
+}
 impl ItemCmd {
-    pub async fn run(
-        self,
-        driver: &mut Driver,
-        prog: &ProgressReporter,
-    ) -> Result<(), anyhow::Error> {
+    pub async fn run(self, driver: &mut Driver, prog: &impl Reporter) -> Result<(), anyhow::Error> {
         macro_rules! run {
             ($($t:ident),*) => {
                 match self {
-                    $(ItemCmd::$t { operation } => {
+                    ItemCmd::Answer { operation } => {
+                        if let Some(spec) = operation.id().map(|a| a.id) {
+                            // reject an answer of another question before it's stored
+                            let fetch = matches!(
+                                operation,
+                                ItemOper::Get { .. }
+                                    | ItemOper::Download { .. }
+                                    | ItemOper::Update { .. }
+                            );
+                            if fetch {
+                                check_driver(driver)?;
+                            }
+                            driver
+                                .check_answer(prog, spec, fetch)
+                                .await
+                                .with_context(|| {
+                                    error_msg::<Answer, _>("check", spec, format_args!(""))
+                                })?;
+                        }
+                        operation.run::<Answer>(driver, prog).await
+                    }
+                    ItemCmd::Question {
+                        with_answers: true,
+                        operation,
+                    } => question_with_answers(operation, driver, prog).await,
+                    $(ItemCmd::$t { operation, .. } => {
                         operation
                             .run::<$t>(driver, prog)
                             .await
@@ -216,6 +393,6 @@ impl ItemCmd {
                 }
             };
         }
-        run!(Answer, Article, Collection, Column, Question, Pin, User)
+        run!(Article, Collection, Column, Question, Pin, Topic, User)
     }
 }
@@ -1,16 +1,26 @@
 #![feature(format_args_nl)]
 
 use anyhow::Context;
-use clap::{FromArgMatches, Parser, Subcommand, ValueEnum};
-use indicatif::HumanDuration;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use indicatif::{HumanBytes, HumanDuration};
 use std::{fs, path::PathBuf};
 use termcolor::{BufferedStandardStream, Color};
+use web_dl_base::media;
 use zhihu_dl::{
-    driver::Driver,
-    progress::{progress_bar::ProgressReporter, OtherJob, Reporter},
+    driver::{manifest::leaf::ErrorPolicy, CancelToken, Driver},
+    element::content::{self, convertor::ConvertFormat, ConvertOpt},
+    progress::{
+        json::JsonReporter, progress_bar::ProgressReporter, silent::Silent, OtherJob, Reporter,
+    },
+    request::{self, RateLimit},
     store,
 };
 
+/// repl history file in store, unless set by `--history`
+const HISTORY_FILE: &str = "history.txt";
+
+mod complete;
+mod config;
 mod container;
 mod item;
 mod manifest;
@@ -27,6 +37,9 @@ enum Command {
         cmd: item::ItemCmd,
     },
     Container {
+        #[arg(long)]
+        /// record failed items and continue, reporting them at the end
+        keep_going: bool,
         #[command(subcommand)]
         cmd: container::ContainerCmd,
     },
@@ -37,8 +50,43 @@ enum Command {
         #[command(subcommand)]
         operation: manifest::ManifestCmd,
     },
+    /// download the object a zhihu url points to
+    Url {
+        url: String,
+        #[command(flatten)]
+        link_opt: LinkOpt,
+    },
     /// migrate store
-    Migrate,
+    Migrate {
+        #[arg(long)]
+        /// move item directories into buckets by id prefix of this length, 0 to unshard
+        shard_prefix: Option<usize>,
+    },
+    /// render stored items with content to a directory tree by type
+    Convert {
+        #[arg(long, value_hint = clap::ValueHint::DirPath)]
+        output_dir: String,
+        #[arg(long)]
+        /// markdown, html or epub, markdown unless set in config
+        format: Option<ConvertFormat>,
+    },
+    /// show object counts and disk usage of store
+    Stats,
+    /// check store consistency
+    #[command(alias = "fsck")]
+    Check,
+    /// remove images no longer referenced by any object
+    Gc {
+        #[arg(long)]
+        /// only report unreferenced images, don't remove them
+        dry_run: bool,
+    },
+    /// remove raw api responses stored with items to reclaim space
+    StripRaw {
+        #[arg(long)]
+        /// only report items with raw data, don't remove it
+        dry_run: bool,
+    },
     /// save store state
     Save,
     Exit {
@@ -46,6 +94,65 @@ enum Command {
         /// force exit, ignore error
         force: bool,
     },
+    /// print shell completion script to stdout
+    #[command(hide = true)]
+    Completions {
+        shell: clap_complete::Shell,
+    },
+}
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.trim().to_owned(), v.trim().to_owned()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got {}", s))
+}
+/// profile from file and flags, `None` keeps the default
+fn request_profile(cli: &Cli) -> anyhow::Result<Option<request::Profile>> {
+    if cli.profile.is_none()
+        && cli.user_agent.is_none()
+        && cli.accept_language.is_none()
+        && cli.header.is_empty()
+    {
+        return Ok(None);
+    }
+    let mut ret: request::Profile = match &cli.profile {
+        Some(p) => ron::from_str(
+            fs::read_to_string(p)
+                .with_context(|| format!("failed to read profile {}", p))?
+                .as_str(),
+        )
+        .with_context(|| format!("failed to parse profile {}", p))?,
+        None => Default::default(),
+    };
+    if let Some(u) = &cli.user_agent {
+        ret.user_agent = u.clone();
+    }
+    if let Some(l) = &cli.accept_language {
+        ret.accept_language = Some(l.clone());
+    }
+    ret.headers.extend(cli.header.iter().cloned());
+    Ok(Some(ret))
+}
+fn warn_rejected_session(driver: &Driver, output: &mut Output) {
+    if driver.is_session_rejected() {
+        output.write_warn(format_args_nl!(
+            "restored session was rejected by server, run init to start a new one"
+        ));
+    }
+}
+/// stop after the current item on the first ctrl-c so that store state is saved,
+/// exit immediately on the second
+async fn handle_interrupt(token: CancelToken) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    token.cancel();
+    slog::warn!(
+        slog_scope::logger(),
+        "interrupted, stopping after the current item, press ctrl-c again to exit now"
+    );
+    if tokio::signal::ctrl_c().await.is_ok() {
+        std::process::exit(130);
+    }
 }
 fn save_state(driver: &mut Driver, output: &mut Output) -> Result<(), anyhow::Error> {
     driver.save().context("failed to save store state")?;
@@ -70,9 +177,62 @@ async fn init_driver(driver: &mut Driver, output: &mut Output) -> Result<(), any
     );
     Ok(())
 }
+fn report_image_failure(driver: &Driver, output: &mut Output) {
+    let stat = driver.client.take_image_stat();
+    if let Some(s) = &mut output.summary {
+        s.images += stat.total;
+        s.images_fetched += stat.fetched;
+        s.image_bytes += stat.bytes;
+        s.failures.extend(
+            stat.failed
+                .iter()
+                .map(|(url, e)| format!("image {}: {}", url, e)),
+        );
+    }
+    if stat.failed.is_empty() {
+        return;
+    }
+    output.write_warn(format_args_nl!(
+        "{} of {} images failed",
+        stat.failed.len(),
+        stat.total
+    ));
+    for (url, e) in stat.failed {
+        output.write_warn(format_args_nl!(
+            "failed to fetch image {}: {:?}",
+            url,
+            anyhow::Error::new(e)
+        ));
+    }
+}
+/// write counts collected in `output` and by the driver along with the result to `path`
+fn write_summary(
+    path: &str,
+    driver: &Driver,
+    output: &mut Output,
+    ret: &anyhow::Result<bool>,
+) -> anyhow::Result<()> {
+    let mut summary = output.summary.take().unwrap_or_default();
+    let stat = driver.stat();
+    summary.success = ret.is_ok();
+    summary.items = stat.items;
+    summary.containers = stat.containers;
+    summary.bytes_transferred = driver.client.bytes_transferred();
+    if let Err(e) = ret {
+        summary.failures.push(format!("{:#}", e));
+    }
+    let file = fs::File::create(path)
+        .with_context(|| format!("failed to create summary file {}", path))?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), &summary)
+        .with_context(|| format!("failed to write summary file {}", path))
+}
 impl Command {
-    fn parse_line(line: Vec<String>) -> clap::error::Result<Self> {
+    /// definition of commands read by the repl and command files
+    fn repl() -> clap::Command {
         Self::augment_subcommands(clap::Command::new("repl").multicall(true))
+    }
+    fn parse_line(line: Vec<String>) -> clap::error::Result<Self> {
+        Self::repl()
             .subcommand_required(true)
             .try_get_matches_from(line.into_iter())
             .and_then(|am| Self::from_arg_matches(&am))
@@ -80,14 +240,43 @@ impl Command {
     fn run(
         self,
         runtime: &tokio::runtime::Runtime,
+        config: &config::Config,
         driver: &mut Driver,
         output: &mut Output,
-        prog: &ProgressReporter,
+        prog: &impl Reporter,
     ) -> Result<bool, anyhow::Error> {
         match self {
             Self::Init => runtime.block_on(init_driver(driver, output))?,
-            Self::Item { cmd } => runtime.block_on(cmd.run(driver, prog))?,
-            Self::Container { cmd } => runtime.block_on(cmd.run(driver, prog))?,
+            Self::Item { cmd } => {
+                let ret = runtime.block_on(cmd.run(driver, prog));
+                report_image_failure(driver, output);
+                ret?
+            }
+            Self::Container { keep_going, cmd } => {
+                driver.set_error_policy(if keep_going {
+                    ErrorPolicy::Continue
+                } else {
+                    ErrorPolicy::FailFast
+                });
+                let ret = runtime.block_on(cmd.run(driver, prog));
+                report_image_failure(driver, output);
+                let failures = manifest::report_failures(output, driver);
+                ret?;
+                failures?
+            }
+            Self::Url { url, link_opt } => {
+                if !driver.is_initialized() {
+                    anyhow::bail!("client is not initialized");
+                }
+                let ret = runtime.block_on(driver.download_url(
+                    prog,
+                    url.as_str(),
+                    !link_opt.link_absolute,
+                    PathBuf::from(link_opt.dest.as_str()),
+                ));
+                report_image_failure(driver, output);
+                ret.with_context(|| format!("failed to download {}", url))?;
+            }
             Self::Save => save_state(driver, output)?,
             Self::Command { file } => {
                 let job = prog.start_job("Running", format_args!("commands in {}", file));
@@ -104,14 +293,102 @@ impl Command {
                             .with_context(|| format!("{}:{}: erroneous quoting", file, idx + 1))?,
                     )
                     .with_context(|| format!("{}:{}: failed to parse command", file, idx + 1))?
-                    .run(runtime, driver, output, prog)?;
+                    .run(runtime, config, driver, output, prog)?;
                 }
                 job.finish("Completed", format_args!("running commands in {}", file,))
             }
             Self::Manifest { operation } => {
-                runtime.block_on(operation.run(prog, output, driver))?
+                let ret = runtime.block_on(operation.run(prog, output, driver));
+                report_image_failure(driver, output);
+                ret?
+            }
+            Self::Migrate { .. } => anyhow::bail!("migrate is not supported in repl or file"),
+            Self::Completions { .. } => {
+                anyhow::bail!("completions is not supported in repl or file")
+            }
+            Self::Convert { output_dir, format } => {
+                let format = format.or(config.format).unwrap_or(ConvertFormat::Markdown);
+                let job = prog.start_job("Converting", format_args!("store to {}", output_dir));
+                let report = driver
+                    .convert_store(&job, format, output_dir.as_str())
+                    .with_context(|| format!("failed to convert store to {}", output_dir))?;
+                job.finish(
+                    "Converted",
+                    format_args!(
+                        "{} items and {} comment threads to {}, skipped {} without content",
+                        report.converted, report.comments, output_dir, report.skipped
+                    ),
+                );
+            }
+            Self::Stats => {
+                let stats = driver
+                    .store
+                    .stats()
+                    .context("failed to collect store stats")?;
+                for (kind, num) in stats.objects {
+                    output.write_tagged(Color::Blue, kind, format_args_nl!("{:>8}", num));
+                }
+                output.write_tagged(
+                    Color::Blue,
+                    "image",
+                    format_args_nl!("{:>8} {}", stats.images, HumanBytes(stats.image_bytes)),
+                );
+                output.write_tagged(
+                    Color::Green,
+                    "Total",
+                    format_args_nl!(
+                        "{:>8} {}",
+                        "",
+                        HumanBytes(stats.store_bytes + stats.image_bytes)
+                    ),
+                );
+            }
+            Self::Check => {
+                let issues = driver.store.check();
+                let count = issues.len();
+                for i in issues {
+                    output.write_warn(format_args_nl!("{:?}", anyhow::Error::new(i)));
+                }
+                if count != 0 {
+                    anyhow::bail!("found {} inconsistencies in store", count);
+                }
+                output.write_tagged(
+                    Color::Green,
+                    "Checked",
+                    format_args_nl!("store, no issue found"),
+                );
+            }
+            Self::Gc { dry_run } => {
+                let report = driver
+                    .store
+                    .gc_images(dry_run)
+                    .context("failed to collect unreferenced images")?;
+                output.write_tagged(
+                    Color::Green,
+                    if dry_run { "Found" } else { "Collected" },
+                    format_args_nl!(
+                        "{} unreferenced of {} images, {}",
+                        report.deleted,
+                        report.scanned,
+                        HumanBytes(report.freed_bytes)
+                    ),
+                );
+            }
+            Self::StripRaw { dry_run } => {
+                let report = driver
+                    .store
+                    .strip_raw(dry_run)
+                    .context("failed to strip raw data")?;
+                output.write_tagged(
+                    Color::Green,
+                    if dry_run { "Found" } else { "Stripped" },
+                    format_args_nl!(
+                        "raw data of {} items, {}",
+                        report.stripped,
+                        HumanBytes(report.freed_bytes)
+                    ),
+                );
             }
-            Self::Migrate => anyhow::bail!("migrate is not supported in repl or file"),
             Self::Exit { force } => {
                 if driver.store.is_dirty() {
                     match save_state(driver, output) {
@@ -135,6 +412,23 @@ impl Command {
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
+enum LinkPolicy {
+    /// link to the redirect target
+    Unwrap,
+    /// keep the redirect url
+    Keep,
+    /// link to the redirect target and keep the redirect url
+    Both,
+}
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ProgressMode {
+    /// progress bars on stdout
+    Bar,
+    /// newline delimited json events on stderr
+    Json,
+}
+#[derive(Debug, Clone, Copy, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum Verbosity {
     Critical,
     Error,
@@ -147,16 +441,150 @@ enum Verbosity {
 #[derive(Debug, Parser)]
 #[command(name = "zhihu-dl", about, version)]
 struct Cli {
-    #[arg(long, default_value = ".store")]
-    store_path: String,
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    /// yaml file with defaults of options, zhihu-dl/config.yaml in XDG config directory by default
+    config: Option<String>,
+    #[arg(long)]
+    /// store directory, .store by default
+    store_path: Option<String>,
     #[arg(long, short)]
     verbosity: Option<Verbosity>,
+    #[arg(long, value_enum, default_value_t = ProgressMode::Bar)]
+    /// how progress is reported
+    progress: ProgressMode,
+    #[arg(long, short)]
+    /// report no progress and print only errors
+    quiet: bool,
+    #[arg(long)]
+    /// print errors in debug format rather than as a list of causes
+    verbose_errors: bool,
     #[arg(long)]
     /// don't init client on start
     no_init: bool,
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    /// write counts of stored items, fetched images and failures of the command as json
+    summary_json: Option<String>,
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    /// file keeping the client session between runs, session.json in store by default
+    session: Option<String>,
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    /// file keeping repl command history, history.txt in store by default
+    history: Option<String>,
+    #[arg(long)]
+    /// open an existing store without modifying it
+    read_only: bool,
+    #[arg(long, default_value_t = 0)]
+    /// bucket item directories by id prefix of this length when creating store
+    shard_prefix: usize,
+    #[arg(long)]
+    /// report what would be fetched and linked without touching network or store
+    dry_run: bool,
+    #[arg(long)]
+    /// maximum requests per second sent to the api
+    rate_limit: Option<f64>,
+    #[arg(long, default_value_t = 0)]
+    /// minimum delay between two api requests in milliseconds
+    request_delay: u64,
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    /// ron file with user_agent, accept_language and headers sent with every request
+    profile: Option<String>,
+    #[arg(long)]
+    /// user agent sent with every request, overrides the profile
+    user_agent: Option<String>,
+    #[arg(long)]
+    /// accept language sent with every request, overrides the profile
+    accept_language: Option<String>,
+    #[arg(long, value_parser = parse_header)]
+    /// extra header sent with every request as NAME=VALUE, can be repeated
+    header: Vec<(String, String)>,
+    #[arg(long)]
+    /// proxy of every request, like http://127.0.0.1:8080 or socks5h://127.0.0.1:1080,
+    /// HTTP_PROXY, HTTPS_PROXY and ALL_PROXY are used when no proxy is given
+    proxy: Option<String>,
+    #[arg(long)]
+    /// proxy of http requests, overrides `--proxy`
+    http_proxy: Option<String>,
+    #[arg(long)]
+    /// proxy of https requests, overrides `--proxy`
+    https_proxy: Option<String>,
+    #[arg(long)]
+    /// comma separated hosts connected without proxy, NO_PROXY by default
+    no_proxy: Option<String>,
+    #[arg(long)]
+    /// host images are fetched from, `*.domain` also matches subdomains, can be repeated,
+    /// *.zhimg.com and *.zhihu.com by default
+    image_host: Vec<String>,
+    #[arg(long)]
+    /// host images are never fetched from, can be repeated
+    block_image_host: Vec<String>,
+    #[arg(long)]
+    /// seconds an api request may take, 30 by default
+    timeout: Option<u64>,
+    #[arg(long)]
+    /// seconds connecting to a server may take, 10 by default
+    connect_timeout: Option<u64>,
+    #[arg(long)]
+    /// seconds an image request may take before it's retried, 60 by default
+    image_timeout: Option<u64>,
+    #[arg(long)]
+    /// don't store raw api responses with fetched items, parsed fields are kept
+    no_raw_data: bool,
+    #[arg(long, default_value_t = 1)]
+    /// number of container items fetched at the same time
+    concurrency: usize,
+    #[arg(long)]
+    /// number of images of an item fetched at the same time, 4 by default
+    image_concurrency: Option<usize>,
+    #[arg(long)]
+    /// map code block language hints to canonical names when converting html
+    normalize_code_language: bool,
+    #[arg(long, value_enum, default_value_t = LinkPolicy::Unwrap)]
+    /// how `link.zhihu.com` redirect links are converted
+    link_policy: LinkPolicy,
+    #[arg(long)]
+    /// drop trailing edit time footers when converting html
+    strip_edit_footer: bool,
+    #[arg(long)]
+    /// drop link cards to paid content and ads when converting html
+    strip_promotion: bool,
+    #[arg(long)]
+    /// collect notes into a numbered footnote list at the end of converted documents
+    aggregate_footnotes: bool,
     #[command(subcommand)]
     command: Option<Command>,
 }
+impl Cli {
+    fn store_path(&self) -> &str {
+        self.store_path.as_deref().unwrap_or(".store")
+    }
+    /// fill options not given on command line from config
+    fn merge(&mut self, config: &config::Config) {
+        fn or<T: Clone>(v: &mut Option<T>, c: &Option<T>) {
+            if v.is_none() {
+                v.clone_from(c);
+            }
+        }
+        or(&mut self.store_path, &config.store_path);
+        or(&mut self.verbosity, &config.verbosity);
+        or(&mut self.rate_limit, &config.rate_limit);
+        or(&mut self.profile, &config.profile);
+        or(&mut self.user_agent, &config.user_agent);
+        or(&mut self.proxy, &config.proxy);
+        or(&mut self.timeout, &config.timeout);
+        or(&mut self.connect_timeout, &config.connect_timeout);
+        or(&mut self.image_timeout, &config.image_timeout);
+        if config.keep_raw == Some(false) {
+            self.no_raw_data = true;
+        }
+        if self.image_host.is_empty() {
+            self.image_host.clone_from(&config.image_hosts);
+        }
+        if self.block_image_host.is_empty() {
+            self.block_image_host
+                .clone_from(&config.blocked_image_hosts);
+        }
+    }
+}
 
 struct LogDrain<D: slog::Drain> {
     progress_bar: indicatif::MultiProgress,
@@ -175,19 +603,50 @@ impl<D: slog::Drain> slog::Drain for LogDrain<D> {
 }
 
 fn run_cli(
-    reporter: &ProgressReporter,
+    reporter: &impl Reporter,
     output: &mut Output,
     cli: Cli,
+    config: config::Config,
 ) -> Result<(), anyhow::Error> {
-    if let Some(Command::Migrate) = cli.command {
-        store::Store::migrate(cli.store_path).context("failed to migrate store")?;
-        output.write_tagged(Color::Green, "Success", format_args_nl!("migrated store"));
+    if let Some(Command::Manifest { operation }) = &cli.command {
+        if let Some(r) = operation.run_offline(output) {
+            return r;
+        }
+    }
+    if let Some(Command::Completions { shell }) = cli.command {
+        // complete the command line interface rather than the multicall repl
+        clap_complete::generate(
+            shell,
+            &mut Cli::command(),
+            "zhihu-dl",
+            &mut std::io::stdout(),
+        );
+        return Ok(());
+    }
+    if let Some(Command::Migrate { shard_prefix }) = cli.command {
+        let job = reporter.start_job("Migrating", format_args!("store {}", cli.store_path()));
+        store::Store::migrate(
+            cli.store_path(),
+            shard_prefix.map(|shard_prefix| store::Layout { shard_prefix }),
+            &job,
+        )
+        .context("failed to migrate store")?;
+        job.finish("Migrated", format_args!("store {}", cli.store_path()));
         return Ok(());
     }
     let runtime = tokio::runtime::Runtime::new().context("failed to create runtime")?;
     let mut driver = {
-        let p = PathBuf::from(cli.store_path.as_str());
-        if p.exists() {
+        let p = PathBuf::from(cli.store_path());
+        if cli.read_only {
+            let d = Driver::open_read_only(p.as_path())
+                .with_context(|| format!("failed to open store as {}", p.display()))?;
+            output.write_tagged(
+                Color::Blue,
+                "Opened",
+                format_args_nl!("store at {} read only", p.display()),
+            );
+            d
+        } else if p.exists() {
             let d = Driver::open(p.as_path())
                 .with_context(|| format!("failed to open store as {}", p.display()))?;
             output.write_tagged(
@@ -197,8 +656,13 @@ fn run_cli(
             );
             d
         } else {
-            let d = Driver::create(p.as_path())
-                .with_context(|| format!("failed to create store at {}", p.display()))?;
+            let d = Driver::create_with_layout(
+                p.as_path(),
+                store::Layout {
+                    shard_prefix: cli.shard_prefix,
+                },
+            )
+            .with_context(|| format!("failed to create store at {}", p.display()))?;
             output.write_tagged(
                 Color::Blue,
                 "Created",
@@ -207,25 +671,138 @@ fn run_cli(
             d
         }
     };
-    if !cli.no_init {
-        runtime.block_on(init_driver(&mut driver, output))?;
+    driver.set_dry_run(cli.dry_run);
+    driver.set_rate_limit(RateLimit {
+        per_second: cli.rate_limit,
+        min_interval: std::time::Duration::from_millis(cli.request_delay),
+    });
+    driver.set_concurrency(cli.concurrency);
+    if let Some(c) = cli.image_concurrency {
+        driver.set_image_concurrency(c);
+    }
+    driver.set_keep_raw(!cli.no_raw_data);
+    if let Some(profile) = request_profile(&cli)? {
+        driver
+            .set_profile(profile)
+            .context("invalid request profile")?;
+    }
+    let proxy = request::ProxyConfig {
+        all: cli.proxy.clone(),
+        http: cli.http_proxy.clone(),
+        https: cli.https_proxy.clone(),
+        no_proxy: cli.no_proxy.clone(),
+    };
+    if !proxy.is_empty() {
+        driver.set_proxy(&proxy).context("invalid proxy")?;
+    }
+    if cli.timeout.is_some() || cli.connect_timeout.is_some() || cli.image_timeout.is_some() {
+        let default = request::Timeouts::default();
+        let secs = |v: Option<u64>, d| v.map_or(d, std::time::Duration::from_secs);
+        driver
+            .set_timeouts(request::Timeouts {
+                request: secs(cli.timeout, default.request),
+                connect: secs(cli.connect_timeout, default.connect),
+                image: secs(cli.image_timeout, default.image),
+            })
+            .context("failed to set timeouts")?;
+    }
+    driver.set_image_hosts(media::HostFilter {
+        allow: if cli.image_host.is_empty() {
+            request::DEFAULT_IMAGE_HOSTS
+                .iter()
+                .map(|h| h.to_string())
+                .collect()
+        } else {
+            cli.image_host.clone()
+        },
+        block: cli.block_image_host.clone(),
+    });
+    driver.set_convert_opt(ConvertOpt {
+        normalize_code_language: cli.normalize_code_language,
+        link_policy: match cli.link_policy {
+            LinkPolicy::Unwrap => content::LinkPolicy::Unwrap,
+            LinkPolicy::Keep => content::LinkPolicy::Keep,
+            LinkPolicy::Both => content::LinkPolicy::Both,
+        },
+        clean: content::clean::CleanConfig {
+            edit_footer: cli.strip_edit_footer,
+            promotion: cli.strip_promotion,
+        },
+        aggregate_footnotes: cli.aggregate_footnotes,
+    });
+    if let Some(s) = &cli.session {
+        driver
+            .set_session_path(s)
+            .with_context(|| format!("failed to restore session from {}", s))?;
+    }
+    if !cli.dry_run {
+        if driver.is_initialized() {
+            output.write_tagged(Color::Blue, "Restored", format_args_nl!("client session"));
+        } else if !cli.no_init {
+            runtime.block_on(init_driver(&mut driver, output))?;
+        }
     }
 
     if let Some(v) = cli.command {
-        let ret = v.run(&runtime, &mut driver, output, reporter);
-        save_state(&mut driver, output)?;
+        runtime.spawn(handle_interrupt(driver.cancel_token()));
+        let ret = v.run(&runtime, &config, &mut driver, output, reporter);
+        if ret.is_err() {
+            warn_rejected_session(&driver, output);
+        }
+        if !driver.is_read_only() && !driver.is_dry_run() {
+            save_state(&mut driver, output)?;
+        }
+        let transferred = driver.client.bytes_transferred();
+        if transferred > 0 {
+            output.write_tagged(
+                Color::Blue,
+                "Transferred",
+                format_args_nl!("{}", HumanBytes(transferred)),
+            );
+        }
+        if let Some(p) = &cli.summary_json {
+            if let Err(e) = write_summary(p, &driver, output, &ret) {
+                if ret.is_ok() {
+                    return Err(e);
+                }
+                output.write_error(e);
+            }
+        }
         return ret.map(|_| ());
     }
-    let mut editor = rustyline::Editor::<(), _>::with_history(
-        rustyline::Config::builder()
-            .auto_add_history(true)
-            .max_history_size(3000)
-            .unwrap()
-            .build(),
-        rustyline::history::MemHistory::new(),
+    // read only stores are left untouched unless a history file is given
+    let history = cli.history.as_ref().map(PathBuf::from).or_else(|| {
+        (!driver.is_read_only()).then(|| PathBuf::from(cli.store_path()).join(HISTORY_FILE))
+    });
+    let config = rustyline::Config::builder()
+        .auto_add_history(true)
+        .max_history_size(3000)
+        .unwrap()
+        .build();
+    let mut editor = rustyline::Editor::<complete::ReplHelper, _>::with_history(
+        config,
+        rustyline::history::FileHistory::with_config(config),
     )
     .context("failed to create editor")?;
+    editor.set_helper(Some(complete::ReplHelper::new(Command::repl())));
+    let history = history.filter(|p| match editor.load_history(p) {
+        Ok(_) => true,
+        Err(rustyline::error::ReadlineError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            true
+        }
+        Err(e) => {
+            output.write_warn(format_args_nl!(
+                "failed to load history from {}, keeping it in memory: {:?}",
+                p.display(),
+                anyhow::Error::new(e)
+            ));
+            false
+        }
+    });
     loop {
+        if let Some(h) = editor.helper_mut() {
+            h.set_ids(complete::stored_ids(driver.store.objects()));
+        }
         let input = match editor
             .readline("zhihu-dl > ")
             .map_err(anyhow::Error::new)
@@ -238,11 +815,12 @@ fn run_cli(
             }
         };
         match Command::parse_line(input) {
-            Ok(cmd) => match cmd.run(&runtime, &mut driver, output, reporter) {
+            Ok(cmd) => match cmd.run(&runtime, &config, &mut driver, output, reporter) {
                 Ok(true) => break,
                 Ok(false) => (),
                 Err(e) => {
                     output.write_error(e);
+                    warn_rejected_session(&driver, output);
                 }
             },
             Err(e) => output
@@ -250,19 +828,60 @@ fn run_cli(
                 .suspend(|| println!("{}", e.render().ansi())),
         }
     }
+    if let Some(p) = history {
+        if let Err(e) = editor.save_history(&p) {
+            output.write_warn(format_args_nl!(
+                "failed to save history to {}: {:?}",
+                p.display(),
+                anyhow::Error::new(e)
+            ));
+        }
+    }
     Ok(())
 }
 
 fn main() {
     use slog::Drain;
-    let cmd = Cli::parse();
-    let reporter = ProgressReporter::new(None);
+    let mut cmd = Cli::parse();
+    let quiet = cmd.quiet;
+    let reporter = match cmd.progress {
+        ProgressMode::Bar if !quiet => Some(ProgressReporter::new(match &cmd.command {
+            Some(Command::Manifest { operation }) => operation.job_count(),
+            _ => None,
+        })),
+        _ => None,
+    };
+    // logs and messages go to stdout, bars are hidden when quiet or reporting json to stderr
+    let multi_progress = reporter.as_ref().map_or_else(
+        || indicatif::MultiProgress::with_draw_target(indicatif::ProgressDrawTarget::hidden()),
+        |r| r.multi_progress.clone(),
+    );
+    let mut output = Output {
+        progress_bar: multi_progress.clone(),
+        buffer: BufferedStandardStream::stdout(if quiet {
+            termcolor::ColorChoice::Never
+        } else {
+            termcolor::ColorChoice::Auto
+        }),
+        quiet,
+        verbose_errors: cmd.verbose_errors,
+        summary: cmd.summary_json.is_some().then(RunSummary::default),
+    };
+    let config = match config::Config::load(cmd.config.as_deref()) {
+        Ok(c) => c,
+        Err(e) => {
+            output.write_error(e);
+            drop(reporter);
+            std::process::exit(1);
+        }
+    };
+    cmd.merge(&config);
 
     let log = slog::Logger::root(
         std::sync::Mutex::new({
             let mut lb = slog_envlogger::LogBuilder::new(
                 (LogDrain {
-                    progress_bar: reporter.multi_progress.clone(),
+                    progress_bar: multi_progress,
                     term: slog_term::FullFormat::new(
                         slog_term::TermDecorator::new().stdout().build(),
                     )
@@ -284,6 +903,8 @@ fn main() {
                         Verbosity::Debug => slog::FilterLevel::Debug,
                     },
                 );
+            } else if quiet {
+                lb = lb.filter(None, slog::FilterLevel::Error);
             } else {
                 lb = lb.filter(None, slog::FilterLevel::Warning);
             }
@@ -298,12 +919,14 @@ fn main() {
     let _scope_guard = slog_scope::set_global_logger(log);
     slog_stdlog::init().unwrap();
 
-    let mut output = Output {
-        progress_bar: reporter.multi_progress.clone(),
-        buffer: BufferedStandardStream::stdout(termcolor::ColorChoice::Auto),
+    let ret = match &reporter {
+        Some(r) => run_cli(r, &mut output, cmd, config),
+        None if quiet => run_cli(&Silent, &mut output, cmd, config),
+        None => run_cli(&JsonReporter, &mut output, cmd, config),
     };
-
-    if let Err(e) = run_cli(&reporter, &mut output, cmd) {
+    if let Err(e) = ret {
         output.write_error(e);
+        drop(reporter);
+        std::process::exit(1);
     }
 }
@@ -8,8 +8,11 @@ use std::{
 };
 use termcolor::Color;
 use zhihu_dl::{
-    driver::{manifest::Manifest, Driver},
-    progress::{progress_bar::ProgressReporter, OtherJob, Reporter},
+    driver::{
+        manifest::{leaf::ErrorPolicy, Manifest},
+        Driver,
+    },
+    progress::{OtherJob, Reporter},
 };
 
 #[derive(Debug, Subcommand)]
@@ -17,6 +20,9 @@ pub enum ManifestCmd {
     Apply {
         #[arg(default_value = "manifest.ron")]
         path: String,
+        #[arg(long)]
+        /// record failed items and continue, reporting them at the end
+        keep_going: bool,
     },
     Format {
         #[arg(default_value = "manifest.ron")]
@@ -25,11 +31,29 @@ pub enum ManifestCmd {
     Update {
         #[arg(default_value = "manifest.ron")]
         path: String,
+        #[arg(long)]
+        /// record failed items and continue, reporting them at the end
+        keep_going: bool,
     },
     Link {
         #[arg(default_value = "manifest.ron")]
         path: String,
     },
+    /// write a manifest reproducing items in store, fails if the file exists
+    Export { path: String },
+    /// check manifest syntax without opening store
+    Validate {
+        #[arg(default_value = "manifest.ron")]
+        path: String,
+    },
+    /// print operations of applying manifest without opening store
+    Plan {
+        #[arg(default_value = "manifest.ron")]
+        path: String,
+        #[arg(long)]
+        /// plan an update instead of applying missing items
+        update: bool,
+    },
 }
 
 fn load_manif(path: &String) -> anyhow::Result<Manifest> {
@@ -41,14 +65,96 @@ fn load_manif(path: &String) -> anyhow::Result<Manifest> {
 fn current_dir() -> anyhow::Result<PathBuf> {
     std::env::current_dir().context("failed to get current directory")
 }
+/// parse manifest, reporting errors with the offending line
+fn validate_manif(path: &String) -> anyhow::Result<Manifest> {
+    let text =
+        fs::read_to_string(path.as_str()).with_context(|| format!("failed to read {}", path))?;
+    ron::from_str(text.as_str()).map_err(|e| {
+        let line = text
+            .lines()
+            .nth(e.position.line.saturating_sub(1))
+            .unwrap_or("");
+        anyhow::anyhow!(
+            "{}:{}:{}: {}\n{}\n{:>col$}",
+            path,
+            e.position.line,
+            e.position.col,
+            e.code,
+            line,
+            "^",
+            col = e.position.col.max(1)
+        )
+    })
+}
+/// print items failed with [`ErrorPolicy::Continue`], fails if there is any
+pub(crate) fn report_failures(output: &mut Output, driver: &mut Driver) -> anyhow::Result<()> {
+    let failures = driver.take_failures();
+    let count = failures.len();
+    for f in failures {
+        let error = anyhow::Error::new(f.error);
+        if let Some(s) = &mut output.summary {
+            s.failures.push(format!("{} {}: {:#}", f.kind, f.id, error));
+        }
+        output.write_warn(format_args_nl!("failed {} {}: {:?}", f.kind, f.id, error));
+    }
+    if count != 0 {
+        anyhow::bail!("{} items failed", count);
+    }
+    Ok(())
+}
 impl ManifestCmd {
+    /// run commands reading only the manifest, which need neither store nor client
+    pub fn run_offline(&self, output: &mut Output) -> Option<anyhow::Result<()>> {
+        match self {
+            Self::Validate { path } => Some(validate_manif(path).map(|_| {
+                output.write_tagged(
+                    Color::Green,
+                    "Validated",
+                    format_args_nl!("manifest {}", path),
+                )
+            })),
+            Self::Plan { path, update } => Some((|| {
+                for s in validate_manif(path)?
+                    .plan(current_dir()?, *update)
+                    .context("failed to load applied leaves")?
+                {
+                    output.write_tagged(Color::Yellow, "Would", format_args_nl!("{}", s));
+                }
+                Ok(())
+            })()),
+            _ => None,
+        }
+    }
+    /// number of top level jobs, for the overall progress bar
+    pub fn job_count(&self) -> Option<u64> {
+        let (path, update) = match self {
+            Self::Apply { path, .. } => (path, false),
+            Self::Update { path, .. } => (path, true),
+            _ => return None,
+        };
+        load_manif(path)
+            .ok()?
+            .job_count(current_dir().ok()?, update)
+            .ok()
+    }
     pub async fn run(
         self,
-        reporter: &ProgressReporter,
+        reporter: &impl Reporter,
         output: &mut Output,
         driver: &mut Driver,
     ) -> Result<(), anyhow::Error> {
+        if let Some(r) = self.run_offline(output) {
+            return r;
+        }
+        if let Self::Apply { keep_going, .. } | Self::Update { keep_going, .. } = &self {
+            driver.set_error_policy(if *keep_going {
+                ErrorPolicy::Continue
+            } else {
+                ErrorPolicy::FailFast
+            });
+        }
         match self {
+            Self::Validate { .. } | Self::Plan { .. } => unreachable!(),
             Self::Format { path } => {
                 let mut file = fs::OpenOptions::new()
                     .read(true)
@@ -72,21 +178,41 @@ impl ManifestCmd {
                     format_args_nl!("ron manifest {}", path),
                 );
             }
-            Self::Apply { path } => {
+            Self::Apply { path, .. } => {
                 let job = reporter.start_job("Applying", format_args!("manifest {}", path));
                 driver
                     .apply_manifest(reporter, &load_manif(&path)?, current_dir()?)
                     .await
                     .context("failed to apply manifest")?;
                 job.finish("Applied", format_args!("manifest {}", path,));
+                report_failures(output, driver)?;
             }
-            Self::Update { path } => {
+            Self::Update { path, .. } => {
                 let job = reporter.start_job("Updating", format_args!("manifest {}", path));
                 driver
                     .update_manifest(reporter, &load_manif(&path)?, current_dir()?)
                     .await
                     .context("failed to update manifest")?;
                 job.finish("Updated", format_args!("manifest {}", path));
+                report_failures(output, driver)?;
+            }
+            Self::Export { path } => {
+                let file = fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(path.as_str())
+                    .with_context(|| format!("failed to create file {}", path))?;
+                ron::ser::to_writer_pretty(
+                    io::BufWriter::new(&file),
+                    &Manifest::Leaf(driver.export_manifest()),
+                    ron::ser::PrettyConfig::default(),
+                )
+                .context("failed to serialize ron")?;
+                output.write_tagged(
+                    Color::Green,
+                    "Exported",
+                    format_args_nl!("store to manifest {}", path),
+                );
             }
             Self::Link { path } => {
                 let job = reporter.start_job(
@@ -1,25 +1,69 @@
+use chrono::{FixedOffset, NaiveDate, TimeZone};
 use clap::Args;
-use std::{fmt, io::Write};
+use serde::Serialize;
+use std::{
+    fmt,
+    io::{self, Write},
+};
 use termcolor::{BufferedStandardStream, Color, ColorSpec, WriteColor};
 use web_dl_base::id::{HasId, OwnedId};
-use zhihu_dl::item::{
-    answer::{Answer, AnswerId},
-    article::{Article, ArticleId},
-    collection::{Collection, CollectionId},
-    column::{Column, ColumnRef},
-    pin::{Pin, PinId},
-    question::{Question, QuestionId},
-    user::{self, User, UserId},
-    Comment, CommentId,
+use zhihu_dl::{
+    driver::DateFilter,
+    item::{
+        answer::{Answer, AnswerId, AnswerSpec},
+        article::{Article, ArticleId},
+        collection::{Collection, CollectionId},
+        column::{Column, ColumnRef},
+        pin::{Pin, PinId},
+        question::{Question, QuestionId},
+        search::{self, Search},
+        topic::{Topic, TopicId},
+        user::{self, User, UserId},
+        Comment, CommentId,
+    },
 };
 
 pub struct Output {
     pub progress_bar: indicatif::MultiProgress,
     pub buffer: BufferedStandardStream,
+    /// print errors only
+    pub quiet: bool,
+    /// print errors with debug format instead of a list of causes
+    pub verbose_errors: bool,
+    /// collected for `--summary-json`
+    pub summary: Option<RunSummary>,
+}
+/// result of a command run written by `--summary-json`
+#[derive(Debug, Default, Serialize)]
+pub struct RunSummary {
+    pub success: bool,
+    /// items stored
+    pub items: usize,
+    /// containers updated
+    pub containers: usize,
+    pub images: usize,
+    pub images_fetched: usize,
+    /// size of fetched images
+    pub image_bytes: u64,
+    /// size of api and image responses
+    pub bytes_transferred: u64,
+    pub failures: Vec<String>,
+}
+/// error message followed by dimmed causes, one per line
+fn render_error<W: WriteColor>(w: &mut W, error: &anyhow::Error) -> io::Result<()> {
+    writeln!(w, "{}", error)?;
+    w.set_color(ColorSpec::new().set_dimmed(true))?;
+    for cause in error.chain().skip(1) {
+        writeln!(w, "  caused by: {}", cause)?;
+    }
+    w.reset()
 }
 #[allow(unused_must_use)]
 impl Output {
     pub fn write_tagged(&mut self, color: Color, tag: &str, fmt: fmt::Arguments<'_>) {
+        if self.quiet {
+            return;
+        }
         self.progress_bar.suspend(|| {
             self.buffer.set_color(ColorSpec::new().set_fg(Some(color)));
             self.buffer.write_fmt(format_args!("{:>13} ", tag));
@@ -34,11 +78,18 @@ impl Output {
                 .set_color(ColorSpec::new().set_fg(Some(Color::Red)));
             self.buffer.write(b"error: ");
             self.buffer.reset();
-            writeln!(&mut self.buffer, "{:?}", error);
+            if self.verbose_errors {
+                writeln!(&mut self.buffer, "{:?}", error);
+            } else {
+                render_error(&mut self.buffer, &error);
+            }
             self.buffer.flush();
         })
     }
     pub fn write_warn(&mut self, fmt: fmt::Arguments<'_>) {
+        if self.quiet {
+            return;
+        }
         self.progress_bar.suspend(|| {
             self.buffer
                 .set_color(ColorSpec::new().set_fg(Some(Color::Yellow)));
@@ -58,6 +109,30 @@ pub struct LinkOpt {
     pub dest: String,
 }
 
+#[derive(Debug, Args)]
+pub struct DateRange {
+    #[arg(long)]
+    /// skip items last updated before the date, as yyyy-mm-dd
+    pub since: Option<NaiveDate>,
+    #[arg(long)]
+    /// skip items created after the date, as yyyy-mm-dd
+    pub until: Option<NaiveDate>,
+}
+impl DateRange {
+    /// whole days in zhihu's timezone
+    pub fn to_filter(&self) -> DateFilter {
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        let start = |d: NaiveDate| {
+            tz.from_local_datetime(&d.and_hms_opt(0, 0, 0).unwrap())
+                .unwrap()
+        };
+        DateFilter {
+            since: self.since.map(start),
+            until: self.until.and_then(|d| d.succ_opt()).map(start),
+        }
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct UserSpec {
     #[arg(long)]
@@ -101,12 +176,29 @@ impl OwnedId<Pin> for NumId {
         PinId(self.id)
     }
 }
+impl OwnedId<Topic> for NumId {
+    fn to_id(&self) -> <Topic as HasId>::Id<'_> {
+        TopicId(self.id)
+    }
+}
 impl OwnedId<Comment> for NumId {
     fn to_id(&self) -> <Comment as HasId>::Id<'_> {
         CommentId(self.id)
     }
 }
 
+#[derive(Debug, Args)]
+pub struct AnswerArg {
+    /// answer id, or question and answer id as `<question>/<answer>`
+    #[arg(long)]
+    pub id: AnswerSpec,
+}
+impl OwnedId<Answer> for AnswerArg {
+    fn to_id(&self) -> <Answer as HasId>::Id<'_> {
+        self.id.answer
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct StrId {
     #[arg(long)]
@@ -117,3 +209,45 @@ impl OwnedId<Column> for StrId {
         ColumnRef(self.id.as_str())
     }
 }
+
+#[derive(Debug, Args)]
+pub struct SearchQuery {
+    #[arg(long, value_parser = parse_query)]
+    /// search keywords
+    pub query: String,
+}
+/// queries are used as directory names in store
+fn parse_query(s: &str) -> Result<String, String> {
+    if s.trim().is_empty() {
+        Err("query is empty".to_owned())
+    } else if s.chars().all(|c| c == '.') {
+        Err(format!("invalid query {}", s))
+    } else {
+        Ok(s.to_owned())
+    }
+}
+impl OwnedId<Search> for SearchQuery {
+    fn to_id(&self) -> <Search as HasId>::Id<'_> {
+        search::Query(self.query.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn render_error() {
+        let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "no such file"))
+            .context("failed to open file a")
+            .context("failed to load manifest")
+            .unwrap_err();
+        let mut buffer = termcolor::Buffer::no_color();
+        super::render_error(&mut buffer, &error).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer.into_inner()).unwrap(),
+            "failed to load manifest\n  caused by: failed to open file a\n  caused by: no such file\n"
+        );
+    }
+}
@@ -56,7 +56,7 @@ fn gen_convert<M>(spec: &[FieldInfo<M>]) -> TokenStream {
     let mut ret = TokenStream::new();
     for i in spec {
         let expr = &i.expr;
-        ret.extend(quote! {#expr.convert_html();});
+        ret.extend(quote! {#expr.convert_html(opt);});
     }
     ret
 }
@@ -87,7 +87,8 @@ fn gen_impl(
             fn is_html_converted(&self) -> bool {
                 #has_convert
             }
-            fn convert_html(&mut self) {
+            #[allow(unused_variables)]
+            fn convert_html(&mut self, opt: &crate::element::content::ConvertOpt) {
                 #convert
             }
             fn get_main_content(&self) -> Option<&'_ crate::element::content::Content> {
@@ -1,47 +1,235 @@
 use crate::{
-    request::Client,
-    store::{Store, StoreError},
+    element::content::{convertor::ConvertFormat, ConvertOpt},
+    request::{
+        Client, Profile, ProfileError, ProxyConfig, ProxyError, RateLimit, SessionError, Timeouts,
+    },
+    store::{Layout, Store, StoreError},
 };
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use web_dl_base::media::{HostFilter, ImageBackend};
 
 pub mod manifest;
 
+const SESSION_FILE: &str = "session.json";
+
 pub struct Driver {
     pub client: Client,
     pub store: Store,
     initialized: bool,
+    dry_run: bool,
+    concurrency: usize,
+    convert_opt: ConvertOpt,
+    session: Option<PathBuf>,
+    error_policy: manifest::leaf::ErrorPolicy,
+    failures: Vec<manifest::leaf::Failure>,
+    date_filter: container::DateFilter,
+    output_format: Option<ConvertFormat>,
+    stat: RunStat,
+    cancel: CancelToken,
+}
+
+/// stops container and manifest loops between items once cancelled, shared across threads
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// items and containers stored since the driver is created
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RunStat {
+    pub items: usize,
+    pub containers: usize,
 }
 
 pub mod item;
-pub use item::ItemError;
+pub use item::{ItemError, EXPORT_IMAGE_DIR};
 
 pub mod container;
-pub use container::ContainerError;
+pub use container::{ContainerError, DateFilter};
+
+pub mod url;
+pub use url::{UrlError, UrlTarget};
+
+pub mod convert;
+pub use convert::{ConvertError, ConvertReport};
+
+pub mod builder;
+pub use builder::{BuildError, DriverBuilder};
 
 impl Driver {
-    pub fn create<P: AsRef<Path>>(store_path: P) -> Result<Self, StoreError> {
-        Ok(Self {
-            client: Client::new(),
-            store: Store::create(store_path)?,
+    fn with_store(store: Store) -> Self {
+        let mut client = Client::new();
+        if !store.is_read_only() {
+            client.set_image_store(store.image_backend().as_fs().cloned());
+        }
+        Self {
+            client,
+            store,
             initialized: false,
-        })
+            dry_run: false,
+            concurrency: 1,
+            convert_opt: ConvertOpt::default(),
+            session: None,
+            error_policy: Default::default(),
+            failures: Vec::new(),
+            date_filter: Default::default(),
+            output_format: None,
+            stat: Default::default(),
+            cancel: Default::default(),
+        }
+        .with_session()
+    }
+    /// configure a driver without the command line interface
+    pub fn builder() -> DriverBuilder {
+        DriverBuilder::default()
+    }
+    pub fn create<P: AsRef<Path>>(store_path: P) -> Result<Self, StoreError> {
+        Ok(Self::with_store(Store::create(store_path)?))
+    }
+    pub fn create_with_layout<P: AsRef<Path>>(
+        store_path: P,
+        layout: Layout,
+    ) -> Result<Self, StoreError> {
+        Ok(Self::with_store(Store::create_with_layout(
+            store_path, layout,
+        )?))
     }
     pub fn open<P: AsRef<Path>>(store_path: P) -> Result<Self, StoreError> {
-        Ok(Self {
-            client: Client::new(),
-            store: Store::open(store_path)?,
-            initialized: false,
-        })
+        Ok(Self::with_store(Store::open(store_path)?))
+    }
+    /// open store read only, see [`Store::open_read_only`]
+    pub fn open_read_only<P: AsRef<Path>>(store_path: P) -> Result<Self, StoreError> {
+        Ok(Self::with_store(Store::open_read_only(store_path)?))
+    }
+    pub fn is_read_only(&self) -> bool {
+        self.store.is_read_only()
     }
+    /// save store state, and the client session once initialized,
+    /// a rejected session is removed so that the next run starts a new one
     pub fn save(&mut self) -> Result<(), StoreError> {
-        self.store.save()
+        self.store.save()?;
+        match &self.session {
+            Some(p) if self.client.is_session_rejected() => {
+                let _ = std::fs::remove_file(p);
+            }
+            Some(p) if self.initialized && !self.dry_run => {
+                if let Err(e) = self.client.save_session(p) {
+                    log::warn!("failed to save session: {:?}", anyhow::Error::new(e));
+                }
+            }
+            _ => (),
+        }
+        Ok(())
     }
     pub async fn init(&mut self) -> Result<(), reqwest::Error> {
         self.client.init().await?;
         self.initialized = true;
         Ok(())
     }
+    /// dry run never touches the network
     pub fn is_initialized(&self) -> bool {
-        self.initialized
+        self.initialized || self.dry_run
+    }
+    /// restore the session kept in store, failing to do so leaves the client uninitialized
+    fn with_session(mut self) -> Self {
+        let path = self.store.root().join(SESSION_FILE);
+        if let Err(e) = self.set_session_path(path) {
+            log::warn!("failed to restore session: {:?}", anyhow::Error::new(e));
+        }
+        self
+    }
+    /// keep the client session in `path` on [`Driver::save`], restoring it if the file exists
+    pub fn set_session_path<P: Into<PathBuf>>(&mut self, path: P) -> Result<(), SessionError> {
+        let path = self.session.insert(path.into());
+        if path.exists() {
+            self.client.load_session(path)?;
+            self.initialized = true;
+        }
+        Ok(())
+    }
+    /// a restored session was refused by the server and [`Driver::init`] should be run again
+    pub fn is_session_rejected(&self) -> bool {
+        self.client.is_session_rejected()
+    }
+    /// report planned fetches and links through the reporter instead of performing them
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+    pub fn set_rate_limit(&mut self, limit: RateLimit) {
+        self.client.set_rate_limit(limit);
+    }
+    /// headers sent with every request, see [`Client::set_profile`]
+    pub fn set_profile(&mut self, profile: Profile) -> Result<(), ProfileError> {
+        self.client.set_profile(profile)
+    }
+    /// hosts images are fetched from, see [`Client::set_image_hosts`]
+    pub fn set_image_hosts(&mut self, hosts: HostFilter) {
+        self.client.set_image_hosts(hosts);
+    }
+    /// images of an item fetched at the same time, see [`Client::set_image_concurrency`]
+    pub fn set_image_concurrency(&mut self, concurrency: usize) {
+        self.client.set_image_concurrency(concurrency);
+    }
+    /// proxies of every request, see [`Client::set_proxy`]
+    pub fn set_proxy(&mut self, proxy: &ProxyConfig) -> Result<(), ProxyError> {
+        self.client.set_proxy(proxy)
+    }
+    /// timeouts of api and image requests, see [`Client::set_timeouts`]
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) -> Result<(), reqwest::Error> {
+        self.client.set_timeouts(timeouts)
+    }
+    /// replace the image backend of the store, fetched images are streamed into
+    /// filesystem backends only, see [`Store::set_image_backend`]
+    pub fn set_image_backend<B: ImageBackend + 'static>(&mut self, backend: B) {
+        self.client.set_image_store(backend.as_fs().cloned());
+        self.store.set_image_backend(backend);
+    }
+    /// store raw api responses with fetched items, see [`Store::set_keep_raw`]
+    pub fn set_keep_raw(&mut self, keep_raw: bool) {
+        self.store.set_keep_raw(keep_raw);
+    }
+    /// maximum number of items of a container processed at the same time, at least 1
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency.max(1);
+    }
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+    /// options of converting raw html of fetched items
+    pub fn set_convert_opt(&mut self, opt: ConvertOpt) {
+        self.convert_opt = opt;
+    }
+    pub fn convert_opt(&self) -> &ConvertOpt {
+        &self.convert_opt
+    }
+    /// also render items linked by [`Driver::download_item`] next to the link
+    pub fn set_output_format(&mut self, format: Option<ConvertFormat>) {
+        self.output_format = format;
+    }
+    /// only store container items in the date range, other items are skipped
+    pub fn set_date_filter(&mut self, filter: container::DateFilter) {
+        self.date_filter = filter;
+    }
+    pub fn stat(&self) -> RunStat {
+        self.stat
+    }
+    /// token interrupting running operations of the driver, they fail with a cancelled error
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
     }
 }
@@ -0,0 +1,184 @@
+use super::{manifest::leaf::ErrorPolicy, Driver};
+use crate::{
+    element::content::ConvertOpt,
+    request::{Profile, ProfileError, ProxyConfig, ProxyError, RateLimit, SessionError, Timeouts},
+    store::{Layout, Store, StoreError},
+};
+use std::path::PathBuf;
+use web_dl_base::media::HostFilter;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BuildError {
+    #[error("store path is not set")]
+    NoStore,
+    #[error("failed to open store at {}", path.display())]
+    Store {
+        path: PathBuf,
+        #[source]
+        source: StoreError,
+    },
+    #[error("invalid request profile")]
+    Profile(#[source] ProfileError),
+    #[error("invalid proxy")]
+    Proxy(#[source] ProxyError),
+    #[error("failed to set timeouts")]
+    Timeouts(#[source] reqwest::Error),
+    #[error("failed to restore session from {}", path.display())]
+    Session {
+        path: PathBuf,
+        #[source]
+        source: SessionError,
+    },
+    #[error("failed to init client")]
+    Init(#[source] reqwest::Error),
+}
+
+/// options of a [`Driver`] applied by [`DriverBuilder::build`], unset ones keep the
+/// defaults of the driver
+///
+/// progress is reported per operation, pass any [`crate::progress::Reporter`] like
+/// [`crate::progress::silent::Silent`] to driver methods
+#[derive(Debug, Default)]
+pub struct DriverBuilder {
+    store: Option<PathBuf>,
+    read_only: bool,
+    layout: Layout,
+    session: Option<PathBuf>,
+    skip_init: bool,
+    dry_run: bool,
+    concurrency: Option<usize>,
+    rate_limit: Option<RateLimit>,
+    profile: Option<Profile>,
+    proxy: Option<ProxyConfig>,
+    timeouts: Option<Timeouts>,
+    image_hosts: Option<HostFilter>,
+    image_concurrency: Option<usize>,
+    convert_opt: Option<ConvertOpt>,
+    error_policy: ErrorPolicy,
+    keep_raw: Option<bool>,
+}
+impl DriverBuilder {
+    /// store opened, or created when it doesn't exist
+    pub fn store<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.store = Some(path.into());
+        self
+    }
+    /// open an existing store without modifying it
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+    /// layout of a newly created store
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+    /// session file used instead of the one in store, see [`Driver::set_session_path`]
+    pub fn session<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.session = Some(path.into());
+        self
+    }
+    /// don't init the client when no session is restored
+    pub fn skip_init(mut self, skip: bool) -> Self {
+        self.skip_init = skip;
+        self
+    }
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = Some(timeouts);
+        self
+    }
+    pub fn image_hosts(mut self, hosts: HostFilter) -> Self {
+        self.image_hosts = Some(hosts);
+        self
+    }
+    pub fn image_concurrency(mut self, concurrency: usize) -> Self {
+        self.image_concurrency = Some(concurrency);
+        self
+    }
+    pub fn convert_opt(mut self, opt: ConvertOpt) -> Self {
+        self.convert_opt = Some(opt);
+        self
+    }
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+    pub fn keep_raw(mut self, keep_raw: bool) -> Self {
+        self.keep_raw = Some(keep_raw);
+        self
+    }
+    /// open the store, configure the driver and init its client unless a session is restored
+    pub async fn build(self) -> Result<Driver, BuildError> {
+        let path = self.store.ok_or(BuildError::NoStore)?;
+        let store = if self.read_only {
+            Store::open_read_only(&path)
+        } else if path.exists() {
+            Store::open(&path)
+        } else {
+            Store::create_with_layout(&path, self.layout)
+        }
+        .map_err(|e| BuildError::Store {
+            path: path.clone(),
+            source: e,
+        })?;
+        let mut driver = Driver::with_store(store);
+        driver.set_dry_run(self.dry_run);
+        driver.set_error_policy(self.error_policy);
+        if let Some(c) = self.concurrency {
+            driver.set_concurrency(c);
+        }
+        if let Some(l) = self.rate_limit {
+            driver.set_rate_limit(l);
+        }
+        if let Some(p) = self.profile {
+            driver.set_profile(p).map_err(BuildError::Profile)?;
+        }
+        if let Some(p) = &self.proxy {
+            driver.set_proxy(p).map_err(BuildError::Proxy)?;
+        }
+        if let Some(t) = self.timeouts {
+            driver.set_timeouts(t).map_err(BuildError::Timeouts)?;
+        }
+        if let Some(h) = self.image_hosts {
+            driver.set_image_hosts(h);
+        }
+        if let Some(c) = self.image_concurrency {
+            driver.set_image_concurrency(c);
+        }
+        if let Some(o) = self.convert_opt {
+            driver.set_convert_opt(o);
+        }
+        if let Some(k) = self.keep_raw {
+            driver.set_keep_raw(k);
+        }
+        if let Some(s) = self.session {
+            driver
+                .set_session_path(s.clone())
+                .map_err(|e| BuildError::Session { path: s, source: e })?;
+        }
+        if !self.skip_init && !driver.is_initialized() {
+            driver.init().await.map_err(BuildError::Init)?;
+        }
+        Ok(driver)
+    }
+}
@@ -1,11 +1,18 @@
-use super::{Driver, ItemError};
+use super::{
+    manifest::leaf::{self, ErrorPolicy, Failure},
+    Driver, ItemError,
+};
 use crate::{
     item::{Item, ItemContainer},
     progress::{self, ContainerJob},
     store::{self, ContainerHandle},
     util::relative_path::{link_to_dest, prepare_dest, DestPrepError, LinkError},
 };
-use std::path::{Path, PathBuf};
+use chrono::{DateTime, FixedOffset};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 use web_dl_base::id::HasId;
 
 #[derive(Debug, thiserror::Error)]
@@ -53,6 +60,29 @@ pub enum ContainerError {
         #[source]
         source: LinkError,
     },
+    #[error("interrupted, items stored so far are kept")]
+    Cancelled,
+}
+
+/// number of newly stored items between two checkpoints of a container update
+const CHECKPOINT_INTERVAL: usize = 50;
+
+/// items of containers kept when fetching, by [`Item::timestamps`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateFilter {
+    /// drop items last updated before this
+    pub since: Option<DateTime<FixedOffset>>,
+    /// drop items created at or after this
+    pub until: Option<DateTime<FixedOffset>>,
+}
+impl DateFilter {
+    /// items without timestamps are always kept
+    pub fn contains<I: Item>(&self, item: &I) -> bool {
+        let Some((created, updated)) = item.timestamps() else {
+            return true;
+        };
+        self.since.map_or(true, |s| updated >= s) && self.until.map_or(true, |u| created < u)
+    }
 }
 
 #[derive(Debug)]
@@ -62,6 +92,10 @@ pub struct ContainerItem<I> {
 }
 
 impl Driver {
+    /// failed items are recorded and skipped rather than failing the container
+    fn keep_going(&self) -> bool {
+        self.error_policy == ErrorPolicy::Continue && !self.cancel.is_cancelled()
+    }
     pub(super) async fn fetch_container<'a, IC, I, O, P>(
         &self,
         prog: &P,
@@ -83,10 +117,33 @@ impl Driver {
                 IC::parse_item(i).map_err(ContainerError::from)
             }))
     }
+    /// persist items stored so far, so an interrupted update can be resumed
+    fn checkpoint_container<IC, I, O>(
+        store: &mut store::Store,
+        id: IC::Id<'_>,
+        items: &[ContainerItem<I>],
+    ) -> Result<(), store::StoreError>
+    where
+        I: Item,
+        IC: ItemContainer<O, I>,
+    {
+        log::debug!("checkpoint {} {} ({})", IC::TYPE, id, IC::OPTION_NAME);
+        {
+            let mut container = IC::save_data(id, store)?;
+            for i in items {
+                container.link_item(i.value.id())?;
+            }
+            container.checkpoint()?;
+        }
+        store.save()
+    }
+    /// with `incremental`, paging stops at the first page listing an item already in the
+    /// stored container, items not seen then are kept rather than marked missing
     async fn update_container_impl<'a, IC, I, O, P>(
         &mut self,
         prog: &P,
         id: IC::Id<'_>,
+        incremental: bool,
     ) -> Result<(Vec<ContainerItem<I>>, Option<PathBuf>), ContainerError>
     where
         I: Item,
@@ -100,50 +157,176 @@ impl Driver {
             id,
             IC::OPTION_NAME
         );
-        let dat = self.fetch_container::<IC, I, O, _>(prog, id).await?;
+        if incremental && IC::in_store(id, &self.store) {
+            let known = IC::stored_ids(id, &self.store)?;
+            self.client.set_paging_stop(
+                |d| IC::parse_item(d.clone()).ok().map(|i| i.id().to_string()),
+                known,
+            );
+        }
+        let dat = self.fetch_container::<IC, I, O, _>(prog, id).await;
+        let partial = self.client.take_paging_stop();
+        let dat = dat?;
+        if self.cancel.is_cancelled() {
+            return Err(ContainerError::Cancelled);
+        }
+        if partial {
+            log::info!(
+                "stopped fetching {} {} ({}) at stored items",
+                IC::TYPE,
+                id,
+                IC::OPTION_NAME
+            );
+        }
+        self.store_container_items::<IC, I, O, _>(prog, id, dat, partial)
+            .await
+    }
+    /// store fetched items of the container, items already in store are skipped so that
+    /// an interrupted update resumes from its last checkpoint
+    async fn store_container_items<'a, IC, I, O, P>(
+        &mut self,
+        prog: &P,
+        id: IC::Id<'_>,
+        dat: impl Iterator<Item = Result<I, ContainerError>> + ExactSizeIterator,
+        partial: bool,
+    ) -> Result<(Vec<ContainerItem<I>>, Option<PathBuf>), ContainerError>
+    where
+        I: Item,
+        IC: ItemContainer<O, I>,
+        P: progress::ItemContainerProg,
+    {
+        use futures::stream::{self, StreamExt};
+        use progress::ItemsProg;
         let mut ret = Vec::with_capacity(dat.len());
+        // items dropped by date filter are not missing on the server
+        let mut filtered = false;
+        // neither are items skipped by the error policy
+        let mut failed = false;
         {
-            let mut p = prog.start_items(dat.len() as u64);
+            let p = prog.start_items(dat.len() as u64);
+            // decide which items to fetch before any of them is stored,
+            // repeated items are fetched only once
+            let mut seen = HashSet::new();
+            let mut pending = Vec::with_capacity(dat.len());
             for item in dat {
-                use progress::ItemsProg;
-                let mut item = item?;
-                if I::in_store(item.id(), &self.store).in_store {
-                    ret.push(ContainerItem {
-                        processed: false,
-                        value: item,
-                    });
+                let item = match item {
+                    Ok(i) => i,
+                    Err(e) if self.keep_going() => {
+                        log::warn!(
+                            "skipped unparsable {} in {} {}: {}",
+                            I::TYPE,
+                            IC::TYPE,
+                            id,
+                            e
+                        );
+                        self.failures.push(Failure {
+                            kind: IC::TYPE,
+                            id: id.to_string(),
+                            error: leaf::Error::Container {
+                                item_kind: I::TYPE,
+                                id: id.to_string(),
+                                kind: IC::TYPE,
+                                option: IC::OPTION_NAME,
+                                source: e,
+                            },
+                        });
+                        p.skip_item();
+                        failed = true;
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                if !self.date_filter.contains(&item) {
+                    log::info!("skip {} {} out of date range", I::TYPE, item.id());
                     p.skip_item();
+                    filtered = true;
+                    continue;
+                }
+                // also skips items found deleted by an earlier run
+                let info = I::in_store(item.id(), &self.store);
+                let fetch = !info.in_store && seen.insert(item.id().to_string());
+                pending.push((fetch, item));
+            }
+            let (client, convert_opt, p) = (&self.client, &self.convert_opt, &p);
+            let mut processed = stream::iter(pending)
+                .map(|(fetch, mut item)| async move {
+                    if fetch {
+                        let i_p = p.start_item(I::TYPE, item.id());
+                        Self::process_item(client, convert_opt, &i_p, &mut item).await;
+                    } else {
+                        p.skip_item();
+                    }
+                    ContainerItem {
+                        processed: fetch,
+                        value: item,
+                    }
+                })
+                .buffer_unordered(self.concurrency);
+            let (mut linked, mut stored) = (0, 0);
+            while let Some(item) = processed.next().await {
+                if !item.processed {
+                    ret.push(item);
                     continue;
                 }
-                let i_p = p.start_item(I::TYPE, item.id());
-                self.process_item(&i_p, &mut item).await;
-                log::info!("add {} {} to store", I::TYPE, item.id());
-                if let Some(v) =
-                    item.save_data(true, &mut self.store)
-                        .map_err(|e| ContainerError::Item {
-                            id: item.id().to_string(),
+                let value = &item.value;
+                log::info!("add {} {} to store", I::TYPE, value.id());
+                let saved = value.save_data(true, &mut self.store).and_then(|v| {
+                    if let Some(v) = v {
+                        log::debug!("store path: {}", v.display());
+                    }
+                    value.add_media(&mut self.store)
+                });
+                match saved {
+                    Ok(()) => (),
+                    Err(e) if self.keep_going() => {
+                        log::warn!("skipped failed {} {}: {}", I::TYPE, value.id(), e);
+                        self.failures.push(Failure {
+                            kind: I::TYPE,
+                            id: value.id().to_string(),
+                            error: leaf::Error::Item {
+                                id: value.id().to_string(),
+                                kind: I::TYPE,
+                                source: ItemError::Store(e),
+                            },
+                        });
+                        failed = true;
+                        continue;
+                    }
+                    Err(e) => {
+                        return Err(ContainerError::Item {
+                            id: value.id().to_string(),
                             source: ItemError::Store(e),
-                        })?
-                {
-                    log::debug!("store path: {}", v.display());
+                        })
+                    }
                 }
-                item.add_media(&mut self.store)
-                    .map_err(|e| ContainerError::Item {
-                        id: item.id().to_string(),
-                        source: ItemError::Media(e),
-                    })?;
                 log::info!(
                     "finished processing {} {} in {} {} ({})",
                     I::TYPE,
-                    item.id(),
+                    value.id(),
                     IC::TYPE,
                     id,
                     IC::OPTION_NAME
                 );
-                ret.push(ContainerItem {
-                    processed: true,
-                    value: item,
-                });
+                ret.push(item);
+                stored += 1;
+                self.stat.items += 1;
+                if stored % CHECKPOINT_INTERVAL == 0 {
+                    Self::checkpoint_container::<IC, I, O>(&mut self.store, id, &ret[linked..])
+                        .map_err(ContainerError::Store)?;
+                    linked = ret.len();
+                }
+                if self.cancel.is_cancelled() {
+                    log::warn!(
+                        "interrupted {} {} ({}) after {} items",
+                        IC::TYPE,
+                        id,
+                        IC::OPTION_NAME,
+                        stored
+                    );
+                    Self::checkpoint_container::<IC, I, O>(&mut self.store, id, &ret[linked..])
+                        .map_err(ContainerError::Store)?;
+                    return Err(ContainerError::Cancelled);
+                }
             }
         }
         let mut container = IC::save_data(id, &mut self.store).map_err(ContainerError::from)?;
@@ -155,8 +338,11 @@ impl Driver {
                     source: e,
                 })?;
         }
-        container.mark_missing();
+        if !partial && !filtered && !failed {
+            container.mark_missing();
+        }
         let sp = container.finish().map_err(ContainerError::Store)?;
+        self.stat.containers += 1;
         Ok((ret, sp))
     }
 
@@ -172,25 +358,44 @@ impl Driver {
     {
         if IC::in_store(id, &self.store) {
             Ok(None)
+        } else if self.dry_run {
+            prog.plan(
+                "Would get",
+                format_args!("{} ({}) in {} {}", I::TYPE, IC::OPTION_NAME, IC::TYPE, id),
+            );
+            Ok(None)
         } else {
             let p = prog.start_item_container::<I, O, IC, _, &str>("Getting", "", id, None);
-            let (ret, _) = self.update_container_impl::<IC, I, O, _>(&p, id).await?;
+            let (ret, _) = self
+                .update_container_impl::<IC, I, O, _>(&p, id, false)
+                .await?;
             p.finish("Got", Some(ret.len()), id);
             Ok(Some(ret))
         }
     }
+    /// fetch items of the container, unless `full`, stop at items already stored with it
     pub async fn update_container<'a, IC, I, O, P>(
         &mut self,
         prog: &P,
         id: <IC as HasId>::Id<'a>,
+        full: bool,
     ) -> Result<Vec<ContainerItem<I>>, ContainerError>
     where
         I: Item,
         IC: ItemContainer<O, I>,
         P: progress::Reporter,
     {
+        if self.dry_run {
+            prog.plan(
+                "Would update",
+                format_args!("{} ({}) in {} {}", I::TYPE, IC::OPTION_NAME, IC::TYPE, id),
+            );
+            return Ok(Vec::new());
+        }
         let p = prog.start_item_container::<I, O, IC, _, &str>("Updating", "", id, None);
-        let (r, _) = self.update_container_impl::<IC, I, O, _>(&p, id).await?;
+        let (r, _) = self
+            .update_container_impl::<IC, I, O, _>(&p, id, !full)
+            .await?;
         p.finish("Updated", Some(r.len()), id);
         Ok(r)
     }
@@ -207,12 +412,27 @@ impl Driver {
         P: progress::Reporter,
         Pat: AsRef<Path>,
     {
+        if self.dry_run {
+            if !IC::in_store(id, &self.store) {
+                prog.plan(
+                    "Would fetch",
+                    format_args!("{} ({}) in {} {}", I::TYPE, IC::OPTION_NAME, IC::TYPE, id),
+                );
+            }
+            prog.plan(
+                "Would link",
+                format_args!("{} {} to {}", IC::TYPE, id, dest.as_ref().display()),
+            );
+            return Ok(None);
+        }
         let canon_dest = prepare_dest(dest.as_ref()).map_err(ContainerError::from)?;
         let (ret, store_path) = if IC::in_store(id, &self.store) {
             (None, IC::store_path(id, &self.store))
         } else {
             let p = prog.start_item_container::<I, O, IC, _, &str>("Downloading", "", id, None);
-            let (v, sp) = self.update_container_impl::<IC, I, O, _>(&p, id).await?;
+            let (v, sp) = self
+                .update_container_impl::<IC, I, O, _>(&p, id, false)
+                .await?;
             p.finish("Downloaded", Some(v.len()), id);
             (Some(v), sp)
         };
@@ -229,3 +449,115 @@ impl Driver {
         Ok(ret)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        driver::CancelToken,
+        item::{Answer, AnswerId, Question, QuestionId, VoidOpt},
+        progress::silent::Silent,
+        raw_data::{Container, RawData, RawDataInfo},
+        store::{info::ItemInfo, StoreContainer},
+    };
+    use futures::executor::block_on;
+    use serde::Deserialize;
+
+    fn answer(id: u64) -> Answer {
+        let data: serde_json::Value =
+            serde_json::from_str(include_str!("../item/fixtures/restricted_answer.json")).unwrap();
+        let mut ret = Answer::from_reply(
+            Deserialize::deserialize(&data).unwrap(),
+            RawData {
+                info: RawDataInfo {
+                    fetch_time: chrono::Utc::now(),
+                    container: Container::Question,
+                    etag: None,
+                    last_modified: None,
+                },
+                data,
+            },
+        );
+        ret.info.id = AnswerId(id);
+        ret
+    }
+
+    #[test]
+    fn resume() {
+        let dir = std::env::temp_dir().join(format!("zhihu-dl-resume-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut driver = Driver::create(&dir).unwrap();
+        let question = QuestionId(7);
+        // found deleted by an earlier run
+        driver
+            .store
+            .objects
+            .answer
+            .entry(AnswerId(4))
+            .or_default()
+            .container = ItemInfo {
+            in_store: true,
+            on_server: false,
+        };
+        let items = || {
+            (1..=4)
+                .map(|i| Ok(answer(i)))
+                .collect::<Vec<_>>()
+                .into_iter()
+        };
+        let stored_ids = |driver: &Driver| {
+            let mut ret =
+                <Question as StoreContainer<VoidOpt, Answer>>::stored_ids(question, &driver.store)
+                    .unwrap()
+                    .into_iter()
+                    .collect::<Vec<_>>();
+            ret.sort();
+            ret
+        };
+
+        // interrupted after the first item, which is checkpointed
+        driver.cancel.cancel();
+        let ret = block_on(
+            driver.store_container_items::<Question, Answer, VoidOpt, _>(
+                &Silent,
+                question,
+                items(),
+                false,
+            ),
+        );
+        assert!(matches!(ret, Err(ContainerError::Cancelled)));
+        assert!(!<Question as StoreContainer<VoidOpt, Answer>>::in_store(
+            question,
+            &driver.store
+        ));
+        assert_eq!(stored_ids(&driver), ["1"]);
+
+        driver.cancel = CancelToken::default();
+        let (ret, _) = block_on(
+            driver.store_container_items::<Question, Answer, VoidOpt, _>(
+                &Silent,
+                question,
+                items(),
+                false,
+            ),
+        )
+        .unwrap();
+        let mut processed = ret
+            .iter()
+            .map(|i| (i.value.info.id.0, i.processed))
+            .collect::<Vec<_>>();
+        processed.sort();
+        assert_eq!(processed, [(1, false), (2, true), (3, true), (4, false)]);
+        assert!(<Question as StoreContainer<VoidOpt, Answer>>::in_store(
+            question,
+            &driver.store
+        ));
+        assert_eq!(stored_ids(&driver), ["1", "2", "3", "4"]);
+        assert!(
+            !driver.store.objects.answer[&AnswerId(4)]
+                .container
+                .on_server
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
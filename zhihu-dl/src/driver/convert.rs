@@ -0,0 +1,288 @@
+use super::Driver;
+use crate::{
+    element::content::convertor::{ConvertFormat, ConvertFormatError},
+    item::{
+        column::ColumnRef,
+        comment::{Comment, CommentId, CommentNode},
+        Answer, Article, Column, Item, Pin, VoidOpt,
+    },
+    progress::{ItemsProg, OtherJob},
+    store::{BasicStoreContainer, BasicStoreItem, StoreError},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+use web_dl_base::{id::HasId, storable};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    #[error("failed to load {kind} {id}")]
+    Load {
+        kind: &'static str,
+        id: String,
+        #[source]
+        source: storable::Error,
+    },
+    #[error("failed to load comments of {kind} {id}")]
+    LoadComments {
+        kind: &'static str,
+        id: String,
+        #[source]
+        source: StoreError,
+    },
+    #[error("failed to convert {kind} {id}")]
+    Convert {
+        kind: &'static str,
+        id: String,
+        #[source]
+        source: ConvertFormatError,
+    },
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConvertReport {
+    pub converted: usize,
+    /// items without converted content
+    pub skipped: usize,
+    /// items with stored comments rendered as threads
+    pub comments: usize,
+}
+
+impl Driver {
+    fn convert_items<'a, I, P>(
+        &self,
+        prog: &P,
+        ids: impl Iterator<Item = I::Id<'a>>,
+        format: ConvertFormat,
+        output_dir: &Path,
+        report: &mut ConvertReport,
+    ) -> Result<(), ConvertError>
+    where
+        I: Item + BasicStoreItem,
+        P: ItemsProg,
+    {
+        for id in ids {
+            let item =
+                <I as storable::Storable>::load(self.store.store_path::<I>(id), Default::default())
+                    .map_err(|e| ConvertError::Load {
+                        kind: I::TYPE,
+                        id: id.to_string(),
+                        source: e,
+                    })?;
+            let Some(document) = item.get_main_content().and_then(|c| c.document.as_ref()) else {
+                log::info!("skip {} {} without converted content", I::TYPE, id);
+                prog.skip_item();
+                report.skipped += 1;
+                continue;
+            };
+            let _p = prog.start_item(I::TYPE, id);
+            format
+                .convert(
+                    self.store.image_root(),
+                    document,
+                    format!("{} {}", I::TYPE, id).as_str(),
+                    output_dir
+                        .join(I::TYPE)
+                        .join(format!("{}.{}", id, format.extension())),
+                )
+                .map_err(|e| ConvertError::Convert {
+                    kind: I::TYPE,
+                    id: id.to_string(),
+                    source: e,
+                })?;
+            report.converted += 1;
+        }
+        Ok(())
+    }
+    /// stored comments of `id` and their replies, by id
+    fn load_comments<IC>(
+        &self,
+        id: IC::Id<'_>,
+        comments: &mut BTreeMap<CommentId, Comment>,
+    ) -> Result<(), ConvertError>
+    where
+        IC: BasicStoreContainer<VoidOpt, Comment, ItemList = BTreeSet<CommentId>>,
+    {
+        let ids = self
+            .store
+            .get_container::<VoidOpt, Comment, IC>(id)
+            .map_err(|e| ConvertError::LoadComments {
+                kind: IC::TYPE,
+                id: id.to_string(),
+                source: e,
+            })?;
+        for c in ids {
+            if comments.contains_key(&c) {
+                continue;
+            }
+            let comment = <Comment as storable::Storable>::load(
+                self.store.store_path::<Comment>(c),
+                Default::default(),
+            )
+            .map_err(|e| ConvertError::Load {
+                kind: Comment::TYPE,
+                id: c.to_string(),
+                source: e,
+            })?;
+            comments.insert(c, comment);
+            if <Comment as BasicStoreContainer<VoidOpt, Comment>>::in_store(c, &self.store) {
+                self.load_comments::<Comment>(c, comments)?;
+            }
+        }
+        Ok(())
+    }
+    /// render stored comments of the items as threads to `<output_dir>/<type>/<id>.comments.<extension>`
+    fn convert_comments<'a, IC>(
+        &self,
+        ids: impl Iterator<Item = IC::Id<'a>>,
+        format: ConvertFormat,
+        output_dir: &Path,
+        report: &mut ConvertReport,
+    ) -> Result<(), ConvertError>
+    where
+        IC: BasicStoreContainer<VoidOpt, Comment, ItemList = BTreeSet<CommentId>>,
+    {
+        for id in ids {
+            if !IC::in_store(id, &self.store) {
+                continue;
+            }
+            let mut comments = BTreeMap::new();
+            self.load_comments::<IC>(id, &mut comments)?;
+            if comments.is_empty() {
+                continue;
+            }
+            log::info!("convert comments of {} {}", IC::TYPE, id);
+            let tree = Comment::build_tree(comments.into_values().collect());
+            format
+                .convert(
+                    self.store.image_root(),
+                    &CommentNode::to_document(&tree),
+                    format!("comments of {} {}", IC::TYPE, id).as_str(),
+                    output_dir.join(IC::TYPE).join(format!(
+                        "{}.comments.{}",
+                        id,
+                        format.extension()
+                    )),
+                )
+                .map_err(|e| ConvertError::Convert {
+                    kind: IC::TYPE,
+                    id: id.to_string(),
+                    source: e,
+                })?;
+            report.comments += 1;
+        }
+        Ok(())
+    }
+    /// render stored answers, articles, pins and column descriptions to
+    /// `<output_dir>/<type>/<id>.<extension>`, linking images in store,
+    /// and stored comments of answers, articles and pins as threads next to them
+    pub fn convert_store<J: OtherJob, P: AsRef<Path>>(
+        &self,
+        job: &J,
+        format: ConvertFormat,
+        output_dir: P,
+    ) -> Result<ConvertReport, ConvertError> {
+        let objects = &self.store.objects;
+        let dir = output_dir.as_ref();
+        let prog = job.start_items(
+            (objects.answer.len()
+                + objects.article.len()
+                + objects.pin.len()
+                + objects.column.len()) as u64,
+        );
+        let mut report = ConvertReport::default();
+        macro_rules! convert {
+            ($t:ty, $field:ident, |$id:ident| $to_id:expr) => {{
+                let ids = objects.$field.iter().filter_map(|($id, v)| {
+                    if v.container.in_store {
+                        Some($to_id)
+                    } else {
+                        prog.skip_item();
+                        None
+                    }
+                });
+                self.convert_items::<$t, _>(&prog, ids, format, dir, &mut report)?;
+            }};
+        }
+        convert!(Answer, answer, |id| *id);
+        convert!(Article, article, |id| *id);
+        convert!(Pin, pin, |id| *id);
+        convert!(Column, column, |id| ColumnRef(id.0.as_str()));
+        self.convert_comments::<Answer>(objects.answer.keys().copied(), format, dir, &mut report)?;
+        self.convert_comments::<Article>(
+            objects.article.keys().copied(),
+            format,
+            dir,
+            &mut report,
+        )?;
+        self.convert_comments::<Pin>(objects.pin.keys().copied(), format, dir, &mut report)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        element::content::{Content, ConvertOpt, HasContent},
+        item::{comment::CommentInfo, AnswerId},
+        progress::silent::Silent,
+        store::ContainerHandle,
+    };
+    use std::{cell::Cell, fs};
+
+    fn comment(id: u64, parent: Option<u64>, text: &str) -> Comment {
+        let mut ret = Comment {
+            version: crate::item::comment::VERSION,
+            info: CommentInfo {
+                id: CommentId(id),
+                parent_id: parent.map(CommentId),
+                author: None,
+                is_author: false,
+                has_child: Cell::new(false),
+                created_time: chrono::DateTime::parse_from_rfc3339("2020-01-01T00:00:00+08:00")
+                    .unwrap(),
+                like_count: 0,
+                author_tags: Vec::new(),
+            },
+            content: Content::default(),
+            raw_data: None,
+        };
+        ret.content.raw_html = Some(format!("<p>{}</p>", text));
+        ret.convert_html(&ConvertOpt::default());
+        ret
+    }
+
+    #[test]
+    fn threaded_comments() {
+        let dir = std::env::temp_dir().join(format!("zhihu-dl-convert-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut driver = Driver::create(dir.join("store")).unwrap();
+        let store = &mut driver.store;
+        store.add_object(true, &comment(10, None, "root")).unwrap();
+        store
+            .add_object(true, &comment(11, Some(10), "reply"))
+            .unwrap();
+        let mut answer = store
+            .add_container::<Answer, VoidOpt, Comment>(AnswerId(1))
+            .unwrap();
+        answer.link_item(CommentId(10)).unwrap();
+        answer.finish().unwrap();
+        let mut root = store
+            .add_container::<Comment, VoidOpt, Comment>(CommentId(10))
+            .unwrap();
+        root.link_item(CommentId(11)).unwrap();
+        root.finish().unwrap();
+
+        let out = dir.join("out");
+        let report = driver
+            .convert_store(&Silent, ConvertFormat::Markdown, &out)
+            .unwrap();
+        assert_eq!(report.comments, 1);
+        let rendered = fs::read_to_string(out.join("answer").join("1.comments.md")).unwrap();
+        assert!(rendered.lines().any(|l| l == "root"), "{}", rendered);
+        assert!(rendered.lines().any(|l| l == "> reply"), "{}", rendered);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
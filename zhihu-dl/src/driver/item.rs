@@ -1,14 +1,31 @@
 use super::Driver;
 use crate::{
-    item::{Fetchable, Item},
+    element::content::{
+        convertor::{ConvertFormat, ConvertFormatError},
+        ConvertOpt,
+    },
+    item::{answer::AnswerSpec, Answer, AnswerId, Fetchable, Item, QuestionId},
     progress::{self, ItemJob},
     raw_data::{self, RawData, RawDataInfo},
-    store::{BasicStoreItem, StoreItem},
-    util::relative_path::{link_to_dest, prepare_dest, DestPrepError, LinkError},
+    request::{Client, Fetched},
+    store::{BasicStoreItem, StoreError, StoreItem},
+    util::relative_path::{export_to_dest, link_to_dest, prepare_dest, DestPrepError, LinkError},
 };
 use serde::Deserialize;
-use std::path::{Path, PathBuf};
-use web_dl_base::{id::HasId, media, storable};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use web_dl_base::{
+    id::HasId,
+    media::{self, ImageRef},
+    storable,
+};
+
+/// directory in an exported item holding its images in image store layout,
+/// content rendered with the export refers to images in it
+pub const EXPORT_IMAGE_DIR: &str = "image_store";
 
 #[derive(Debug, thiserror::Error)]
 pub enum ItemError {
@@ -34,13 +51,7 @@ pub enum ItemError {
     Store(
         #[source]
         #[from]
-        storable::Error,
-    ),
-    #[error("failed to store images")]
-    Media(
-        #[source]
-        #[from]
-        media::Error,
+        StoreError,
     ),
     #[error("failed to link {} to {}", store_path.display() ,dest.display())]
     Link {
@@ -49,6 +60,27 @@ pub enum ItemError {
         #[source]
         source: LinkError,
     },
+    #[error("failed to load stored item")]
+    Load(#[source] Box<storable::Error>),
+    #[error("failed to export image {image} to {}", dest.display())]
+    ExportImage {
+        image: String,
+        dest: PathBuf,
+        #[source]
+        source: Box<media::Error>,
+    },
+    #[error("failed to render item to {}", dest.display())]
+    Render {
+        dest: PathBuf,
+        #[source]
+        source: ConvertFormatError,
+    },
+    #[error("answer {answer} belongs to question {found}, not {expected}")]
+    QuestionMismatch {
+        answer: AnswerId,
+        expected: QuestionId,
+        found: QuestionId,
+    },
 }
 impl ItemError {
     pub(super) fn is_not_found(&self) -> bool {
@@ -60,18 +92,28 @@ impl ItemError {
     }
 }
 
+/// raw data of an item, or the stored item when it's not modified
+enum Response<I> {
+    Stored(I, PathBuf),
+    Fetched(serde_json::Value, RawDataInfo),
+}
+
 impl Driver {
     pub(super) async fn process_item<I: Item, P: progress::ItemProg>(
-        &self,
+        client: &Client,
+        convert_opt: &ConvertOpt,
         prog: &P,
         item: &mut I,
     ) {
+        if let Some(reason) = item.get_main_content().and_then(|c| c.restriction()) {
+            log::warn!("{} {} is restricted: {}", I::TYPE, item.id(), reason);
+        }
         log::info!("getting images for {} {}", I::TYPE, item.id());
-        if item.get_images(&self.client, prog).await {
-            prog.sleep(self.client.request_interval).await;
+        if item.get_images(client, prog).await {
+            prog.sleep(client.request_interval).await;
         }
         log::info!("converting html for {} {}", I::TYPE, item.id());
-        item.convert_html();
+        item.convert_html(convert_opt);
     }
 
     async fn process_response<I, P>(
@@ -79,6 +121,7 @@ impl Driver {
         prog: &P,
         on_server: bool,
         data: serde_json::Value,
+        info: RawDataInfo,
     ) -> Result<(I, PathBuf), ItemError>
     where
         I: Item + BasicStoreItem,
@@ -86,15 +129,9 @@ impl Driver {
     {
         let mut ret: I = I::from_reply(
             I::Reply::deserialize(&data).map_err(ItemError::from)?,
-            RawData {
-                info: RawDataInfo {
-                    fetch_time: chrono::Utc::now(),
-                    container: raw_data::Container::None,
-                },
-                data,
-            },
+            RawData { info, data },
         );
-        self.process_item(prog, &mut ret).await;
+        Self::process_item(&self.client, &self.convert_opt, prog, &mut ret).await;
         log::info!("add item {} {} to store", I::TYPE, ret.id());
         let dest = self
             .store
@@ -102,25 +139,94 @@ impl Driver {
             .map_err(ItemError::from)?;
         log::debug!("store path: {}", dest.display());
         self.store.add_media(&ret).map_err(ItemError::from)?;
+        self.stat.items += 1;
         Ok((ret, dest))
     }
 
+    /// fetch raw data of `id`, or load it from store when it's not modified
+    async fn fetch_response<'a, I, P>(
+        &mut self,
+        prog: &P,
+        id: <I as HasId>::Id<'a>,
+    ) -> Result<Response<I>, ItemError>
+    where
+        I: Fetchable + Item + BasicStoreItem,
+        P: progress::ItemProg,
+    {
+        log::info!("fetching raw data for {} {}", I::TYPE, id);
+        let previous = if <I as StoreItem>::in_store(id, &self.store).in_store {
+            RawDataInfo::load_of_object(self.store.store_path::<I>(id))
+        } else {
+            None
+        };
+        match self
+            .client
+            .fetch_json(prog, I::request(&self.client, id), previous.as_ref())
+            .await
+            .map_err(ItemError::from)?
+        {
+            Fetched::NotModified => {
+                log::info!("{} {} not modified", I::TYPE, id);
+                let ret = self
+                    .store
+                    .get_object::<I>(id, Default::default())
+                    .map_err(StoreError::from)?;
+                Ok(Response::Stored(ret, self.store.store_path::<I>(id)))
+            }
+            Fetched::Modified {
+                data,
+                etag,
+                last_modified,
+            } => {
+                log::trace!("raw data {:#?}", data);
+                let info = RawDataInfo {
+                    fetch_time: chrono::Utc::now(),
+                    container: raw_data::Container::None,
+                    etag,
+                    last_modified,
+                };
+                Ok(Response::Fetched(data, info))
+            }
+        }
+    }
+
     async fn update_item_impl<'a, I, P>(
         &mut self,
         prog: &P,
         id: <I as HasId>::Id<'a>,
-    ) -> Result<(I, PathBuf), ItemError>
+    ) -> Result<(I, PathBuf, bool), ItemError>
     where
         I: Fetchable + Item + BasicStoreItem,
         P: progress::ItemProg,
     {
-        self.process_response(prog, true, {
-            log::info!("fetching raw data for {} {}", I::TYPE, id);
-            let data = I::fetch(&self.client, id).await.map_err(ItemError::from)?;
-            log::trace!("raw data {:#?}", data);
-            data
-        })
-        .await
+        let (data, info) = match self.fetch_response::<I, _>(prog, id).await? {
+            Response::Stored(v, sp) => return Ok((v, sp, false)),
+            Response::Fetched(data, info) => (data, info),
+        };
+        let Some(to) = I::redirect(&data).filter(|to| to.to_string() != id.to_string()) else {
+            return self
+                .process_response(prog, true, data, info)
+                .await
+                .map(|(v, sp)| (v, sp, true));
+        };
+        // follow a moved or merged object once, and keep it under the canonical id
+        log::warn!("{} {} is redirected to {}", I::TYPE, id, to);
+        let ret = match self.fetch_response::<I, _>(prog, to).await? {
+            Response::Stored(v, sp) => (v, sp, false),
+            Response::Fetched(data, info) => self
+                .process_response(prog, true, data, info)
+                .await
+                .map(|(v, sp)| (v, sp, true))?,
+        };
+        self.store
+            .add_redirect::<I>(id, to)
+            .map_err(ItemError::from)?;
+        Ok(ret)
+    }
+    /// whether `id` is in store, directly or as an alias of a redirected object
+    fn item_stored<I: BasicStoreItem>(&self, id: <I as HasId>::Id<'_>) -> bool {
+        <I as StoreItem>::in_store(id, &self.store).in_store
+            || self.store.redirect::<I>(id).is_some()
     }
 
     pub async fn get_item<'a, I, P>(
@@ -132,7 +238,10 @@ impl Driver {
         I: Fetchable + Item + BasicStoreItem,
         P: progress::Reporter,
     {
-        Ok(if <I as StoreItem>::in_store(id, &self.store).in_store {
+        Ok(if self.item_stored::<I>(id) {
+            None
+        } else if self.dry_run {
+            prog.plan("Would get", format_args!("{} {}", I::TYPE, id));
             None
         } else {
             let p = prog.start_item::<&str, _>("Getting", "", I::TYPE, id, None);
@@ -141,6 +250,56 @@ impl Driver {
             Some(ret)
         })
     }
+    /// check that the answer of `spec` belongs to the question given with it, the answer is
+    /// fetched when it's not in store and `fetch` is set, and only stored when it matches
+    pub async fn check_answer<P: progress::Reporter>(
+        &mut self,
+        prog: &P,
+        spec: AnswerSpec,
+        fetch: bool,
+    ) -> Result<(), ItemError> {
+        let Some(expected) = spec.question else {
+            return Ok(());
+        };
+        let found = if self.item_stored::<Answer>(spec.answer) {
+            self.store
+                .get_object::<Answer>(spec.answer, Default::default())
+                .map_err(StoreError::from)?
+                .info
+                .question
+                .id
+        } else if !fetch {
+            return Ok(());
+        } else if self.dry_run {
+            prog.plan(
+                "Would check",
+                format_args!("answer {} of question {}", spec.answer, expected),
+            );
+            return Ok(());
+        } else {
+            let p = prog.start_item::<&str, _>("Getting", "", Answer::TYPE, spec.answer, None);
+            match self.fetch_response::<Answer, _>(&p, spec.answer).await? {
+                Response::Stored(v, _) => v.info.question.id,
+                Response::Fetched(data, info) => {
+                    let found = Answer::question_of(&data)?;
+                    if found == expected {
+                        self.process_response::<Answer, _>(&p, true, data, info)
+                            .await?;
+                        p.finish("Got", spec.answer);
+                    }
+                    found
+                }
+            }
+        };
+        if found != expected {
+            return Err(ItemError::QuestionMismatch {
+                answer: spec.answer,
+                expected,
+                found,
+            });
+        }
+        Ok(())
+    }
     pub async fn add_raw_item<I, P>(
         &mut self,
         prog: &P,
@@ -151,7 +310,13 @@ impl Driver {
         I: Item + BasicStoreItem,
         P: progress::ItemProg,
     {
-        self.process_response::<I, _>(prog, on_server, data)
+        let info = RawDataInfo {
+            fetch_time: chrono::Utc::now(),
+            container: raw_data::Container::None,
+            etag: None,
+            last_modified: None,
+        };
+        self.process_response::<I, _>(prog, on_server, data, info)
             .await
             .map(|v| v.0)
     }
@@ -168,15 +333,18 @@ impl Driver {
         P: progress::Reporter,
         Pat: AsRef<Path>,
     {
+        if self.dry_run {
+            if !<I as StoreItem>::in_store(id, &self.store).in_store {
+                prog.plan("Would fetch", format_args!("{} {}", I::TYPE, id));
+            }
+            prog.plan(
+                "Would link",
+                format_args!("{} {} to {}", I::TYPE, id, dest.as_ref().display()),
+            );
+            return Ok(None);
+        }
         let canon_dest = prepare_dest(dest.as_ref()).map_err(ItemError::DestPrep)?;
-        let (v, store_path) = if <I as StoreItem>::in_store(id, &self.store).in_store {
-            (None, self.store.store_path::<I>(id))
-        } else {
-            let p = prog.start_item::<&str, _>("Downloading", "", I::TYPE, id, None);
-            let (v, sp) = self.update_item_impl::<I, _>(&p, id).await?;
-            p.finish("Downloaded", id);
-            (Some(v), sp)
-        };
+        let (v, store_path) = self.fetch_missing_item::<I, _>(prog, id).await?;
         log::info!(
             "link {} {} ({}) to {}",
             I::TYPE,
@@ -189,21 +357,270 @@ impl Driver {
             dest: canon_dest,
             source: e,
         })?;
+        if let Some(format) = self.output_format {
+            self.render_item::<I>(
+                id,
+                v.as_ref(),
+                format,
+                dest.as_ref(),
+                self.store.image_root(),
+            )?;
+        }
+        prog.link_item(I::TYPE, id, dest);
+        Ok(v)
+    }
+    /// write main content of the item as `format` to `dest` with the format's extension appended,
+    /// images are referred to in `images_store`
+    fn render_item<I: Item + BasicStoreItem>(
+        &self,
+        id: <I as HasId>::Id<'_>,
+        fetched: Option<&I>,
+        format: ConvertFormat,
+        dest: &Path,
+        images_store: &Path,
+    ) -> Result<(), ItemError> {
+        let loaded;
+        let item = match fetched {
+            Some(v) => v,
+            None => {
+                loaded = <I as storable::Storable>::load(
+                    self.store.store_path::<I>(id),
+                    Default::default(),
+                )
+                .map_err(|e| ItemError::Load(Box::new(e)))?;
+                &loaded
+            }
+        };
+        let Some(document) = item.get_main_content().and_then(|c| c.document.as_ref()) else {
+            log::warn!(
+                "skip rendering {} {} without converted content",
+                I::TYPE,
+                id
+            );
+            return Ok(());
+        };
+        let mut path = dest.as_os_str().to_owned();
+        path.push(".");
+        path.push(format.extension());
+        let path = PathBuf::from(path);
+        log::info!("render {} {} to {}", I::TYPE, id, path.display());
+        format
+            .convert(
+                images_store,
+                document,
+                format!("{} {}", I::TYPE, id).as_str(),
+                &path,
+            )
+            .map_err(|e| ItemError::Render {
+                dest: path,
+                source: e,
+            })
+    }
+    /// copy an item and all images it references to `dest` instead of linking it,
+    /// files are hard linked unless `copy` is set or hard linking fails
+    pub async fn export_item<'a, I, P, Pat>(
+        &mut self,
+        prog: &P,
+        id: <I as HasId>::Id<'a>,
+        copy: bool,
+        dest: Pat,
+    ) -> Result<Option<I>, ItemError>
+    where
+        I: Fetchable + Item + BasicStoreItem,
+        P: progress::Reporter,
+        Pat: AsRef<Path>,
+    {
+        if self.dry_run {
+            if !<I as StoreItem>::in_store(id, &self.store).in_store {
+                prog.plan("Would fetch", format_args!("{} {}", I::TYPE, id));
+            }
+            prog.plan(
+                "Would export",
+                format_args!("{} {} to {}", I::TYPE, id, dest.as_ref().display()),
+            );
+            return Ok(None);
+        }
+        let canon_dest = prepare_dest(dest.as_ref()).map_err(ItemError::DestPrep)?;
+        let (v, store_path) = self.fetch_missing_item::<I, _>(prog, id).await?;
+        log::info!(
+            "export {} {} ({}) to {}",
+            I::TYPE,
+            id,
+            store_path.display(),
+            canon_dest.display()
+        );
+        export_to_dest(copy, store_path.as_path(), &canon_dest).map_err(|e| ItemError::Link {
+            store_path,
+            dest: canon_dest.clone(),
+            source: e,
+        })?;
+        let mut images = BTreeMap::new();
+        let mut visit = |r: &ImageRef| {
+            images.insert(r.to_string(), r.clone());
+        };
+        match &v {
+            Some(v) => v.visit_images(&mut visit),
+            None => self
+                .store
+                .get_object::<I>(id, Default::default())
+                .map_err(|e| ItemError::Load(Box::new(e)))?
+                .visit_images(&mut visit),
+        }
+        let images_store = canon_dest.join(EXPORT_IMAGE_DIR);
+        if !images.is_empty() {
+            self.export_images(copy, images, images_store.clone())?;
+        }
+        if let Some(format) = self.output_format {
+            self.render_item::<I>(id, v.as_ref(), format, dest.as_ref(), &images_store)?;
+        }
         prog.link_item(I::TYPE, id, dest);
         Ok(v)
     }
+    fn export_images(
+        &self,
+        copy: bool,
+        images: BTreeMap<String, ImageRef>,
+        dir: PathBuf,
+    ) -> Result<(), ItemError> {
+        let backend = self.store.image_backend();
+        if !dir.exists() {
+            fs::create_dir_all(&dir).map_err(|e| ItemError::Link {
+                store_path: self.store.image_root().clone(),
+                dest: dir.clone(),
+                source: LinkError::CreateDir {
+                    dir: dir.clone(),
+                    source: e,
+                },
+            })?;
+        }
+        let err = |image: String, dest: PathBuf, e| ItemError::ExportImage {
+            image,
+            dest,
+            source: Box::new(e),
+        };
+        for (name, r) in images {
+            let dest = dir.join(name.as_str());
+            if !copy && backend.link(&r.hash, &r.extension, &dest).is_ok() {
+                continue;
+            }
+            let data = backend
+                .get(&r.hash, &r.extension)
+                .map_err(|e| err(name.clone(), dest.clone(), e))?;
+            fs::write(&dest, data).map_err(|e| {
+                let path = dest.clone();
+                err(
+                    name,
+                    dest,
+                    media::Error::Fs {
+                        op: media::FsErrorOp::WriteFile,
+                        path,
+                        source: e,
+                    },
+                )
+            })?;
+        }
+        Ok(())
+    }
+    /// fetch an item not in store, returns the fetched item and its store path
+    async fn fetch_missing_item<'a, I, P>(
+        &mut self,
+        prog: &P,
+        id: <I as HasId>::Id<'a>,
+    ) -> Result<(Option<I>, PathBuf), ItemError>
+    where
+        I: Fetchable + Item + BasicStoreItem,
+        P: progress::Reporter,
+    {
+        Ok(if self.item_stored::<I>(id) {
+            (None, self.store.store_path::<I>(id))
+        } else {
+            let p = prog.start_item::<&str, _>("Downloading", "", I::TYPE, id, None);
+            let (v, sp, _) = self.update_item_impl::<I, _>(&p, id).await?;
+            p.finish("Downloaded", id);
+            (Some(v), sp)
+        })
+    }
     pub async fn update_item<'a, I, P>(
         &mut self,
         prog: &P,
         id: <I as HasId>::Id<'a>,
-    ) -> Result<I, ItemError>
+    ) -> Result<Option<I>, ItemError>
     where
         I: Fetchable + Item + BasicStoreItem,
         P: progress::Reporter,
     {
+        if self.dry_run {
+            prog.plan("Would update", format_args!("{} {}", I::TYPE, id));
+            return Ok(None);
+        }
         let p = prog.start_item::<&str, _>("Updating", "", I::TYPE, id, None);
-        let ret = self.update_item_impl(&p, id).await?;
-        p.finish("Updated", id);
-        Ok(ret.0)
+        let (ret, _, changed) = self.update_item_impl(&p, id).await?;
+        p.finish(if changed { "Updated" } else { "Unchanged" }, id);
+        Ok(Some(ret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{element::content::HasContent, progress::silent::Silent};
+    use futures::executor::block_on;
+
+    #[test]
+    fn export_render() {
+        let dir = std::env::temp_dir().join(format!("zhihu-dl-export-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut driver = Driver::create(dir.join("store")).unwrap();
+        let data = b"image";
+        let hash = media::HashAlgo::default().digest(data);
+        let url = format!("https://pic1.zhimg.com/{}.png", hash);
+        let reply: serde_json::Value =
+            serde_json::from_str(include_str!("../item/fixtures/restricted_answer.json")).unwrap();
+        let mut answer = Answer::from_reply(
+            Deserialize::deserialize(&reply).unwrap(),
+            RawData {
+                info: RawDataInfo {
+                    fetch_time: chrono::Utc::now(),
+                    container: raw_data::Container::None,
+                    etag: None,
+                    last_modified: None,
+                },
+                data: reply,
+            },
+        );
+        answer.content.raw_html = Some(format!("<figure><img src=\"{}\"></figure>", url));
+        answer.content.info.images = vec![ImageRef {
+            url,
+            hash: hash.clone(),
+            extension: "png".to_string(),
+            original_hash: None,
+            animated: false,
+            mime: None,
+            data: Some(data[..].into()),
+        }];
+        answer.convert_html(&ConvertOpt::default());
+        driver.store.add_object(true, &answer).unwrap();
+        driver.store.add_media(&answer).unwrap();
+
+        driver.set_output_format(Some(ConvertFormat::Markdown));
+        let dest = dir.join("export");
+        block_on(driver.export_item::<Answer, _, _>(&Silent, answer.info.id, true, &dest)).unwrap();
+        let image = dest
+            .canonicalize()
+            .unwrap()
+            .join(EXPORT_IMAGE_DIR)
+            .join(format!("{}.png", hash));
+        assert_eq!(fs::read(&image).unwrap(), data);
+        // image paths are relative to the rendered file
+        let rendered = fs::read_to_string(dir.join("export.md")).unwrap();
+        let relative = Path::new("export")
+            .join(EXPORT_IMAGE_DIR)
+            .join(format!("{}.png", hash));
+        assert!(
+            rendered.contains(relative.to_str().unwrap()),
+            "{}",
+            rendered
+        );
+        fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -13,6 +13,10 @@ pub mod leaf;
 
 pub mod branch;
 
+pub mod plan;
+
+pub mod export;
+
 #[derive(Debug)]
 pub enum FsOp {
     CreateDir,
@@ -133,6 +137,36 @@ fn save_leaves<P: AsRef<Path>>(path: P, leaves: &spec::ManifestLeaf) -> Result<(
     Ok(())
 }
 
+impl spec::Manifest {
+    /// leaves applied by [`apply_manifest`] or, with `update`, [`update_manifest`]
+    ///
+    /// [`apply_manifest`]: super::Driver::apply_manifest
+    /// [`update_manifest`]: super::Driver::update_manifest
+    fn pending_leaf<Pat: AsRef<Path>>(
+        &self,
+        dest: Pat,
+        update: bool,
+    ) -> Result<spec::ManifestLeaf, Error> {
+        let mut leaves = self.merged_leaf();
+        if !update {
+            leaves.diff(&load_leaves(dest)?);
+        }
+        Ok(leaves)
+    }
+    /// number of items and containers reported at the top level when applying to `dest`
+    pub fn job_count<Pat: AsRef<Path>>(&self, dest: Pat, update: bool) -> Result<u64, Error> {
+        self.pending_leaf(dest, update).map(|l| l.job_count())
+    }
+    /// operations done when applying to `dest`, see [`spec::ManifestLeaf::plan`]
+    pub fn plan<Pat: AsRef<Path>>(
+        &self,
+        dest: Pat,
+        update: bool,
+    ) -> Result<Vec<plan::Step>, Error> {
+        self.pending_leaf(dest, update).map(|l| l.plan())
+    }
+}
+
 impl super::Driver {
     pub async fn update_manifest<P: Reporter, Pat: AsRef<Path>>(
         &mut self,
@@ -144,7 +178,11 @@ impl super::Driver {
         self.apply_manifest_leaf(prog, &leaves)
             .await
             .map_err(Error::from)?;
-        save_leaves(dest.as_ref(), &leaves)?;
+        if !self.failures.is_empty() {
+            log::warn!("applied leaves are not saved as some items failed");
+        } else if !self.dry_run {
+            save_leaves(dest.as_ref(), &leaves)?;
+        }
         self.link_manifest(prog, manifest, dest)
             .map_err(Error::from)
     }
@@ -162,7 +200,12 @@ impl super::Driver {
         })
         .await
         .map_err(Error::from)?;
-        save_leaves(dest.as_ref(), &manifest.merged_leaf())?;
+        if !self.failures.is_empty() {
+            // keep failed items pending for the next run
+            log::warn!("applied leaves are not saved as some items failed");
+        } else if !self.dry_run {
+            save_leaves(dest.as_ref(), &manifest.merged_leaf())?;
+        }
         self.link_manifest(prog, manifest, dest)
             .map_err(Error::from)
     }
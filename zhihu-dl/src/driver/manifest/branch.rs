@@ -59,7 +59,12 @@ impl Driver {
     ) -> Result<(), Error> {
         let mut path = path.join(I::TYPE);
         path.push(name.to_string());
-        if !path.exists() {
+        if self.dry_run {
+            prog.plan(
+                "Would link",
+                format_args!("{} {} to {}", I::TYPE, id, path.display()),
+            );
+        } else if !path.exists() {
             let sp = self.store.item_path::<I>(id);
             link_to_dest(true, &sp, &path).map_err(|e| Error::Link {
                 store_path: sp,
@@ -80,7 +85,9 @@ impl Driver {
             Manifest::Branch(b) => {
                 for (d, m) in b {
                     let path = path.join(d);
-                    create_dir(&path)?;
+                    if !self.dry_run {
+                        create_dir(&path)?;
+                    }
                     self.link_impl(prog, path, m)?;
                 }
             }
@@ -120,7 +127,11 @@ impl Driver {
     ) -> Result<(), Error> {
         self.link_impl(
             prog,
-            prepare_dest_parent(dest.as_ref()).map_err(Error::from)?,
+            if self.dry_run {
+                dest.as_ref().to_path_buf()
+            } else {
+                prepare_dest_parent(dest.as_ref()).map_err(Error::from)?
+            },
             manifest,
         )
     }
@@ -0,0 +1,189 @@
+use super::spec::*;
+use crate::{
+    driver::Driver,
+    item::{user, User, UserId},
+    store::{info, ObjectInfo},
+};
+use std::collections::BTreeMap;
+use web_dl_base::storable::Storable;
+
+fn flag<C: Default>(fetched: bool) -> Option<C> {
+    fetched.then(C::default)
+}
+/// child of items whose comments were fetched
+fn basic(comment: Option<CommentChild>) -> ItemOption<BasicChild> {
+    ItemOption {
+        child: comment.map(|c| BasicChild { comment: Some(c) }),
+    }
+}
+fn nonempty<C: Default + PartialEq>(child: C) -> Option<C> {
+    (child != C::default()).then_some(child)
+}
+
+/// manifest leaf listing every item in store, with children that were fetched
+///
+/// Comments are recorded per store, so child comments are requested for every
+/// commented item once any comment had its children fetched. Users whose url token
+/// can't be found by `url_token` are skipped.
+pub fn export_leaf<F: FnMut(UserId) -> Option<String>>(
+    objects: &ObjectInfo,
+    mut url_token: F,
+) -> ManifestLeaf {
+    let comment = CommentChild {
+        child: objects.comment.values().any(|c| c.comment).then_some(true),
+    };
+    let mut ret = ManifestLeaf::default();
+    for (id, v) in objects.answer.iter().filter(|(_, v)| v.container.in_store) {
+        ret.answer.insert(*id, basic(v.comment.then_some(comment)));
+    }
+    for (id, v) in objects.article.iter().filter(|(_, v)| v.container.in_store) {
+        ret.article.insert(*id, basic(v.comment.then_some(comment)));
+    }
+    for (id, v) in objects.pin.iter().filter(|(_, v)| v.container.in_store) {
+        ret.pin.insert(*id, basic(v.comment.then_some(comment)));
+    }
+    for (id, v) in objects
+        .collection
+        .iter()
+        .filter(|(_, v)| v.container.in_store)
+    {
+        ret.collection.insert(
+            *id,
+            ItemOption {
+                child: nonempty(CollectionChild {
+                    item: flag(v.item),
+                    comment: v.comment.then_some(comment),
+                }),
+            },
+        );
+    }
+    for (id, v) in objects.column.iter().filter(|(_, v)| v.container.in_store) {
+        ret.column.insert(
+            id.clone(),
+            ItemOption {
+                child: nonempty(ColumnChild {
+                    regular: flag(v.item),
+                    pinned: flag(v.pinned_item),
+                }),
+            },
+        );
+    }
+    for (id, v) in objects
+        .question
+        .iter()
+        .filter(|(_, v)| v.container.in_store)
+    {
+        ret.question.insert(
+            *id,
+            ItemOption {
+                child: nonempty(QuestionChild {
+                    comment: v.comment.then_some(comment),
+                    answer: flag(v.answer),
+                }),
+            },
+        );
+    }
+    let mut users = BTreeMap::new();
+    for (id, v) in objects.user.iter().filter(|(_, v)| v.container.in_store) {
+        let Some(token) = url_token(*id) else {
+            continue;
+        };
+        users.insert(
+            token,
+            UserOption {
+                id: *id,
+                child: nonempty(UserChild {
+                    answer: flag(v.answer),
+                    article: flag(v.article),
+                    collection: nonempty(UserCollection {
+                        created: flag(v.collection.created),
+                        liked: flag(v.collection.liked),
+                    }),
+                    column: flag(v.column),
+                    pin: flag(v.pin),
+                    question: flag(v.question),
+                }),
+                ..Default::default()
+            },
+        );
+    }
+    ret.user = users;
+    ret
+}
+
+impl Driver {
+    /// manifest leaf reproducing items in store, see [`export_leaf`]
+    pub fn export_manifest(&self) -> ManifestLeaf {
+        export_leaf(&self.store.objects, |id| {
+            match User::load(
+                self.store.store_path::<User>(user::StoreId(id, "")),
+                Default::default(),
+            ) {
+                Ok(u) => Some(u.info.url_token),
+                Err(e) => {
+                    log::warn!("skipped exporting user {}: {:?}", id, anyhow::Error::new(e));
+                    None
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::{AnswerId, CollectionId};
+
+    #[test]
+    fn export() {
+        let stored = info::ItemInfo {
+            in_store: true,
+            on_server: true,
+        };
+        let mut objects = ObjectInfo::default();
+        objects.answer.insert(
+            AnswerId(1),
+            info::Answer {
+                container: stored,
+                comment: true,
+            },
+        );
+        objects.answer.insert(AnswerId(2), Default::default());
+        objects.collection.insert(
+            CollectionId(3),
+            info::Collection {
+                container: stored,
+                item: true,
+                comment: false,
+            },
+        );
+        objects.user.insert(
+            UserId([0; 16]),
+            info::User {
+                container: stored,
+                pin: true,
+                ..Default::default()
+            },
+        );
+        let leaf = export_leaf(&objects, |_| Some("token".to_owned()));
+        assert_eq!(leaf.answer.len(), 1);
+        assert_eq!(
+            leaf.answer[&AnswerId(1)].child,
+            Some(BasicChild {
+                comment: Some(CommentChild::default())
+            })
+        );
+        assert_eq!(
+            leaf.collection[&CollectionId(3)].child,
+            Some(CollectionChild {
+                item: Some(Default::default()),
+                comment: None
+            })
+        );
+        assert_eq!(
+            leaf.user["token"].child.and_then(|c| c.pin),
+            Some(Default::default())
+        );
+        assert!(export_leaf(&objects, |_| None).user.is_empty());
+    }
+}
@@ -4,8 +4,8 @@ use crate::{
     item::{
         any::Any,
         column::{self, ColumnRef},
-        user, Answer, Article, Collection, Column, Comment, Fetchable, Item, ItemContainer, Pin,
-        Question, User, VoidOpt,
+        user, Answer, AnswerId, Article, Collection, Column, Comment, Fetchable, Item,
+        ItemContainer, Pin, Question, User, VoidOpt,
     },
     progress::{ContainerJob, ItemContainerProg, ItemsProg, Reporter},
     store::{BasicStoreItem, StoreItem},
@@ -13,8 +13,27 @@ use crate::{
 use std::fmt::Display;
 use web_dl_base::{id::HasId, storable};
 
+/// what to do when an item or container fails while applying a manifest or updating a container
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    #[default]
+    FailFast,
+    /// record the failure and go on with other items, see [`Driver::take_failures`]
+    Continue,
+}
+
+/// item whose processing failed with [`ErrorPolicy::Continue`]
+#[derive(Debug)]
+pub struct Failure {
+    pub kind: &'static str,
+    pub id: String,
+    pub error: Error,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[error("interrupted")]
+    Cancelled,
     #[error("failed to process item {kind} {id}")]
     Item {
         id: String,
@@ -298,12 +317,44 @@ impl<I: OptDisplay> Display for ShowOpt<I> {
 }
 
 impl Driver {
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.error_policy = policy;
+    }
+    /// failures recorded since last call
+    pub fn take_failures(&mut self) -> Vec<Failure> {
+        std::mem::take(&mut self.failures)
+    }
+    /// record a failed `result` of `kind` `id` when continuing on errors,
+    /// and stop once the driver is cancelled
+    fn recover<I: Display>(
+        &mut self,
+        kind: &'static str,
+        id: I,
+        result: Result<(), Error>,
+    ) -> Result<(), Error> {
+        match result {
+            // an interrupted run stops rather than going on with other items
+            Ok(()) if self.cancel.is_cancelled() => Err(Error::Cancelled),
+            Err(error)
+                if self.error_policy == ErrorPolicy::Continue && !self.cancel.is_cancelled() =>
+            {
+                log::warn!("skipped failed {} {}: {}", kind, id, error);
+                self.failures.push(Failure {
+                    kind,
+                    id: id.to_string(),
+                    error,
+                });
+                Ok(())
+            }
+            r => r,
+        }
+    }
     async fn apply_container<IC: ItemContainer<O, I>, I: Item, O, P: Reporter>(
         &mut self,
         prog: &P,
         id: IC::Id<'_>,
     ) -> Result<Vec<ContainerItem<I>>, Error> {
-        self.update_container::<IC, I, O, _>(prog, id)
+        self.update_container::<IC, I, O, _>(prog, id, true)
             .await
             .map_err(|e| Error::Container {
                 item_kind: I::TYPE,
@@ -341,12 +392,11 @@ impl Driver {
                 Some(ShowOpt(child)),
             );
             {
-                let mut p = prog.start_items(roots.len() as u64);
+                let p = prog.start_items(roots.len() as u64);
                 for i in &roots {
-                    let _p_i = ItemsProg::start_item(&mut p, I::TYPE, i.value.id());
-                    child
-                        .apply_child(self, &prog, &i.value)
-                        .await
+                    let _p_i = ItemsProg::start_item(&p, I::TYPE, i.value.id());
+                    let r = child.apply_child(self, &prog, &i.value).await;
+                    self.recover(I::TYPE, i.value.id(), r)
                         .map_err(|e| Error::SubContainer {
                             id: body.id().to_string(),
                             kind: IC::TYPE,
@@ -422,31 +472,61 @@ impl Driver {
         leaf: &ManifestLeaf,
     ) -> Result<(), Error> {
         for (id, opt) in &leaf.answer {
-            self.apply_basic::<Answer, _, _>(prog, *id, opt.child)
-                .await?;
+            let r = self.apply_basic::<Answer, _, _>(prog, *id, opt.child).await;
+            self.recover(Answer::TYPE, id, r)?;
         }
         for (id, opt) in &leaf.article {
-            self.apply_basic::<Article, _, _>(prog, *id, opt.child)
-                .await?;
+            let r = self
+                .apply_basic::<Article, _, _>(prog, *id, opt.child)
+                .await;
+            self.recover(Article::TYPE, id, r)?;
         }
         for (id, opt) in &leaf.collection {
-            self.apply_basic::<Collection, _, _>(prog, *id, opt.child)
-                .await?;
+            let r = self
+                .apply_basic::<Collection, _, _>(prog, *id, opt.child)
+                .await;
+            self.recover(Collection::TYPE, id, r)?;
         }
         for (id, opt) in &leaf.column {
-            self.apply_basic::<Column, _, _>(prog, ColumnRef(id.0.as_str()), opt.child)
-                .await?;
+            let r = self
+                .apply_basic::<Column, _, _>(prog, ColumnRef(id.0.as_str()), opt.child)
+                .await;
+            self.recover(Column::TYPE, id, r)?;
         }
         for (id, opt) in &leaf.pin {
-            self.apply_basic::<Pin, _, _>(prog, *id, opt.child).await?;
+            let r = self.apply_basic::<Pin, _, _>(prog, *id, opt.child).await;
+            self.recover(Pin::TYPE, id, r)?;
         }
         for (id, opt) in &leaf.question {
-            self.apply_basic::<Question, _, _>(prog, *id, opt.child)
-                .await?;
+            let r = self
+                .apply_basic::<Question, _, _>(prog, *id, opt.child)
+                .await;
+            self.recover(Question::TYPE, id, r)?;
         }
         for (url_token, opt) in &leaf.user {
-            self.apply_basic(prog, user::StoreId(opt.id, url_token.as_str()), opt.child)
-                .await?;
+            let id = user::StoreId(opt.id, url_token.as_str());
+            let r = self.apply_basic(prog, id, opt.child).await;
+            self.recover(User::TYPE, url_token, r)?;
+            if let Some(sel) = &opt.answer {
+                let answers: Vec<AnswerId> = match sel {
+                    Selection::All => match self
+                        .apply_container::<User, Answer, VoidOpt, _>(prog, id)
+                        .await
+                    {
+                        Ok(v) => v.iter().map(|i| i.value.id()).collect(),
+                        Err(e) => {
+                            self.recover(User::TYPE, url_token, Err(e))?;
+                            continue;
+                        }
+                    },
+                    Selection::Ids(ids) => ids.iter().map(|i| AnswerId(*i)).collect(),
+                };
+                let child = opt.child.and_then(|c| c.answer);
+                for a in answers {
+                    let r = self.apply_basic::<Answer, _, _>(prog, a, child).await;
+                    self.recover(Answer::TYPE, a, r)?;
+                }
+            }
         }
         Ok(())
     }
@@ -0,0 +1,244 @@
+use super::spec::*;
+use crate::item::{
+    any::Any, column, user, Answer, Article, Collection, Column, Comment, Item, ItemContainer, Pin,
+    Question, User, VoidOpt,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+/// id of every item found by the enclosing container
+const EACH: &str = "*";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// fetch an item
+    Item { kind: &'static str, id: String },
+    /// update items in a container
+    Container {
+        kind: &'static str,
+        id: String,
+        item_kind: &'static str,
+        option: &'static str,
+    },
+}
+impl Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Item { kind, id } => write!(f, "get {} {}", kind, id),
+            Self::Container {
+                kind,
+                id,
+                item_kind,
+                option,
+            } => write!(f, "update {} ({}) in {} {}", item_kind, option, kind, id),
+        }
+    }
+}
+
+/// operation done when applying a manifest, nested steps are done for every item found
+/// by the enclosing container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub depth: usize,
+    pub operation: Operation,
+}
+impl Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:width$}{}", "", self.operation, width = self.depth * 2)
+    }
+}
+
+fn container<IC, O, I>(id: String) -> Operation
+where
+    IC: ItemContainer<O, I>,
+    I: Item,
+{
+    Operation::Container {
+        kind: IC::TYPE,
+        id,
+        item_kind: I::TYPE,
+        option: IC::OPTION_NAME,
+    }
+}
+
+struct Planner(Vec<Step>);
+impl Planner {
+    fn item<I: Item>(&mut self, depth: usize, id: String) {
+        self.0.push(Step {
+            depth,
+            operation: Operation::Item { kind: I::TYPE, id },
+        })
+    }
+    /// mirrors `apply_sub_container`, children are applied to every item found
+    fn sub_container<IC, O, I, Opt>(&mut self, depth: usize, id: &str, child: Option<Opt>)
+    where
+        IC: ItemContainer<O, I>,
+        I: Item,
+        Opt: PlanChild<I>,
+    {
+        let Some(child) = child else {
+            return;
+        };
+        self.0.push(Step {
+            depth,
+            operation: container::<IC, O, I>(id.to_owned()),
+        });
+        if child != Opt::default() {
+            child.plan(self, depth + 1, EACH);
+        }
+    }
+}
+
+trait PlanChild<I>: Default + Eq + Copy {
+    fn plan(&self, planner: &mut Planner, depth: usize, id: &str);
+}
+impl PlanChild<Comment> for CommentChild {
+    fn plan(&self, planner: &mut Planner, depth: usize, id: &str) {
+        planner.0.push(Step {
+            depth,
+            operation: container::<Comment, VoidOpt, Comment>(id.to_owned()),
+        })
+    }
+}
+macro_rules! basic_child {
+    ($($t:ty),+) => {
+        $(impl PlanChild<$t> for BasicChild {
+            fn plan(&self, planner: &mut Planner, depth: usize, id: &str) {
+                planner.sub_container::<$t, VoidOpt, Comment, _>(depth, id, self.comment)
+            }
+        })+
+    };
+}
+basic_child!(Answer, Any, Article, Pin);
+impl PlanChild<Collection> for CollectionChild {
+    fn plan(&self, planner: &mut Planner, depth: usize, id: &str) {
+        planner.sub_container::<Collection, VoidOpt, Comment, _>(depth, id, self.comment);
+        planner.sub_container::<Collection, VoidOpt, Any, _>(depth, id, self.item);
+    }
+}
+impl PlanChild<Column> for ColumnChild {
+    fn plan(&self, planner: &mut Planner, depth: usize, id: &str) {
+        planner.sub_container::<Column, column::Pinned, Any, _>(depth, id, self.pinned);
+        planner.sub_container::<Column, column::Regular, Any, _>(depth, id, self.regular);
+    }
+}
+impl PlanChild<Question> for QuestionChild {
+    fn plan(&self, planner: &mut Planner, depth: usize, id: &str) {
+        planner.sub_container::<Question, VoidOpt, Answer, _>(depth, id, self.answer);
+        planner.sub_container::<Question, VoidOpt, Comment, _>(depth, id, self.comment);
+    }
+}
+impl PlanChild<User> for UserChild {
+    fn plan(&self, planner: &mut Planner, depth: usize, id: &str) {
+        planner.sub_container::<User, VoidOpt, Answer, _>(depth, id, self.answer);
+        planner.sub_container::<User, VoidOpt, Article, _>(depth, id, self.article);
+        planner.sub_container::<User, user::Created, Collection, _>(
+            depth,
+            id,
+            self.collection.and_then(|v| v.created),
+        );
+        planner.sub_container::<User, user::Liked, Collection, _>(
+            depth,
+            id,
+            self.collection.and_then(|v| v.liked),
+        );
+        planner.sub_container::<User, VoidOpt, Column, _>(depth, id, self.column);
+        planner.sub_container::<User, VoidOpt, Pin, _>(depth, id, self.pin);
+    }
+}
+
+fn plan_items<I, K, C>(planner: &mut Planner, items: &BTreeMap<K, ItemOption<C>>)
+where
+    I: Item,
+    K: Display,
+    C: PlanChild<I>,
+{
+    for (id, opt) in items {
+        let id = id.to_string();
+        planner.item::<I>(0, id.clone());
+        if let Some(c) = opt.child {
+            if c != C::default() {
+                c.plan(planner, 0, id.as_str());
+            }
+        }
+    }
+}
+
+impl ManifestLeaf {
+    /// operations of [`Driver::apply_manifest_leaf`] in order, without touching network or store
+    ///
+    /// [`Driver::apply_manifest_leaf`]: crate::driver::Driver::apply_manifest_leaf
+    pub fn plan(&self) -> Vec<Step> {
+        let mut planner = Planner(Vec::new());
+        plan_items::<Answer, _, _>(&mut planner, &self.answer);
+        plan_items::<Article, _, _>(&mut planner, &self.article);
+        plan_items::<Collection, _, _>(&mut planner, &self.collection);
+        plan_items::<Column, _, _>(&mut planner, &self.column);
+        plan_items::<Pin, _, _>(&mut planner, &self.pin);
+        plan_items::<Question, _, _>(&mut planner, &self.question);
+        for (url_token, opt) in &self.user {
+            planner.item::<User>(0, url_token.clone());
+            if let Some(c) = opt.child {
+                if c != UserChild::default() {
+                    c.plan(&mut planner, 0, url_token.as_str());
+                }
+            }
+            let answer = opt.child.and_then(|c| c.answer);
+            match &opt.answer {
+                Some(Selection::All) => {
+                    planner.0.push(Step {
+                        depth: 0,
+                        operation: container::<User, VoidOpt, Answer>(url_token.clone()),
+                    });
+                    planner.item::<Answer>(1, EACH.to_owned());
+                    if let Some(c) = answer.filter(|c| *c != BasicChild::default()) {
+                        PlanChild::<Answer>::plan(&c, &mut planner, 1, EACH);
+                    }
+                }
+                Some(Selection::Ids(ids)) => {
+                    for id in ids {
+                        let id = id.to_string();
+                        planner.item::<Answer>(0, id.clone());
+                        if let Some(c) = answer.filter(|c| *c != BasicChild::default()) {
+                            PlanChild::<Answer>::plan(&c, &mut planner, 0, id.as_str());
+                        }
+                    }
+                }
+                None => (),
+            }
+        }
+        planner.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::QuestionId;
+
+    #[test]
+    fn plan() {
+        let leaf = ManifestLeaf {
+            question: BTreeMap::from([(
+                QuestionId(1),
+                ItemOption {
+                    child: Some(QuestionChild {
+                        comment: None,
+                        answer: Some(BasicChild {
+                            comment: Some(CommentChild::default()),
+                        }),
+                    }),
+                },
+            )]),
+            ..Default::default()
+        };
+        let lines: Vec<_> = leaf.plan().iter().map(|s| s.to_string()).collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("get question 1"), "{:?}", lines);
+        assert!(lines[1].starts_with("update answer"), "{:?}", lines);
+        assert!(lines[2].starts_with("  update comment"), "{:?}", lines);
+        assert!(lines[2].ends_with("in answer *"), "{:?}", lines);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::item::{AnswerId, ArticleId, CollectionId, ColumnId, PinId, QuestionId, UserId};
 use serde::{Deserialize, Serialize};
-use std::collections::{btree_map, BTreeMap};
+use std::collections::{btree_map, BTreeMap, BTreeSet};
 
 mod option {
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -137,13 +137,126 @@ child_opt!(
     (pin: PinChild),
     (question: QuestionChild)
 );
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+/// items of a container selected by id, written as `"all"` or a list of ids
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// every item found in the container
+    All,
+    Ids(BTreeSet<u64>),
+}
+const SELECT_ALL: &str = "all";
+impl Default for Selection {
+    fn default() -> Self {
+        Self::Ids(BTreeSet::new())
+    }
+}
+impl ConfValue for Selection {
+    fn merge(&mut self, other: Self) {
+        match (self, other) {
+            (Self::All, _) => (),
+            (s, Self::All) => *s = Self::All,
+            (Self::Ids(i), Self::Ids(o)) => i.extend(o),
+        }
+    }
+    fn diff(&mut self, other: &Self) {
+        match (self, other) {
+            (s, Self::All) => *s = Self::default(),
+            (Self::All, Self::Ids(_)) => (),
+            (Self::Ids(i), Self::Ids(o)) => i.retain(|v| !o.contains(v)),
+        }
+    }
+}
+impl Serialize for Selection {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::All => serializer.serialize_str(SELECT_ALL),
+            Self::Ids(i) => serializer.collect_seq(i),
+        }
+    }
+}
+impl<'de> Deserialize<'de> for Selection {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::{self, SeqAccess, Unexpected, Visitor};
+        use std::fmt;
+
+        /// element of an id list, `"all"` is recognized to report it clearly
+        enum Entry {
+            All,
+            Id(u64),
+        }
+        impl<'de> Deserialize<'de> for Entry {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct EntryVisitor;
+                impl<'de> Visitor<'de> for EntryVisitor {
+                    type Value = Entry;
+                    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        f.write_str("an id")
+                    }
+                    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Entry, E> {
+                        Ok(Entry::Id(v))
+                    }
+                    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Entry, E> {
+                        u64::try_from(v)
+                            .map(Entry::Id)
+                            .map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+                    }
+                    fn visit_str<E: de::Error>(self, v: &str) -> Result<Entry, E> {
+                        if v == SELECT_ALL {
+                            Ok(Entry::All)
+                        } else {
+                            Err(E::invalid_value(Unexpected::Str(v), &self))
+                        }
+                    }
+                }
+                deserializer.deserialize_any(EntryVisitor)
+            }
+        }
+
+        struct SelectionVisitor;
+        impl<'de> Visitor<'de> for SelectionVisitor {
+            type Value = Selection;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "\"{}\" or a list of ids", SELECT_ALL)
+            }
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Selection, E> {
+                if v == SELECT_ALL {
+                    Ok(Selection::All)
+                } else {
+                    Err(E::invalid_value(Unexpected::Str(v), &self))
+                }
+            }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Selection, A::Error> {
+                let mut ids = BTreeSet::new();
+                while let Some(e) = seq.next_element()? {
+                    match e {
+                        Entry::All => {
+                            return Err(de::Error::custom(format_args!(
+                                "\"{}\" can't be combined with explicit ids",
+                                SELECT_ALL
+                            )))
+                        }
+                        Entry::Id(i) => {
+                            ids.insert(i);
+                        }
+                    }
+                }
+                Ok(Selection::Ids(ids))
+            }
+        }
+        deserializer.deserialize_any(SelectionVisitor)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct UserOption {
     pub id: UserId,
     #[serde(default, with = "option", skip_serializing_if = "Option::is_none")]
     pub container: Option<bool>,
     #[serde(default, with = "option", skip_serializing_if = "Option::is_none")]
     pub child: Option<UserChild>,
+    /// answers of the user applied as items, with options of `child.answer`
+    #[serde(default, with = "option", skip_serializing_if = "Option::is_none")]
+    pub answer: Option<Selection>,
 }
 impl Default for UserOption {
     fn default() -> Self {
@@ -151,6 +264,7 @@ impl Default for UserOption {
             id: UserId([0; 16]),
             container: None,
             child: None,
+            answer: None,
         }
     }
 }
@@ -158,10 +272,12 @@ impl ConfValue for UserOption {
     fn merge(&mut self, other: Self) {
         self.container.merge(other.container);
         self.child.merge(other.child);
+        self.answer.merge(other.answer);
     }
     fn diff(&mut self, other: &Self) {
         self.container.diff(&other.container);
         self.child.diff(&other.child);
+        self.answer.diff(&other.answer);
     }
 }
 
@@ -192,6 +308,77 @@ leaf! {
     (user: String, UserOption)
 }
 
+/// containers reported at the top level for an enabled child option,
+/// processing its own children is reported once more
+fn sub_jobs<C: Default + PartialEq>(child: &Option<C>) -> u64 {
+    child
+        .as_ref()
+        .map_or(0, |c| if *c == C::default() { 1 } else { 2 })
+}
+fn basic_jobs(child: &Option<BasicChild>) -> u64 {
+    child.map_or(0, |c| sub_jobs(&c.comment))
+}
+impl ManifestLeaf {
+    /// number of items and containers reported at the top level when applying the leaf
+    pub fn job_count(&self) -> u64 {
+        let basic = self
+            .answer
+            .values()
+            .chain(self.article.values())
+            .chain(self.pin.values())
+            .map(|o| 1 + basic_jobs(&o.child))
+            .sum::<u64>();
+        let collection = self
+            .collection
+            .values()
+            .map(|o| {
+                1 + o
+                    .child
+                    .map_or(0, |c| sub_jobs(&c.comment) + sub_jobs(&c.item))
+            })
+            .sum::<u64>();
+        let column = self
+            .column
+            .values()
+            .map(|o| {
+                1 + o
+                    .child
+                    .map_or(0, |c| sub_jobs(&c.pinned) + sub_jobs(&c.regular))
+            })
+            .sum::<u64>();
+        let question = self
+            .question
+            .values()
+            .map(|o| {
+                1 + o
+                    .child
+                    .map_or(0, |c| sub_jobs(&c.comment) + sub_jobs(&c.answer))
+            })
+            .sum::<u64>();
+        let user = self
+            .user
+            .values()
+            .map(|o| {
+                let answers = match &o.answer {
+                    Some(Selection::All) => 1,
+                    Some(Selection::Ids(i)) => i.len() as u64,
+                    None => 0,
+                };
+                1 + answers
+                    + o.child.map_or(0, |c| {
+                        sub_jobs(&c.answer)
+                            + sub_jobs(&c.article)
+                            + c.collection
+                                .map_or(0, |col| sub_jobs(&col.created) + sub_jobs(&col.liked))
+                            + sub_jobs(&c.column)
+                            + sub_jobs(&c.pin)
+                    })
+            })
+            .sum::<u64>();
+        basic + collection + column + question + user
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Manifest {
     Leaf(ManifestLeaf),
@@ -210,3 +397,60 @@ impl Manifest {
         ret
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_count() {
+        let comment = BasicChild {
+            comment: Some(CommentChild::default()),
+        };
+        let leaf = ManifestLeaf {
+            answer: BTreeMap::from([
+                (AnswerId(1), ItemOption { child: None }),
+                (
+                    AnswerId(2),
+                    ItemOption {
+                        child: Some(comment),
+                    },
+                ),
+            ]),
+            question: BTreeMap::from([(
+                QuestionId(3),
+                ItemOption {
+                    child: Some(QuestionChild {
+                        comment: None,
+                        answer: Some(comment),
+                    }),
+                },
+            )]),
+            ..Default::default()
+        };
+        // answer 1, answer 2 and its comments, question 3 with its answers and their comments
+        assert_eq!(leaf.job_count(), 1 + 2 + 3);
+    }
+
+    #[test]
+    fn selection() {
+        assert_eq!(
+            ron::from_str::<Selection>("\"all\"").unwrap(),
+            Selection::All
+        );
+        let ids = ron::from_str::<Selection>("[3, 1]").unwrap();
+        assert_eq!(ids, Selection::Ids(BTreeSet::from([1, 3])));
+        assert_eq!(ron::to_string(&ids).unwrap(), "[1,3]");
+        assert_eq!(ron::to_string(&Selection::All).unwrap(), "\"all\"");
+        let err = ron::from_str::<Selection>("[1, \"all\"]").unwrap_err();
+        assert!(err.to_string().contains("can't be combined"), "{}", err);
+        assert!(ron::from_str::<Selection>("\"some\"").is_err());
+
+        let mut s = Selection::Ids(BTreeSet::from([1]));
+        s.merge(Selection::All);
+        assert_eq!(s, Selection::All);
+        let mut s = Selection::Ids(BTreeSet::from([1, 2]));
+        s.diff(&Selection::Ids(BTreeSet::from([2])));
+        assert_eq!(s, Selection::Ids(BTreeSet::from([1])));
+    }
+}
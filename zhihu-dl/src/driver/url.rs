@@ -0,0 +1,121 @@
+use super::{Driver, ItemError};
+pub use crate::item::url::UrlTarget;
+use crate::{
+    item::{
+        column::ColumnRef, url::ParseUrlError, user::StoreId, Collection, Column, Fetchable, Item,
+        UserId,
+    },
+    progress::{self, Reporter},
+    raw_data::FromRaw,
+    store::BasicStoreItem,
+};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UrlError {
+    #[error(transparent)]
+    Parse(#[from] ParseUrlError),
+    #[error("failed to resolve user {0}")]
+    ResolveUser(String, #[source] reqwest::Error),
+    #[error("failed to download {0}")]
+    Item(String, #[source] Box<ItemError>),
+}
+
+impl Driver {
+    async fn resolve_user<P: progress::Progress>(
+        &self,
+        prog: &P,
+        url_token: &str,
+    ) -> Result<UserId, reqwest::Error> {
+        #[derive(Deserialize)]
+        struct Reply {
+            id: FromRaw<UserId>,
+        }
+        let resp = self
+            .client
+            .send(
+                prog,
+                self.client.http_client.get(format!(
+                    "https://www.zhihu.com/api/v4/members/{}",
+                    url_token
+                )),
+            )
+            .await?
+            .error_for_status()?;
+        self.client.json::<Reply>(resp).await.map(|r| r.id.0)
+    }
+    async fn download_url_item<'a, I, P, Pat>(
+        &mut self,
+        prog: &P,
+        id: I::Id<'a>,
+        relative: bool,
+        dest: Pat,
+    ) -> Result<(), UrlError>
+    where
+        I: Fetchable + Item + BasicStoreItem,
+        P: Reporter,
+        Pat: AsRef<Path>,
+    {
+        self.download_item::<I, _, _>(prog, id, relative, dest)
+            .await
+            .map(|_| ())
+            .map_err(|e| UrlError::Item(format!("{} {}", I::TYPE, id), Box::new(e)))
+    }
+    /// download the object a zhihu url points to, see [`UrlTarget`] for supported urls
+    pub async fn download_url<P: Reporter, Pat: AsRef<Path>>(
+        &mut self,
+        prog: &P,
+        url: &str,
+        relative: bool,
+        dest: Pat,
+    ) -> Result<UrlTarget, UrlError> {
+        use crate::item::{Answer, Article, Pin, Question, User};
+        let target: UrlTarget = url.parse()?;
+        log::info!("url {} points to {}", url, target);
+        match &target {
+            UrlTarget::Answer(i) => {
+                self.download_url_item::<Answer, _, _>(prog, *i, relative, dest)
+                    .await
+            }
+            UrlTarget::Article(i) => {
+                self.download_url_item::<Article, _, _>(prog, *i, relative, dest)
+                    .await
+            }
+            UrlTarget::Collection(i) => {
+                self.download_url_item::<Collection, _, _>(prog, *i, relative, dest)
+                    .await
+            }
+            UrlTarget::Column(i) => {
+                self.download_url_item::<Column, _, _>(
+                    prog,
+                    ColumnRef(i.0.as_str()),
+                    relative,
+                    dest,
+                )
+                .await
+            }
+            UrlTarget::Pin(i) => {
+                self.download_url_item::<Pin, _, _>(prog, *i, relative, dest)
+                    .await
+            }
+            UrlTarget::Question(i) => {
+                self.download_url_item::<Question, _, _>(prog, *i, relative, dest)
+                    .await
+            }
+            UrlTarget::User(t) if self.dry_run => {
+                prog.plan("Would fetch", format_args!("user {}", t));
+                Ok(())
+            }
+            UrlTarget::User(t) => {
+                let id = self
+                    .resolve_user(prog, t)
+                    .await
+                    .map_err(|e| UrlError::ResolveUser(t.clone(), e))?;
+                self.download_url_item::<User, _, _>(prog, StoreId(id, t), relative, dest)
+                    .await
+            }
+        }?;
+        Ok(target)
+    }
+}
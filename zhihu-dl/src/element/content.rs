@@ -17,8 +17,10 @@ use web_dl_base::{
     storable::Storable,
 };
 
+pub mod clean;
 pub mod document;
 mod html_reader;
+mod html_writer;
 
 pub trait Convertor {
     type Config<'a>
@@ -32,16 +34,132 @@ pub trait Convertor {
         dest: P,
     ) -> Result<(), Self::Err>;
 }
-// pub mod convertor {
-//     pub mod pandoc;
-// }
+pub mod convertor {
+    use super::{document::Document, Convertor};
+    use std::{path::Path, str::FromStr};
+    use web_dl_base::media;
 
-pub const VERSION: Version = Version { major: 1, minor: 1 };
+    pub mod epub;
+    pub mod html;
+    pub mod markdown;
+    // pub mod pandoc;
+
+    /// rendered output selectable at runtime
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ConvertFormat {
+        Markdown,
+        Html,
+        Epub,
+    }
+    #[derive(Debug, thiserror::Error)]
+    pub enum ConvertFormatError {
+        #[error("failed to convert to markdown")]
+        Markdown(
+            #[from]
+            #[source]
+            markdown::ConvertError,
+        ),
+        #[error("failed to convert to html")]
+        Html(
+            #[from]
+            #[source]
+            html::ConvertError,
+        ),
+        #[error("failed to convert to epub")]
+        Epub(
+            #[from]
+            #[source]
+            epub::ConvertError,
+        ),
+    }
+    #[derive(Debug, thiserror::Error)]
+    #[error("unknown format {0}, expected markdown, html or epub")]
+    pub struct UnknownFormat(String);
+
+    impl FromStr for ConvertFormat {
+        type Err = UnknownFormat;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "markdown" | "md" => Ok(Self::Markdown),
+                "html" => Ok(Self::Html),
+                "epub" => Ok(Self::Epub),
+                _ => Err(UnknownFormat(s.to_owned())),
+            }
+        }
+    }
+    impl ConvertFormat {
+        pub fn extension(self) -> &'static str {
+            match self {
+                Self::Markdown => "md",
+                Self::Html => "html",
+                Self::Epub => "epub",
+            }
+        }
+        /// render with default config of the convertor, `title` is used by html and epub
+        pub fn convert<S: AsRef<Path>, P: AsRef<Path>>(
+            self,
+            image_store: S,
+            document: &Document,
+            title: &str,
+            dest: P,
+        ) -> Result<(), ConvertFormatError> {
+            match self {
+                Self::Markdown => {
+                    markdown::Markdown::convert(image_store, document, &Default::default(), dest)?
+                }
+                Self::Html => html::Html::convert(
+                    image_store,
+                    document,
+                    &html::HtmlConfig {
+                        title: Some(title),
+                        ..Default::default()
+                    },
+                    dest,
+                )?,
+                Self::Epub => epub::Epub::convert(
+                    image_store,
+                    document,
+                    &epub::EpubConfig {
+                        title,
+                        ..Default::default()
+                    },
+                    dest,
+                )?,
+            }
+            Ok(())
+        }
+    }
+
+    /// path of an image relative to the converted file
+    pub(crate) fn image_path(src: &media::Image, images_store: &Path) -> String {
+        match src {
+            media::Image::Url(s) => s.to_owned(),
+            media::Image::Ref(r) => media::FsBackend::new(images_store)
+                .path(&r.hash, r.extension.as_str())
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+}
+
+pub const VERSION: Version = Version { major: 1, minor: 2 };
+
+/// whether the whole content was returned by the server
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentState {
+    #[default]
+    Full,
+    /// paywalled or login walled, content holds at most a preview
+    Restricted { reason: String },
+}
 
 #[derive(Debug, Clone, Storable, StoreImage, Serialize, Deserialize)]
 #[store(format = "yaml")]
 pub struct ContentInfo {
     pub is_empty: bool,
+    #[serde(default)]
+    pub state: ContentState,
     #[has_image]
     pub images: Vec<ImageRef>,
 }
@@ -69,6 +187,7 @@ impl<'de> Deserialize<'de> for FromRaw<Content> {
                 version: VERSION,
                 info: ContentInfo {
                     is_empty: d.is_empty(),
+                    state: ContentState::Full,
                     images: Vec::new(),
                 },
                 document: None,
@@ -83,6 +202,7 @@ impl Default for Content {
             version: VERSION,
             info: ContentInfo {
                 is_empty: true,
+                state: ContentState::Full,
                 images: Vec::new(),
             },
             document: None,
@@ -92,6 +212,33 @@ impl Default for Content {
 }
 
 impl Content {
+    /// reason why the content is incomplete
+    pub fn restriction(&self) -> Option<&str> {
+        match &self.info.state {
+            ContentState::Full => None,
+            ContentState::Restricted { reason } => Some(reason.as_str()),
+        }
+    }
+    /// stored image fetched from `url`
+    pub fn image_ref(&self, url: &str) -> Option<&ImageRef> {
+        self.info.images.iter().find(|i| i.url == url)
+    }
+    /// append images as figures, skipping those already in the html
+    pub(crate) fn append_figures<'a, I: IntoIterator<Item = &'a str>>(&mut self, urls: I) {
+        let html = self.raw_html.get_or_insert_with(String::new);
+        for url in urls {
+            if html.contains(url) {
+                continue;
+            }
+            html.push_str("<figure><img src=\"");
+            html.push_str(&url.replace('&', "&amp;").replace('"', "&quot;"));
+            html.push_str("\"></figure>");
+        }
+        self.info.is_empty = html.is_empty();
+        if html.is_empty() {
+            self.raw_html = None;
+        }
+    }
     fn image_map(&self) -> HashMap<&'_ str, &'_ ImageRef> {
         self.info
             .images
@@ -99,13 +246,15 @@ impl Content {
             .map(|i| (i.url.as_str(), i))
             .collect()
     }
-    pub fn convert_inline(&mut self) {
+    pub fn convert_inline(&mut self, opt: &ConvertOpt) {
         self.document = self
             .raw_html
             .as_ref()
-            .map(|d| html_reader::from_raw_html_inline(d, &self.image_map()))
+            .map(|d| html_reader::from_raw_html_inline(d, &self.image_map(), opt.link_policy))
     }
-    pub(crate) fn image_urls(&self) -> HashSet<Url> {
+    /// urls of images and video posters in the raw html, as fetched with the content,
+    /// equations are included, see [`document::is_equation_url`]
+    pub fn image_urls(&self) -> HashSet<Url> {
         let html = match &self.raw_html {
             Some(h) => h,
             None => return HashSet::default(),
@@ -145,6 +294,22 @@ impl Content {
                         }
                         TokenSinkResult::Continue
                     }
+                    // poster of video embeds
+                    Token::TagToken(Tag {
+                        kind: TagKind::StartTag,
+                        name: local_name!("a"),
+                        attrs,
+                        ..
+                    }) => {
+                        if let Some(u) = attrs
+                            .iter()
+                            .find(|i| i.name.local.as_bytes() == b"data-poster")
+                            .and_then(|i| Url::parse(&i.value).ok())
+                        {
+                            self.0.insert(u);
+                        }
+                        TokenSinkResult::Continue
+                    }
                     _ => TokenSinkResult::Continue,
                 }
             }
@@ -165,8 +330,16 @@ impl Content {
         if urls.is_empty() {
             false
         } else {
-            self.info.images =
-                fetch_images_iter(&client.http_client, images_prog, urls.into_iter()).await;
+            let mut report = fetch_images_iter(
+                &client.http_client,
+                images_prog,
+                urls.into_iter(),
+                client.image_concurrency,
+                &client.image_fetch,
+            )
+            .await;
+            client.record_images(&mut report);
+            self.info.images = report.images;
             true
         }
     }
@@ -193,9 +366,32 @@ impl Display for SelectorError {
 }
 impl std::error::Error for SelectorError {}
 
+/// handling of `link.zhihu.com` redirect links
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkPolicy {
+    /// link to the redirect target
+    #[default]
+    Unwrap,
+    /// keep the redirect url
+    Keep,
+    /// link to the redirect target and keep the redirect url as original
+    Both,
+}
+
+/// options of converting raw html to [`document::Document`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConvertOpt {
+    /// map code block language hints to canonical names, see [`document::canonical_language`]
+    pub normalize_code_language: bool,
+    pub link_policy: LinkPolicy,
+    pub clean: clean::CleanConfig,
+    /// collect notes into a footnote list at the end, see [`document::Document::aggregate_footnotes`]
+    pub aggregate_footnotes: bool,
+}
+
 pub trait HasContent {
     fn is_html_converted(&self) -> bool;
-    fn convert_html(&mut self);
+    fn convert_html(&mut self, opt: &ConvertOpt);
 
     fn get_main_content(&self) -> Option<&'_ Content>;
 }
@@ -205,11 +401,19 @@ impl HasContent for Content {
     fn is_html_converted(&self) -> bool {
         self.document.is_some()
     }
-    fn convert_html(&mut self) {
-        self.document = self
-            .raw_html
-            .as_ref()
-            .map(|h| html_reader::from_raw_html(h.as_str(), &self.image_map()));
+    fn convert_html(&mut self, opt: &ConvertOpt) {
+        self.document = self.raw_html.as_ref().map(|h| {
+            let mut doc =
+                html_reader::from_raw_html(h.as_str(), &self.image_map(), opt.link_policy);
+            if opt.normalize_code_language {
+                doc.normalize_code_languages();
+            }
+            clean::clean_document(&mut doc, &opt.clean);
+            if opt.aggregate_footnotes {
+                doc.aggregate_footnotes();
+            }
+            doc
+        });
     }
     fn get_main_content(&self) -> Option<&'_ Content> {
         Some(self)
@@ -220,9 +424,9 @@ impl<I: HasContent> HasContent for Vec<I> {
     fn is_html_converted(&self) -> bool {
         self.iter().all(I::is_html_converted)
     }
-    fn convert_html(&mut self) {
+    fn convert_html(&mut self, opt: &ConvertOpt) {
         for i in self {
-            i.convert_html()
+            i.convert_html(opt)
         }
     }
     fn get_main_content(&self) -> Option<&'_ Content> {
@@ -233,9 +437,9 @@ impl<I: HasContent> HasContent for Option<I> {
     fn is_html_converted(&self) -> bool {
         self.as_ref().map_or(true, I::is_html_converted)
     }
-    fn convert_html(&mut self) {
+    fn convert_html(&mut self, opt: &ConvertOpt) {
         if let Some(v) = self {
-            v.convert_html()
+            v.convert_html(opt)
         }
     }
     fn get_main_content(&self) -> Option<&'_ Content> {
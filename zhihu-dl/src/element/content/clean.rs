@@ -0,0 +1,151 @@
+use super::document::*;
+
+/// noise removed from parsed documents, nothing is removed by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CleanConfig {
+    /// drop trailing `编辑于`/`发布于` paragraphs
+    pub edit_footer: bool,
+    /// drop link cards to zhihu paid content and ad landing pages
+    pub promotion: bool,
+}
+
+const FOOTER_PREFIXES: [&str; 2] = ["编辑于", "发布于"];
+/// paths of `www.zhihu.com` pages promoted by link cards
+const PROMOTION_PATHS: [&str; 5] = ["/xen/market/", "/market/", "/lives/", "/remix/", "/pub/"];
+/// hosts serving ad landing pages
+const PROMOTION_HOSTS: [&str; 1] = ["xg.zhihu.com"];
+
+fn paragraph_text(block: &Block) -> Option<String> {
+    match block {
+        Block::Plain(l) | Block::Paragraph(l) => {
+            let mut ret = String::new();
+            l.iter().for_each(|i| inline_to_text(i, &mut ret));
+            Some(ret)
+        }
+        _ => None,
+    }
+}
+fn is_blank(block: &Block) -> bool {
+    paragraph_text(block).map_or(false, |t| t.trim().is_empty())
+}
+fn is_edit_footer(block: &Block) -> bool {
+    paragraph_text(block).map_or(false, |t| {
+        let t = t.trim_start();
+        FOOTER_PREFIXES.iter().any(|p| t.starts_with(p))
+    })
+}
+fn is_promotion(block: &Block) -> bool {
+    let target = match block {
+        Block::Paragraph(l) => match l.as_slice() {
+            [Inline::Link { target, .. }] => target,
+            _ => return false,
+        },
+        _ => return false,
+    };
+    let Ok(u) = url::Url::parse(target) else {
+        return false;
+    };
+    match u.host_str() {
+        Some("www.zhihu.com") => PROMOTION_PATHS.iter().any(|p| u.path().starts_with(p)),
+        Some(h) => PROMOTION_HOSTS.contains(&h),
+        None => false,
+    }
+}
+
+/// remove noise blocks selected by `config` from the top level of the document
+pub fn clean_document(document: &mut Document, config: &CleanConfig) {
+    if config.promotion {
+        document.data.retain(|b| {
+            let ret = !is_promotion(b);
+            if !ret {
+                log::debug!("removed promotion {:?}", b);
+            }
+            ret
+        });
+    }
+    if config.edit_footer {
+        loop {
+            let end = document
+                .data
+                .iter()
+                .rposition(|b| !is_blank(b))
+                .map_or(0, |p| p + 1);
+            document.data.truncate(end);
+            match document.data.last() {
+                Some(b) if is_edit_footer(b) => {
+                    log::debug!("removed edit footer {:?}", b);
+                    document.data.pop();
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn para(s: &str) -> Block {
+        Block::Paragraph(vec![Inline::Text(s.to_owned())])
+    }
+    fn link_card(target: &str) -> Block {
+        Block::Paragraph(vec![Inline::Link {
+            description: Some(vec![Inline::Text("card".to_owned())]),
+            target: target.to_owned(),
+            original: None,
+        }])
+    }
+    fn document() -> Document {
+        Document {
+            version: VERSION,
+            data: vec![
+                para("编辑于开头的正文"),
+                link_card("https://www.zhihu.com/xen/market/remix/paid_column/1"),
+                link_card("https://www.zhihu.com/question/1"),
+                para("text"),
+                para("编辑于 2023-01-01 12:00・IP 属地北京"),
+                para(" "),
+            ],
+        }
+    }
+    fn texts(doc: &Document) -> Vec<String> {
+        doc.data
+            .iter()
+            .map(|b| match b {
+                Block::Paragraph(l) => match l.as_slice() {
+                    [Inline::Link { target, .. }] => target.clone(),
+                    _ => paragraph_text(b).unwrap(),
+                },
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_keeps_everything() {
+        let mut doc = document();
+        clean_document(&mut doc, &CleanConfig::default());
+        assert_eq!(doc.data.len(), 6);
+    }
+
+    #[test]
+    fn clean() {
+        let mut doc = document();
+        clean_document(
+            &mut doc,
+            &CleanConfig {
+                edit_footer: true,
+                promotion: true,
+            },
+        );
+        assert_eq!(
+            texts(&doc),
+            [
+                "编辑于开头的正文",
+                "https://www.zhihu.com/question/1",
+                "text"
+            ]
+        );
+    }
+}
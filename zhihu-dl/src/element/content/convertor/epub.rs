@@ -0,0 +1,420 @@
+use super::{super::document::*, html};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Seek, Write},
+    path::{Path, PathBuf},
+};
+use web_dl_base::media::{self, ImageBackend, ImageRef};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+/// directory of images inside the package, relative to the content documents
+const IMAGE_DIR: &str = "images";
+
+fn inline_images<'a>(inline: &'a Inline, dest: &mut BTreeMap<String, &'a ImageRef>) {
+    match inline {
+        Inline::Emphasis(v) | Inline::Strong(v) => v.iter().for_each(|i| inline_images(i, dest)),
+        Inline::Image {
+            src: media::Image::Ref(r),
+            description,
+            ..
+        } => {
+            dest.insert(r.to_string(), r);
+            description
+                .iter()
+                .flatten()
+                .for_each(|i| inline_images(i, dest));
+        }
+        Inline::Image { description, .. } => description
+            .iter()
+            .flatten()
+            .for_each(|i| inline_images(i, dest)),
+        Inline::Link { description, .. } => description
+            .iter()
+            .flatten()
+            .for_each(|i| inline_images(i, dest)),
+        Inline::Note { content } => block_images(content, dest),
+        Inline::Text(_)
+        | Inline::Break
+        | Inline::Code { .. }
+        | Inline::Math { .. }
+        | Inline::FootnoteRef { .. }
+        | Inline::Checkbox { .. } => (),
+    }
+}
+fn block_images<'a>(blocks: &'a [Block], dest: &mut BTreeMap<String, &'a ImageRef>) {
+    for b in blocks {
+        match b {
+            Block::Header { content, .. } | Block::Plain(content) | Block::Paragraph(content) => {
+                content.iter().for_each(|i| inline_images(i, dest))
+            }
+            Block::Figure {
+                src, description, ..
+            } => {
+                if let media::Image::Ref(r) = src {
+                    dest.insert(r.to_string(), r);
+                }
+                description
+                    .iter()
+                    .flatten()
+                    .for_each(|i| inline_images(i, dest));
+            }
+            Block::Video {
+                poster: Some(media::Image::Ref(r)),
+                ..
+            } => {
+                dest.insert(r.to_string(), r);
+            }
+            Block::SimpleTable { body } => {
+                body.iter().flatten().for_each(|c| block_images(c, dest))
+            }
+            Block::BlockQuote { content } => block_images(content, dest),
+            Block::UnorderedList { items }
+            | Block::OrderedList { items, .. }
+            | Block::FootnoteList { notes: items } => {
+                items.iter().for_each(|i| block_images(i, dest))
+            }
+            Block::HorizontalRule
+            | Block::CodeBlock { .. }
+            | Block::Math { .. }
+            | Block::Video { .. } => (),
+        }
+    }
+}
+
+fn media_type(image: &ImageRef) -> String {
+    if let Some(m) = &image.mime {
+        return m.clone();
+    }
+    match image.extension.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+    .to_owned()
+}
+fn escaped(text: &str) -> String {
+    let mut ret = String::new();
+    html::escape(text, &mut ret);
+    ret
+}
+
+/// a content document of the epub
+#[derive(Debug, Clone, Copy)]
+pub struct Chapter<'a> {
+    pub title: &'a str,
+    pub document: &'a Document,
+}
+
+pub struct Epub;
+#[derive(Debug, Clone)]
+pub struct EpubConfig<'a> {
+    pub title: &'a str,
+    pub author: Option<&'a str>,
+    pub language: &'a str,
+    /// unique identifier of the publication, derived from title when `None`
+    pub identifier: Option<&'a str>,
+}
+impl<'a> Default for EpubConfig<'a> {
+    fn default() -> Self {
+        Self {
+            title: "Untitled",
+            author: None,
+            language: "zh",
+            identifier: None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    #[error("failed to prepare destination path")]
+    DestPrep(
+        #[from]
+        #[source]
+        crate::util::relative_path::DestPrepError,
+    ),
+    #[error("failed to create {}", path.display())]
+    Create {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to read image {image}")]
+    Image {
+        image: String,
+        #[source]
+        source: Box<media::Error>,
+    },
+    #[error("failed to write epub package")]
+    Zip(
+        #[from]
+        #[source]
+        zip::result::ZipError,
+    ),
+    #[error("failed to write epub package")]
+    Write(
+        #[from]
+        #[source]
+        std::io::Error,
+    ),
+}
+
+fn chapter_name(idx: usize) -> String {
+    format!("chapter{}.xhtml", idx + 1)
+}
+fn content_document(title: &str, language: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+            "<!DOCTYPE html>\n",
+            "<html xmlns=\"http://www.w3.org/1999/xhtml\" ",
+            "xmlns:epub=\"http://www.idpf.org/2007/ops\" xml:lang=\"{1}\">\n",
+            "<head>\n<meta charset=\"utf-8\" />\n<title>{0}</title>\n</head>\n",
+            "<body>\n{2}</body>\n</html>\n"
+        ),
+        escaped(title),
+        escaped(language),
+        body
+    )
+}
+fn nav_document(chapters: &[Chapter], config: &EpubConfig) -> String {
+    let mut body = String::from("<nav epub:type=\"toc\" id=\"toc\">\n<ol>\n");
+    for (idx, c) in chapters.iter().enumerate() {
+        body.push_str(
+            format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                chapter_name(idx),
+                escaped(c.title)
+            )
+            .as_str(),
+        );
+    }
+    body.push_str("</ol>\n</nav>\n");
+    content_document(config.title, config.language, body.as_str())
+}
+fn package_document(
+    chapters: &[Chapter],
+    images: &BTreeMap<String, &ImageRef>,
+    config: &EpubConfig,
+) -> String {
+    let identifier = match config.identifier {
+        Some(i) => i.to_owned(),
+        None => format!("web-dl:{}", config.title),
+    };
+    let mut ret = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+            "<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" ",
+            "unique-identifier=\"uid\">\n",
+            "<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n",
+            "<dc:identifier id=\"uid\">{}</dc:identifier>\n",
+            "<dc:title>{}</dc:title>\n",
+            "<dc:language>{}</dc:language>\n"
+        ),
+        escaped(identifier.as_str()),
+        escaped(config.title),
+        escaped(config.language)
+    );
+    if let Some(a) = config.author {
+        ret.push_str(format!("<dc:creator>{}</dc:creator>\n", escaped(a)).as_str());
+    }
+    ret.push_str(
+        format!(
+            "<meta property=\"dcterms:modified\">{}</meta>\n</metadata>\n<manifest>\n",
+            chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ")
+        )
+        .as_str(),
+    );
+    ret.push_str(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\" />\n",
+    );
+    for idx in 0..chapters.len() {
+        ret.push_str(
+            format!(
+                "<item id=\"chapter{}\" href=\"{}\" media-type=\"application/xhtml+xml\" />\n",
+                idx + 1,
+                chapter_name(idx)
+            )
+            .as_str(),
+        );
+    }
+    for (idx, (name, r)) in images.iter().enumerate() {
+        ret.push_str(
+            format!(
+                "<item id=\"image{}\" href=\"{}/{}\" media-type=\"{}\" />\n",
+                idx + 1,
+                IMAGE_DIR,
+                escaped(name),
+                escaped(media_type(r).as_str())
+            )
+            .as_str(),
+        );
+    }
+    ret.push_str("</manifest>\n<spine>\n");
+    for idx in 0..chapters.len() {
+        ret.push_str(format!("<itemref idref=\"chapter{}\" />\n", idx + 1).as_str());
+    }
+    ret.push_str("</spine>\n</package>\n");
+    ret
+}
+
+/// write chapters and the images they reference as an epub3 package
+pub fn write_epub<W: Write + Seek>(
+    writer: W,
+    image_store: &Path,
+    chapters: &[Chapter],
+    config: &EpubConfig,
+) -> Result<W, ConvertError> {
+    let mut images = BTreeMap::new();
+    for c in chapters {
+        block_images(&c.document.data, &mut images);
+    }
+    let backend = media::FsBackend::new(image_store);
+
+    let mut zip = ZipWriter::new(writer);
+    // mimetype must be the first entry and stored uncompressed
+    zip.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("META-INF/container.xml", options)?;
+    zip.write_all(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+            "<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n",
+            "<rootfiles>\n",
+            "<rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\" />\n",
+            "</rootfiles>\n",
+            "</container>\n"
+        )
+        .as_bytes(),
+    )?;
+    zip.start_file("OEBPS/content.opf", options)?;
+    zip.write_all(package_document(chapters, &images, config).as_bytes())?;
+    zip.start_file("OEBPS/nav.xhtml", options)?;
+    zip.write_all(nav_document(chapters, config).as_bytes())?;
+    for (idx, c) in chapters.iter().enumerate() {
+        zip.start_file(format!("OEBPS/{}", chapter_name(idx)), options)?;
+        let body = html::to_xhtml_body(c.document, Path::new(IMAGE_DIR));
+        zip.write_all(content_document(c.title, config.language, body.as_str()).as_bytes())?;
+    }
+    for (name, r) in images {
+        let data = backend
+            .get(&r.hash, &r.extension)
+            .map_err(|e| ConvertError::Image {
+                image: name.clone(),
+                source: Box::new(e),
+            })?;
+        // images are already compressed
+        zip.start_file(
+            format!("OEBPS/{}/{}", IMAGE_DIR, name),
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )?;
+        zip.write_all(&data)?;
+    }
+    Ok(zip.finish()?)
+}
+
+/// write chapters as an epub file at `dest`
+pub fn convert_chapters<S: AsRef<Path>, P: AsRef<Path>>(
+    image_store: S,
+    chapters: &[Chapter],
+    config: &EpubConfig,
+    dest: P,
+) -> Result<(), ConvertError> {
+    use crate::util::relative_path::prepare_dest;
+    let canon_dest = prepare_dest(dest.as_ref()).map_err(ConvertError::from)?;
+    let file = File::create(&canon_dest).map_err(|e| ConvertError::Create {
+        path: canon_dest,
+        source: e,
+    })?;
+    write_epub(file, image_store.as_ref(), chapters, config).map(|_| ())
+}
+
+impl super::super::Convertor for Epub {
+    type Config<'a>
+        = EpubConfig<'a>
+    where
+        Self: 'a;
+    type Err = ConvertError;
+    fn convert<S: AsRef<Path>, P: AsRef<Path>>(
+        image_store: S,
+        document: &Document,
+        config: &Self::Config<'_>,
+        dest: P,
+    ) -> Result<(), Self::Err> {
+        convert_chapters(
+            image_store,
+            &[Chapter {
+                title: config.title,
+                document,
+            }],
+            config,
+            dest,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::content::document;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn package() {
+        let doc = Document {
+            version: document::VERSION,
+            data: vec![
+                Block::Paragraph(vec![
+                    Inline::Text("a".to_owned()),
+                    Inline::Break,
+                    Inline::Text("b".to_owned()),
+                ]),
+                Block::Figure {
+                    alt_text: Some("remote".to_owned()),
+                    description: None,
+                    src: media::Image::Url("https://example.com/a.png".to_owned()),
+                },
+            ],
+        };
+        let data = write_epub(
+            Cursor::new(Vec::new()),
+            Path::new("images"),
+            &[Chapter {
+                title: "Answer",
+                document: &doc,
+            }],
+            &EpubConfig {
+                title: "Book",
+                author: Some("author"),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .into_inner();
+        let mut archive = zip::ZipArchive::new(Cursor::new(data)).unwrap();
+        assert_eq!(archive.by_index(0).unwrap().name(), "mimetype");
+        let mut chapter = String::new();
+        archive
+            .by_name("OEBPS/chapter1.xhtml")
+            .unwrap()
+            .read_to_string(&mut chapter)
+            .unwrap();
+        assert!(chapter.contains("<p>a<br />\nb</p>\n<figure>\nremote\n</figure>"));
+        let mut opf = String::new();
+        archive
+            .by_name("OEBPS/content.opf")
+            .unwrap()
+            .read_to_string(&mut opf)
+            .unwrap();
+        assert!(opf.contains("<dc:creator>author</dc:creator>"));
+        assert!(opf.contains("<itemref idref=\"chapter1\" />"));
+    }
+}
@@ -0,0 +1,481 @@
+use super::{super::document::*, image_path};
+use std::path::{Path, PathBuf};
+use web_dl_base::media;
+
+pub(crate) fn escape(text: &str, dest: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => dest.push_str("&amp;"),
+            '<' => dest.push_str("&lt;"),
+            '>' => dest.push_str("&gt;"),
+            '"' => dest.push_str("&quot;"),
+            '\'' => dest.push_str("&#39;"),
+            _ => dest.push(c),
+        }
+    }
+}
+
+struct Writer<'a> {
+    images_store: &'a Path,
+    /// emit self-closing void elements and skip remote images, for epub content documents
+    xhtml: bool,
+    notes: Vec<String>,
+    dest: String,
+}
+impl<'a> Writer<'a> {
+    fn inline_text(inlines: &[Inline], dest: &mut String) {
+        for i in inlines {
+            match i {
+                Inline::Text(t) => dest.push_str(t),
+                Inline::Break => dest.push(' '),
+                Inline::Code { code } => dest.push_str(code),
+                Inline::Emphasis(v) | Inline::Strong(v) => Self::inline_text(v, dest),
+                Inline::Math { tex_code } => dest.push_str(tex_code),
+                Inline::Note { .. } | Inline::FootnoteRef { .. } | Inline::Checkbox { .. } => (),
+                Inline::Image { alt_text, .. } => alt_text.iter().for_each(|t| dest.push_str(t)),
+                Inline::Link { description, .. } => {
+                    description.iter().for_each(|v| Self::inline_text(v, dest))
+                }
+            }
+        }
+    }
+    fn void_end(&self) -> &'static str {
+        if self.xhtml {
+            " />"
+        } else {
+            ">"
+        }
+    }
+    fn img(&mut self, alt_text: &Option<String>, title: Option<&str>, src: &media::Image) {
+        if let (true, media::Image::Url(u)) = (self.xhtml, src) {
+            log::warn!("skipped image {} not in image store", u);
+            if let Some(alt) = alt_text {
+                escape(alt, &mut self.dest);
+            }
+            return;
+        }
+        self.dest.push_str("<img src=\"");
+        escape(image_path(src, self.images_store).as_str(), &mut self.dest);
+        self.dest.push_str("\" alt=\"");
+        if let Some(alt) = alt_text {
+            escape(alt, &mut self.dest);
+        }
+        self.dest.push('"');
+        if let Some(t) = title {
+            self.dest.push_str(" title=\"");
+            escape(t, &mut self.dest);
+            self.dest.push('"');
+        }
+        self.dest.push_str(self.void_end());
+    }
+    fn tagged(&mut self, tag: &str, inlines: &[Inline]) {
+        self.dest.push('<');
+        self.dest.push_str(tag);
+        self.dest.push('>');
+        self.inlines(inlines);
+        self.dest.push_str("</");
+        self.dest.push_str(tag);
+        self.dest.push('>');
+    }
+    fn inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Text(t) => escape(t, &mut self.dest),
+            Inline::Break => {
+                self.dest.push_str("<br");
+                self.dest.push_str(self.void_end());
+                self.dest.push('\n');
+            }
+            Inline::Emphasis(e) => self.tagged("em", e),
+            Inline::Strong(s) => self.tagged("strong", s),
+            Inline::Code { code } => {
+                self.dest.push_str("<code>");
+                escape(code, &mut self.dest);
+                self.dest.push_str("</code>");
+            }
+            Inline::Math { tex_code } => {
+                self.dest.push_str("<span class=\"math inline\">\\(");
+                escape(tex_code.trim(), &mut self.dest);
+                self.dest.push_str("\\)</span>");
+            }
+            Inline::Image {
+                alt_text,
+                description,
+                src,
+            } => {
+                let title = description.as_ref().map(|d| {
+                    let mut s = String::new();
+                    Self::inline_text(d, &mut s);
+                    s
+                });
+                self.img(alt_text, title.as_deref(), src)
+            }
+            Inline::Link {
+                description,
+                target,
+                ..
+            } => {
+                self.dest.push_str("<a href=\"");
+                escape(target, &mut self.dest);
+                self.dest.push_str("\">");
+                match description {
+                    Some(d) => self.inlines(d),
+                    None => escape(target, &mut self.dest),
+                }
+                self.dest.push_str("</a>");
+            }
+            Inline::Checkbox { checked } => self.dest.push_str(match (self.xhtml, *checked) {
+                (false, true) => "<input type=\"checkbox\" disabled checked> ",
+                (false, false) => "<input type=\"checkbox\" disabled> ",
+                (true, true) => {
+                    "<input type=\"checkbox\" disabled=\"disabled\" checked=\"checked\" /> "
+                }
+                (true, false) => "<input type=\"checkbox\" disabled=\"disabled\" /> ",
+            }),
+            Inline::Note { content } => {
+                // reserve the number first, notes may contain notes
+                self.notes.push(String::new());
+                let n = self.notes.len();
+                self.notes[n - 1] = self.render_blocks(content);
+                self.footnote_ref(n);
+            }
+            Inline::FootnoteRef { index } => self.footnote_ref(*index),
+        }
+    }
+    fn inlines(&mut self, inlines: &[Inline]) {
+        for i in inlines {
+            self.inline(i);
+        }
+    }
+    fn footnote_ref(&mut self, n: usize) {
+        self.dest.push_str(
+            format!(
+                "<sup class=\"footnote-ref\"><a href=\"#fn{0}\" id=\"fnref{0}\">{0}</a></sup>",
+                n
+            )
+            .as_str(),
+        );
+    }
+    fn footnote_section(&mut self, notes: &[String]) {
+        self.dest.push_str("<section class=\"footnotes\">\n<ol>\n");
+        for (idx, n) in notes.iter().enumerate() {
+            self.dest.push_str(
+                format!(
+                    "<li id=\"fn{0}\">{1} <a href=\"#fnref{0}\">↩</a></li>\n",
+                    idx + 1,
+                    n
+                )
+                .as_str(),
+            );
+        }
+        self.dest.push_str("</ol>\n</section>");
+    }
+    /// render blocks aside from the current output
+    fn render_blocks(&mut self, blocks: &[Block]) -> String {
+        let outer = std::mem::take(&mut self.dest);
+        self.blocks(blocks);
+        std::mem::replace(&mut self.dest, outer)
+    }
+
+    fn header(&mut self, level: usize, content: &[Inline], id: Option<&str>) {
+        let level = level.clamp(1, 6);
+        self.dest.push_str(format!("<h{}", level).as_str());
+        if let Some(id) = id {
+            self.dest.push_str(" id=\"");
+            escape(id, &mut self.dest);
+            self.dest.push('"');
+        }
+        self.dest.push('>');
+        self.inlines(content);
+        self.dest.push_str(format!("</h{}>", level).as_str());
+    }
+    fn block(&mut self, block: &Block) {
+        match block {
+            Block::Header { level, content } => self.header(*level, content, None),
+            Block::HorizontalRule => {
+                self.dest.push_str("<hr");
+                self.dest.push_str(self.void_end());
+            }
+            Block::Plain(l) => self.inlines(l),
+            Block::Paragraph(l) => self.tagged("p", l),
+            Block::CodeBlock { language, code } => {
+                self.dest.push_str("<pre><code");
+                if let Some(l) = language {
+                    self.dest.push_str(" class=\"language-");
+                    escape(l, &mut self.dest);
+                    self.dest.push('"');
+                }
+                self.dest.push('>');
+                escape(code, &mut self.dest);
+                self.dest.push_str("</code></pre>");
+            }
+            Block::Math { tex_code } => {
+                self.dest.push_str("<div class=\"math display\">\\[");
+                escape(tex_code.trim(), &mut self.dest);
+                self.dest.push_str("\\]</div>");
+            }
+            Block::Figure {
+                alt_text,
+                description,
+                src,
+            } => {
+                self.dest.push_str("<figure>\n");
+                self.img(alt_text, None, src);
+                if let Some(d) = description {
+                    self.dest.push('\n');
+                    self.tagged("figcaption", d);
+                }
+                self.dest.push_str("\n</figure>");
+            }
+            Block::Video { poster, url } => {
+                self.dest.push_str("<p><a href=\"");
+                escape(url, &mut self.dest);
+                self.dest.push_str("\">");
+                match poster {
+                    Some(p) => self.img(&Some("video".to_owned()), None, p),
+                    None => escape(url, &mut self.dest),
+                }
+                self.dest.push_str("</a></p>");
+            }
+            Block::SimpleTable { body } => {
+                self.dest.push_str("<table>\n<tbody>\n");
+                for r in body {
+                    self.dest.push_str("<tr>\n");
+                    for c in r {
+                        self.dest.push_str("<td>");
+                        self.blocks(c);
+                        self.dest.push_str("</td>\n");
+                    }
+                    self.dest.push_str("</tr>\n");
+                }
+                self.dest.push_str("</tbody>\n</table>");
+            }
+            Block::BlockQuote { content } => {
+                self.dest.push_str("<blockquote>\n");
+                self.blocks(content);
+                self.dest.push_str("\n</blockquote>");
+            }
+            Block::UnorderedList { items } => self.list("ul", "", items),
+            Block::OrderedList { start: 1, items } => self.list("ol", "", items),
+            Block::OrderedList { start, items } => {
+                self.list("ol", format!(" start=\"{}\"", start).as_str(), items)
+            }
+            Block::FootnoteList { notes } => {
+                let notes: Vec<_> = notes.iter().map(|n| self.render_blocks(n)).collect();
+                self.footnote_section(&notes);
+            }
+        }
+    }
+    fn list(&mut self, tag: &str, attrs: &str, items: &[Blocks]) {
+        self.dest.push_str(format!("<{}{}>\n", tag, attrs).as_str());
+        for i in items {
+            self.dest.push_str("<li>");
+            self.blocks(i);
+            self.dest.push_str("</li>\n");
+        }
+        self.dest.push_str(format!("</{}>", tag).as_str());
+    }
+    fn blocks(&mut self, blocks: &[Block]) {
+        for (idx, b) in blocks.iter().enumerate() {
+            if idx != 0 {
+                self.dest.push('\n');
+            }
+            self.block(b);
+        }
+    }
+}
+
+/// render document as html fragment, with footnotes at the end
+pub fn to_html_body(document: &Document, images_store: &Path) -> String {
+    render_body(document, images_store, false, false)
+}
+/// render document as xhtml fragment for epub content documents,
+/// images not in image store are skipped
+pub(crate) fn to_xhtml_body(document: &Document, images_store: &Path) -> String {
+    render_body(document, images_store, true, false)
+}
+fn render_body(document: &Document, images_store: &Path, xhtml: bool, toc: bool) -> String {
+    let mut w = Writer {
+        images_store,
+        xhtml,
+        notes: Vec::new(),
+        dest: String::new(),
+    };
+    if toc {
+        let entries = document.table_of_contents();
+        let slugs = header_slugs(&entries);
+        if !entries.is_empty() {
+            w.dest.push_str("<nav class=\"toc\">\n<ul>\n");
+            for ((level, text), slug) in entries.iter().zip(slugs.iter()) {
+                w.dest
+                    .push_str(format!("<li class=\"toc-level-{}\"><a href=\"#", level).as_str());
+                escape(slug, &mut w.dest);
+                w.dest.push_str("\">");
+                escape(text, &mut w.dest);
+                w.dest.push_str("</a></li>\n");
+            }
+            w.dest.push_str("</ul>\n</nav>\n");
+        }
+        let mut slugs = slugs.iter();
+        for (idx, b) in document.data.iter().enumerate() {
+            if idx != 0 {
+                w.dest.push('\n');
+            }
+            match b {
+                Block::Header { level, content } => {
+                    w.header(*level, content, slugs.next().map(String::as_str))
+                }
+                b => w.block(b),
+            }
+        }
+    } else {
+        w.blocks(&document.data);
+    }
+    if !w.notes.is_empty() {
+        let notes = std::mem::take(&mut w.notes);
+        w.dest.push('\n');
+        w.footnote_section(&notes);
+    }
+    w.dest.push('\n');
+    w.dest
+}
+
+/// render document as a complete html page
+pub fn to_html(document: &Document, images_store: &Path, config: &HtmlConfig) -> String {
+    let mut ret = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    if let Some(t) = config.title {
+        ret.push_str("<title>");
+        escape(t, &mut ret);
+        ret.push_str("</title>\n");
+    }
+    if let Some(m) = config.mathjax {
+        ret.push_str("<script async src=\"");
+        escape(m, &mut ret);
+        ret.push_str("\"></script>\n");
+    }
+    ret.push_str("</head>\n<body>\n");
+    ret.push_str(render_body(document, images_store, false, config.toc).as_str());
+    ret.push_str("</body>\n</html>\n");
+    ret
+}
+
+pub struct Html;
+#[derive(Debug, Clone, Default)]
+pub struct HtmlConfig<'a> {
+    pub title: Option<&'a str>,
+    /// url of the mathjax script rendering `\(...\)` math, math is left as tex when `None`
+    pub mathjax: Option<&'a str>,
+    /// prepend a table of contents linking to the top level headers
+    pub toc: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    #[error("failed to prepare destination path")]
+    DestPrep(
+        #[from]
+        #[source]
+        crate::util::relative_path::DestPrepError,
+    ),
+    #[error("failed to write {}", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl super::super::Convertor for Html {
+    type Config<'a>
+        = HtmlConfig<'a>
+    where
+        Self: 'a;
+    type Err = ConvertError;
+    fn convert<S: AsRef<Path>, P: AsRef<Path>>(
+        image_store: S,
+        document: &Document,
+        config: &Self::Config<'_>,
+        dest: P,
+    ) -> Result<(), Self::Err> {
+        use crate::util::relative_path::{prepare_dest, relative_path_to};
+        let canon_dest = prepare_dest(dest.as_ref()).map_err(ConvertError::from)?;
+        let image_store =
+            relative_path_to(image_store.as_ref(), &canon_dest).unwrap_or_else(|| {
+                log::warn!(
+                    "failed to make image store `{}` relative to `{}`",
+                    image_store.as_ref().display(),
+                    dest.as_ref().display()
+                );
+                image_store.as_ref().to_path_buf()
+            });
+        std::fs::write(
+            &canon_dest,
+            to_html(document, image_store.as_path(), config),
+        )
+        .map_err(|e| ConvertError::Write {
+            path: canon_dest,
+            source: e,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::content::document;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_owned())
+    }
+
+    #[test]
+    fn convert() {
+        let doc = Document {
+            version: document::VERSION,
+            data: vec![
+                Block::Header {
+                    level: 1,
+                    content: vec![text("a < b")],
+                },
+                Block::Paragraph(vec![
+                    Inline::Emphasis(vec![text("see")]),
+                    text(" "),
+                    Inline::Math {
+                        tex_code: "x^2".to_owned(),
+                    },
+                    Inline::Note {
+                        content: vec![Block::Plain(vec![text("note")])],
+                    },
+                ]),
+                Block::CodeBlock {
+                    language: Some("rust".to_owned()),
+                    code: "a && b".to_owned(),
+                },
+                Block::Figure {
+                    alt_text: Some("alt".to_owned()),
+                    description: Some(vec![text("caption")]),
+                    src: media::Image::Url("https://example.com/a.png".to_owned()),
+                },
+                Block::UnorderedList {
+                    items: vec![vec![Block::Plain(vec![text("item")])]],
+                },
+            ],
+        };
+        assert_eq!(
+            to_html_body(&doc, Path::new("images")),
+            concat!(
+                "<h1>a &lt; b</h1>\n",
+                "<p><em>see</em> <span class=\"math inline\">\\(x^2\\)</span>",
+                "<sup class=\"footnote-ref\"><a href=\"#fn1\" id=\"fnref1\">1</a></sup></p>\n",
+                "<pre><code class=\"language-rust\">a &amp;&amp; b</code></pre>\n",
+                "<figure>\n",
+                "<img src=\"https://example.com/a.png\" alt=\"alt\">\n",
+                "<figcaption>caption</figcaption>\n",
+                "</figure>\n",
+                "<ul>\n<li>item</li>\n</ul>\n",
+                "<section class=\"footnotes\">\n<ol>\n",
+                "<li id=\"fn1\">note <a href=\"#fnref1\">↩</a></li>\n",
+                "</ol>\n</section>\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,530 @@
+use super::{super::document::*, image_path};
+use std::path::{Path, PathBuf};
+use web_dl_base::media;
+
+fn escape(text: &str, config: &MarkdownConfig, dest: &mut String) {
+    let mut line_start = dest.is_empty() || dest.ends_with('\n');
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '~' => dest.push('\\'),
+            '$' if config.tex_math_dollars => dest.push('\\'),
+            '#' | '-' | '+' if line_start => dest.push('\\'),
+            _ => (),
+        }
+        dest.push(c);
+        line_start = c == '\n' || (line_start && c.is_whitespace());
+    }
+}
+fn footnote(content: &str, n: usize) -> String {
+    prefix_lines(content, format!("[^{}]: ", n).as_str(), "    ")
+}
+/// backtick fence longer than any backtick run in `code`
+fn fence(code: &str, min: usize) -> String {
+    let longest = code.split(|c| c != '`').map(|s| s.len()).max().unwrap_or(0);
+    "`".repeat(min.max(longest + 1))
+}
+fn prefix_lines(text: &str, first: &str, rest: &str) -> String {
+    let mut ret = String::new();
+    for (idx, l) in text.lines().enumerate() {
+        if idx != 0 {
+            ret.push('\n');
+        }
+        let p = if idx == 0 { first } else { rest };
+        if l.is_empty() {
+            ret.push_str(p.trim_end());
+        } else {
+            ret.push_str(p);
+            ret.push_str(l);
+        }
+    }
+    ret
+}
+
+struct Writer<'a> {
+    images_store: &'a Path,
+    config: &'a MarkdownConfig,
+    notes: Vec<String>,
+}
+impl<'a> Writer<'a> {
+    fn inline_text(inlines: &[Inline], dest: &mut String) {
+        for i in inlines {
+            match i {
+                Inline::Text(t) => dest.push_str(t),
+                Inline::Break => dest.push(' '),
+                Inline::Code { code } => dest.push_str(code),
+                Inline::Emphasis(v) | Inline::Strong(v) => Self::inline_text(v, dest),
+                Inline::Math { tex_code } => dest.push_str(tex_code),
+                Inline::Note { .. } | Inline::FootnoteRef { .. } | Inline::Checkbox { .. } => (),
+                Inline::Image { alt_text, .. } => alt_text.iter().for_each(|t| dest.push_str(t)),
+                Inline::Link { description, .. } => {
+                    description.iter().for_each(|v| Self::inline_text(v, dest))
+                }
+            }
+        }
+    }
+    fn image(
+        &self,
+        alt_text: &Option<String>,
+        description: &Option<Vec<Inline>>,
+        src: &media::Image,
+        dest: &mut String,
+    ) {
+        dest.push_str("![");
+        if let Some(alt) = alt_text {
+            escape(alt, self.config, dest);
+        }
+        dest.push_str("](<");
+        dest.push_str(image_path(src, self.images_store).as_str());
+        dest.push('>');
+        if let Some(d) = description {
+            let mut title = String::new();
+            Self::inline_text(d, &mut title);
+            if !title.is_empty() {
+                dest.push_str(" \"");
+                dest.push_str(title.replace('"', "\\\"").as_str());
+                dest.push('"');
+            }
+        }
+        dest.push(')');
+    }
+    fn delimited(&mut self, delim: &str, inlines: &[Inline], dest: &mut String) {
+        let mut s = String::new();
+        self.inlines(inlines, &mut s);
+        let s = s.trim();
+        if !s.is_empty() {
+            dest.push_str(delim);
+            dest.push_str(s);
+            dest.push_str(delim);
+        }
+    }
+    fn inline(&mut self, inline: &Inline, dest: &mut String) {
+        match inline {
+            Inline::Text(t) => escape(t, self.config, dest),
+            Inline::Break => dest.push_str("\\\n"),
+            Inline::Emphasis(e) => self.delimited("*", e, dest),
+            Inline::Strong(s) => self.delimited("**", s, dest),
+            Inline::Code { code } => {
+                let f = fence(code, 1);
+                let pad = if code.starts_with('`') || code.ends_with('`') {
+                    " "
+                } else {
+                    ""
+                };
+                dest.push_str(&f);
+                dest.push_str(pad);
+                dest.push_str(code.replace('\n', " ").as_str());
+                dest.push_str(pad);
+                dest.push_str(&f);
+            }
+            Inline::Math { tex_code } if self.config.tex_math_dollars => {
+                dest.push('$');
+                dest.push_str(tex_code.trim());
+                dest.push('$');
+            }
+            Inline::Math { tex_code } => self.inline(
+                &Inline::Code {
+                    code: tex_code.trim().to_owned(),
+                },
+                dest,
+            ),
+            Inline::Image {
+                alt_text,
+                description,
+                src,
+            } => self.image(alt_text, description, src, dest),
+            Inline::Link {
+                description: Some(d),
+                target,
+                ..
+            } => {
+                dest.push('[');
+                self.inlines(d, dest);
+                dest.push_str("](<");
+                dest.push_str(target);
+                dest.push_str(">)");
+            }
+            Inline::Link {
+                description: None,
+                target,
+                ..
+            } => {
+                dest.push('<');
+                dest.push_str(target);
+                dest.push('>');
+            }
+            Inline::Checkbox { checked } => dest.push_str(if *checked { "[x] " } else { "[ ] " }),
+            Inline::Note { content } => {
+                // reserve the number first, notes may contain notes
+                self.notes.push(String::new());
+                let n = self.notes.len();
+                self.notes[n - 1] = self.blocks(content);
+                dest.push_str(format!("[^{}]", n).as_str());
+            }
+            Inline::FootnoteRef { index } => dest.push_str(format!("[^{}]", index).as_str()),
+        }
+    }
+    fn inlines(&mut self, inlines: &[Inline], dest: &mut String) {
+        for i in inlines {
+            self.inline(i, dest);
+        }
+    }
+
+    fn list<'b, I: Iterator<Item = (String, &'b Blocks)>>(&mut self, items: I) -> String {
+        let mut ret = Vec::new();
+        for (marker, item) in items {
+            let indent = " ".repeat(marker.len());
+            let content = self.blocks(item);
+            ret.push(if content.is_empty() {
+                marker.trim_end().to_owned()
+            } else {
+                prefix_lines(&content, &marker, &indent)
+            });
+        }
+        ret.join("\n")
+    }
+    fn table(&mut self, body: &[Vec<Blocks>]) -> String {
+        let n = body.iter().map(|r| r.len()).max().unwrap_or(0);
+        if n == 0 {
+            return String::new();
+        }
+        let mut rows = Vec::with_capacity(body.len() + 1);
+        for (idx, r) in body.iter().enumerate() {
+            let mut row = String::from("|");
+            for c in 0..n {
+                let cell = r.get(c).map(|c| self.blocks(c)).unwrap_or_default();
+                row.push(' ');
+                row.push_str(
+                    cell.lines()
+                        .filter(|l| !l.is_empty())
+                        .collect::<Vec<_>>()
+                        .join("<br>")
+                        .as_str(),
+                );
+                row.push_str(" |");
+            }
+            rows.push(row);
+            // gfm tables require a header, the first row is used
+            if idx == 0 {
+                rows.push(format!("|{}", " --- |".repeat(n)));
+            }
+        }
+        rows.join("\n")
+    }
+    fn header(&mut self, level: usize, content: &[Inline], anchor: Option<&str>) -> String {
+        let mut ret = "#".repeat(level.clamp(1, 6));
+        ret.push(' ');
+        if let Some(a) = anchor {
+            ret.push_str(format!("<a id=\"{}\"></a>", a).as_str());
+        }
+        let mut s = String::new();
+        self.inlines(content, &mut s);
+        ret.push_str(s.replace("\\\n", " ").replace('\n', " ").trim());
+        ret
+    }
+    fn block(&mut self, block: &Block) -> String {
+        let mut ret = String::new();
+        match block {
+            Block::Header { level, content } => ret = self.header(*level, content, None),
+            Block::HorizontalRule => ret.push_str("---"),
+            Block::Plain(l) | Block::Paragraph(l) => {
+                self.inlines(l, &mut ret);
+                ret = ret.trim().to_owned();
+            }
+            Block::CodeBlock { language, code } => {
+                let f = fence(code, 3);
+                ret.push_str(&f);
+                if let Some(l) = language {
+                    ret.push_str(l);
+                }
+                ret.push('\n');
+                ret.push_str(code);
+                if !code.ends_with('\n') {
+                    ret.push('\n');
+                }
+                ret.push_str(&f);
+            }
+            Block::Math { tex_code } if self.config.tex_math_dollars => {
+                ret.push_str("$$\n");
+                ret.push_str(tex_code.trim());
+                ret.push_str("\n$$");
+            }
+            Block::Math { tex_code } => {
+                ret = self.block(&Block::CodeBlock {
+                    language: Some("math".to_owned()),
+                    code: tex_code.trim().to_owned(),
+                })
+            }
+            Block::Figure {
+                alt_text,
+                description,
+                src,
+            } => self.image(alt_text, description, src, &mut ret),
+            Block::Video {
+                poster: Some(p),
+                url,
+            } => {
+                ret.push('[');
+                self.image(&Some("video".to_owned()), &None, p, &mut ret);
+                ret.push_str("](<");
+                ret.push_str(url);
+                ret.push_str(">)");
+            }
+            Block::Video { poster: None, url } => {
+                ret.push('<');
+                ret.push_str(url);
+                ret.push('>');
+            }
+            Block::SimpleTable { body } => ret = self.table(body),
+            Block::BlockQuote { content } => {
+                ret = prefix_lines(&self.blocks(content), "> ", "> ");
+            }
+            Block::UnorderedList { items } => {
+                ret = self.list(items.iter().map(|i| ("- ".to_owned(), i)));
+            }
+            Block::OrderedList { start, items } => {
+                ret = self.list(
+                    items
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, i)| (format!("{}. ", idx as u64 + *start as u64), i)),
+                );
+            }
+            Block::FootnoteList { notes } => {
+                ret = notes
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, n)| footnote(&self.blocks(n), idx + 1))
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+            }
+        }
+        ret
+    }
+    fn blocks(&mut self, blocks: &[Block]) -> String {
+        blocks
+            .iter()
+            .map(|b| self.block(b))
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// render document as commonmark with gfm tables and footnotes
+pub fn to_markdown(document: &Document, images_store: &Path, config: &MarkdownConfig) -> String {
+    let mut w = Writer {
+        images_store,
+        config,
+        notes: Vec::new(),
+    };
+    let mut ret = if config.toc {
+        let entries = document.table_of_contents();
+        let slugs = header_slugs(&entries);
+        let min_level = entries.iter().map(|(l, _)| *l).min().unwrap_or(1);
+        let toc = entries
+            .iter()
+            .zip(slugs.iter())
+            .map(|((level, text), slug)| {
+                let mut ret = "  ".repeat((level - min_level) as usize);
+                ret.push_str("- [");
+                escape(text, config, &mut ret);
+                ret.push_str(format!("](#{})", slug).as_str());
+                ret
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut slugs = slugs.iter();
+        std::iter::once(toc)
+            .chain(document.data.iter().map(|b| match b {
+                Block::Header { level, content } => {
+                    w.header(*level, content, slugs.next().map(String::as_str))
+                }
+                b => w.block(b),
+            }))
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    } else {
+        w.blocks(&document.data)
+    };
+    for (idx, n) in w.notes.iter().enumerate() {
+        ret.push_str("\n\n");
+        ret.push_str(footnote(n, idx + 1).as_str());
+    }
+    ret.push('\n');
+    ret
+}
+
+pub struct Markdown;
+#[derive(Debug, Clone)]
+pub struct MarkdownConfig {
+    /// write math as `$...$`, as inline code otherwise
+    pub tex_math_dollars: bool,
+    /// prepend a table of contents linking to the top level headers
+    pub toc: bool,
+}
+impl Default for MarkdownConfig {
+    fn default() -> Self {
+        Self {
+            tex_math_dollars: true,
+            toc: false,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    #[error("failed to prepare destination path")]
+    DestPrep(
+        #[from]
+        #[source]
+        crate::util::relative_path::DestPrepError,
+    ),
+    #[error("failed to write {}", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl super::super::Convertor for Markdown {
+    type Config<'a>
+        = MarkdownConfig
+    where
+        Self: 'a;
+    type Err = ConvertError;
+    fn convert<S: AsRef<Path>, P: AsRef<Path>>(
+        image_store: S,
+        document: &Document,
+        config: &Self::Config<'_>,
+        dest: P,
+    ) -> Result<(), Self::Err> {
+        use crate::util::relative_path::{prepare_dest, relative_path_to};
+        let canon_dest = prepare_dest(dest.as_ref()).map_err(ConvertError::from)?;
+        let image_store =
+            relative_path_to(image_store.as_ref(), &canon_dest).unwrap_or_else(|| {
+                log::warn!(
+                    "failed to make image store `{}` relative to `{}`",
+                    image_store.as_ref().display(),
+                    dest.as_ref().display()
+                );
+                image_store.as_ref().to_path_buf()
+            });
+        std::fs::write(
+            &canon_dest,
+            to_markdown(document, image_store.as_path(), config),
+        )
+        .map_err(|e| ConvertError::Write {
+            path: canon_dest,
+            source: e,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::content::document;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_owned())
+    }
+    fn plain(s: &str) -> Blocks {
+        vec![Block::Plain(vec![text(s)])]
+    }
+
+    #[test]
+    fn convert() {
+        let doc = Document {
+            version: document::VERSION,
+            data: vec![
+                Block::Header {
+                    level: 2,
+                    content: vec![text("Title")],
+                },
+                Block::Paragraph(vec![
+                    text("a*b "),
+                    Inline::Strong(vec![text("bold")]),
+                    text(" "),
+                    Inline::Math {
+                        tex_code: "x^2".to_owned(),
+                    },
+                    Inline::Note {
+                        content: vec![Block::Paragraph(vec![text("note")])],
+                    },
+                ]),
+                Block::UnorderedList {
+                    items: vec![
+                        vec![
+                            Block::Plain(vec![text("one")]),
+                            Block::OrderedList {
+                                start: 1,
+                                items: vec![plain("nested"), plain("two")],
+                            },
+                        ],
+                        plain("three"),
+                    ],
+                },
+                Block::SimpleTable {
+                    body: vec![vec![plain("h1"), plain("h2")], vec![plain("a|b")]],
+                },
+                Block::CodeBlock {
+                    language: Some("rust".to_owned()),
+                    code: "let a = 1;".to_owned(),
+                },
+            ],
+        };
+        assert_eq!(
+            to_markdown(&doc, Path::new("images"), &MarkdownConfig::default()),
+            concat!(
+                "## Title\n\n",
+                "a\\*b **bold** $x^2$[^1]\n\n",
+                "- one\n\n",
+                "  1. nested\n",
+                "  2. two\n",
+                "- three\n\n",
+                "| h1 | h2 |\n",
+                "| --- | --- |\n",
+                "| a\\|b |  |\n\n",
+                "```rust\n",
+                "let a = 1;\n",
+                "```\n\n",
+                "[^1]: note\n"
+            )
+        );
+    }
+
+    #[test]
+    fn toc() {
+        let header = |level, s: &str| Block::Header {
+            level,
+            content: vec![text(s)],
+        };
+        let doc = Document {
+            version: document::VERSION,
+            data: vec![
+                header(2, "Intro"),
+                Block::Paragraph(vec![text("text")]),
+                header(3, "Intro"),
+            ],
+        };
+        assert_eq!(
+            to_markdown(
+                &doc,
+                Path::new("images"),
+                &MarkdownConfig {
+                    toc: true,
+                    ..Default::default()
+                }
+            ),
+            concat!(
+                "- [Intro](#intro)\n",
+                "  - [Intro](#intro-1)\n\n",
+                "## <a id=\"intro\"></a>Intro\n\n",
+                "text\n\n",
+                "### <a id=\"intro-1\"></a>Intro\n"
+            )
+        );
+    }
+}
@@ -2,21 +2,6 @@ use super::super::document::*;
 use std::path::Path;
 use web_dl_base::media;
 
-fn inline_to_text(inline: &Inline, dest: &mut String) {
-    match inline {
-        Inline::Text(t) => dest.push_str(t.as_str()),
-        Inline::Break => dest.push('\n'),
-        Inline::Code { code } => dest.push_str(code.as_str()),
-        Inline::Emphasis(e) => e.iter().for_each(|i| inline_to_text(i, dest)),
-        Inline::Strong(s) => s.iter().for_each(|i| inline_to_text(i, dest)),
-        Inline::Math { tex_code } => dest.push_str(tex_code.as_str()),
-        Inline::Note { .. } => (),
-        Inline::Image { alt_text, .. } => alt_text.iter().for_each(|t| dest.push_str(t.as_str())),
-        Inline::Link { description, .. } => description
-            .iter()
-            .for_each(|v| v.iter().for_each(|i| inline_to_text(i, dest))),
-    }
-}
 fn text(input: &str, dest: &mut Vec<pandoc_ast::Inline>) {
     for i in input
         .chars()
@@ -79,6 +64,7 @@ fn proc_inline(inline: &Inline, images_store: &Path, dest: &mut Vec<pandoc_ast::
         Inline::Link {
             description,
             target,
+            ..
         } => dest.push(pandoc_ast::Inline::Link(
             pandoc_ast::Attr::default(),
             description
@@ -93,7 +79,20 @@ fn proc_inline(inline: &Inline, images_store: &Path, dest: &mut Vec<pandoc_ast::
         Inline::Note { content } => {
             dest.push(pandoc_ast::Inline::Note(proc_blocks(content, images_store)))
         }
+        Inline::FootnoteRef { index } => dest.push(pandoc_ast::Inline::Superscript(Vec::from([
+            pandoc_ast::Inline::Link(
+                (format!("fnref{}", index), Vec::new(), Vec::new()),
+                Vec::from([pandoc_ast::Inline::Str(index.to_string())]),
+                (format!("#fn{}", index), String::default()),
+            ),
+        ]))),
         Inline::Strong(s) => dest.push(pandoc_ast::Inline::Strong(proc_inlines(s, images_store))),
+        Inline::Checkbox { checked } => {
+            dest.push(pandoc_ast::Inline::Str(
+                if *checked { "☒" } else { "☐" }.to_string(),
+            ));
+            dest.push(pandoc_ast::Inline::Space);
+        }
         Inline::Text(t) => text(t, dest),
     }
 }
@@ -118,6 +117,10 @@ fn proc_block(block: &Block, images_store: &Path) -> pandoc_ast::Block {
                 }),
             code.to_owned(),
         ),
+        Block::Math { tex_code } => pandoc_ast::Block::Para(Vec::from([pandoc_ast::Inline::Math(
+            pandoc_ast::MathType::DisplayMath,
+            tex_code.to_owned(),
+        )])),
         Block::Figure {
             alt_text,
             description,
@@ -128,15 +131,25 @@ fn proc_block(block: &Block, images_store: &Path) -> pandoc_ast::Block {
             src,
             images_store,
         )])),
+        Block::Video { poster, url } => {
+            pandoc_ast::Block::Para(Vec::from([pandoc_ast::Inline::Link(
+                pandoc_ast::Attr::default(),
+                Vec::from([match poster {
+                    Some(p) => proc_image(&Some("video".to_owned()), &None, p, images_store),
+                    None => pandoc_ast::Inline::Str(url.to_owned()),
+                }]),
+                (url.to_owned(), String::default()),
+            )]))
+        }
         Block::Header { level, content } => pandoc_ast::Block::Header(
             *level as i64,
             pandoc_ast::Attr::default(),
             proc_inlines(content, images_store),
         ),
         Block::HorizontalRule => pandoc_ast::Block::HorizontalRule,
-        Block::OrderedList { items } => pandoc_ast::Block::OrderedList(
+        Block::OrderedList { start, items } => pandoc_ast::Block::OrderedList(
             (
-                1,
+                *start as i64,
                 pandoc_ast::ListNumberStyle::DefaultStyle,
                 pandoc_ast::ListNumberDelim::DefaultDelim,
             ),
@@ -190,6 +203,30 @@ fn proc_block(block: &Block, images_store: &Path) -> pandoc_ast::Block {
                 .map(|i| proc_blocks(i, images_store))
                 .collect(),
         ),
+        Block::FootnoteList { notes } => pandoc_ast::Block::Div(
+            (
+                String::default(),
+                Vec::from(["footnotes".to_owned()]),
+                Vec::default(),
+            ),
+            Vec::from([pandoc_ast::Block::OrderedList(
+                (
+                    1,
+                    pandoc_ast::ListNumberStyle::DefaultStyle,
+                    pandoc_ast::ListNumberDelim::DefaultDelim,
+                ),
+                notes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, n)| {
+                        Vec::from([pandoc_ast::Block::Div(
+                            (format!("fn{}", idx + 1), Vec::default(), Vec::default()),
+                            proc_blocks(n, images_store),
+                        )])
+                    })
+                    .collect(),
+            )]),
+        ),
     }
 }
 fn proc_blocks(blocks: &Vec<Block>, images_store: &Path) -> Vec<pandoc_ast::Block> {
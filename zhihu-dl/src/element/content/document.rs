@@ -1,10 +1,11 @@
 use crate::meta::Version;
 use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashSet};
 use web_dl_base::{media::Image, storable::Storable};
 
-pub const VERSION: Version = Version { major: 0, minor: 1 };
+pub const VERSION: Version = Version { major: 0, minor: 5 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Inline {
     Text(String),
     Emphasis(Vec<Inline>),
@@ -24,13 +25,26 @@ pub enum Inline {
     Link {
         description: Option<Vec<Inline>>,
         target: String,
+        /// redirect url wrapping `target` in the source, kept with [`LinkPolicy::Both`]
+        ///
+        /// [`LinkPolicy::Both`]: super::LinkPolicy::Both
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        original: Option<String>,
     },
     Note {
         content: Vec<Block>,
     },
+    /// reference to the 1-based entry of the [`Block::FootnoteList`], see [`Document::aggregate_footnotes`]
+    FootnoteRef {
+        index: usize,
+    },
+    /// task list checkbox at the start of a list item
+    Checkbox {
+        checked: bool,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Block {
     Header {
         level: usize,
@@ -43,11 +57,20 @@ pub enum Block {
         language: Option<String>,
         code: String,
     },
+    /// display math
+    Math {
+        tex_code: String,
+    },
     Figure {
         alt_text: Option<String>,
         description: Option<Vec<Inline>>,
         src: Image,
     },
+    /// embedded video, only the poster image is fetched
+    Video {
+        poster: Option<Image>,
+        url: String,
+    },
     SimpleTable {
         body: Vec<Vec<Blocks>>,
     },
@@ -58,14 +81,473 @@ pub enum Block {
         items: Vec<Blocks>,
     },
     OrderedList {
+        #[serde(default = "default_start")]
+        start: u32,
         items: Vec<Blocks>,
     },
+    /// content of notes collected at the end of the document, see [`Document::aggregate_footnotes`]
+    FootnoteList {
+        notes: Vec<Blocks>,
+    },
 }
 pub type Blocks = Vec<Block>;
+fn default_start() -> u32 {
+    1
+}
 
-#[derive(Debug, Clone, Storable, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Storable, Serialize, Deserialize)]
 #[store(format = "ron")]
 pub struct Document {
     pub version: Version,
     pub data: Vec<Block>,
 }
+
+/// plain text of an inline, notes are skipped
+pub fn inline_to_text(inline: &Inline, dest: &mut String) {
+    match inline {
+        Inline::Text(t) => dest.push_str(t.as_str()),
+        Inline::Break => dest.push('\n'),
+        Inline::Code { code } => dest.push_str(code.as_str()),
+        Inline::Emphasis(e) => e.iter().for_each(|i| inline_to_text(i, dest)),
+        Inline::Strong(s) => s.iter().for_each(|i| inline_to_text(i, dest)),
+        Inline::Math { tex_code } => dest.push_str(tex_code.as_str()),
+        Inline::Note { .. } | Inline::FootnoteRef { .. } | Inline::Checkbox { .. } => (),
+        Inline::Image { alt_text, .. } => alt_text.iter().for_each(|t| dest.push_str(t.as_str())),
+        Inline::Link { description, .. } => description
+            .iter()
+            .for_each(|v| v.iter().for_each(|i| inline_to_text(i, dest))),
+    }
+}
+
+/// url of the image zhihu renders an equation as
+pub fn equation_url(tex_code: &str) -> String {
+    format!(
+        "https://www.zhihu.com/equation?tex={}",
+        url::form_urlencoded::byte_serialize(tex_code.as_bytes()).collect::<String>()
+    )
+}
+/// whether `url` is an equation image, see [`equation_url`]
+pub fn is_equation_url(url: &url::Url) -> bool {
+    url.domain() == Some("www.zhihu.com") && url.path() == "/equation"
+}
+
+/// canonical name of a code language hint, unknown hints are returned unchanged
+pub fn canonical_language(lang: &str) -> &str {
+    match lang.to_ascii_lowercase().as_str() {
+        "js" | "jsx" | "node" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "py" | "py3" | "python3" => "python",
+        "rs" => "rust",
+        "rb" => "ruby",
+        "sh" | "shell" | "zsh" => "bash",
+        "c++" | "cc" | "cxx" | "hpp" => "cpp",
+        "c#" | "cs" => "csharp",
+        "kt" => "kotlin",
+        "golang" => "go",
+        "yml" => "yaml",
+        "md" => "markdown",
+        "tex" | "latex" => "latex",
+        "html" | "htm" | "xhtml" => "html",
+        "objc" | "objective-c" => "objectivec",
+        "ps1" | "pwsh" => "powershell",
+        "vb" => "vbnet",
+        "text" | "plain" | "plaintext" | "txt" => "text",
+        _ => lang,
+    }
+}
+
+fn normalize_inlines(inlines: &mut [Inline]) {
+    for i in inlines {
+        match i {
+            Inline::Emphasis(v) | Inline::Strong(v) => normalize_inlines(v),
+            Inline::Note { content } => normalize_blocks(content),
+            _ => (),
+        }
+    }
+}
+fn normalize_blocks(blocks: &mut [Block]) {
+    for b in blocks {
+        match b {
+            Block::CodeBlock {
+                language: Some(l), ..
+            } => {
+                let c = canonical_language(l);
+                if c != l {
+                    *l = c.to_owned();
+                }
+            }
+            Block::Header { content, .. } | Block::Plain(content) | Block::Paragraph(content) => {
+                normalize_inlines(content)
+            }
+            Block::SimpleTable { body } => {
+                body.iter_mut().flatten().for_each(|c| normalize_blocks(c))
+            }
+            Block::BlockQuote { content } => normalize_blocks(content),
+            Block::UnorderedList { items }
+            | Block::OrderedList { items, .. }
+            | Block::FootnoteList { notes: items } => {
+                items.iter_mut().for_each(|i| normalize_blocks(i))
+            }
+            _ => (),
+        }
+    }
+}
+
+fn aggregate_inlines(inlines: &mut [Inline], notes: &mut Vec<Blocks>) {
+    for i in inlines {
+        match i {
+            Inline::Emphasis(v) | Inline::Strong(v) => aggregate_inlines(v, notes),
+            Inline::Image {
+                description: Some(v),
+                ..
+            }
+            | Inline::Link {
+                description: Some(v),
+                ..
+            } => aggregate_inlines(v, notes),
+            Inline::Note { content } => {
+                let mut content = std::mem::take(content);
+                // reserve the number first, notes may contain notes
+                notes.push(Vec::new());
+                let index = notes.len();
+                aggregate_blocks(&mut content, notes);
+                notes[index - 1] = content;
+                *i = Inline::FootnoteRef { index };
+            }
+            _ => (),
+        }
+    }
+}
+fn aggregate_blocks(blocks: &mut [Block], notes: &mut Vec<Blocks>) {
+    for b in blocks {
+        match b {
+            Block::Header { content, .. } | Block::Plain(content) | Block::Paragraph(content) => {
+                aggregate_inlines(content, notes)
+            }
+            Block::Figure {
+                description: Some(d),
+                ..
+            } => aggregate_inlines(d, notes),
+            Block::SimpleTable { body } => body
+                .iter_mut()
+                .flatten()
+                .for_each(|c| aggregate_blocks(c, notes)),
+            Block::BlockQuote { content } => aggregate_blocks(content, notes),
+            Block::UnorderedList { items } | Block::OrderedList { items, .. } => {
+                items.iter_mut().for_each(|i| aggregate_blocks(i, notes))
+            }
+            _ => (),
+        }
+    }
+}
+
+fn image_url(src: &Image) -> &str {
+    match src {
+        Image::Url(u) => u.as_str(),
+        Image::Ref(r) => r.url.as_str(),
+    }
+}
+struct ImageUrls<'a> {
+    math: bool,
+    seen: HashSet<Cow<'a, str>>,
+    urls: Vec<Cow<'a, str>>,
+}
+impl<'a> ImageUrls<'a> {
+    fn push(&mut self, url: Cow<'a, str>) {
+        if self.seen.insert(url.clone()) {
+            self.urls.push(url);
+        }
+    }
+    fn equation(&mut self, tex_code: &str) {
+        if self.math {
+            self.push(Cow::Owned(equation_url(tex_code)));
+        }
+    }
+    fn inlines(&mut self, inlines: &'a [Inline]) {
+        for i in inlines {
+            match i {
+                Inline::Emphasis(v) | Inline::Strong(v) => self.inlines(v),
+                Inline::Math { tex_code } => self.equation(tex_code),
+                Inline::Image {
+                    description, src, ..
+                } => {
+                    self.push(Cow::Borrowed(image_url(src)));
+                    description.iter().for_each(|d| self.inlines(d));
+                }
+                Inline::Link {
+                    description: Some(d),
+                    ..
+                } => self.inlines(d),
+                Inline::Note { content } => self.blocks(content),
+                _ => (),
+            }
+        }
+    }
+    fn blocks(&mut self, blocks: &'a [Block]) {
+        for b in blocks {
+            match b {
+                Block::Header { content, .. }
+                | Block::Plain(content)
+                | Block::Paragraph(content) => self.inlines(content),
+                Block::Math { tex_code } => self.equation(tex_code),
+                Block::Figure {
+                    description, src, ..
+                } => {
+                    self.push(Cow::Borrowed(image_url(src)));
+                    description.iter().for_each(|d| self.inlines(d));
+                }
+                Block::Video {
+                    poster: Some(p), ..
+                } => self.push(Cow::Borrowed(image_url(p))),
+                Block::SimpleTable { body } => body.iter().flatten().for_each(|c| self.blocks(c)),
+                Block::BlockQuote { content } => self.blocks(content),
+                Block::UnorderedList { items }
+                | Block::OrderedList { items, .. }
+                | Block::FootnoteList { notes: items } => items.iter().for_each(|i| self.blocks(i)),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// anchor of a header, keeping letters and digits of any script
+pub fn slugify(text: &str) -> String {
+    let mut ret = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() {
+            ret.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !ret.ends_with('-') {
+            ret.push('-');
+        }
+    }
+    let ret = ret.trim_end_matches('-');
+    if ret.is_empty() {
+        String::from("section")
+    } else {
+        ret.to_owned()
+    }
+}
+/// unique anchors of table of contents entries, repeated slugs get a numeric suffix
+pub fn header_slugs(toc: &[(u8, String)]) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    toc.iter()
+        .map(|(_, t)| {
+            let base = slugify(t);
+            let mut slug = base.clone();
+            let mut n = 0;
+            while !seen.insert(slug.clone()) {
+                n += 1;
+                slug = format!("{}-{}", base, n);
+            }
+            slug
+        })
+        .collect()
+}
+
+impl Document {
+    /// map language hints of code blocks to canonical names, see [`canonical_language`]
+    pub fn normalize_code_languages(&mut self) {
+        normalize_blocks(&mut self.data)
+    }
+    /// replace notes with numbered [`Inline::FootnoteRef`]s in reading order,
+    /// and append their content as a [`Block::FootnoteList`]
+    pub fn aggregate_footnotes(&mut self) {
+        let mut notes = Vec::new();
+        aggregate_blocks(&mut self.data, &mut notes);
+        if !notes.is_empty() {
+            self.data.push(Block::FootnoteList { notes });
+        }
+    }
+    /// urls of figures, inline images and video posters in reading order without duplicates,
+    /// equations are listed as their zhihu image, see [`equation_url`], when `math` is set
+    pub fn image_urls(&self, math: bool) -> Vec<Cow<'_, str>> {
+        let mut v = ImageUrls {
+            math,
+            seen: HashSet::new(),
+            urls: Vec::new(),
+        };
+        v.blocks(&self.data);
+        v.urls
+    }
+    /// level and text of top level headers
+    pub fn table_of_contents(&self) -> Vec<(u8, String)> {
+        self.data
+            .iter()
+            .filter_map(|b| match b {
+                Block::Header { level, content } => {
+                    let mut text = String::new();
+                    content.iter().for_each(|i| inline_to_text(i, &mut text));
+                    Some(((*level).clamp(1, 6) as u8, text.trim().to_owned()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_language() {
+        let code = |l: &str| Block::CodeBlock {
+            language: Some(l.to_owned()),
+            code: String::new(),
+        };
+        let mut doc = Document {
+            version: VERSION,
+            data: vec![
+                code("js"),
+                Block::BlockQuote {
+                    content: vec![code("Py")],
+                },
+                code("brainfuck"),
+            ],
+        };
+        doc.normalize_code_languages();
+        let langs: Vec<_> = doc
+            .data
+            .iter()
+            .flat_map(|b| match b {
+                Block::BlockQuote { content } => content.iter().collect(),
+                b => vec![b],
+            })
+            .map(|b| match b {
+                Block::CodeBlock { language, .. } => language.clone().unwrap(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(langs, ["javascript", "python", "brainfuck"]);
+    }
+
+    #[test]
+    fn aggregate_footnotes() {
+        let text = |s: &str| Inline::Text(s.to_owned());
+        let note = |s: &str| Inline::Note {
+            content: vec![Block::Paragraph(vec![text(s)])],
+        };
+        let mut doc = Document {
+            version: VERSION,
+            data: vec![
+                Block::Paragraph(vec![
+                    text("a"),
+                    note("first"),
+                    Inline::Strong(vec![note("second")]),
+                ]),
+                Block::UnorderedList {
+                    items: vec![vec![Block::Plain(vec![Inline::Note {
+                        content: vec![Block::Paragraph(vec![text("third"), note("nested")])],
+                    }])]],
+                },
+                Block::Paragraph(vec![note("last")]),
+            ],
+        };
+        doc.aggregate_footnotes();
+        let para = |v: Vec<Inline>| vec![Block::Paragraph(v)];
+        let r = |index| Inline::FootnoteRef { index };
+        assert_eq!(
+            doc.data,
+            [
+                Block::Paragraph(vec![text("a"), r(1), Inline::Strong(vec![r(2)])]),
+                Block::UnorderedList {
+                    items: vec![vec![Block::Plain(vec![r(3)])]],
+                },
+                Block::Paragraph(vec![r(5)]),
+                Block::FootnoteList {
+                    notes: vec![
+                        para(vec![text("first")]),
+                        para(vec![text("second")]),
+                        para(vec![text("third"), r(4)]),
+                        para(vec![text("nested")]),
+                        para(vec![text("last")]),
+                    ]
+                },
+            ]
+        );
+
+        let mut plain = Document {
+            version: VERSION,
+            data: vec![Block::Paragraph(vec![text("no notes")])],
+        };
+        plain.aggregate_footnotes();
+        assert_eq!(plain.data.len(), 1);
+    }
+
+    #[test]
+    fn table_of_contents() {
+        let header = |level, s: &str| Block::Header {
+            level,
+            content: vec![Inline::Text(s.to_owned())],
+        };
+        let doc = Document {
+            version: VERSION,
+            data: vec![
+                header(1, "Intro"),
+                Block::Paragraph(vec![Inline::Text("text".to_owned())]),
+                header(2, "第一节 Setup!"),
+                header(2, "Intro"),
+                header(3, "intro-1"),
+                header(2, "?"),
+            ],
+        };
+        let toc = doc.table_of_contents();
+        assert_eq!(
+            toc.iter().map(|(l, _)| *l).collect::<Vec<_>>(),
+            [1, 2, 2, 3, 2]
+        );
+        assert_eq!(
+            header_slugs(&toc),
+            ["intro", "第一节-setup", "intro-1", "intro-1-1", "section"]
+        );
+    }
+
+    #[test]
+    fn image_urls() {
+        let url = |s: &str| Image::Url(s.to_owned());
+        let doc = Document {
+            version: VERSION,
+            data: vec![
+                Block::Figure {
+                    alt_text: None,
+                    description: None,
+                    src: url("https://pic1.zhimg.com/a.jpg"),
+                },
+                Block::Paragraph(vec![
+                    Inline::Math {
+                        tex_code: "x^2".to_owned(),
+                    },
+                    Inline::Note {
+                        content: vec![Block::Plain(vec![Inline::Image {
+                            alt_text: None,
+                            description: None,
+                            src: url("https://pic2.zhimg.com/b.png"),
+                        }])],
+                    },
+                ]),
+                Block::Video {
+                    poster: Some(url("https://pic1.zhimg.com/a.jpg")),
+                    url: "https://www.zhihu.com/video/1".to_owned(),
+                },
+                Block::UnorderedList {
+                    items: vec![vec![Block::Video {
+                        poster: Some(url("https://pic3.zhimg.com/c.jpg")),
+                        url: "https://www.zhihu.com/video/2".to_owned(),
+                    }]],
+                },
+            ],
+        };
+        assert_eq!(
+            doc.image_urls(false),
+            [
+                "https://pic1.zhimg.com/a.jpg",
+                "https://pic2.zhimg.com/b.png",
+                "https://pic3.zhimg.com/c.jpg"
+            ]
+        );
+        let urls = doc.image_urls(true);
+        assert_eq!(urls[1], "https://www.zhihu.com/equation?tex=x%5E2");
+        assert!(is_equation_url(&url::Url::parse(&urls[1]).unwrap()));
+        assert_eq!(urls.len(), 4);
+    }
+}
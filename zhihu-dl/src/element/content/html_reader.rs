@@ -1,5 +1,5 @@
-use super::document::*;
-use anyhow::Context;
+use super::{document::*, LinkPolicy};
+use anyhow::Context as _;
 use ego_tree::NodeRef;
 use scraper::{node::Element, Node};
 use std::collections::HashMap;
@@ -18,6 +18,23 @@ fn proc_external_link(url_str: &str) -> anyhow::Result<String> {
     }
     anyhow::bail!("no target url found");
 }
+/// target and original url of a link under the policy
+fn proc_link_target(url_str: &str, policy: LinkPolicy) -> (String, Option<String>) {
+    if policy == LinkPolicy::Keep {
+        return (url_str.to_string(), None);
+    }
+    let target = proc_external_link(url_str).unwrap_or_else(|e| {
+        log::warn!("failed to convert external url `{}` : {:?}", url_str, e);
+        url_str.to_string()
+    });
+    let original = (policy == LinkPolicy::Both && target != url_str).then(|| url_str.to_string());
+    (target, original)
+}
+
+struct Context<'b, 'a> {
+    image_map: &'b HashMap<&'a str, &'a media::ImageRef>,
+    link_policy: LinkPolicy,
+}
 
 fn proc_link_desc(root: NodeRef<'_, Node>) -> Option<Vec<Inline>> {
     let mut ret = String::new();
@@ -40,22 +57,38 @@ fn proc_link_desc(root: NodeRef<'_, Node>) -> Option<Vec<Inline>> {
         Some(Vec::from([Inline::Text(ret)]))
     }
 }
-fn proc_link(e: &Element, root: NodeRef<'_, Node>) -> anyhow::Result<Inline> {
+fn proc_link(e: &Element, root: NodeRef<'_, Node>, ctx: &Context) -> anyhow::Result<Inline> {
+    let (target, original) = match e.attr("href") {
+        Some(r) => proc_link_target(r, ctx.link_policy),
+        None => anyhow::bail!("href not found"),
+    };
     Ok(Inline::Link {
         description: proc_link_desc(root),
-        target: match e.attr("href") {
-            Some(r) => match proc_external_link(r) {
-                Ok(v) => v,
-                Err(e) => {
-                    log::warn!("failed to convert external url `{}` : {:?}", r, e);
-                    r.to_string()
-                }
-            },
-            None => anyhow::bail!("href not found"),
-        },
+        target,
+        original,
     })
 }
 
+/// `a.video-box` video embed
+fn proc_video(e: &Element, root: NodeRef<'_, Node>, ctx: &Context) -> anyhow::Result<Block> {
+    let url = match (e.attr("data-lens-id"), e.attr("href")) {
+        (Some(id), _) if !id.is_empty() => format!("https://www.zhihu.com/video/{}", id),
+        (_, Some(r)) => proc_link_target(r, ctx.link_policy).0,
+        _ => anyhow::bail!("video url not found"),
+    };
+    let poster = e
+        .attr("data-poster")
+        .filter(|p| !p.is_empty())
+        .or_else(|| {
+            find_elem(root, "img").and_then(|(i, _)| i.attr("data-original").or(i.attr("src")))
+        })
+        .map(|p| match ctx.image_map.get(p) {
+            Some(r) => media::Image::Ref((*r).to_owned()),
+            None => media::Image::Url(p.to_owned()),
+        });
+    Ok(Block::Video { poster, url })
+}
+
 fn proc_code(root: NodeRef<'_, Node>) -> String {
     let mut ret = String::new();
     for i in root.descendants() {
@@ -65,14 +98,10 @@ fn proc_code(root: NodeRef<'_, Node>) -> String {
     }
     ret
 }
-fn proc_inline_img<'a>(
-    e: &Element,
-    src: &str,
-    image_map: &HashMap<&'a str, &'a media::ImageRef>,
-) -> anyhow::Result<Inline> {
+fn proc_inline_img(e: &Element, src: &str, ctx: &Context) -> anyhow::Result<Inline> {
     let u = url::Url::parse(src).context("failed to parse url")?;
     let alt = e.attr("alt");
-    if u.domain() == Some("www.zhihu.com") && u.path() == "/equation" {
+    if is_equation_url(&u) {
         if let Some(t) = alt {
             return Ok(Inline::Math {
                 tex_code: t.to_string(),
@@ -90,29 +119,25 @@ fn proc_inline_img<'a>(
     return Ok(Inline::Image {
         alt_text: alt.map(str::to_string),
         description: None,
-        src: match image_map.get(src) {
+        src: match ctx.image_map.get(src) {
             Some(r) => media::Image::Ref((*r).to_owned()),
             None => media::Image::Url(src.to_owned()),
         },
     });
 }
 
-fn proc_note(e: &Element) -> anyhow::Result<Inline> {
+fn proc_note(e: &Element, ctx: &Context) -> anyhow::Result<Inline> {
     if e.attr("data-draft-type") != Some("reference") {
         anyhow::bail!("unknown supscript");
     }
     if let Some(v) = e.attr("data-url") {
         if !v.is_empty() {
+            let (target, original) = proc_link_target(v, ctx.link_policy);
             return Ok(Inline::Note {
                 content: Vec::from([Block::Plain(Vec::from([Inline::Link {
                     description: None,
-                    target: match proc_external_link(v) {
-                        Ok(l) => l,
-                        Err(e) => {
-                            log::warn!("failed to convert link `{}`: {:?}", v, e);
-                            v.to_string()
-                        }
-                    },
+                    target,
+                    original,
                 }]))]),
             });
         }
@@ -127,26 +152,26 @@ fn proc_note(e: &Element) -> anyhow::Result<Inline> {
     anyhow::bail!("reference target not found");
 }
 
-fn proc_inline_elem<'a>(
+fn proc_inline_elem(
     child: NodeRef<'_, Node>,
     e: &Element,
-    image_map: &HashMap<&'a str, &'a media::ImageRef>,
+    ctx: &Context,
 ) -> anyhow::Result<Inline> {
     match e.name() {
-        "a" => proc_link(e, child).context("failed toprocess link"),
-        "b" => Ok(Inline::Strong(proc_inlines(child, image_map))),
+        "a" => proc_link(e, child, ctx).context("failed toprocess link"),
+        "b" => Ok(Inline::Strong(proc_inlines(child, ctx))),
         "br" => Ok(Inline::Break),
-        "em" | "i" => Ok(Inline::Emphasis(proc_inlines(child, image_map))),
+        "em" | "i" => Ok(Inline::Emphasis(proc_inlines(child, ctx))),
         "code" => Ok(Inline::Code {
             code: proc_code(child),
         }),
-        "sup" => proc_note(e).context("failed to process note"),
+        "sup" => proc_note(e, ctx).context("failed to process note"),
         "img" => {
             let src = e
                 .attr("data-original")
                 .or(e.attr("src"))
                 .context("can't found image source")?;
-            proc_inline_img(e, src, image_map).or_else(|err| {
+            proc_inline_img(e, src, ctx).or_else(|err| {
                 log::warn!("process image failed: {:?}", err);
                 Ok(Inline::Image {
                     alt_text: None,
@@ -160,10 +185,7 @@ fn proc_inline_elem<'a>(
         }
     }
 }
-fn proc_inlines<'a>(
-    root: NodeRef<'_, Node>,
-    image_map: &HashMap<&'a str, &'a media::ImageRef>,
-) -> Vec<Inline> {
+fn proc_inlines(root: NodeRef<'_, Node>, ctx: &Context) -> Vec<Inline> {
     let mut ret = Vec::new();
     for child in root.children() {
         let value = child.value();
@@ -171,7 +193,7 @@ fn proc_inlines<'a>(
             ret.push(Inline::Text(t.to_string()));
             continue;
         } else if let Some(e) = value.as_element() {
-            match proc_inline_elem(child, e, image_map) {
+            match proc_inline_elem(child, e, ctx) {
                 Ok(v) => ret.push(v),
                 Err(err) => {
                     log::warn!("failed to process element {:#?}: {:?}", e, err);
@@ -205,10 +227,7 @@ fn proc_code_block(root: NodeRef<'_, Node>) -> anyhow::Result<Block> {
         code: proc_code(code_node),
     })
 }
-fn proc_figure<'a>(
-    root: NodeRef<'_, Node>,
-    image_map: &HashMap<&'a str, &'a media::ImageRef>,
-) -> anyhow::Result<Block> {
+fn proc_figure(root: NodeRef<'_, Node>, ctx: &Context) -> anyhow::Result<Block> {
     let (img, _) = find_elem(root, "img").context("can't find img tag")?;
     let src = img
         .attr("data-original")
@@ -217,32 +236,105 @@ fn proc_figure<'a>(
         .context("can't find image src")?;
     Ok(Block::Figure {
         alt_text: img.attr("alt").map(str::to_string),
-        description: find_elem(root, "figcaption")
-            .map(|(_, cap_ref)| proc_inlines(cap_ref, image_map)),
-        src: match image_map.get(src) {
+        description: find_elem(root, "figcaption").map(|(_, cap_ref)| proc_inlines(cap_ref, ctx)),
+        src: match ctx.image_map.get(src) {
             Some(v) => media::Image::Ref((*v).to_owned()),
             None => media::Image::Url(src.to_string()),
         },
     })
 }
-fn proc_list<'a>(
-    root: NodeRef<'_, Node>,
-    image_map: &HashMap<&'a str, &'a media::ImageRef>,
-) -> Vec<Vec<Block>> {
+/// paragraph holding only an equation is display math
+fn proc_paragraph(root: NodeRef<'_, Node>, ctx: &Context) -> Block {
+    let inlines = proc_inlines(root, ctx);
+    let mut content = inlines
+        .iter()
+        .filter(|i| !matches!(i, Inline::Text(t) if t.trim().is_empty()));
+    if let (Some(Inline::Math { tex_code }), None) = (content.next(), content.next()) {
+        // zhihu ends tex of display equations with a line break
+        let tex = tex_code.trim_end();
+        return Block::Math {
+            tex_code: tex
+                .strip_suffix("\\\\")
+                .unwrap_or(tex)
+                .trim_end()
+                .to_string(),
+        };
+    }
+    Block::Paragraph(inlines)
+}
+fn is_checkbox(e: &Element) -> bool {
+    e.name() == "input" && e.attr("type") == Some("checkbox")
+}
+/// list item content, nested lists are split out of the surrounding text
+fn proc_list_item(root: NodeRef<'_, Node>, ctx: &Context) -> Vec<Block> {
+    let mut ret = Vec::new();
+    let mut inlines = Vec::new();
+    for child in root.children() {
+        let value = child.value();
+        match value.as_element() {
+            Some(e) if e.name() == "ul" || e.name() == "ol" => {
+                if inlines
+                    .iter()
+                    .any(|i| !matches!(i, Inline::Text(t) if t.trim().is_empty()))
+                {
+                    ret.push(Block::Paragraph(std::mem::take(&mut inlines)));
+                } else {
+                    inlines.clear();
+                }
+                ret.push(proc_list_elem(child, e, ctx));
+            }
+            Some(e) if is_checkbox(e) && ret.is_empty() && inlines.is_empty() => {
+                inlines.push(Inline::Checkbox {
+                    checked: e.attr("checked").is_some(),
+                });
+            }
+            Some(e) => match proc_inline_elem(child, e, ctx) {
+                Ok(v) => inlines.push(v),
+                Err(err) => log::warn!("failed to process element {:#?}: {:?}", e, err),
+            },
+            None => {
+                if let Some(t) = value.as_text() {
+                    // checkbox is followed by a separating space on output
+                    let t = match inlines.as_slice() {
+                        [Inline::Checkbox { .. }] => t.trim_start(),
+                        _ => t,
+                    };
+                    inlines.push(Inline::Text(t.to_string()));
+                } else if !value.is_comment() {
+                    log::warn!("unexpected node {:#?}", value);
+                }
+            }
+        }
+    }
+    if !inlines.is_empty() || ret.is_empty() {
+        ret.push(Block::Paragraph(inlines));
+    }
+    ret
+}
+/// `ul` or `ol` element
+fn proc_list_elem(root: NodeRef<'_, Node>, e: &Element, ctx: &Context) -> Block {
+    if e.name() == "ol" {
+        Block::OrderedList {
+            start: e
+                .attr("start")
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(1),
+            items: proc_list(root, ctx),
+        }
+    } else {
+        Block::UnorderedList {
+            items: proc_list(root, ctx),
+        }
+    }
+}
+fn proc_list(root: NodeRef<'_, Node>, ctx: &Context) -> Vec<Vec<Block>> {
     let mut ret = Vec::new();
     for child in root.children() {
         let value = child.value();
         if let Some(e) = value.as_element() {
             match e.name() {
-                "li" => ret.push(Vec::from([Block::Paragraph(proc_inlines(
-                    child, image_map,
-                ))])),
-                "ul" => ret.push(Vec::from([Block::UnorderedList {
-                    items: proc_list(child, image_map),
-                }])),
-                "ol" => ret.push(Vec::from([Block::OrderedList {
-                    items: proc_list(child, image_map),
-                }])),
+                "li" => ret.push(proc_list_item(child, ctx)),
+                "ul" | "ol" => ret.push(Vec::from([proc_list_elem(child, e, ctx)])),
                 _ => {
                     log::warn!("ignored unknown list element {:#?}", e)
                 }
@@ -253,10 +345,7 @@ fn proc_list<'a>(
     }
     ret
 }
-fn proc_table<'a>(
-    root: NodeRef<'_, Node>,
-    image_map: &HashMap<&'a str, &'a media::ImageRef>,
-) -> Block {
+fn proc_table(root: NodeRef<'_, Node>, ctx: &Context) -> Block {
     fn check_elem(node: &Node, name: &str, context: &str) -> bool {
         if let Some(e) = node.as_element() {
             if e.name() == name {
@@ -278,7 +367,7 @@ fn proc_table<'a>(
                     let mut row = Vec::new();
                     for j in b.children() {
                         if check_elem(j.value(), "td", "cell") {
-                            row.push(Vec::from([Block::Plain(proc_inlines(j, image_map))]));
+                            row.push(Vec::from([Block::Plain(proc_inlines(j, ctx))]));
                         }
                     }
                     ret.push(row);
@@ -289,15 +378,18 @@ fn proc_table<'a>(
     Block::SimpleTable { body: ret }
 }
 
-fn try_proc_block_elem<'a>(
+fn try_proc_block_elem(
     child: NodeRef<'_, Node>,
     e: &Element,
-    image_map: &HashMap<&'a str, &'a media::ImageRef>,
+    ctx: &Context,
 ) -> anyhow::Result<Block> {
     match e.name() {
+        "a" if e.classes().any(|c| c == "video-box") => {
+            proc_video(e, child, ctx).context("failed to process video")
+        }
         // link card
         "a" => Ok(Block::Paragraph(Vec::from([
-            proc_link(e, child).context("failed to process link card")?
+            proc_link(e, child, ctx).context("failed to process link card")?
         ]))),
         "div" => {
             if let Some("highlight") = e.attr("class") {
@@ -306,26 +398,18 @@ fn try_proc_block_elem<'a>(
                 anyhow::bail!("unknown div {:#?}", e);
             }
         }
-        "figure" => proc_figure(child, image_map),
+        "figure" => proc_figure(child, ctx),
         "blockquote" => Ok(Block::BlockQuote {
-            content: Vec::from([Block::Paragraph(proc_inlines(child, image_map))]),
-        }),
-        "ul" => Ok(Block::UnorderedList {
-            items: proc_list(child, image_map),
-        }),
-        "ol" => Ok(Block::OrderedList {
-            items: proc_list(child, image_map),
+            content: Vec::from([Block::Paragraph(proc_inlines(child, ctx))]),
         }),
-        "p" => Ok(Block::Paragraph(proc_inlines(child, image_map))),
+        "ul" | "ol" => Ok(proc_list_elem(child, e, ctx)),
+        "p" => Ok(proc_paragraph(child, ctx)),
         _ => {
             anyhow::bail!("unknown element");
         }
     }
 }
-fn proc_block<'a>(
-    root: NodeRef<'_, Node>,
-    image_map: &HashMap<&'a str, &'a media::ImageRef>,
-) -> Vec<Block> {
+fn proc_block(root: NodeRef<'_, Node>, ctx: &Context) -> Vec<Block> {
     let mut ret = Vec::new();
     for child in root.children() {
         let value = child.value();
@@ -333,38 +417,33 @@ fn proc_block<'a>(
             ret.push(match e.name() {
                 "h1" => Block::Header {
                     level: 1,
-                    content: proc_inlines(child, image_map),
+                    content: proc_inlines(child, ctx),
                 },
                 "h2" => Block::Header {
                     level: 2,
-                    content: proc_inlines(child, image_map),
+                    content: proc_inlines(child, ctx),
                 },
                 "h3" => Block::Header {
                     level: 3,
-                    content: proc_inlines(child, image_map),
+                    content: proc_inlines(child, ctx),
                 },
                 "h4" => Block::Header {
                     level: 4,
-                    content: proc_inlines(child, image_map),
+                    content: proc_inlines(child, ctx),
                 },
                 "h5" => Block::Header {
                     level: 5,
-                    content: proc_inlines(child, image_map),
+                    content: proc_inlines(child, ctx),
                 },
                 "h6" => Block::Header {
                     level: 6,
-                    content: proc_inlines(child, image_map),
+                    content: proc_inlines(child, ctx),
                 },
                 "hr" => Block::HorizontalRule,
-                "ul" => Block::UnorderedList {
-                    items: proc_list(child, image_map),
-                },
-                "ol" => Block::OrderedList {
-                    items: proc_list(child, image_map),
-                },
-                "p" => Block::Paragraph(proc_inlines(child, image_map)),
-                "table" => proc_table(child, image_map),
-                _ => match try_proc_block_elem(child, e, image_map) {
+                "ul" | "ol" => proc_list_elem(child, e, ctx),
+                "p" => proc_paragraph(child, ctx),
+                "table" => proc_table(child, ctx),
+                _ => match try_proc_block_elem(child, e, ctx) {
                     Ok(v) => v,
                     Err(err) => {
                         log::warn!("failed to process element {:#?}: {:?}", e, err);
@@ -382,12 +461,16 @@ fn proc_block<'a>(
 pub fn from_raw_html<'a>(
     input: &str,
     image_map: &HashMap<&'a str, &'a media::ImageRef>,
+    link_policy: LinkPolicy,
 ) -> Document {
     Document {
         version: VERSION,
         data: proc_block(
             *scraper::Html::parse_fragment(input).root_element(),
-            image_map,
+            &Context {
+                image_map,
+                link_policy,
+            },
         ),
     }
 }
@@ -395,12 +478,188 @@ pub fn from_raw_html<'a>(
 pub fn from_raw_html_inline<'a>(
     input: &str,
     image_map: &HashMap<&'a str, &'a media::ImageRef>,
+    link_policy: LinkPolicy,
 ) -> Document {
     Document {
         version: VERSION,
         data: Vec::from([Block::Plain(proc_inlines(
             *scraper::Html::parse_fragment(input).root_element(),
-            image_map,
+            &Context {
+                image_map,
+                link_policy,
+            },
         ))]),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<Block> {
+        from_raw_html(input, &HashMap::new(), LinkPolicy::default()).data
+    }
+    fn item_text(item: &[Block]) -> String {
+        match item {
+            [Block::Paragraph(v), ..] => v
+                .iter()
+                .filter_map(|i| match i {
+                    Inline::Text(t) => Some(t.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => panic!("unexpected list item {:?}", item),
+        }
+    }
+
+    #[test]
+    fn ordered_list_start() {
+        match parse("<ol start=\"5\"><li>a</li><li>b</li></ol><ol><li>c</li></ol>").as_slice() {
+            [Block::OrderedList { start: 5, items }, Block::OrderedList {
+                start: 1,
+                items: rest,
+            }] => {
+                assert_eq!(items.len(), 2);
+                assert_eq!(item_text(&items[1]), "b");
+                assert_eq!(item_text(&rest[0]), "c");
+            }
+            v => panic!("unexpected blocks {:?}", v),
+        }
+    }
+
+    #[test]
+    fn nested_list() {
+        let blocks = parse(concat!(
+            "<ul><li>one<ol start=\"3\"><li>x</li><li>y<ul><li>z</li></ul></li></ol></li>",
+            "<li>two</li></ul>"
+        ));
+        let [Block::UnorderedList { items }] = blocks.as_slice() else {
+            panic!("unexpected blocks {:?}", blocks);
+        };
+        assert_eq!(items.len(), 2);
+        assert_eq!(item_text(&items[1]), "two");
+        let [Block::Paragraph(_), Block::OrderedList {
+            start: 3,
+            items: nested,
+        }] = items[0].as_slice()
+        else {
+            panic!("unexpected list item {:?}", items[0]);
+        };
+        assert_eq!(item_text(&nested[0]), "x");
+        assert!(matches!(
+            nested[1].as_slice(),
+            [Block::Paragraph(_), Block::UnorderedList { items }] if item_text(&items[0]) == "z"
+        ));
+    }
+
+    #[test]
+    fn task_list() {
+        let blocks = parse(concat!(
+            "<ul><li><input type=\"checkbox\" checked> done</li>",
+            "<li><input type=\"checkbox\"> todo</li><li>plain</li></ul>"
+        ));
+        let [Block::UnorderedList { items }] = blocks.as_slice() else {
+            panic!("unexpected blocks {:?}", blocks);
+        };
+        assert!(matches!(
+            items[0].as_slice(),
+            [Block::Paragraph(v)] if matches!(
+                v.as_slice(),
+                [Inline::Checkbox { checked: true }, Inline::Text(t)] if t == "done"
+            )
+        ));
+        assert!(matches!(
+            items[1].as_slice(),
+            [Block::Paragraph(v)] if matches!(
+                v.as_slice(),
+                [Inline::Checkbox { checked: false }, Inline::Text(t)] if t == "todo"
+            )
+        ));
+        assert_eq!(item_text(&items[2]), "plain");
+    }
+
+    #[test]
+    fn link_policy() {
+        let input = concat!(
+            "<p><a href=\"https://link.zhihu.com/?target=https%3A//example.com/a\">a</a>",
+            "<a href=\"https://example.com/b\">b</a></p>"
+        );
+        let links = |policy| match from_raw_html(input, &HashMap::new(), policy)
+            .data
+            .as_slice()
+        {
+            [Block::Paragraph(v)] => v
+                .iter()
+                .map(|i| match i {
+                    Inline::Link {
+                        target, original, ..
+                    } => (target.clone(), original.clone()),
+                    i => panic!("unexpected inline {:?}", i),
+                })
+                .collect::<Vec<_>>(),
+            v => panic!("unexpected blocks {:?}", v),
+        };
+        let wrapped = "https://link.zhihu.com/?target=https%3A//example.com/a";
+        let b = ("https://example.com/b".to_owned(), None);
+        assert_eq!(
+            links(LinkPolicy::Unwrap),
+            [("https://example.com/a".to_owned(), None), b.clone()]
+        );
+        assert_eq!(
+            links(LinkPolicy::Keep),
+            [(wrapped.to_owned(), None), b.clone()]
+        );
+        assert_eq!(
+            links(LinkPolicy::Both),
+            [
+                ("https://example.com/a".to_owned(), Some(wrapped.to_owned())),
+                b
+            ]
+        );
+    }
+
+    #[test]
+    fn math() {
+        let blocks = parse(concat!(
+            "<p><img src=\"https://www.zhihu.com/equation?tex=E%3Dmc%5E2%5C%5C\" ",
+            "alt=\"E=mc^2\\\\\" eeimg=\"1\"/></p>",
+            "<p>where <img src=\"https://www.zhihu.com/equation?tex=c\" alt=\"c\" eeimg=\"1\"/> ",
+            "is constant</p>"
+        ));
+        match blocks.as_slice() {
+            [Block::Math { tex_code }, Block::Paragraph(v)] => {
+                assert_eq!(tex_code, "E=mc^2");
+                assert!(matches!(
+                    v.as_slice(),
+                    [Inline::Text(_), Inline::Math { tex_code }, Inline::Text(_)] if tex_code == "c"
+                ));
+            }
+            v => panic!("unexpected blocks {:?}", v),
+        }
+    }
+
+    #[test]
+    fn video() {
+        let blocks = parse(concat!(
+            "<a class=\"video-box\" href=\"https://link.zhihu.com/?target=https%3A//www.zhihu.com/video/123\" ",
+            "data-poster=\"https://pic1.zhimg.com/v2-a.jpg\" data-lens-id=\"123\">",
+            "<img class=\"thumbnail\" src=\"https://pic1.zhimg.com/v2-a.jpg\"/>",
+            "<span class=\"content\"><span class=\"title\">title</span></span></a>",
+            "<a class=\"video-box\" href=\"https://link.zhihu.com/?target=https%3A//example.com/v\"></a>"
+        ));
+        match blocks.as_slice() {
+            [Block::Video {
+                poster: Some(media::Image::Url(p)),
+                url,
+            }, Block::Video {
+                poster: None,
+                url: other,
+            }] => {
+                assert_eq!(p, "https://pic1.zhimg.com/v2-a.jpg");
+                assert_eq!(url, "https://www.zhihu.com/video/123");
+                assert_eq!(other, "https://example.com/v");
+            }
+            v => panic!("unexpected blocks {:?}", v),
+        }
+    }
+}
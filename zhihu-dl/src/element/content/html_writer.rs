@@ -0,0 +1,328 @@
+use super::{convertor::html::escape, document::*};
+use web_dl_base::media;
+
+fn image_src(src: &media::Image) -> &str {
+    match src {
+        media::Image::Url(u) => u.as_str(),
+        media::Image::Ref(r) => r.url.as_str(),
+    }
+}
+fn inline_text(inlines: &[Inline], dest: &mut String) {
+    for i in inlines {
+        match i {
+            Inline::Text(t) => dest.push_str(t),
+            Inline::Break => dest.push('\n'),
+            Inline::Code { code } => dest.push_str(code),
+            Inline::Emphasis(v) | Inline::Strong(v) => inline_text(v, dest),
+            Inline::Math { tex_code } => dest.push_str(tex_code),
+            Inline::Note { .. } | Inline::FootnoteRef { .. } | Inline::Checkbox { .. } => (),
+            Inline::Image { alt_text, .. } => alt_text.iter().for_each(|t| dest.push_str(t)),
+            Inline::Link {
+                description,
+                target,
+                ..
+            } => match description {
+                Some(d) => inline_text(d, dest),
+                None => dest.push_str(target),
+            },
+        }
+    }
+}
+fn blocks_text(blocks: &[Block], dest: &mut String) {
+    for b in blocks {
+        match b {
+            Block::Header { content, .. } | Block::Plain(content) | Block::Paragraph(content) => {
+                inline_text(content, dest)
+            }
+            Block::CodeBlock { code, .. } => dest.push_str(code),
+            Block::Math { tex_code } => dest.push_str(tex_code),
+            Block::BlockQuote { content } => blocks_text(content, dest),
+            Block::UnorderedList { items } | Block::OrderedList { items, .. } => {
+                items.iter().for_each(|i| blocks_text(i, dest))
+            }
+            Block::SimpleTable { body } => body.iter().flatten().for_each(|c| blocks_text(c, dest)),
+            Block::HorizontalRule
+            | Block::Figure { .. }
+            | Block::Video { .. }
+            | Block::FootnoteList { .. } => (),
+        }
+    }
+}
+
+/// writes markup in the form zhihu uses, so that reading it back yields the same document
+struct Writer<'a> {
+    notes: usize,
+    /// content of aggregated notes, written back inline at their references
+    footnotes: &'a [Blocks],
+    dest: String,
+}
+impl<'a> Writer<'a> {
+    fn attr(&mut self, name: &str, value: &str) {
+        self.dest.push(' ');
+        self.dest.push_str(name);
+        self.dest.push_str("=\"");
+        escape(value, &mut self.dest);
+        self.dest.push('"');
+    }
+    fn tagged(&mut self, tag: &str, inlines: &[Inline]) {
+        self.dest.push_str(format!("<{}>", tag).as_str());
+        self.inlines(inlines);
+        self.dest.push_str(format!("</{}>", tag).as_str());
+    }
+    fn img(&mut self, alt_text: Option<&str>, src: &str) {
+        self.dest.push_str("<img");
+        self.attr("src", src);
+        if let Some(a) = alt_text {
+            self.attr("alt", a);
+        }
+        self.dest.push('>');
+    }
+    fn equation(&mut self, tex_code: &str) {
+        let src = equation_url(tex_code);
+        self.dest.push_str("<img");
+        self.attr("src", src.as_str());
+        self.attr("alt", tex_code);
+        self.dest.push_str(" eeimg=\"1\">");
+    }
+    fn inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Text(t) => escape(t, &mut self.dest),
+            Inline::Break => self.dest.push_str("<br>"),
+            Inline::Emphasis(e) => self.tagged("em", e),
+            Inline::Strong(s) => self.tagged("b", s),
+            Inline::Code { code } => {
+                self.dest.push_str("<code>");
+                escape(code, &mut self.dest);
+                self.dest.push_str("</code>");
+            }
+            Inline::Math { tex_code } => self.equation(tex_code),
+            Inline::Image { alt_text, src, .. } => self.img(alt_text.as_deref(), image_src(src)),
+            Inline::Link {
+                description,
+                target,
+                original,
+            } => {
+                self.dest.push_str("<a");
+                self.attr("href", original.as_ref().unwrap_or(target));
+                self.dest.push('>');
+                if let Some(d) = description {
+                    let mut s = String::new();
+                    inline_text(d, &mut s);
+                    escape(s.as_str(), &mut self.dest);
+                }
+                self.dest.push_str("</a>");
+            }
+            Inline::Checkbox { checked } => self.dest.push_str(if *checked {
+                "<input type=\"checkbox\" checked>"
+            } else {
+                "<input type=\"checkbox\">"
+            }),
+            Inline::Note { content } => self.note(content),
+            Inline::FootnoteRef { index } => {
+                let footnotes = self.footnotes;
+                self.note(
+                    footnotes
+                        .get(index.wrapping_sub(1))
+                        .map(Vec::as_slice)
+                        .unwrap_or_default(),
+                )
+            }
+        }
+    }
+    fn note(&mut self, content: &[Block]) {
+        self.notes += 1;
+        self.dest.push_str("<sup");
+        let url = match content {
+            [Block::Plain(l)] => match l.as_slice() {
+                [Inline::Link {
+                    description: None,
+                    target,
+                    original,
+                }] => Some(original.as_ref().unwrap_or(target)),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(u) = url {
+            self.attr("data-text", "");
+            self.attr("data-url", u);
+        } else {
+            let mut s = String::new();
+            blocks_text(content, &mut s);
+            self.attr("data-text", s.as_str());
+            self.attr("data-url", "");
+        }
+        self.dest.push_str(
+            format!(
+                concat!(
+                    " data-draft-node=\"inline\" data-draft-type=\"reference\"",
+                    " data-numero=\"{0}\">[{0}]</sup>"
+                ),
+                self.notes
+            )
+            .as_str(),
+        );
+    }
+    fn inlines(&mut self, inlines: &[Inline]) {
+        inlines.iter().for_each(|i| self.inline(i));
+    }
+    /// content of elements read as a single run of inlines, such as list items and table cells
+    fn inline_blocks(&mut self, blocks: &[Block]) {
+        for b in blocks {
+            match b {
+                Block::Plain(l) | Block::Paragraph(l) => self.inlines(l),
+                b => self.block(b),
+            }
+        }
+    }
+    fn block(&mut self, block: &Block) {
+        match block {
+            Block::Header { level, content } => {
+                self.tagged(format!("h{}", (*level).clamp(1, 6)).as_str(), content)
+            }
+            Block::HorizontalRule => self.dest.push_str("<hr>"),
+            Block::Plain(l) => self.inlines(l),
+            Block::Paragraph(l) => self.tagged("p", l),
+            Block::CodeBlock { language, code } => {
+                self.dest.push_str("<div class=\"highlight\"><pre><code");
+                if let Some(l) = language {
+                    self.attr("class", format!("language-{}", l).as_str());
+                }
+                self.dest.push('>');
+                escape(code, &mut self.dest);
+                self.dest.push_str("</code></pre></div>");
+            }
+            Block::Math { tex_code } => {
+                self.dest.push_str("<p>");
+                self.equation(tex_code);
+                self.dest.push_str("</p>");
+            }
+            Block::Figure {
+                alt_text,
+                description,
+                src,
+            } => {
+                self.dest.push_str("<figure>");
+                self.img(alt_text.as_deref(), image_src(src));
+                if let Some(d) = description {
+                    self.tagged("figcaption", d);
+                }
+                self.dest.push_str("</figure>");
+            }
+            Block::Video { poster, url } => {
+                self.dest.push_str("<a class=\"video-box\"");
+                self.attr("href", url);
+                if let Some(p) = poster {
+                    self.attr("data-poster", image_src(p));
+                }
+                if let Some(id) = url.strip_prefix("https://www.zhihu.com/video/") {
+                    self.attr("data-lens-id", id);
+                }
+                self.dest.push_str("></a>");
+            }
+            Block::SimpleTable { body } => {
+                self.dest.push_str("<table><tbody>");
+                for r in body {
+                    self.dest.push_str("<tr>");
+                    for c in r {
+                        self.dest.push_str("<td>");
+                        self.inline_blocks(c);
+                        self.dest.push_str("</td>");
+                    }
+                    self.dest.push_str("</tr>");
+                }
+                self.dest.push_str("</tbody></table>");
+            }
+            Block::BlockQuote { content } => {
+                self.dest.push_str("<blockquote>");
+                self.inline_blocks(content);
+                self.dest.push_str("</blockquote>");
+            }
+            Block::UnorderedList { items } => self.list("<ul>", "</ul>", items),
+            Block::OrderedList { start: 1, items } => self.list("<ol>", "</ol>", items),
+            Block::OrderedList { start, items } => {
+                self.list(format!("<ol start=\"{}\">", start).as_str(), "</ol>", items)
+            }
+            Block::FootnoteList { .. } => (),
+        }
+    }
+    fn list(&mut self, open: &str, close: &str, items: &[Blocks]) {
+        self.dest.push_str(open);
+        for i in items {
+            self.dest.push_str("<li>");
+            self.inline_blocks(i);
+            self.dest.push_str("</li>");
+        }
+        self.dest.push_str(close);
+    }
+}
+
+impl Document {
+    /// reconstruct zhihu html markup of the document, reading it back yields an equal document
+    /// as long as notes are not aggregated
+    pub fn to_html(&self) -> String {
+        let footnotes = self
+            .data
+            .iter()
+            .find_map(|b| match b {
+                Block::FootnoteList { notes } => Some(notes.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[]);
+        let mut w = Writer {
+            notes: 0,
+            footnotes,
+            dest: String::new(),
+        };
+        self.data.iter().for_each(|b| w.block(b));
+        w.dest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{html_reader::from_raw_html, LinkPolicy};
+    use std::collections::HashMap;
+
+    const FIXTURE: &str = concat!(
+        "<h2>标题 &amp; title</h2>",
+        "<p>text <b>bold <em>both</em></b><br><code>a &lt; b</code> ",
+        "<img src=\"https://www.zhihu.com/equation?tex=x%5E2\" alt=\"x^2\" eeimg=\"1\"/>",
+        "<a href=\"https://link.zhihu.com/?target=https%3A//example.com\" class=\" external\">",
+        "<span>example</span></a>",
+        "<sup data-text=\"note &quot;text&quot;\" data-url=\"\" data-draft-node=\"inline\" ",
+        "data-draft-type=\"reference\" data-numero=\"1\">[1]</sup>",
+        "<sup data-text=\"\" data-url=\"https://example.com/ref\" data-draft-node=\"inline\" ",
+        "data-draft-type=\"reference\" data-numero=\"2\">[2]</sup></p>",
+        "<hr/>",
+        "<p><img src=\"https://www.zhihu.com/equation?tex=%5Csum_i+x_i%5C%5C\" alt=\"\\sum_i x_i\\\\\" ",
+        "eeimg=\"1\"/></p>",
+        "<figure data-size=\"normal\"><noscript><img src=\"https://pic1.zhimg.com/a.jpg\"/></noscript>",
+        "<img src=\"data:image/svg+xml;utf8,&lt;svg/&gt;\" data-original=\"https://pic1.zhimg.com/a.jpg\" ",
+        "alt=\"alt\"/><figcaption>caption</figcaption></figure>",
+        "<div class=\"highlight\"><pre><code class=\"language-rust\">fn main() {\n    a &amp;&amp; b\n}\n",
+        "</code></pre></div>",
+        "<blockquote>quote<br>line</blockquote>",
+        "<ol start=\"3\"><li>one<ul><li><input type=\"checkbox\" checked> done</li></ul></li>",
+        "<li>two</li></ol>",
+        "<table data-draft-node=\"block\"><tbody><tr><td>a</td><td><b>b</b></td></tr></tbody></table>",
+        "<a class=\"video-box\" href=\"https://link.zhihu.com/?target=https%3A//www.zhihu.com/video/1\" ",
+        "data-poster=\"https://pic1.zhimg.com/p.jpg\" data-lens-id=\"1\">",
+        "<img class=\"thumbnail\" src=\"https://pic1.zhimg.com/p.jpg\"/></a>"
+    );
+
+    #[test]
+    fn round_trip() {
+        let image_map = HashMap::new();
+        for policy in [LinkPolicy::Unwrap, LinkPolicy::Keep, LinkPolicy::Both] {
+            let doc = from_raw_html(FIXTURE, &image_map, policy);
+            let html = doc.to_html();
+            assert_eq!(
+                from_raw_html(html.as_str(), &image_map, policy),
+                doc,
+                "{}",
+                html
+            );
+        }
+    }
+}
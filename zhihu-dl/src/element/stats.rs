@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// counts shown with an answer or article when it was fetched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub voteup: u64,
+    pub comments: u64,
+    pub favorites: u64,
+    pub thanks: u64,
+}
+
+/// count fields of an api reply, absent fields are zero
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct Reply {
+    pub voteup_count: u64,
+    pub comment_count: u64,
+    pub favlists_count: u64,
+    pub thanks_count: u64,
+}
+impl From<Reply> for Stats {
+    fn from(value: Reply) -> Self {
+        Self {
+            voteup: value.voteup_count,
+            comments: value.comment_count,
+            favorites: value.favlists_count,
+            thanks: value.thanks_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_reply() {
+        #[derive(Deserialize)]
+        struct Item {
+            id: u64,
+            #[serde(flatten)]
+            stats: Reply,
+        }
+        let item: Item = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "voteup_count": 10,
+            "comment_count": 2,
+        }))
+        .unwrap();
+        assert_eq!(item.id, 1);
+        assert_eq!(
+            Stats::from(item.stats),
+            Stats {
+                voteup: 10,
+                comments: 2,
+                favorites: 0,
+                thanks: 0
+            }
+        );
+    }
+}
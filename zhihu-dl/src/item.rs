@@ -1,16 +1,27 @@
 use crate::{element::content::HasContent, progress, raw_data::RawData, request::Client, store};
+use chrono::{DateTime, FixedOffset};
 use serde::Deserialize;
 use web_dl_base::id::HasId;
 
 pub trait Fetchable: HasId {
-    async fn fetch<'a>(
-        client: &Client,
-        id: Self::Id<'a>,
-    ) -> Result<serde_json::Value, reqwest::Error>;
+    /// api request fetching the object
+    fn request<'a>(client: &Client, id: Self::Id<'a>) -> reqwest::RequestBuilder;
+    /// canonical id when the fetched `data` is an alias of a moved or merged object
+    #[allow(unused_variables)]
+    fn redirect<'a>(data: &'a serde_json::Value) -> Option<Self::Id<'a>>
+    where
+        Self: 'a,
+    {
+        None
+    }
 }
 pub trait Item: Sized + HasId + HasContent + store::StoreItem {
     type Reply: for<'de> Deserialize<'de>;
     fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self;
+    /// creation and last update time, `None` for items without them
+    fn timestamps(&self) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        None
+    }
     async fn get_images<P: progress::ItemProg>(&mut self, client: &Client, prog: &P) -> bool;
 }
 
@@ -66,7 +77,16 @@ pub use pin::{Pin, PinId};
 pub mod question;
 pub use question::{Question, QuestionId};
 
+pub mod search;
+pub use search::Search;
+
+pub mod topic;
+pub use topic::{Topic, TopicId};
+
 pub mod user;
 pub use user::{User, UserId};
 
 pub mod other;
+
+pub mod url;
+pub use url::UrlTarget;
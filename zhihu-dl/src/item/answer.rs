@@ -1,5 +1,9 @@
 use crate::{
-    element::{content::HasContent, Author, Content},
+    element::{
+        content::{ContentState, HasContent},
+        stats::{self, Stats},
+        Author, Content,
+    },
     item::comment,
     meta::Version,
     raw_data::{FromRaw, RawData},
@@ -16,7 +20,7 @@ use web_dl_base::{
     storable::Storable,
 };
 
-const VERSION: Version = Version { major: 1, minor: 2 };
+const VERSION: Version = Version { major: 1, minor: 4 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct AnswerId(pub u64);
@@ -37,10 +41,51 @@ impl OwnedId<Answer> for AnswerId {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum ParseSpecError {
+    #[error("invalid question id")]
+    Question(#[source] std::num::ParseIntError),
+    #[error("invalid answer id")]
+    Answer(#[source] std::num::ParseIntError),
+}
+/// answer id, optionally with the question it is expected to answer,
+/// written as `<answer>` or `<question>/<answer>` as in answer urls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnswerSpec {
+    pub question: Option<crate::item::question::QuestionId>,
+    pub answer: AnswerId,
+}
+impl Display for AnswerSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.question {
+            Some(q) => write!(f, "{}/{}", q, self.answer),
+            None => self.answer.fmt(f),
+        }
+    }
+}
+impl FromStr for AnswerSpec {
+    type Err = ParseSpecError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (question, answer) = match s.split_once('/') {
+            Some((q, a)) => (Some(u64::from_str(q).map_err(ParseSpecError::Question)?), a),
+            None => (None, s),
+        };
+        Ok(Self {
+            question: question.map(crate::item::question::QuestionId),
+            answer: AnswerId::from_str(answer).map_err(ParseSpecError::Answer)?,
+        })
+    }
+}
+impl OwnedId<Answer> for AnswerSpec {
+    fn to_id(&self) -> <Answer as HasId>::Id<'_> {
+        self.answer
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AnsweredQuestion {
-    id: crate::item::question::QuestionId,
-    title: String,
+    pub id: crate::item::question::QuestionId,
+    pub title: String,
 }
 
 #[derive(Debug, Storable, Serialize, Deserialize)]
@@ -53,6 +98,9 @@ pub struct AnswerInfo {
     pub has_comment: Cell<bool>,
     pub created_time: DateTime<FixedOffset>,
     pub updated_time: DateTime<FixedOffset>,
+    /// absent in answers stored before stats were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<Stats>,
 }
 
 #[derive(Debug, Storable, HasContent, StoreImage, Serialize, Deserialize)]
@@ -75,7 +123,19 @@ impl HasId for Answer {
         self.info.id
     }
 }
-basic_store_item!(Answer, answer);
+basic_store_item!(Answer, answer, VERSION;
+    fn upgrade(&mut self, version: Version) {
+        // times were read as local time before 1.4
+        if version < (Version { major: 1, minor: 4 }) {
+            if let Some((c, u)) = self.raw_data.as_ref().and_then(|r| {
+                crate::raw_data::times(&r.data, "created_time", "updated_time")
+            }) {
+                self.info.created_time = c;
+                self.info.updated_time = u;
+            }
+        }
+    }
+);
 
 #[derive(Deserialize)]
 struct ReplyQuestion {
@@ -83,39 +143,83 @@ struct ReplyQuestion {
     title: String,
 }
 #[derive(Deserialize)]
+struct PaidInfo {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    has_purchased: bool,
+}
+#[derive(Deserialize)]
 pub struct Reply {
     id: u64,
     author: FromRaw<Option<Author>>,
     question: ReplyQuestion,
-    comment_count: u64,
+    #[serde(flatten)]
+    stats: stats::Reply,
     created_time: FromRaw<DateTime<FixedOffset>>,
     updated_time: FromRaw<DateTime<FixedOffset>>,
-    content: FromRaw<Content>,
+    /// missing in some restricted answers
+    #[serde(default)]
+    content: Option<FromRaw<Content>>,
+    #[serde(default)]
+    paid_info: Option<PaidInfo>,
+    #[serde(default)]
+    content_need_truncated: bool,
+}
+impl Reply {
+    /// reason why the content returned is incomplete
+    fn restriction(&self) -> Option<String> {
+        if let Some(p) = self.paid_info.as_ref().filter(|p| !p.has_purchased) {
+            Some(format!("paid content ({})", p.kind))
+        } else if self.content_need_truncated {
+            Some("login required".to_owned())
+        } else if self.content.is_none() {
+            Some("content unavailable".to_owned())
+        } else {
+            None
+        }
+    }
 }
 impl super::Fetchable for Answer {
-    async fn fetch<'a>(
-        client: &crate::request::Client,
-        id: AnswerId,
-    ) -> Result<serde_json::Value, reqwest::Error> {
-        client
-            .request_signed::<Zse96V3, _>(
-                Method::GET,
-                Url::parse_with_params(
-                    format!("https://www.zhihu.com/api/v4/answers/{}", id).as_str(),
-                    &[("include", "content;comment_count;voteup_count")],
-                )
-                .unwrap(),
+    fn request<'a>(client: &crate::request::Client, id: AnswerId) -> reqwest::RequestBuilder {
+        client.request_signed::<Zse96V3, _>(
+            Method::GET,
+            Url::parse_with_params(
+                format!("https://www.zhihu.com/api/v4/answers/{}", id).as_str(),
+                &[(
+                    "include",
+                    "content;comment_count;voteup_count;favlists_count;thanks_count",
+                )],
             )
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await
+            .unwrap(),
+        )
+    }
+}
+impl Answer {
+    /// check that the answer belongs to the question given in `spec`
+    pub fn matches(&self, spec: AnswerSpec) -> bool {
+        spec.question.map_or(true, |q| q == self.info.question.id)
+    }
+    /// question of fetched answer data, checked before the answer is stored
+    pub(crate) fn question_of(
+        data: &serde_json::Value,
+    ) -> Result<crate::item::question::QuestionId, serde_json::Error> {
+        ReplyQuestion::deserialize(&data["question"])
+            .map(|q| crate::item::question::QuestionId(q.id))
     }
 }
 impl super::Item for Answer {
     type Reply = Reply;
+    fn timestamps(&self) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        Some((self.info.created_time, self.info.updated_time))
+    }
     fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self {
+        let state = match reply.restriction() {
+            Some(reason) => ContentState::Restricted { reason },
+            None => ContentState::Full,
+        };
+        let mut content = reply.content.map_or_else(Content::default, |c| c.0);
+        content.info.state = state;
         Answer {
             version: VERSION,
             info: AnswerInfo {
@@ -125,11 +229,12 @@ impl super::Item for Answer {
                     id: crate::item::question::QuestionId(reply.question.id),
                     title: reply.question.title,
                 },
-                has_comment: Cell::new(reply.comment_count > 0),
+                has_comment: Cell::new(reply.stats.comment_count > 0),
                 created_time: reply.created_time.0,
                 updated_time: reply.updated_time.0,
+                stats: Some(reply.stats.into()),
             },
-            content: reply.content.0,
+            content,
             raw_data: Some(raw_data),
         }
     }
@@ -148,3 +253,76 @@ comment_store_container!(Answer, answer);
 comment_container!(Answer, info.has_comment);
 
 item_list_btree!(Answer, AnswerId);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        item::{question::QuestionId, Item},
+        raw_data::{Container, RawDataInfo},
+    };
+
+    fn parse(data: &str) -> Answer {
+        let data: serde_json::Value = serde_json::from_str(data).unwrap();
+        Answer::from_reply(
+            Reply::deserialize(&data).unwrap(),
+            RawData {
+                info: RawDataInfo {
+                    fetch_time: chrono::Utc::now(),
+                    container: Container::None,
+                    etag: None,
+                    last_modified: None,
+                },
+                data,
+            },
+        )
+    }
+
+    #[test]
+    fn restricted() {
+        let answer = parse(include_str!("fixtures/restricted_answer.json"));
+        assert_eq!(answer.info.id, AnswerId(2));
+        assert_eq!(answer.content.restriction(), Some("paid content (paid)"));
+        assert!(answer.content.raw_html.is_some());
+
+        let mut data: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/restricted_answer.json")).unwrap();
+        let obj = data.as_object_mut().unwrap();
+        obj.remove("paid_info");
+        obj.remove("content");
+        let answer = parse(data.to_string().as_str());
+        assert_eq!(answer.content.restriction(), Some("content unavailable"));
+        assert!(answer.content.info.is_empty);
+    }
+
+    #[test]
+    fn answer_spec() {
+        let data: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/restricted_answer.json")).unwrap();
+        assert_eq!(
+            Answer::question_of(&data).unwrap(),
+            parse(data.to_string().as_str()).info.question.id
+        );
+        assert!(Answer::question_of(&serde_json::json!({ "id": 2 })).is_err());
+
+        assert_eq!(
+            AnswerSpec::from_str("2").unwrap(),
+            AnswerSpec {
+                question: None,
+                answer: AnswerId(2)
+            }
+        );
+        let spec = AnswerSpec::from_str("1/2").unwrap();
+        assert_eq!(spec.question, Some(QuestionId(1)));
+        assert_eq!(spec.answer, AnswerId(2));
+        assert_eq!(spec.to_string(), "1/2");
+        assert!(matches!(
+            AnswerSpec::from_str("q/2"),
+            Err(ParseSpecError::Question(_))
+        ));
+        assert!(matches!(
+            AnswerSpec::from_str("1/"),
+            Err(ParseSpecError::Answer(_))
+        ));
+    }
+}
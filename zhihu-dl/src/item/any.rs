@@ -7,6 +7,7 @@ use crate::{
     raw_data::RawData,
     store::{self, ItemList, StoreContainer, StoreItem},
 };
+use chrono::{DateTime, FixedOffset};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeSet,
@@ -83,7 +84,7 @@ impl StoreItem for Any {
             }
         }
     }
-    fn add_media(&self, store: &mut store::Store) -> Result<(), web_dl_base::media::Error> {
+    fn add_media(&self, store: &mut store::Store) -> Result<(), store::StoreError> {
         match self {
             Any::Answer(a) => a.add_media(store),
             Any::Article(a) => a.add_media(store),
@@ -94,7 +95,7 @@ impl StoreItem for Any {
         &self,
         on_server: bool,
         store: &mut crate::store::Store,
-    ) -> Result<Option<PathBuf>, web_dl_base::storable::Error> {
+    ) -> Result<Option<PathBuf>, store::StoreError> {
         match self {
             Any::Answer(a) => a.save_data(on_server, store),
             Any::Article(a) => a.save_data(on_server, store),
@@ -109,7 +110,7 @@ impl StoreItem for Any {
         on_server: bool,
         store: &mut crate::store::Store,
         dest: P,
-    ) -> Result<Option<crate::store::LinkInfo>, web_dl_base::storable::Error> {
+    ) -> Result<Option<crate::store::LinkInfo>, store::StoreError> {
         match self {
             Any::Answer(a) => a.save_data_link(on_server, store, dest),
             Any::Article(a) => a.save_data_link(on_server, store, dest),
@@ -133,6 +134,13 @@ pub enum Reply {
 }
 impl super::Item for Any {
     type Reply = Reply;
+    fn timestamps(&self) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        match self {
+            Any::Answer(a) => a.timestamps(),
+            Any::Article(a) => a.timestamps(),
+            Any::Other(_) => None,
+        }
+    }
     fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self {
         match reply {
             Reply::Answer(a) => Any::Answer(answer::Answer::from_reply(a, raw_data)),
@@ -201,6 +209,12 @@ impl ItemList<Any> for AnyList {
         self.answer.set_item_info(info, store);
         self.article.set_item_info(info, store);
     }
+    fn ids(&self) -> std::collections::HashSet<String> {
+        let answers = self.answer.iter().map(|a| AnyId::Answer(*a).to_string());
+        answers
+            .chain(self.article.iter().map(|a| AnyId::Article(*a).to_string()))
+            .collect()
+    }
 }
 
 pub enum AnyContainer<'a, 'b> {
@@ -226,6 +240,13 @@ impl<'a, 'b> store::ContainerHandle<comment::Comment> for AnyContainer<'a, 'b> {
             Self::Other => (),
         }
     }
+    fn checkpoint(&mut self) -> Result<(), store::StoreError> {
+        match self {
+            Self::Answer(a) => a.checkpoint(),
+            Self::Article(a) => a.checkpoint(),
+            Self::Other => Ok(()),
+        }
+    }
     fn finish(self) -> Result<Option<PathBuf>, store::StoreError> {
         match self {
             Self::Answer(a) => a.finish(),
@@ -1,5 +1,9 @@
 use crate::{
-    element::{content::HasContent, Author, Content},
+    element::{
+        content::HasContent,
+        stats::{self, Stats},
+        Author, Content,
+    },
     item::comment,
     meta::Version,
     progress,
@@ -16,7 +20,7 @@ use web_dl_base::{
     storable::Storable,
 };
 
-pub const VERSION: Version = Version { major: 1, minor: 2 };
+pub const VERSION: Version = Version { major: 1, minor: 4 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ArticleId(pub u64);
@@ -49,6 +53,9 @@ pub struct ArticleInfo {
     pub has_comment: Cell<bool>,
     pub created_time: DateTime<FixedOffset>,
     pub updated_time: DateTime<FixedOffset>,
+    /// absent in articles stored before stats were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<Stats>,
 }
 
 #[derive(Debug, Storable, HasContent, StoreImage, Serialize, Deserialize)]
@@ -72,21 +79,23 @@ impl HasId for Article {
         self.info.id
     }
 }
-basic_store_item!(Article, article);
+basic_store_item!(Article, article, VERSION;
+    fn upgrade(&mut self, version: Version) {
+        // times were read as local time before 1.4
+        if version < (Version { major: 1, minor: 4 }) {
+            if let Some((c, u)) = self
+                .raw_data
+                .as_ref()
+                .and_then(|r| crate::raw_data::times(&r.data, "created", "updated"))
+            {
+                self.info.created_time = c;
+                self.info.updated_time = u;
+            }
+        }
+    }
+);
 
 impl Article {
-    async fn send_request(
-        client: &Client,
-        id: ArticleId,
-    ) -> Result<reqwest::Response, reqwest::Error> {
-        log::debug!("fetching article {}", id);
-        client
-            .http_client
-            .get(format!("https://www.zhihu.com/api/v4/articles/{}", id))
-            .send()
-            .await?
-            .error_for_status()
-    }
     pub async fn fix_cover<P: progress::ItemProg>(
         &mut self,
         client: &Client,
@@ -96,16 +105,20 @@ impl Article {
         struct Reply {
             title_image: FromRaw<Option<Image>>,
         }
-        self.info.cover = Self::send_request(client, self.info.id)
+        use super::Fetchable;
+        let resp = client
+            .send(prog, Self::request(client, self.info.id))
             .await?
-            .json::<Reply>()
-            .await?
-            .title_image
-            .0;
+            .error_for_status()?;
+        self.info.cover = client.json::<Reply>(resp).await?.title_image.0;
         match &mut self.info.cover {
             Some(c) => {
-                c.fetch(&client.http_client, &mut prog.start_images(1))
-                    .await;
+                c.fetch(
+                    &client.http_client,
+                    &mut prog.start_images(1),
+                    &client.image_fetch,
+                )
+                .await;
             }
             None => (),
         }
@@ -117,7 +130,8 @@ pub struct Reply {
     id: u64,
     title: String,
     author: FromRaw<Author>,
-    comment_count: u64,
+    #[serde(flatten)]
+    stats: stats::Reply,
     #[serde(default)]
     title_image: FromRaw<Option<Image>>,
     created: FromRaw<DateTime<FixedOffset>>,
@@ -125,15 +139,17 @@ pub struct Reply {
     content: FromRaw<Content>,
 }
 impl super::Fetchable for Article {
-    async fn fetch<'a>(
-        client: &crate::request::Client,
-        id: ArticleId,
-    ) -> Result<serde_json::Value, reqwest::Error> {
-        Self::send_request(client, id).await?.json().await
+    fn request<'a>(client: &crate::request::Client, id: ArticleId) -> reqwest::RequestBuilder {
+        client
+            .http_client
+            .get(format!("https://www.zhihu.com/api/v4/articles/{}", id))
     }
 }
 impl super::Item for Article {
     type Reply = Reply;
+    fn timestamps(&self) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        Some((self.info.created_time, self.info.updated_time))
+    }
     fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self {
         Article {
             version: VERSION,
@@ -142,9 +158,10 @@ impl super::Item for Article {
                 title: reply.title,
                 author: reply.author.0,
                 cover: reply.title_image.0,
-                has_comment: Cell::new(reply.comment_count > 0),
+                has_comment: Cell::new(reply.stats.comment_count > 0),
                 created_time: reply.created.0,
                 updated_time: reply.updated.0,
+                stats: Some(reply.stats.into()),
             },
             content: reply.content.0,
             raw_data: Some(raw_data),
@@ -155,7 +172,10 @@ impl super::Item for Article {
         let mut prog = prog.start_images(u.len() as u64 + 1);
         self.content.fetch_images(client, &mut prog, u).await
             | match &mut self.info.cover {
-                Some(c) => c.fetch(&client.http_client, &mut prog).await,
+                Some(c) => {
+                    c.fetch(&client.http_client, &mut prog, &client.image_fetch)
+                        .await
+                }
                 None => false,
             }
     }
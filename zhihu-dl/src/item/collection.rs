@@ -68,21 +68,13 @@ impl HasId for Collection {
         self.info.id
     }
 }
-basic_store_item!(Collection, collection);
+basic_store_item!(Collection, collection, VERSION);
 
 impl super::Fetchable for Collection {
-    async fn fetch<'a>(
-        client: &crate::request::Client,
-        id: CollectionId,
-    ) -> Result<serde_json::Value, reqwest::Error> {
+    fn request<'a>(client: &crate::request::Client, id: CollectionId) -> reqwest::RequestBuilder {
         client
             .http_client
             .get(format!("https://www.zhihu.com/api/v4/collections/{}", id))
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await
     }
 }
 
@@ -102,6 +94,9 @@ pub struct Wrapper {
 }
 impl super::Item for Collection {
     type Reply = Wrapper;
+    fn timestamps(&self) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        Some((self.info.created_time, self.info.updated_time))
+    }
     fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self {
         let d = reply.collection;
         Collection {
@@ -84,6 +84,7 @@ impl HasId for Column {
     }
 }
 impl BasicStoreItem for Column {
+    const VERSION: Version = VERSION;
     fn in_store(
         id: Self::Id<'_>,
         store: &crate::store::ObjectInfo,
@@ -106,22 +107,17 @@ impl BasicStoreItem for Column {
             .or_default()
             .container = info;
     }
+    fn remove_info(id: Self::Id<'_>, store: &mut crate::store::ObjectInfo) -> bool {
+        store.column.remove(id.0).is_some()
+    }
 }
 
 impl super::Fetchable for Column {
-    async fn fetch<'a>(
-        client: &crate::request::Client,
-        id: Self::Id<'a>,
-    ) -> Result<serde_json::Value, reqwest::Error> {
+    fn request<'a>(client: &crate::request::Client, id: Self::Id<'a>) -> reqwest::RequestBuilder {
         client
             .http_client
             .get(format!("https://www.zhihu.com/api/v4/columns/{}", id))
             .query(&[("include", "intro,created")])
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await
     }
 }
 
@@ -138,6 +134,9 @@ pub struct Reply {
 }
 impl super::Item for Column {
     type Reply = Reply;
+    fn timestamps(&self) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        Some((self.info.created_time, self.info.updated_time))
+    }
     fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self {
         Self {
             version: VERSION,
@@ -169,7 +168,10 @@ impl super::Item for Column {
                 .fetch_images(client, &mut prog, url_d)
                 .await
             | match &mut self.info.image {
-                Some(i) => i.fetch(&client.http_client, &mut prog).await,
+                Some(i) => {
+                    i.fetch(&client.http_client, &mut prog, &client.image_fetch)
+                        .await
+                }
                 None => {
                     prog.skip();
                     false
@@ -189,6 +191,9 @@ impl ItemList<Column> for BTreeSet<ColumnId> {
             store.objects.column.entry(i.clone()).or_default().container = info;
         }
     }
+    fn ids(&self) -> std::collections::HashSet<String> {
+        self.iter().map(|i| i.to_string()).collect()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -1,7 +1,10 @@
 use crate::{
     element::{
         author::Author,
-        content::{Content, HasContent},
+        content::{
+            document::{self, Block, Document, Inline},
+            Content, HasContent,
+        },
     },
     meta::Version,
     progress,
@@ -11,10 +14,10 @@ use crate::{
 };
 use chrono::{DateTime, FixedOffset};
 use serde::{Deserialize, Serialize};
-use std::{cell::Cell, fmt::Display, str::FromStr};
+use std::{cell::Cell, collections::HashMap, fmt::Display, str::FromStr};
 use web_dl_base::{id::HasId, media::StoreImage, storable::Storable};
 
-pub const VERSION: Version = Version { major: 2, minor: 1 };
+pub const VERSION: Version = Version { major: 2, minor: 2 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct CommentId(pub u64);
@@ -47,6 +50,11 @@ pub struct CommentInfo {
     pub is_author: bool,
     pub has_child: Cell<bool>,
     pub created_time: DateTime<FixedOffset>,
+    #[serde(default)]
+    pub like_count: u64,
+    /// labels shown beside the author, such as the author of the commented item
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub author_tags: Vec<String>,
 }
 
 #[derive(Debug, Storable, HasContent, StoreImage, Serialize, Deserialize)]
@@ -69,8 +77,12 @@ impl HasId for Comment {
         self.info.id
     }
 }
-basic_store_item!(Comment, comment);
+basic_store_item!(Comment, comment, VERSION);
 
+#[derive(Deserialize)]
+struct Tag {
+    text: String,
+}
 #[derive(Deserialize)]
 pub struct Reply {
     id: FromRaw<CommentId>,
@@ -81,9 +93,16 @@ pub struct Reply {
     created_time: FromRaw<DateTime<FixedOffset>>,
     #[serde(default)]
     content: FromRaw<Content>,
+    #[serde(default)]
+    like_count: u64,
+    #[serde(default)]
+    author_tag: Vec<Tag>,
 }
 impl super::Item for Comment {
     type Reply = Reply;
+    fn timestamps(&self) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        Some((self.info.created_time, self.info.created_time))
+    }
     fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self {
         Self {
             version: VERSION,
@@ -98,6 +117,8 @@ impl super::Item for Comment {
                 is_author: reply.is_author,
                 has_child: Cell::new(reply.child_comment_count > 0),
                 created_time: reply.created_time.0,
+                like_count: reply.like_count,
+                author_tags: reply.author_tag.into_iter().map(|t| t.text).collect(),
             },
             content: reply.content.0,
             raw_data: Some(raw_data),
@@ -116,6 +137,91 @@ impl super::Item for Comment {
 }
 item_list_btree!(Comment, CommentId);
 
+/// comment with replies to it, see [`Comment::build_tree`]
+#[derive(Debug)]
+pub struct CommentNode {
+    pub comment: Comment,
+    pub replies: Vec<CommentNode>,
+}
+fn sort_nodes(nodes: &mut [CommentNode]) {
+    nodes.sort_by_key(|n| (n.comment.info.created_time, n.comment.info.id));
+}
+impl Comment {
+    /// nest replies under their parents, oldest first
+    ///
+    /// Replies whose parent is not in `comments` are kept at top level.
+    pub fn build_tree(comments: Vec<Comment>) -> Vec<CommentNode> {
+        fn attach(
+            comment: Comment,
+            children: &mut HashMap<CommentId, Vec<Comment>>,
+        ) -> CommentNode {
+            let mut replies: Vec<_> = children
+                .remove(&comment.info.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|c| attach(c, children))
+                .collect();
+            sort_nodes(&mut replies);
+            CommentNode { comment, replies }
+        }
+        let ids: std::collections::HashSet<_> = comments.iter().map(|c| c.info.id).collect();
+        let mut roots = Vec::new();
+        let mut children: HashMap<CommentId, Vec<Comment>> = HashMap::new();
+        for c in comments {
+            match c.info.parent_id.filter(|p| ids.contains(p)) {
+                Some(p) => children.entry(p).or_default().push(c),
+                None => roots.push(c),
+            }
+        }
+        let mut ret: Vec<_> = roots
+            .into_iter()
+            .map(|c| attach(c, &mut children))
+            .collect();
+        // comments in a reply cycle are not reachable from any root
+        while let Some(p) = children.keys().min().copied() {
+            for c in children.remove(&p).unwrap_or_default() {
+                ret.push(attach(c, &mut children));
+            }
+        }
+        sort_nodes(&mut ret);
+        ret
+    }
+}
+impl CommentNode {
+    /// threaded comments as a document for convertors, replies are quoted under their parent
+    ///
+    /// Content is taken from converted html, see [`HasContent::convert_html`].
+    pub fn to_document(nodes: &[CommentNode]) -> Document {
+        fn push_nodes(nodes: &[CommentNode], dest: &mut Vec<Block>) {
+            for n in nodes {
+                let info = &n.comment.info;
+                dest.push(Block::Paragraph(vec![
+                    Inline::Strong(vec![Inline::Text(
+                        info.author
+                            .as_ref()
+                            .map_or_else(|| "anonymous".to_owned(), |a| a.name.clone()),
+                    )]),
+                    Inline::Text(format!(" {}", info.created_time.format("%Y-%m-%d %H:%M"))),
+                ]));
+                if let Some(d) = &n.comment.content.document {
+                    dest.extend(d.data.iter().cloned());
+                }
+                if !n.replies.is_empty() {
+                    let mut content = Vec::new();
+                    push_nodes(&n.replies, &mut content);
+                    dest.push(Block::BlockQuote { content });
+                }
+            }
+        }
+        let mut data = Vec::new();
+        push_nodes(nodes, &mut data);
+        Document {
+            version: document::VERSION,
+            data,
+        }
+    }
+}
+
 macro_rules! comment_store_container {
     ($t:ty, $i:ident) => {
         impl crate::store::BasicStoreContainer<super::VoidOpt, crate::item::comment::Comment>
@@ -219,3 +325,98 @@ macro_rules! comment_container {
 pub(crate) fn has_comment_default() -> Cell<bool> {
     Cell::new(true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(id: u64, parent: Option<u64>) -> Comment {
+        Comment {
+            version: VERSION,
+            info: CommentInfo {
+                id: CommentId(id),
+                parent_id: parent.map(CommentId),
+                author: None,
+                is_author: false,
+                has_child: Cell::new(false),
+                created_time: DateTime::parse_from_rfc3339("2020-01-01T00:00:00+08:00").unwrap()
+                    + chrono::Duration::seconds(id as i64),
+                like_count: 0,
+                author_tags: Vec::new(),
+            },
+            content: Content::default(),
+            raw_data: None,
+        }
+    }
+    fn shape(nodes: &[CommentNode]) -> Vec<(u64, Vec<u64>)> {
+        nodes
+            .iter()
+            .map(|n| {
+                (
+                    n.comment.info.id.0,
+                    n.replies.iter().map(|r| r.comment.info.id.0).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse() {
+        use super::super::Item;
+        let data: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/comment.json")).unwrap();
+        let c = Comment::from_reply(
+            Reply::deserialize(&data).unwrap(),
+            RawData {
+                info: raw_data::RawDataInfo {
+                    fetch_time: chrono::Utc::now(),
+                    container: raw_data::Container::None,
+                    etag: None,
+                    last_modified: None,
+                },
+                data,
+            },
+        );
+        assert_eq!(c.info.id, CommentId(10));
+        assert_eq!(c.info.parent_id, Some(CommentId(9)));
+        assert_eq!(c.info.like_count, 42);
+        assert_eq!(c.info.author_tags, ["作者"]);
+        assert!(c.info.is_author);
+
+        let stored: CommentInfo = serde_yaml::from_str(
+            "id: 1\nparent_id: null\nauthor: null\nis_author: false\nhas_child: false\ncreated_time: 2020-01-01T00:00:00+08:00\n",
+        )
+        .unwrap();
+        assert_eq!(stored.like_count, 0);
+        assert!(stored.author_tags.is_empty());
+    }
+
+    #[test]
+    fn build_tree() {
+        let tree = Comment::build_tree(vec![
+            comment(3, Some(2)),
+            comment(2, Some(1)),
+            comment(5, Some(99)),
+            comment(1, None),
+            comment(4, Some(1)),
+            comment(6, Some(7)),
+            comment(7, Some(6)),
+        ]);
+        assert_eq!(shape(&tree), [(1, vec![2, 4]), (5, vec![]), (7, vec![6])]);
+        assert_eq!(shape(&tree[0].replies), [(2, vec![3]), (4, vec![])]);
+
+        let doc = CommentNode::to_document(&tree[..1]);
+        assert_eq!(doc.data.len(), 2);
+        let Block::BlockQuote { content } = &doc.data[1] else {
+            panic!("replies not quoted: {:?}", doc.data);
+        };
+        assert!(matches!(
+            content.as_slice(),
+            [
+                Block::Paragraph(_),
+                Block::BlockQuote { .. },
+                Block::Paragraph(_)
+            ]
+        ));
+    }
+}
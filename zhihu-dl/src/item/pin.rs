@@ -1,6 +1,6 @@
 use crate::{
     element::{content::HasContent, Author, Content},
-    item::comment,
+    item::{comment, UrlTarget},
     meta::Version,
     raw_data::{FromRaw, RawData, StrU64},
     store::BasicStoreItem,
@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 use std::{cell::Cell, collections::HashSet, fmt::Display, str::FromStr};
 use web_dl_base::{
     id::{HasId, OwnedId},
-    media::StoreImage,
+    media::{ImageRef, StoreImage},
     storable::Storable,
 };
 
@@ -43,6 +43,20 @@ pub struct PinContent {
     pub content_html: Content,
 }
 
+/// link card attached to a pin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinLink {
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+}
+impl PinLink {
+    /// zhihu object the link points to
+    pub fn target(&self) -> Option<UrlTarget> {
+        self.url.parse().ok()
+    }
+}
+
 #[derive(Debug, Storable, Serialize, Deserialize)]
 #[store(format = "yaml")]
 pub struct PinInfo {
@@ -53,6 +67,11 @@ pub struct PinInfo {
     pub has_comment: Cell<bool>,
     pub created_time: DateTime<FixedOffset>,
     pub updated_time: DateTime<FixedOffset>,
+    /// urls of attached images in order, they are appended to content as figures
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<PinLink>,
 }
 
 #[derive(Debug, Storable, StoreImage, HasContent, Serialize, Deserialize)]
@@ -64,7 +83,17 @@ pub struct PinBody {
     pub content: PinContent,
 }
 
-pub const VERSION: Version = Version { major: 1, minor: 1 };
+impl PinBody {
+    /// attached images in order, `None` for images not fetched
+    pub fn image_refs(&self) -> impl Iterator<Item = Option<&ImageRef>> {
+        self.info
+            .images
+            .iter()
+            .map(|u| self.content.content_html.image_ref(u))
+    }
+}
+
+pub const VERSION: Version = Version { major: 1, minor: 3 };
 
 #[derive(Debug, Storable, HasContent, StoreImage, Serialize, Deserialize)]
 pub struct Pin {
@@ -87,27 +116,53 @@ impl HasId for Pin {
         self.body.info.id
     }
 }
-basic_store_item!(Pin, pin);
+basic_store_item!(Pin, pin, VERSION;
+    fn upgrade(&mut self, version: Version) {
+        fn repair(body: &mut PinBody, data: &serde_json::Value) {
+            if let Some((c, u)) = crate::raw_data::times(data, "created", "updated") {
+                body.info.created_time = c;
+                body.info.updated_time = u;
+            }
+        }
+        // times were read as local time before 1.3
+        if version < (Version { major: 1, minor: 3 }) {
+            if let Some(r) = &self.raw_data {
+                repair(&mut self.body, &r.data);
+                if let Some(repin) = &mut self.repin {
+                    repair(repin, &r.data["repin"]);
+                }
+            }
+        }
+    }
+);
 item_list_btree!(Pin, PinId);
 
 impl super::Fetchable for Pin {
-    async fn fetch<'a>(
-        client: &crate::request::Client,
-        id: PinId,
-    ) -> Result<serde_json::Value, reqwest::Error> {
+    fn request<'a>(client: &crate::request::Client, id: PinId) -> reqwest::RequestBuilder {
         client
             .http_client
             .get(format!("https://www.zhihu.com/api/v4/v2/pins/{}", id))
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await
     }
 }
 comment_store_container!(Pin, pin);
 comment_container!(Pin, body.info.has_comment);
 
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Block {
+    Image {
+        url: String,
+        #[serde(default)]
+        original_url: String,
+    },
+    Link {
+        url: String,
+        #[serde(default)]
+        title: String,
+    },
+    #[serde(other)]
+    Other,
+}
 #[derive(Deserialize)]
 pub struct Reply {
     id: StrU64,
@@ -116,13 +171,36 @@ pub struct Reply {
     updated: FromRaw<DateTime<FixedOffset>>,
     comment_count: u64,
     content_html: FromRaw<Content>,
+    /// blocks of the pin, images and link cards are not in `content_html`
+    #[serde(default)]
+    content: Vec<Block>,
     #[serde(default)]
     repin: Option<Box<Reply>>,
 }
 impl super::Item for Pin {
     type Reply = Reply;
+    fn timestamps(&self) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        Some((self.body.info.created_time, self.body.info.updated_time))
+    }
     fn from_reply(mut reply: Self::Reply, raw_data: RawData) -> Self {
         fn to_body(data: Reply, repin_id: Option<PinId>) -> PinBody {
+            let mut images = Vec::new();
+            let mut link = None;
+            for b in data.content {
+                match b {
+                    Block::Image { url, original_url } => images.push(if original_url.is_empty() {
+                        url
+                    } else {
+                        original_url
+                    }),
+                    Block::Link { url, title } if link.is_none() => {
+                        link = Some(PinLink { url, title })
+                    }
+                    _ => (),
+                }
+            }
+            let mut content_html = data.content_html.0;
+            content_html.append_figures(images.iter().map(String::as_str));
             PinBody {
                 info: PinInfo {
                     id: PinId(data.id.0),
@@ -131,10 +209,12 @@ impl super::Item for Pin {
                     has_comment: Cell::new(data.comment_count > 0),
                     created_time: data.created.0,
                     updated_time: data.updated.0,
+                    images,
+                    link,
                 },
                 content: PinContent {
                     version: CONTENT_VERSION,
-                    content_html: data.content_html.0,
+                    content_html,
                 },
             }
         }
@@ -174,3 +254,53 @@ impl super::Item for Pin {
             }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        element::content::{document::Block, ConvertOpt},
+        item::{AnswerId, Item},
+        raw_data::{Container, RawDataInfo},
+    };
+
+    #[test]
+    fn multi_image() {
+        let data: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/multi_image_pin.json")).unwrap();
+        let mut pin = Pin::from_reply(
+            Reply::deserialize(&data).unwrap(),
+            RawData {
+                info: RawDataInfo {
+                    fetch_time: chrono::Utc::now(),
+                    container: Container::None,
+                    etag: None,
+                    last_modified: None,
+                },
+                data,
+            },
+        );
+        let info = &pin.body.info;
+        assert_eq!(
+            info.images,
+            [
+                "https://pic1.zhimg.com/v2-aaaa_r.jpg",
+                "https://pic2.zhimg.com/v2-bbbb_b.png"
+            ]
+        );
+        assert_eq!(
+            info.link.as_ref().and_then(PinLink::target),
+            Some(UrlTarget::Answer(AnswerId(2)))
+        );
+        assert!(pin.body.image_refs().all(|r| r.is_none()));
+
+        pin.convert_html(&ConvertOpt::default());
+        let doc = pin.body.content.content_html.document.as_ref().unwrap();
+        let figures = doc
+            .data
+            .iter()
+            .filter(|b| matches!(b, Block::Figure { .. }))
+            .count();
+        assert_eq!(figures, 2);
+    }
+}
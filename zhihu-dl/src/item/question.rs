@@ -68,7 +68,7 @@ impl HasId for Question {
         self.info.id
     }
 }
-basic_store_item!(Question, question);
+basic_store_item!(Question, question, VERSION);
 
 comment_store_container!(Question, question);
 comment_container!(Question, info.has_comment);
@@ -76,27 +76,24 @@ comment_container!(Question, info.has_comment);
 item_list_btree!(Question, QuestionId);
 
 impl super::Fetchable for Question {
-    async fn fetch<'a>(
-        client: &crate::request::Client,
-        id: QuestionId,
-    ) -> Result<serde_json::Value, reqwest::Error> {
-        client
-            .request_signed::<Zse96V3, _>(
-                Method::GET,
-                Url::parse_with_params(
-                    format!("https://www.zhihu.com/api/v4/questions/{}", id).as_str(),
-                    &[(
-                        "include",
-                        "author,description,is_anonymous;detail;comment_count;answer_count;excerpt",
-                    )],
-                )
-                .unwrap(),
+    fn request<'a>(client: &crate::request::Client, id: QuestionId) -> reqwest::RequestBuilder {
+        client.request_signed::<Zse96V3, _>(
+            Method::GET,
+            Url::parse_with_params(
+                format!("https://www.zhihu.com/api/v4/questions/{}", id).as_str(),
+                &[(
+                    "include",
+                    "author,description,is_anonymous;detail;comment_count;answer_count;excerpt",
+                )],
             )
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await
+            .unwrap(),
+        )
+    }
+    /// merged questions are replied with a redirection to the question they are merged into
+    fn redirect<'a>(data: &'a serde_json::Value) -> Option<QuestionId> {
+        data.pointer("/redirection/to/id")
+            .and_then(serde_json::Value::as_u64)
+            .map(QuestionId)
     }
 }
 #[derive(Deserialize)]
@@ -112,6 +109,9 @@ pub struct Reply {
 }
 impl super::Item for Question {
     type Reply = Reply;
+    fn timestamps(&self) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        Some((self.info.created_time, self.info.updated_time))
+    }
     fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self {
         Self {
             version: VERSION,
@@ -168,3 +168,21 @@ impl super::ItemContainer<super::VoidOpt, super::answer::Answer> for Question {
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Fetchable;
+
+    #[test]
+    fn redirect() {
+        let data: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/merged_question.json")).unwrap();
+        assert_eq!(Question::redirect(&data), Some(QuestionId(21)));
+        assert!(Reply::deserialize(&data).is_ok());
+        assert_eq!(
+            Question::redirect(&serde_json::json!({ "id": 21, "redirection": null })),
+            None
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use super::{
+    any::{self, Any},
+    other::{OtherInfo, OtherItem},
+};
+use crate::{
+    raw_data::{self, RawData},
+    request::Zse96V3,
+    store::{self, BasicStoreContainer},
+};
+use reqwest::Url;
+use serde::Deserialize;
+use std::fmt::Display;
+use web_dl_base::id::HasId;
+
+/// search keywords, displayed percent encoded so that they can be used as a file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Query<'a>(pub &'a str);
+impl<'a> Display for Query<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        url::form_urlencoded::byte_serialize(self.0.as_bytes()).try_for_each(|s| f.write_str(s))
+    }
+}
+
+/// keyword search, results are stored as a container of the query
+#[derive(Debug)]
+pub struct Search {
+    pub query: String,
+}
+impl HasId for Search {
+    const TYPE: &'static str = "search";
+    type Id<'a> = Query<'a>;
+    fn id(&self) -> Self::Id<'_> {
+        Query(self.query.as_str())
+    }
+}
+
+/// general search results, answers and articles
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOpt;
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum Entry {
+    #[serde(rename = "search_result")]
+    Result { object: any::Reply },
+    /// related queries, ads and other cards
+    #[serde(other)]
+    Other,
+}
+
+impl BasicStoreContainer<SearchOpt, Any> for Search {
+    const OPTION_NAME: &'static str = "item";
+    type ItemList = any::AnyList;
+    fn in_store(id: Self::Id<'_>, store: &store::Store) -> bool {
+        store.objects.search.get(id.0).map_or(false, |v| v.item)
+    }
+    fn add_info(id: Self::Id<'_>, store: &mut store::Store) {
+        store
+            .objects
+            .search
+            .entry(id.0.to_owned())
+            .or_default()
+            .item = true;
+    }
+}
+impl super::ItemContainer<SearchOpt, Any> for Search {
+    async fn fetch_items<'a, P: crate::progress::ItemContainerProg>(
+        client: &crate::request::Client,
+        prog: &P,
+        id: Self::Id<'a>,
+    ) -> Result<std::collections::LinkedList<RawData>, reqwest::Error> {
+        client
+            .get_paged_sign::<{ raw_data::Container::Search }, Zse96V3, _, _>(
+                prog.start_fetch(),
+                Url::parse_with_params(
+                    "https://www.zhihu.com/api/v4/search_v3",
+                    &[
+                        ("t", "general"),
+                        ("q", id.0),
+                        ("correction", "1"),
+                        ("offset", "0"),
+                        ("limit", "20"),
+                    ],
+                )
+                .unwrap(),
+            )
+            .await
+    }
+    fn parse_item(raw_data: RawData) -> Result<Any, serde_json::Error> {
+        use super::Item;
+        Entry::deserialize(&raw_data.data).map(|e| match e {
+            Entry::Result { object } => Any::from_reply(object, raw_data),
+            Entry::Other => Any::Other(OtherItem {
+                info: OtherInfo::deserialize(&raw_data.data).ok(),
+                raw_data,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{item::ItemContainer, raw_data::RawDataInfo};
+
+    fn raw(data: serde_json::Value) -> RawData {
+        RawData {
+            info: RawDataInfo {
+                fetch_time: chrono::Utc::now(),
+                container: raw_data::Container::Search,
+                etag: None,
+                last_modified: None,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn parse_item() {
+        let answer: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/restricted_answer.json")).unwrap();
+        let item = Search::parse_item(raw(serde_json::json!({
+            "type": "search_result",
+            "highlight": { "title": "<em>rust</em>" },
+            "object": answer,
+        })))
+        .unwrap();
+        assert!(matches!(item, Any::Answer(a) if a.info.id == crate::item::AnswerId(2)));
+        let item = Search::parse_item(raw(serde_json::json!({
+            "type": "search_result",
+            "object": { "type": "zvideo", "id": "1" },
+        })))
+        .unwrap();
+        assert!(matches!(item, Any::Other(_)));
+        let item = Search::parse_item(raw(serde_json::json!({
+            "type": "relevant_query",
+            "query_list": [{ "query": "rust" }],
+        })))
+        .unwrap();
+        assert!(matches!(item, Any::Other(OtherItem { info: None, .. })));
+        assert_eq!(Query("a/b c").to_string(), "a%2Fb+c");
+    }
+}
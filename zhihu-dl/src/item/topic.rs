@@ -0,0 +1,187 @@
+use crate::{
+    element::{content::HasContent, Content},
+    meta::Version,
+    raw_data::{self, FromRaw, RawData, StrU64},
+    store::{self, BasicStoreContainer, BasicStoreItem},
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+use web_dl_base::{
+    id::{HasId, OwnedId},
+    media::{Image, StoreImage},
+    storable::Storable,
+};
+
+use super::any;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TopicId(pub u64);
+impl Display for TopicId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+impl FromStr for TopicId {
+    type Err = <u64 as FromStr>::Err;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str(s).map(Self)
+    }
+}
+impl OwnedId<Topic> for TopicId {
+    fn to_id(&self) -> <Topic as HasId>::Id<'_> {
+        *self
+    }
+}
+
+#[derive(Debug, Storable, StoreImage, Serialize, Deserialize)]
+#[store(format = "yaml")]
+pub struct TopicInfo {
+    pub id: TopicId,
+    pub name: String,
+    #[has_image(path = "dyn_extension")]
+    pub avatar: Option<Image>,
+}
+
+pub const VERSION: Version = Version { major: 1, minor: 0 };
+#[derive(Debug, Storable, StoreImage, HasContent, Serialize, Deserialize)]
+pub struct Topic {
+    #[store(path(ext = "yaml"))]
+    pub version: Version,
+    #[has_image(path = "flatten")]
+    #[store(path(ext = "yaml"))]
+    pub info: TopicInfo,
+    #[has_image]
+    #[content(main)]
+    pub introduction: Content,
+    #[store(raw_data)]
+    pub raw_data: Option<RawData>,
+}
+
+impl HasId for Topic {
+    const TYPE: &'static str = "topic";
+    type Id<'a> = TopicId;
+    fn id(&self) -> TopicId {
+        self.info.id
+    }
+}
+basic_store_item!(Topic, topic, VERSION);
+
+impl super::Fetchable for Topic {
+    fn request<'a>(client: &crate::request::Client, id: TopicId) -> reqwest::RequestBuilder {
+        client
+            .http_client
+            .get(format!("https://www.zhihu.com/api/v4/topics/{}", id))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Reply {
+    id: StrU64,
+    name: String,
+    #[serde(default)]
+    avatar_url: FromRaw<Option<Image>>,
+    #[serde(default)]
+    introduction: FromRaw<Content>,
+}
+impl super::Item for Topic {
+    type Reply = Reply;
+    fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self {
+        Topic {
+            version: VERSION,
+            info: TopicInfo {
+                id: TopicId(reply.id.0),
+                name: reply.name,
+                avatar: reply.avatar_url.0,
+            },
+            introduction: reply.introduction.0,
+            raw_data: Some(raw_data),
+        }
+    }
+    async fn get_images<P: crate::progress::ItemProg>(
+        &mut self,
+        client: &crate::request::Client,
+        prog: &P,
+    ) -> bool {
+        use crate::progress::ImagesProg;
+        let u = self.introduction.image_urls();
+        let mut prog = prog.start_images(u.len() as u64 + 1);
+        self.introduction.fetch_images(client, &mut prog, u).await
+            | match &mut self.info.avatar {
+                Some(i) => {
+                    i.fetch(&client.http_client, &mut prog, &client.image_fetch)
+                        .await
+                }
+                None => {
+                    prog.skip();
+                    false
+                }
+            }
+    }
+}
+
+/// essence feed of the topic
+impl BasicStoreContainer<super::VoidOpt, super::any::Any> for Topic {
+    const OPTION_NAME: &'static str = "item";
+    type ItemList = any::AnyList;
+    fn in_store(id: Self::Id<'_>, store: &store::Store) -> bool {
+        store.objects.topic.get(&id).map_or(false, |v| v.item)
+    }
+    fn add_info(id: Self::Id<'_>, store: &mut store::Store) {
+        store.objects.topic.entry(id).or_default().item = true;
+    }
+}
+impl super::ItemContainer<super::VoidOpt, super::any::Any> for Topic {
+    async fn fetch_items<'a, P: crate::progress::ItemContainerProg>(
+        client: &crate::request::Client,
+        prog: &P,
+        id: Self::Id<'a>,
+    ) -> Result<std::collections::LinkedList<RawData>, reqwest::Error> {
+        client
+            .get_paged::<{ raw_data::Container::Topic }, _, _>(
+                prog.start_fetch(),
+                format!("https://www.zhihu.com/api/v4/topics/{}/feeds/essence", id),
+            )
+            .await
+    }
+    fn parse_item(raw_data: RawData) -> Result<super::any::Any, serde_json::Error> {
+        use super::{any::Any, Item};
+        #[derive(Deserialize)]
+        struct Reply {
+            target: any::Reply,
+        }
+        Reply::deserialize(&raw_data.data).map(|r| Any::from_reply(r.target, raw_data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{item::Item, raw_data::RawDataInfo};
+
+    #[test]
+    fn parse() {
+        let data = serde_json::json!({
+            "id": "19550517",
+            "type": "topic",
+            "name": "互联网",
+            "avatar_url": "",
+            "introduction": "<p>intro</p>",
+        });
+        let topic = Topic::from_reply(
+            Reply::deserialize(&data).unwrap(),
+            RawData {
+                info: RawDataInfo {
+                    fetch_time: chrono::Utc::now(),
+                    container: raw_data::Container::None,
+                    etag: None,
+                    last_modified: None,
+                },
+                data,
+            },
+        );
+        assert_eq!(topic.id(), TopicId(19550517));
+        assert_eq!(topic.info.name, "互联网");
+        assert!(topic.info.avatar.is_none());
+        assert!(!topic.introduction.info.is_empty);
+    }
+}
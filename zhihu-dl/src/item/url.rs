@@ -0,0 +1,120 @@
+use super::{AnswerId, ArticleId, CollectionId, ColumnId, PinId, QuestionId};
+use reqwest::Url;
+use std::{fmt::Display, str::FromStr};
+
+const SUPPORTED: &str = concat!(
+    "zhihu.com/question/<question>/answer/<answer>\n",
+    "zhihu.com/answer/<answer>\n",
+    "zhihu.com/question/<question>\n",
+    "zhuanlan.zhihu.com/p/<article>\n",
+    "zhihu.com/column/<column>, zhuanlan.zhihu.com/<column>\n",
+    "zhihu.com/collection/<collection>\n",
+    "zhihu.com/pin/<pin>\n",
+    "zhihu.com/people/<url token>, zhihu.com/org/<url token>",
+);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseUrlError {
+    #[error("invalid url {url}")]
+    Parse {
+        url: String,
+        #[source]
+        source: url::ParseError,
+    },
+    #[error("unsupported url {0}, expect one of\n{SUPPORTED}")]
+    Unsupported(String),
+}
+
+/// object a zhihu url points to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlTarget {
+    Answer(AnswerId),
+    Article(ArticleId),
+    Collection(CollectionId),
+    Column(ColumnId),
+    Pin(PinId),
+    Question(QuestionId),
+    /// user url token
+    User(String),
+}
+impl Display for UrlTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Answer(i) => write!(f, "answer {}", i),
+            Self::Article(i) => write!(f, "article {}", i),
+            Self::Collection(i) => write!(f, "collection {}", i),
+            Self::Column(i) => write!(f, "column {}", i),
+            Self::Pin(i) => write!(f, "pin {}", i),
+            Self::Question(i) => write!(f, "question {}", i),
+            Self::User(t) => write!(f, "user {}", t),
+        }
+    }
+}
+impl FromStr for UrlTarget {
+    type Err = ParseUrlError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let url = if s.contains("://") {
+            Url::parse(s)
+        } else {
+            Url::parse(format!("https://{}", s).as_str())
+        }
+        .map_err(|e| ParseUrlError::Parse {
+            url: s.to_owned(),
+            source: e,
+        })?;
+        let unsupported = || ParseUrlError::Unsupported(s.to_owned());
+        let host = url.host_str().ok_or_else(unsupported)?;
+        if host != "zhihu.com" && !host.ends_with(".zhihu.com") {
+            return Err(unsupported());
+        }
+        let seg: Vec<&str> = url
+            .path_segments()
+            .map(|s| s.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        fn id<T: FromStr>(s: &str, e: impl FnOnce() -> ParseUrlError) -> Result<T, ParseUrlError> {
+            s.parse().map_err(|_| e())
+        }
+        Ok(match (host, seg.as_slice()) {
+            ("zhuanlan.zhihu.com", ["p", a, ..]) => Self::Article(id(a, unsupported)?),
+            ("zhuanlan.zhihu.com", [c]) => Self::Column(ColumnId(c.to_string())),
+            (_, ["question", _, "answer", a, ..]) | (_, ["answer", a, ..]) => {
+                Self::Answer(id(a, unsupported)?)
+            }
+            (_, ["question", q, ..]) => Self::Question(id(q, unsupported)?),
+            (_, ["p", a, ..]) => Self::Article(id(a, unsupported)?),
+            (_, ["column", c, ..]) => Self::Column(ColumnId(c.to_string())),
+            (_, ["collection", c, ..]) => Self::Collection(id(c, unsupported)?),
+            (_, ["pin", p, ..]) => Self::Pin(id(p, unsupported)?),
+            (_, ["people" | "org", t, ..]) => Self::User(t.to_string()),
+            _ => return Err(unsupported()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UrlTarget;
+
+    #[test]
+    fn parse() {
+        let p = |s: &str| s.parse::<UrlTarget>().map_err(|e| e.to_string());
+        assert_eq!(
+            p("https://www.zhihu.com/question/1/answer/2?utm=x"),
+            Ok(UrlTarget::Answer(crate::item::AnswerId(2)))
+        );
+        assert_eq!(
+            p("zhuanlan.zhihu.com/p/3"),
+            Ok(UrlTarget::Article(crate::item::ArticleId(3)))
+        );
+        assert_eq!(
+            p("https://zhuanlan.zhihu.com/slug"),
+            Ok(UrlTarget::Column(crate::item::ColumnId("slug".to_owned())))
+        );
+        assert_eq!(
+            p("https://www.zhihu.com/people/token/answers"),
+            Ok(UrlTarget::User("token".to_owned()))
+        );
+        assert!(p("https://www.zhihu.com/topic/1").is_err());
+        assert!(p("https://example.com/question/1").is_err());
+    }
+}
@@ -17,7 +17,17 @@ use web_dl_base::{
     storable::Storable,
 };
 
-const VERSION: Version = Version { major: 1, minor: 0 };
+const VERSION: Version = Version { major: 1, minor: 1 };
+/// counts shown on the profile when the user was fetched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserStats {
+    pub follower: u64,
+    pub following: u64,
+    pub answer: u64,
+    pub article: u64,
+    pub question: u64,
+}
 #[derive(Debug, Storable, StoreImage, Serialize, Deserialize)]
 #[store(format = "yaml")]
 pub struct UserInfo {
@@ -30,6 +40,9 @@ pub struct UserInfo {
     pub avatar: Image,
     #[has_image(path = "dyn_extension")]
     pub cover: Option<Image>,
+    /// absent in users stored before stats were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<UserStats>,
 }
 
 #[derive(Debug, Storable, StoreImage, HasContent, Serialize, Deserialize)]
@@ -62,6 +75,7 @@ impl HasId for User {
     }
 }
 impl BasicStoreItem for User {
+    const VERSION: Version = VERSION;
     fn in_store(
         id: Self::Id<'_>,
         store: &crate::store::ObjectInfo,
@@ -75,22 +89,20 @@ impl BasicStoreItem for User {
     ) {
         store.user.entry(id.0).or_default().container = info;
     }
+    fn remove_info(id: Self::Id<'_>, store: &mut crate::store::ObjectInfo) -> bool {
+        store.user.remove(&id.0).is_some()
+    }
 }
 
 impl super::Fetchable for User {
-    async fn fetch<'a>(
-        client: &crate::request::Client,
-        id: Self::Id<'a>,
-    ) -> Result<serde_json::Value, reqwest::Error> {
+    fn request<'a>(client: &crate::request::Client, id: Self::Id<'a>) -> reqwest::RequestBuilder {
         client
             .http_client
             .get(format!("https://www.zhihu.com/api/v4/members/{}", id))
-            .query(&[("include", "description,cover_url")])
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await
+            .query(&[(
+                "include",
+                "description,cover_url,follower_count,following_count,answer_count,articles_count,question_count",
+            )])
     }
 }
 
@@ -104,10 +116,33 @@ pub struct Reply {
     avatar_url: FromRaw<Image>,
     cover_url: FromRaw<Option<Image>>,
     description: FromRaw<Content>,
+    #[serde(default)]
+    follower_count: Option<u64>,
+    #[serde(default)]
+    following_count: Option<u64>,
+    #[serde(default)]
+    answer_count: Option<u64>,
+    #[serde(default)]
+    articles_count: Option<u64>,
+    #[serde(default)]
+    question_count: Option<u64>,
+}
+impl Reply {
+    /// counts are only returned when included in the request
+    fn stats(&self) -> Option<UserStats> {
+        self.follower_count.map(|follower| UserStats {
+            follower,
+            following: self.following_count.unwrap_or_default(),
+            answer: self.answer_count.unwrap_or_default(),
+            article: self.articles_count.unwrap_or_default(),
+            question: self.question_count.unwrap_or_default(),
+        })
+    }
 }
 impl super::Item for User {
     type Reply = Reply;
     fn from_reply(reply: Self::Reply, raw_data: RawData) -> Self {
+        let stats = reply.stats();
         Self {
             version: VERSION,
             info: UserInfo {
@@ -118,6 +153,7 @@ impl super::Item for User {
                 headline: reply.headline,
                 avatar: reply.avatar_url.0,
                 cover: reply.cover_url.0,
+                stats,
             },
             description: reply.description.0,
             raw_data: Some(raw_data),
@@ -129,9 +165,15 @@ impl super::Item for User {
         prog: &P,
     ) -> bool {
         let mut p = prog.start_images(1 + if self.info.cover.is_some() { 1 } else { 0 });
-        self.info.avatar.fetch(&client.http_client, &mut p).await
+        self.info
+            .avatar
+            .fetch(&client.http_client, &mut p, &client.image_fetch)
+            .await
             | match &mut self.info.cover {
-                Some(c) => c.fetch(&client.http_client, &mut p).await,
+                Some(c) => {
+                    c.fetch(&client.http_client, &mut p, &client.image_fetch)
+                        .await
+                }
                 None => false,
             }
     }
@@ -14,7 +14,8 @@ use crate::{
     raw_data::{self, RawData, StrU64},
     request::Zse96V3,
     store::{
-        BasicStoreContainer, ContainerHandle, ItemList, LinkInfo, Store, StoreContainer, StoreItem,
+        BasicStoreContainer, ContainerHandle, ItemList, LinkInfo, Store, StoreContainer,
+        StoreError, StoreItem,
     },
 };
 use serde::{Deserialize, Serialize};
@@ -187,7 +188,7 @@ impl StoreItem for Activity {
         }
         id_targets!(id_v, id.target)
     }
-    fn add_media(&self, store: &mut Store) -> Result<(), web_dl_base::media::Error> {
+    fn add_media(&self, store: &mut Store) -> Result<(), StoreError> {
         macro_rules! id_v {
             ($i:tt) => {
                 Ok(())
@@ -198,11 +199,7 @@ impl StoreItem for Activity {
         }
         targets!(id_v, &self.target)
     }
-    fn save_data(
-        &self,
-        on_server: bool,
-        store: &mut Store,
-    ) -> Result<Option<PathBuf>, web_dl_base::storable::Error> {
+    fn save_data(&self, on_server: bool, store: &mut Store) -> Result<Option<PathBuf>, StoreError> {
         macro_rules! id_v {
             ($i:tt) => {{
                 $i.warn();
@@ -219,7 +216,7 @@ impl StoreItem for Activity {
         on_server: bool,
         store: &mut crate::store::Store,
         dest: P,
-    ) -> Result<Option<LinkInfo>, web_dl_base::storable::Error> {
+    ) -> Result<Option<LinkInfo>, StoreError> {
         macro_rules! id_v {
             ($i:tt) => {{
                 $i.warn();
@@ -311,6 +308,14 @@ impl<'a, 'b> ContainerHandle<comment::Comment> for ActivityComCont<'a, 'b> {
         }
         container_targets!(v, (), self)
     }
+    fn checkpoint(&mut self) -> Result<(), crate::store::StoreError> {
+        macro_rules! v {
+            ($t:ident) => {
+                $t.checkpoint()
+            };
+        }
+        container_targets!(v, Ok(()), self)
+    }
     fn finish(self) -> Result<Option<PathBuf>, crate::store::StoreError> {
         macro_rules! v {
             ($t:ident) => {
@@ -455,6 +460,10 @@ impl ItemList<Activity> for ActivityList {
         }
         target!(answer, article, column, collection, pin, question);
     }
+    /// activities are identified by their own id, which isn't listed
+    fn ids(&self) -> std::collections::HashSet<String> {
+        Default::default()
+    }
 }
 impl BasicStoreContainer<VoidOpt, Activity> for super::User {
     const OPTION_NAME: &'static str = "item";
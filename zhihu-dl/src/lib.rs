@@ -1,3 +1,9 @@
+//! downloader of zhihu answers, articles, pins and their containers into a store
+//!
+//! configure a [`Driver`] with [`Driver::builder`], then fetch with its async methods
+//! like [`Driver::download_item`] and [`Driver::update_container`], passing a reporter
+//! from [`progress`], and call [`Driver::save`] when done
+
 #![feature(async_fn_in_trait)]
 #![feature(adt_const_params)]
 #![feature(maybe_uninit_uninit_array)]
@@ -8,6 +14,7 @@
 pub mod element {
     pub mod author;
     pub mod content;
+    pub mod stats;
 
     pub use self::{author::Author, content::Content};
 }
@@ -16,6 +23,7 @@ pub mod element {
 pub mod store;
 
 pub mod driver;
+pub use driver::{Driver, DriverBuilder};
 pub mod item;
 pub mod meta;
 pub mod progress;
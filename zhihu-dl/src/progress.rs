@@ -18,8 +18,8 @@ pub trait ItemsProg: Progress {
     type ItemRep<'a>: ItemProg
     where
         Self: 'a;
-    fn start_item<I: Display>(&mut self, kind: &str, id: I) -> Self::ItemRep<'_>;
-    fn skip_item(&mut self);
+    fn start_item<I: Display>(&self, kind: &str, id: I) -> Self::ItemRep<'_>;
+    fn skip_item(&self);
 }
 pub trait ItemContainerProg: Progress {
     type FetchRep<'a>: FetchProg
@@ -40,6 +40,10 @@ pub trait ContainerJob: ItemContainerProg {
     fn finish<I: Display>(self, operation: &str, num: Option<usize>, id: I);
 }
 pub trait OtherJob {
+    type ItemsRep<'a>: ItemsProg
+    where
+        Self: 'a;
+    fn start_items(&self, count: u64) -> Self::ItemsRep<'_>;
     fn finish<I: Display>(self, operation: &str, msg: I);
 }
 pub trait Reporter: Progress {
@@ -55,6 +59,8 @@ pub trait Reporter: Progress {
         option: Option<O>,
     ) -> Self::ItemRep<'_>;
     fn link_item<I: Display, P: AsRef<Path>>(&self, kind: &str, id: I, dest: P);
+    /// report an operation skipped in dry run
+    fn plan<I: Display>(&self, operation: &str, msg: I);
 
     type ItemContainerRep<'a>: ContainerJob + Reporter
     where
@@ -84,5 +90,6 @@ pub trait Reporter: Progress {
     fn start_job<I: Display>(&self, operation: &str, msg: I) -> Self::JobRep<'_>;
 }
 
+pub mod json;
 pub mod progress_bar;
 pub mod silent;
@@ -0,0 +1,306 @@
+//! newline delimited json events on stderr, for driving the downloader from scripts
+use super::*;
+use serde_json::{json, Value};
+use std::{
+    fmt::Display,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+/// write one event as a single line, so that events of concurrent tasks never interleave
+fn emit(event: Value) {
+    let mut line = event.to_string();
+    line.push('\n');
+    let _ = std::io::stderr().lock().write_all(line.as_bytes());
+}
+fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+async fn sleep(duration: Duration) {
+    emit(json!({"event": "sleep", "duration_ms": duration.as_millis() as u64}));
+    tokio::time::sleep(duration).await
+}
+
+/// progress below items and containers
+pub struct Sub;
+impl Progress for Sub {
+    async fn sleep(&self, duration: Duration) {
+        sleep(duration).await
+    }
+}
+impl FetchProg for Sub {
+    fn set_count(&mut self, count: Option<u64>) {
+        emit(json!({"event": "fetch_count", "count": count}))
+    }
+    fn inc(&mut self, delta: u64) {
+        emit(json!({"event": "fetch_inc", "delta": delta}))
+    }
+}
+impl ImageProg for Sub {
+    fn set_size(&mut self, size: Option<u64>) {
+        emit(json!({"event": "image_size", "size": size}))
+    }
+    fn inc(&mut self, delta: u64) {
+        emit(json!({"event": "inc", "delta": delta}))
+    }
+    fn retry(&mut self, attempt: u32) {
+        emit(json!({"event": "retry", "attempt": attempt}))
+    }
+}
+impl ImagesProg for Sub {
+    type ImageRep<'a> = Sub;
+    fn start_image<I: Display>(&self, url: I) -> Self::ImageRep<'_> {
+        emit(json!({"event": "start_image", "url": url.to_string()}));
+        Sub
+    }
+    fn skip(&self) {
+        emit(json!({"event": "skip_image"}))
+    }
+}
+impl ItemProg for Sub {
+    type ImagesRep<'a> = Sub;
+    fn start_images(&self, count: u64) -> Self::ImagesRep<'_> {
+        emit(json!({"event": "start_images", "count": count}));
+        Sub
+    }
+}
+impl ItemsProg for Sub {
+    type ItemRep<'a> = Sub;
+    fn start_item<I: Display>(&self, kind: &str, id: I) -> Self::ItemRep<'_> {
+        emit(json!({"event": "process_item", "kind": kind, "id": id.to_string()}));
+        Sub
+    }
+    fn skip_item(&self) {
+        emit(json!({"event": "skip_item"}))
+    }
+}
+impl ItemContainerProg for Sub {
+    type FetchRep<'a> = Sub;
+    fn start_fetch(&self) -> Self::FetchRep<'_> {
+        emit(json!({"event": "start_fetch"}));
+        Sub
+    }
+    type ItemsRep<'a> = Sub;
+    fn start_items(&self, count: u64) -> Self::ItemsRep<'_> {
+        emit(json!({"event": "start_items", "count": count}));
+        Sub
+    }
+}
+
+pub struct Item {
+    start_time: Instant,
+    kind: &'static str,
+}
+impl Progress for Item {
+    async fn sleep(&self, duration: Duration) {
+        sleep(duration).await
+    }
+}
+impl ItemProg for Item {
+    type ImagesRep<'a> = Sub;
+    fn start_images(&self, count: u64) -> Self::ImagesRep<'_> {
+        Sub.start_images(count)
+    }
+}
+impl ItemJob for Item {
+    fn finish<I: Display>(self, operation: &str, id: I) {
+        emit(json!({
+            "event": "finish_item",
+            "operation": operation,
+            "kind": self.kind,
+            "id": id.to_string(),
+            "elapsed_ms": elapsed_ms(self.start_time),
+        }))
+    }
+}
+
+pub struct Container {
+    start_time: Instant,
+    kind: &'static str,
+    item_kind: &'static str,
+}
+impl Progress for Container {
+    async fn sleep(&self, duration: Duration) {
+        sleep(duration).await
+    }
+}
+impl ItemContainerProg for Container {
+    type FetchRep<'a> = Sub;
+    fn start_fetch(&self) -> Self::FetchRep<'_> {
+        Sub.start_fetch()
+    }
+    type ItemsRep<'a> = Sub;
+    fn start_items(&self, count: u64) -> Self::ItemsRep<'_> {
+        Sub.start_items(count)
+    }
+}
+impl ContainerJob for Container {
+    fn finish<I: Display>(self, operation: &str, num: Option<usize>, id: I) {
+        emit(json!({
+            "event": "finish_container",
+            "operation": operation,
+            "kind": self.kind,
+            "item_kind": self.item_kind,
+            "id": id.to_string(),
+            "count": num,
+            "elapsed_ms": elapsed_ms(self.start_time),
+        }))
+    }
+}
+
+pub struct Job {
+    start_time: Instant,
+}
+impl OtherJob for Job {
+    type ItemsRep<'a> = Sub;
+    fn start_items(&self, count: u64) -> Self::ItemsRep<'_> {
+        Sub.start_items(count)
+    }
+    fn finish<I: Display>(self, operation: &str, msg: I) {
+        emit(json!({
+            "event": "finish_job",
+            "operation": operation,
+            "message": msg.to_string(),
+            "elapsed_ms": elapsed_ms(self.start_time),
+        }))
+    }
+}
+
+/// top level reporter, nested containers report in the same way
+pub struct JsonReporter;
+impl Progress for JsonReporter {
+    async fn sleep(&self, duration: Duration) {
+        sleep(duration).await
+    }
+}
+
+fn start_item<O: Display, I: Display>(
+    operation: &str,
+    kind: &'static str,
+    id: I,
+    option: Option<O>,
+) -> Item {
+    emit(json!({
+        "event": "start_item",
+        "operation": operation,
+        "kind": kind,
+        "id": id.to_string(),
+        "option": option.map(|o| o.to_string()),
+    }));
+    Item {
+        start_time: Instant::now(),
+        kind,
+    }
+}
+fn link_item<I: Display, P: AsRef<Path>>(kind: &str, id: I, dest: P) {
+    emit(json!({
+        "event": "link_item",
+        "kind": kind,
+        "id": id.to_string(),
+        "dest": dest.as_ref().display().to_string(),
+    }))
+}
+fn plan<I: Display>(operation: &str, msg: I) {
+    emit(json!({"event": "plan", "operation": operation, "message": msg.to_string()}))
+}
+fn start_item_container<II, IO, IC, I, O>(operation: &str, id: I, option: Option<O>) -> Container
+where
+    II: item::Item,
+    IC: item::ItemContainer<IO, II>,
+    I: Display,
+    O: Display,
+{
+    emit(json!({
+        "event": "start_container",
+        "operation": operation,
+        "kind": IC::TYPE,
+        "item_kind": II::TYPE,
+        "option_name": IC::OPTION_NAME,
+        "id": id.to_string(),
+        "option": option.map(|o| o.to_string()),
+    }));
+    Container {
+        start_time: Instant::now(),
+        kind: IC::TYPE,
+        item_kind: II::TYPE,
+    }
+}
+fn link_container<II, IO, IC, I, P>(id: I, dest: P)
+where
+    II: item::Item,
+    IC: item::ItemContainer<IO, II>,
+    I: Display,
+    P: AsRef<Path>,
+{
+    emit(json!({
+        "event": "link_container",
+        "kind": IC::TYPE,
+        "item_kind": II::TYPE,
+        "option_name": IC::OPTION_NAME,
+        "id": id.to_string(),
+        "dest": dest.as_ref().display().to_string(),
+    }))
+}
+fn start_job<I: Display>(operation: &str, msg: I) -> Job {
+    emit(json!({"event": "start_job", "operation": operation, "message": msg.to_string()}));
+    Job {
+        start_time: Instant::now(),
+    }
+}
+
+macro_rules! impl_reporter {
+    ($t:ty) => {
+        impl Reporter for $t {
+            type ItemRep<'a> = Item;
+            fn start_item<O: Display, I: Display>(
+                &self,
+                operation: &str,
+                _: &'static str,
+                kind: &'static str,
+                id: I,
+                option: Option<O>,
+            ) -> Self::ItemRep<'_> {
+                start_item(operation, kind, id, option)
+            }
+            fn link_item<I: Display, P: AsRef<Path>>(&self, kind: &str, id: I, dest: P) {
+                link_item(kind, id, dest)
+            }
+            fn plan<I: Display>(&self, operation: &str, msg: I) {
+                plan(operation, msg)
+            }
+
+            type ItemContainerRep<'a> = Container;
+            fn start_item_container<II, IO, IC, I, O>(
+                &self,
+                operation: &str,
+                _: &'static str,
+                id: I,
+                option: Option<O>,
+            ) -> Self::ItemContainerRep<'_>
+            where
+                II: item::Item,
+                IC: item::ItemContainer<IO, II>,
+                I: Display,
+                O: Display,
+            {
+                start_item_container::<II, IO, IC, I, O>(operation, id, option)
+            }
+            fn link_container<II, IO, IC, I, P>(&self, id: I, dest: P)
+            where
+                II: item::Item,
+                IC: item::ItemContainer<IO, II>,
+                I: Display,
+                P: AsRef<Path>,
+            {
+                link_container::<II, IO, IC, I, P>(id, dest)
+            }
+
+            type JobRep<'a> = Job;
+            fn start_job<I: Display>(&self, operation: &str, msg: I) -> Self::JobRep<'_> {
+                start_job(operation, msg)
+            }
+        }
+    };
+}
+impl_reporter!(JsonReporter);
+impl_reporter!(Container);
@@ -2,20 +2,52 @@ use super::*;
 use indicatif::{HumanDuration, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use std::{
     fmt::Display,
+    sync::atomic::{AtomicU64, Ordering},
     time::{Duration, SystemTime},
 };
 use yansi::Paint;
 
 const TICK_INTERVAL: Duration = Duration::from_millis(100);
+/// image download of known size
+const IMAGE_SIZED_TEMPLATE: &str = "{bar:40} {bytes}/{total_bytes} {bytes_per_sec} eta {eta}";
+/// image download of unknown size
+const IMAGE_UNSIZED_TEMPLATE: &str = "{spinner} fetched {bytes} {bytes_per_sec} in {elapsed}";
 lazy_static::lazy_static!(
     static ref DEFAULT_BAR_STYLE: ProgressStyle = ProgressStyle::default_bar()
         .template("{bar:40} {pos}/{len} {wide_msg}")
         .unwrap();
 );
 
+/// images fetched below an item or container
+#[derive(Default)]
+struct ImageStat {
+    images: AtomicU64,
+    bytes: AtomicU64,
+}
+/// structured event of a finished item or container operation, besides the printed line
+fn log_finished(
+    operation: &str,
+    kind: &str,
+    id: &str,
+    items: Option<usize>,
+    duration: Duration,
+    stat: &ImageStat,
+) {
+    slog::info!(slog_scope::logger(), "{} {} {}", operation, kind, id;
+        "operation" => operation,
+        "kind" => kind,
+        "id" => id,
+        "items" => items,
+        "duration_ms" => duration.as_millis() as u64,
+        "images" => stat.images.load(Ordering::Relaxed),
+        "bytes" => stat.bytes.load(Ordering::Relaxed)
+    );
+}
+
 pub struct SubProgress<'a> {
     multi_progress: &'a MultiProgress,
     progress_bar: ProgressBar,
+    stat: Option<&'a ImageStat>,
 }
 impl<'a> Drop for SubProgress<'a> {
     fn drop(&mut self) {
@@ -75,7 +107,7 @@ impl<'a> ImageProg for SubProgress<'a> {
             Some(sz) => {
                 self.progress_bar.set_style(
                     ProgressStyle::default_bar()
-                        .template("{bar:40} {bytes}/{total_bytes}")
+                        .template(IMAGE_SIZED_TEMPLATE)
                         .unwrap(),
                 );
                 self.progress_bar.set_length(sz);
@@ -83,7 +115,7 @@ impl<'a> ImageProg for SubProgress<'a> {
             None => {
                 self.progress_bar.set_style(
                     ProgressStyle::default_spinner()
-                        .template("{spinner} fetched {bytes}")
+                        .template(IMAGE_UNSIZED_TEMPLATE)
                         .unwrap(),
                 );
                 self.progress_bar.enable_steady_tick(TICK_INTERVAL);
@@ -91,27 +123,41 @@ impl<'a> ImageProg for SubProgress<'a> {
         }
     }
     fn inc(&mut self, delta: u64) {
+        if let Some(s) = self.stat {
+            s.bytes.fetch_add(delta, Ordering::Relaxed);
+        }
         self.progress_bar.inc(delta)
     }
+    fn retry(&mut self, _: u32) {
+        if let Some(s) = self.stat {
+            s.bytes
+                .fetch_sub(self.progress_bar.position(), Ordering::Relaxed);
+        }
+        self.progress_bar.set_position(0)
+    }
 }
 impl<'a> ImagesProg for SubProgress<'a> {
     type ImageRep<'b> = SubProgress<'b>
         where Self:'a+'b;
-    fn start_image<I: Display>(&mut self, url: I) -> Self::ImageRep<'_> {
+    fn start_image<I: Display>(&self, url: I) -> Self::ImageRep<'_> {
         self.progress_bar.inc(1);
         self.progress_bar
             .set_message(format!("fetching image {}", url));
+        if let Some(s) = self.stat {
+            s.images.fetch_add(1, Ordering::Relaxed);
+        }
         Self {
             multi_progress: self.multi_progress,
             progress_bar: self.multi_progress.add(ProgressBar::hidden()),
+            stat: self.stat,
         }
     }
-    fn skip(&mut self) {
+    fn skip(&self) {
         self.progress_bar.inc(1)
     }
 }
 
-pub struct SubWrapper<'a>(pub &'a MultiProgress);
+pub struct SubWrapper<'a>(pub &'a MultiProgress, Option<&'a ImageStat>);
 
 impl<'a> Progress for SubWrapper<'a> {
     async fn sleep(&self, duration: std::time::Duration) {
@@ -119,42 +165,56 @@ impl<'a> Progress for SubWrapper<'a> {
     }
 }
 
-fn item_start_images(prog: &MultiProgress, count: u64) -> SubProgress<'_> {
+fn item_start_images<'a>(
+    prog: &'a MultiProgress,
+    count: u64,
+    stat: Option<&'a ImageStat>,
+) -> SubProgress<'a> {
     SubProgress {
         multi_progress: prog,
         progress_bar: prog.add(ProgressBar::new(count).with_style(DEFAULT_BAR_STYLE.clone())),
+        stat,
     }
 }
 impl<'a> ItemProg for SubWrapper<'a> {
     type ImagesRep<'b> = SubProgress<'b> where Self:'a+'b;
     fn start_images(&self, count: u64) -> Self::ImagesRep<'_> {
-        item_start_images(self.0, count)
+        item_start_images(self.0, count, self.1)
     }
 }
 impl<'a> ItemsProg for SubProgress<'a> {
     type ItemRep<'b> = SubWrapper<'b> where Self:'a+'b;
-    fn start_item<I: Display>(&mut self, kind: &str, id: I) -> Self::ItemRep<'_> {
+    fn start_item<I: Display>(&self, kind: &str, id: I) -> Self::ItemRep<'_> {
         self.progress_bar.inc(1);
         self.progress_bar
             .set_message(format!("processing {} {}", kind, id));
-        SubWrapper(self.multi_progress)
+        SubWrapper(self.multi_progress, self.stat)
     }
-    fn skip_item(&mut self) {
+    fn skip_item(&self) {
         self.progress_bar.inc(1);
         self.progress_bar.set_message("processing");
     }
 }
 
+fn plan<I: Display>(prog: &MultiProgress, operation: &str, msg: I) {
+    prog.suspend(|| println!("{:>13} {}", Paint::yellow(operation), msg));
+}
 fn container_start_fetch(prog: &MultiProgress) -> SubProgress<'_> {
     SubProgress {
         multi_progress: prog,
         progress_bar: prog.add(ProgressBar::new_spinner().with_message("fetching")),
+        stat: None,
     }
 }
-fn container_start_items(prog: &MultiProgress, count: u64) -> SubProgress<'_> {
+fn container_start_items<'a>(
+    prog: &'a MultiProgress,
+    count: u64,
+    stat: Option<&'a ImageStat>,
+) -> SubProgress<'a> {
     SubProgress {
         multi_progress: prog,
         progress_bar: prog.add(ProgressBar::new(count).with_style(DEFAULT_BAR_STYLE.clone())),
+        stat,
     }
 }
 impl<'a> ItemContainerProg for SubWrapper<'a> {
@@ -164,7 +224,7 @@ impl<'a> ItemContainerProg for SubWrapper<'a> {
     }
     type ItemsRep<'b> = SubProgress<'b> where Self:'a+'b ;
     fn start_items(&self, count: u64) -> Self::ItemsRep<'_> {
-        container_start_items(self.0, count)
+        container_start_items(self.0, count, self.1)
     }
 }
 
@@ -180,7 +240,9 @@ impl Progress for ProgressReporter {
 impl Drop for ProgressReporter {
     fn drop(&mut self) {
         self.progress_bar.finish();
-        self.multi_progress.clear().unwrap();
+        if !self.multi_progress.is_hidden() {
+            let _ = self.multi_progress.clear();
+        }
     }
 }
 
@@ -190,6 +252,7 @@ pub struct Item<'a> {
     prefix: &'static str,
     kind: &'static str,
     option: String,
+    stat: ImageStat,
 }
 impl<'a> Item<'a> {
     fn new<I: Display, O: Display>(
@@ -220,6 +283,7 @@ impl<'a> Item<'a> {
             prefix,
             kind,
             option,
+            stat: ImageStat::default(),
         }
     }
     fn link<I: Display, P: AsRef<Path>>(
@@ -248,11 +312,13 @@ impl<'a> ItemProg for Item<'a> {
     type ImagesRep<'b> = SubProgress<'b>
         where Self:'a+'b;
     fn start_images(&self, count: u64) -> Self::ImagesRep<'_> {
-        item_start_images(self.multi_progress, count)
+        item_start_images(self.multi_progress, count, Some(&self.stat))
     }
 }
 impl<'a> ItemJob for Item<'a> {
     fn finish<I: Display>(self, operation: &str, id: I) {
+        let duration = SystemTime::now().duration_since(self.start_time).unwrap();
+        let id = id.to_string();
         self.multi_progress.suspend(|| {
             println!(
                 "{op:>13} {prefix}{kind} {id} {opt} took {dur}",
@@ -261,9 +327,10 @@ impl<'a> ItemJob for Item<'a> {
                 kind = self.kind,
                 id = id,
                 opt = self.option,
-                dur = HumanDuration(SystemTime::now().duration_since(self.start_time).unwrap())
+                dur = HumanDuration(duration)
             )
         });
+        log_finished(operation, self.kind, &id, None, duration, &self.stat);
     }
 }
 
@@ -275,6 +342,7 @@ pub struct Container<'a> {
     item_kind: &'static str,
     option_name: &'static str,
     option: String,
+    stat: ImageStat,
 }
 impl<'a> Progress for Container<'a> {
     async fn sleep(&self, duration: std::time::Duration) {
@@ -319,6 +387,7 @@ impl<'a> Container<'a> {
             item_kind: II::TYPE,
             option_name: IC::OPTION_NAME,
             option,
+            stat: ImageStat::default(),
         }
     }
     fn link<II, IO, IC, I, P>(multi_progress: &'a MultiProgress, id: I, dest: P)
@@ -350,11 +419,13 @@ impl<'a> ItemContainerProg for Container<'a> {
     type ItemsRep<'b> = SubProgress<'b>
         where Self:'a+'b;
     fn start_items(&self, count: u64) -> Self::ItemsRep<'_> {
-        container_start_items(self.multi_progress, count)
+        container_start_items(self.multi_progress, count, Some(&self.stat))
     }
 }
 impl<'a> ContainerJob for Container<'a> {
     fn finish<I: Display>(self, operation: &str, num: Option<usize>, id: I) {
+        let duration = SystemTime::now().duration_since(self.start_time).unwrap();
+        let id = id.to_string();
         self.multi_progress.suspend(|| {
             println!(
                 "{op:>13} {prefix}{num}{item_kind} ({item_opt}) in {kind} {id} {opt} took {dur}",
@@ -369,9 +440,10 @@ impl<'a> ContainerJob for Container<'a> {
                 kind = self.kind,
                 id = id,
                 opt = self.option,
-                dur = HumanDuration(SystemTime::now().duration_since(self.start_time).unwrap())
+                dur = HumanDuration(duration)
             )
         });
+        log_finished(operation, self.kind, &id, num, duration, &self.stat);
     }
 }
 
@@ -389,6 +461,10 @@ impl<'a> Job<'a> {
     }
 }
 impl<'a> OtherJob for Job<'a> {
+    type ItemsRep<'b> = SubProgress<'b> where Self:'a+'b;
+    fn start_items(&self, count: u64) -> Self::ItemsRep<'_> {
+        container_start_items(self.multi_progress, count, None)
+    }
     fn finish<I: Display>(self, operation: &str, msg: I) {
         self.multi_progress.suspend(|| {
             println!(
@@ -416,6 +492,9 @@ impl<'b> Reporter for Container<'b> {
     fn link_item<I: Display, P: AsRef<Path>>(&self, kind: &str, id: I, dest: P) {
         Item::link(self.multi_progress, kind, id, dest)
     }
+    fn plan<I: Display>(&self, operation: &str, msg: I) {
+        plan(self.multi_progress, operation, msg)
+    }
 
     type ItemContainerRep<'a> = Container<'a> where Self:'a;
     fn start_item_container<II, IO, IC, I, O>(
@@ -479,6 +558,9 @@ impl Reporter for ProgressReporter {
     fn link_item<I: Display, P: AsRef<Path>>(&self, kind: &str, id: I, dest: P) {
         Item::link(&self.multi_progress, kind, id, dest)
     }
+    fn plan<I: Display>(&self, operation: &str, msg: I) {
+        plan(&self.multi_progress, operation, msg)
+    }
 
     type ItemContainerRep<'a> = Container<'a>;
     fn start_item_container<II, IO, IC, I, O>(
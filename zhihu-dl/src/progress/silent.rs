@@ -1,6 +1,8 @@
 use super::*;
 use std::fmt::Display;
 
+/// reporter discarding every event, for quiet mode and driving the driver in tests
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Silent;
 impl Progress for Silent {
     async fn sleep(&self, duration: std::time::Duration) {
@@ -15,13 +17,14 @@ impl FetchProg for Silent {
 impl ImageProg for Silent {
     fn set_size(&mut self, _: Option<u64>) {}
     fn inc(&mut self, _: u64) {}
+    fn retry(&mut self, _: u32) {}
 }
 impl ImagesProg for Silent {
     type ImageRep<'a> = Silent;
-    fn start_image<I: Display>(&mut self, _: I) -> Self::ImageRep<'_> {
+    fn start_image<I: Display>(&self, _: I) -> Self::ImageRep<'_> {
         Silent
     }
-    fn skip(&mut self) {}
+    fn skip(&self) {}
 }
 
 impl ItemProg for Silent {
@@ -32,10 +35,10 @@ impl ItemProg for Silent {
 }
 impl ItemsProg for Silent {
     type ItemRep<'a> = Silent;
-    fn start_item<I: Display>(&mut self, _: &str, _: I) -> Self::ItemRep<'_> {
+    fn start_item<I: Display>(&self, _: &str, _: I) -> Self::ItemRep<'_> {
         Silent
     }
-    fn skip_item(&mut self) {}
+    fn skip_item(&self) {}
 }
 impl ItemContainerProg for Silent {
     type FetchRep<'a> = Silent;
@@ -56,6 +59,10 @@ impl ContainerJob for Silent {
     fn finish<I: Display>(self, _: &str, _: Option<usize>, _: I) {}
 }
 impl OtherJob for Silent {
+    type ItemsRep<'a> = Silent;
+    fn start_items(&self, _: u64) -> Self::ItemsRep<'_> {
+        Silent
+    }
     fn finish<I>(self, _: &str, _: I) {}
 }
 
@@ -65,6 +72,7 @@ impl Reporter for Silent {
         Silent
     }
     fn link_item<I, P>(&self, _: &str, _: I, _: P) {}
+    fn plan<I>(&self, _: &str, _: I) {}
 
     type ItemContainerRep<'a> = Silent;
     fn start_item_container<II, IO, IC, I, O>(
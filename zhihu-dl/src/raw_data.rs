@@ -35,20 +35,35 @@ impl<T: Default> Default for FromRaw<T> {
     }
 }
 
+/// unix seconds, in china standard time as shown on the site
 impl<'de> Deserialize<'de> for FromRaw<DateTime<FixedOffset>> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        i64::deserialize(deserializer).map(|v| {
-            FromRaw(DateTime::from_local(
-                NaiveDateTime::from_timestamp_opt(v, 0).unwrap(),
-                FixedOffset::east_opt(8 * 3600).unwrap(),
-            ))
-        })
+        let v = i64::deserialize(deserializer)?;
+        NaiveDateTime::from_timestamp_opt(v, 0)
+            .map(|t| {
+                FromRaw(DateTime::from_utc(
+                    t,
+                    FixedOffset::east_opt(8 * 3600).unwrap(),
+                ))
+            })
+            .ok_or_else(|| de::Error::custom(format_args!("timestamp {} out of range", v)))
     }
 }
 
+/// creation and update time under `created` and `updated` of raw api data,
+/// used to repair times that older versions stored 8 hours early
+pub(crate) fn times(
+    data: &serde_json::Value,
+    created: &str,
+    updated: &str,
+) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    let time = |k: &str| FromRaw::<DateTime<FixedOffset>>::deserialize(&data[k]).ok();
+    Some((time(created)?.0, time(updated)?.0))
+}
+
 impl<'de> Deserialize<'de> for FromRaw<Option<Image>> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -93,21 +108,54 @@ impl<'de> Deserialize<'de> for FromRaw<Image> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, std::marker::ConstParamTy, Serialize, Deserialize)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    std::marker::ConstParamTy,
+    Serialize,
+    Deserialize,
+)]
 pub enum Container {
     None,
     Activity,
     Collection,
     Column,
     Question,
+    Search,
+    Topic,
     User,
 }
 
+/// directory of the raw data field in stored objects
+pub(crate) const RAW_DATA: &str = "raw_data";
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Storable)]
 #[store(format = "yaml")]
 pub struct RawDataInfo {
     pub fetch_time: DateTime<Utc>,
     pub container: Container,
+    /// `ETag` of the response, for conditional requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// `Last-Modified` of the response, for conditional requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+impl RawDataInfo {
+    /// load info of the raw data stored with an object at `path`
+    pub(crate) fn load_of_object<P: AsRef<std::path::Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref().join(RAW_DATA).join("info.yaml");
+        if path.exists() {
+            Self::load(path, Default::default()).ok()
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Storable, Serialize, Deserialize)]
@@ -117,3 +165,19 @@ pub struct RawData {
     #[store(path(ext = "json"))]
     pub data: serde_json::Value,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp() {
+        let FromRaw(t) =
+            FromRaw::<DateTime<FixedOffset>>::deserialize(serde_json::json!(1600000000)).unwrap();
+        assert_eq!(t.timestamp(), 1600000000);
+        assert_eq!(t.to_rfc3339(), "2020-09-13T20:26:40+08:00");
+        assert!(
+            FromRaw::<DateTime<FixedOffset>>::deserialize(serde_json::json!(i64::MAX)).is_err()
+        );
+    }
+}
@@ -1,13 +1,101 @@
-use reqwest::{self as req, IntoUrl, Method};
-use reqwest_cookie_store::CookieStoreMutex;
-use std::{sync::Arc, time::Duration};
+use crate::raw_data::RawDataInfo;
+use reqwest::{self as req, header, IntoUrl, Method};
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use serde::de::DeserializeOwned;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use web_dl_base::{
+    media::{ByteCounter, FetchError, FetchOpt, FetchReport, FsBackend, HostFilter},
+    progress::Progress,
+};
 
 pub struct Client {
     pub(crate) http_client: req::Client,
     pub(crate) request_interval: Duration,
+    pub(crate) image_concurrency: usize,
+    pub(crate) image_fetch: FetchOpt,
+    profile: Profile,
+    proxies: Vec<req::Proxy>,
+    timeouts: Timeouts,
+    image_stat: Mutex<ImageStat>,
+    /// shared with image fetches in `image_fetch`
+    transferred: ByteCounter,
+    limiter: Mutex<rate_limit::Limiter>,
+    paging_stop: Mutex<Option<paging::Stop>>,
+    /// the first response after restoring a session is checked for rejection
+    check_session: AtomicBool,
+    session_rejected: AtomicBool,
     cookie_store: Arc<CookieStoreMutex>,
 }
 
+/// response of a conditional request
+#[derive(Debug)]
+pub(crate) enum Fetched {
+    NotModified,
+    Modified {
+        data: serde_json::Value,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("failed to {op} session file {}", path.display())]
+    Io {
+        op: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("failed to process cookies in session file {}", path.display())]
+    Cookie {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// hosts images are fetched from unless set by [`Client::set_image_hosts`]
+pub const DEFAULT_IMAGE_HOSTS: &[&str] = &["*.zhimg.com", "*.zhihu.com"];
+
+/// timeouts of requests, a hung connection fails instead of stalling the download
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// whole api request including its body, 30s by default
+    pub request: Duration,
+    /// establishing a connection, 10s by default
+    pub connect: Duration,
+    /// whole image request, 60s by default as images are larger than api replies
+    pub image: Duration,
+}
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            request: Duration::from_secs(30),
+            connect: Duration::from_secs(10),
+            image: Duration::from_secs(60),
+        }
+    }
+}
+
+/// images fetched since last [`Client::take_image_stat`]
+#[derive(Debug, Default)]
+pub struct ImageStat {
+    pub total: usize,
+    pub fetched: usize,
+    /// size of fetched image data
+    pub bytes: u64,
+    pub failed: Vec<(req::Url, FetchError)>,
+}
+
 pub(crate) trait Signer {
     fn sign_request<U>(client: &Client, method: Method, url: U) -> req::RequestBuilder
     where
@@ -21,9 +109,15 @@ impl Signer for NoSign {
     }
 }
 
+mod rate_limit;
+pub use rate_limit::RateLimit;
+mod profile;
+pub use profile::{Profile, ProfileError};
+mod proxy;
+pub use proxy::{ProxyConfig, ProxyError};
 mod user_agent;
 mod zse96_v3;
-pub use zse96_v3::Zse96V3;
+pub use zse96_v3::{zse96_v3_sign, Zse96V3};
 
 impl Client {
     pub fn new() -> Self {
@@ -31,12 +125,35 @@ impl Client {
     }
     pub fn with_http_client(client_builder: req::ClientBuilder) -> Result<Self, reqwest::Error> {
         let cookie_store = Arc::new(CookieStoreMutex::default());
+        let transferred = ByteCounter::default();
+        let timeouts = Timeouts::default();
         Ok(Self {
             http_client: client_builder
                 .cookie_provider(cookie_store.clone())
                 .user_agent(user_agent::CHROME)
+                .timeout(timeouts.request)
+                .connect_timeout(timeouts.connect)
                 .build()?,
+            profile: Profile::default(),
+            proxies: Vec::new(),
+            timeouts,
             request_interval: Duration::from_secs(5),
+            image_concurrency: web_dl_base::media::DEFAULT_CONCURRENCY,
+            image_fetch: FetchOpt {
+                hosts: HostFilter {
+                    allow: DEFAULT_IMAGE_HOSTS.iter().map(|h| h.to_string()).collect(),
+                    block: Vec::new(),
+                },
+                transferred: transferred.clone(),
+                timeout: Some(timeouts.image),
+                ..Default::default()
+            },
+            image_stat: Mutex::default(),
+            transferred,
+            limiter: Mutex::new(rate_limit::Limiter::new(RateLimit::default())),
+            paging_stop: Mutex::default(),
+            check_session: AtomicBool::new(false),
+            session_rejected: AtomicBool::new(false),
             cookie_store,
         })
     }
@@ -44,8 +161,10 @@ impl Client {
         self.http_client
             .get("https://www.zhihu.com/explore")
             .send()
-            .await
-            .map(|_| ())
+            .await?;
+        self.check_session.store(false, Ordering::Relaxed);
+        self.session_rejected.store(false, Ordering::Relaxed);
+        Ok(())
     }
     pub(crate) fn request_signed<S: Signer, U: IntoUrl>(
         &self,
@@ -54,6 +173,223 @@ impl Client {
     ) -> req::RequestBuilder {
         S::sign_request(self, method, url)
     }
+    pub fn set_rate_limit(&mut self, limit: RateLimit) {
+        *self.limiter.get_mut().unwrap() = rate_limit::Limiter::new(limit);
+    }
+    fn build_http_client(
+        &self,
+        profile: &Profile,
+        headers: header::HeaderMap,
+        proxies: &[req::Proxy],
+        timeouts: Timeouts,
+    ) -> reqwest::Result<req::Client> {
+        proxies
+            .iter()
+            .fold(req::ClientBuilder::new(), |b, p| b.proxy(p.clone()))
+            .cookie_provider(self.cookie_store.clone())
+            .user_agent(profile.user_agent.as_str())
+            .default_headers(headers)
+            .timeout(timeouts.request)
+            .connect_timeout(timeouts.connect)
+            .build()
+    }
+    /// rebuild the http client sending headers of `profile`, cookies are kept
+    pub fn set_profile(&mut self, profile: Profile) -> Result<(), ProfileError> {
+        self.http_client = self
+            .build_http_client(
+                &profile,
+                profile.header_map()?,
+                &self.proxies,
+                self.timeouts,
+            )
+            .map_err(ProfileError::Build)?;
+        log::debug!("request profile: {:?}", profile);
+        self.profile = profile;
+        Ok(())
+    }
+    /// rebuild the http client sending requests through `proxy`, cookies are kept
+    pub fn set_proxy(&mut self, proxy: &ProxyConfig) -> Result<(), ProxyError> {
+        let proxies = proxy.proxies()?;
+        // headers of the current profile were checked when it was set
+        let headers = self.profile.header_map().unwrap_or_default();
+        self.http_client = self
+            .build_http_client(&self.profile, headers, &proxies, self.timeouts)
+            .map_err(ProxyError::Build)?;
+        log::debug!("request proxy: {:?}", proxy);
+        self.proxies = proxies;
+        Ok(())
+    }
+    /// rebuild the http client with `timeouts`, images are fetched with their own timeout
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) -> reqwest::Result<()> {
+        let headers = self.profile.header_map().unwrap_or_default();
+        self.http_client =
+            self.build_http_client(&self.profile, headers, &self.proxies, timeouts)?;
+        log::debug!("request timeouts: {:?}", timeouts);
+        self.image_fetch.timeout = Some(timeouts.image);
+        self.timeouts = timeouts;
+        Ok(())
+    }
+    pub fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+    /// images on other hosts are skipped
+    pub fn set_image_hosts(&mut self, hosts: HostFilter) {
+        self.image_fetch.hosts = hosts;
+    }
+    /// maximum number of images of an item fetched at the same time, at least 1
+    pub fn set_image_concurrency(&mut self, concurrency: usize) {
+        self.image_concurrency = concurrency.max(1);
+    }
+    pub fn image_concurrency(&self) -> usize {
+        self.image_concurrency
+    }
+    /// stream fetched images into `store`, they are kept in memory until stored when `None`
+    pub fn set_image_store(&mut self, store: Option<FsBackend>) {
+        self.image_fetch.stream_to = store;
+    }
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+    pub fn rate_limit(&self) -> RateLimit {
+        self.limiter.lock().unwrap().limit()
+    }
+    /// send request once the rate limiter allows, backing off as asked by `429` responses
+    pub(crate) async fn send<P: Progress>(
+        &self,
+        prog: &P,
+        request: req::RequestBuilder,
+    ) -> reqwest::Result<req::Response> {
+        let wait = self.limiter.lock().unwrap().reserve(Instant::now());
+        if !wait.is_zero() {
+            prog.sleep(wait).await;
+        }
+        let resp = request.send().await?;
+        if self.check_session.swap(false, Ordering::Relaxed)
+            && matches!(
+                resp.status(),
+                req::StatusCode::UNAUTHORIZED | req::StatusCode::FORBIDDEN
+            )
+        {
+            log::warn!("restored session was rejected with {}", resp.status());
+            self.session_rejected.store(true, Ordering::Relaxed);
+        }
+        if let Some(d) = rate_limit::retry_after(&resp) {
+            log::warn!("rate limited by server, retry after {:?}", d);
+            self.limiter.lock().unwrap().back_off(Instant::now(), d);
+        }
+        Ok(resp)
+    }
+    /// fetch json, asking the server to reply `304 Not Modified` if unchanged since `previous`
+    pub(crate) async fn fetch_json<P: Progress>(
+        &self,
+        prog: &P,
+        mut request: req::RequestBuilder,
+        previous: Option<&RawDataInfo>,
+    ) -> reqwest::Result<Fetched> {
+        if let Some(p) = previous {
+            if let Some(e) = &p.etag {
+                request = request.header(header::IF_NONE_MATCH, e);
+            }
+            if let Some(m) = &p.last_modified {
+                request = request.header(header::IF_MODIFIED_SINCE, m);
+            }
+        }
+        let resp = self.send(prog, request).await?;
+        if resp.status() == req::StatusCode::NOT_MODIFIED {
+            return Ok(Fetched::NotModified);
+        }
+        let resp = resp.error_for_status()?;
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v: &header::HeaderValue| v.to_str().ok())
+                .map(str::to_owned)
+        };
+        let (etag, last_modified) = (header(header::ETAG), header(header::LAST_MODIFIED));
+        Ok(Fetched::Modified {
+            data: self.json(resp).await?,
+            etag,
+            last_modified,
+        })
+    }
+    /// read the body of `resp` as json, counting its size as transferred
+    pub(crate) async fn json<T: DeserializeOwned>(
+        &self,
+        resp: req::Response,
+    ) -> reqwest::Result<T> {
+        let body = resp.bytes().await?;
+        self.transferred.add(body.len() as u64);
+        req::Response::from(http::Response::new(body)).json().await
+    }
+    /// size of api and image responses received by the client, after decompression
+    pub fn bytes_transferred(&self) -> u64 {
+        self.transferred.get()
+    }
+    pub(crate) fn record_images(&self, report: &mut FetchReport) {
+        let mut stat = self.image_stat.lock().unwrap();
+        stat.total += report.total();
+        stat.fetched += report.fetched;
+        stat.bytes += report.bytes;
+        stat.failed.append(&mut report.failed);
+    }
+    pub fn take_image_stat(&self) -> ImageStat {
+        std::mem::take(&mut *self.image_stat.lock().unwrap())
+    }
+    /// restore cookies written by [`Client::save_session`], expired ones are dropped
+    pub fn load_session<P: AsRef<Path>>(&self, path: P) -> Result<(), SessionError> {
+        let path = path.as_ref();
+        let file = fs::File::open(path).map_err(|e| SessionError::Io {
+            op: "open",
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        *self.cookie_store.lock().unwrap() = CookieStore::load_json(io::BufReader::new(file))
+            .map_err(|e| SessionError::Cookie {
+                path: path.to_path_buf(),
+                source: e,
+            })?;
+        self.check_session.store(true, Ordering::Relaxed);
+        self.session_rejected.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+    /// write cookies to `path`, readable only by the owner as they authenticate the session
+    pub fn save_session<P: AsRef<Path>>(&self, path: P) -> Result<(), SessionError> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        let io_err = |op, path: &Path| {
+            let path = path.to_path_buf();
+            move |e| SessionError::Io {
+                op,
+                path,
+                source: e,
+            }
+        };
+        // a leftover file may have been created with other permissions
+        let _ = fs::remove_file(&tmp);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut writer = io::BufWriter::new(options.open(&tmp).map_err(io_err("create", &tmp))?);
+        self.cookie_store
+            .lock()
+            .unwrap()
+            .save_incl_expired_and_nonpersistent_json(&mut writer)
+            .map_err(|e| SessionError::Cookie {
+                path: tmp.clone(),
+                source: e,
+            })?;
+        writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+            .and_then(|f| f.sync_all())
+            .map_err(io_err("write", &tmp))?;
+        fs::rename(&tmp, path).map_err(io_err("replace", path))
+    }
+    /// a restored session was refused by the server, [`Client::init`] starts a new one
+    pub fn is_session_rejected(&self) -> bool {
+        self.session_rejected.load(Ordering::Relaxed)
+    }
 }
 impl Default for Client {
     fn default() -> Self {
@@ -62,3 +398,23 @@ impl Default for Client {
 }
 
 pub(crate) mod paging;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeouts() {
+        let mut client = Client::new();
+        assert_eq!(client.timeouts(), Timeouts::default());
+        assert_eq!(client.image_fetch.timeout, Some(Timeouts::default().image));
+        let timeouts = Timeouts {
+            request: Duration::from_secs(5),
+            connect: Duration::from_secs(1),
+            image: Duration::from_secs(120),
+        };
+        client.set_timeouts(timeouts).unwrap();
+        assert_eq!(client.timeouts(), timeouts);
+        assert_eq!(client.image_fetch.timeout, Some(timeouts.image));
+    }
+}
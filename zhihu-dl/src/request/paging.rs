@@ -6,7 +6,7 @@ use crate::{
 use chrono::Utc;
 use reqwest::{IntoUrl, Method};
 use serde::{de, Deserialize};
-use std::collections::LinkedList;
+use std::collections::{HashSet, LinkedList};
 
 #[derive(Deserialize)]
 struct Paging {
@@ -42,6 +42,8 @@ fn deserialize_data<'de, const C: Container, D: de::Deserializer<'de>>(
     deserializer.deserialize_seq(DataVisitor(RawDataInfo {
         fetch_time: Utc::now(),
         container: C,
+        etag: None,
+        last_modified: None,
     }))
 }
 
@@ -53,7 +55,46 @@ struct PagedData<const C: Container> {
     paging: Option<Paging>,
 }
 
+/// stop fetching pages once a page lists a known item, for endpoints listing newest first
+pub(crate) struct Stop {
+    id_of: fn(&RawData) -> Option<String>,
+    known: HashSet<String>,
+    stopped: bool,
+}
+
 impl Client {
+    /// stop paging after a page containing an item whose id, as given by `id_of`, is known
+    pub(crate) fn set_paging_stop(
+        &self,
+        id_of: fn(&RawData) -> Option<String>,
+        known: HashSet<String>,
+    ) {
+        *self.paging_stop.lock().unwrap() = Some(Stop {
+            id_of,
+            known,
+            stopped: false,
+        });
+    }
+    /// remove the condition set by [`Client::set_paging_stop`], returns whether paging stopped
+    /// before the last page
+    pub(crate) fn take_paging_stop(&self) -> bool {
+        self.paging_stop
+            .lock()
+            .unwrap()
+            .take()
+            .map_or(false, |s| s.stopped)
+    }
+    fn stop_paging(&self, page: &LinkedList<RawData>) -> bool {
+        match self.paging_stop.lock().unwrap().as_mut() {
+            Some(s) => {
+                s.stopped = page
+                    .iter()
+                    .any(|d| (s.id_of)(d).map_or(false, |i| s.known.contains(&i)));
+                s.stopped
+            }
+            None => false,
+        }
+    }
     pub(crate) async fn get_paged_sign<const C: Container, S, P, U>(
         &self,
         mut prog: P,
@@ -65,13 +106,11 @@ impl Client {
         U: IntoUrl,
     {
         let (mut ret, mut paging) = {
-            let pd = self
-                .request_signed::<S, U>(Method::GET, url)
-                .send()
+            let resp = self
+                .send(&prog, self.request_signed::<S, U>(Method::GET, url))
                 .await?
-                .error_for_status()?
-                .json::<PagedData<C>>()
-                .await?;
+                .error_for_status()?;
+            let pd = self.json::<PagedData<C>>(resp).await?;
             (pd.data, pd.paging)
         };
         prog.set_count(match &paging {
@@ -80,23 +119,28 @@ impl Client {
         });
         prog.inc(ret.len() as u64);
         prog.sleep(self.request_interval).await;
+        if self.stop_paging(&ret) {
+            return Ok(ret);
+        }
         while let Some(Paging {
             is_end: false,
             next,
             ..
         }) = paging
         {
-            let mut pd = self
-                .request_signed::<S, String>(Method::GET, next)
-                .send()
+            let resp = self
+                .send(&prog, self.request_signed::<S, String>(Method::GET, next))
                 .await?
-                .error_for_status()?
-                .json::<PagedData<C>>()
-                .await?;
+                .error_for_status()?;
+            let mut pd = self.json::<PagedData<C>>(resp).await?;
             prog.inc(pd.data.len() as u64);
+            let stop = self.stop_paging(&pd.data);
             ret.append(&mut pd.data);
             paging = pd.paging;
             prog.sleep(self.request_interval).await;
+            if stop {
+                break;
+            }
         }
         Ok(ret)
     }
@@ -109,3 +153,36 @@ impl Client {
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(ids: &[u64]) -> LinkedList<RawData> {
+        ids.iter()
+            .map(|i| RawData {
+                info: RawDataInfo {
+                    fetch_time: Utc::now(),
+                    container: Container::None,
+                    etag: None,
+                    last_modified: None,
+                },
+                data: serde_json::json!({ "id": i }),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn paging_stop() {
+        let client = Client::new();
+        assert!(!client.stop_paging(&page(&[1])));
+        client.set_paging_stop(
+            |d| d.data["id"].as_u64().map(|i| i.to_string()),
+            HashSet::from(["2".to_owned()]),
+        );
+        assert!(!client.stop_paging(&page(&[4, 3])));
+        assert!(client.stop_paging(&page(&[3, 2, 1])));
+        assert!(client.take_paging_stop());
+        assert!(!client.take_paging_stop());
+    }
+}
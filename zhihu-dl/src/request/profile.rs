@@ -0,0 +1,102 @@
+use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileError {
+    #[error("user agent is empty")]
+    EmptyUserAgent,
+    #[error("header {0} is managed by the client and can't be set")]
+    Reserved(String),
+    #[error("invalid header name {0}")]
+    Name(String, #[source] header::InvalidHeaderName),
+    #[error("invalid value of header {0}")]
+    Value(String, #[source] header::InvalidHeaderValue),
+    #[error("failed to build http client")]
+    Build(#[source] reqwest::Error),
+}
+
+/// headers sent with every request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub user_agent: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accept_language: Option<String>,
+    /// other headers by name, headers added by signing are rejected
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            user_agent: super::user_agent::CHROME.to_owned(),
+            accept_language: None,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+impl Profile {
+    /// default headers of the http client, user agent is set separately
+    pub(super) fn header_map(&self) -> Result<HeaderMap, ProfileError> {
+        if self.user_agent.trim().is_empty() {
+            return Err(ProfileError::EmptyUserAgent);
+        }
+        let mut ret = HeaderMap::new();
+        if let Some(l) = &self.accept_language {
+            ret.insert(
+                header::ACCEPT_LANGUAGE,
+                HeaderValue::from_str(l)
+                    .map_err(|e| ProfileError::Value(header::ACCEPT_LANGUAGE.to_string(), e))?,
+            );
+        }
+        for (k, v) in self.headers.iter() {
+            let name = HeaderName::from_bytes(k.as_bytes())
+                .map_err(|e| ProfileError::Name(k.clone(), e))?;
+            if super::zse96_v3::SIGN_HEADERS.contains(&name.as_str())
+                || name == header::USER_AGENT
+                || name == header::COOKIE
+            {
+                return Err(ProfileError::Reserved(k.clone()));
+            }
+            ret.insert(
+                name,
+                HeaderValue::from_str(v).map_err(|e| ProfileError::Value(k.clone(), e))?,
+            );
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_map() {
+        let mut profile = Profile {
+            accept_language: Some("zh-CN".to_owned()),
+            ..Default::default()
+        };
+        profile
+            .headers
+            .insert("X-Requested-With".to_owned(), "fetch".to_owned());
+        let map = profile.header_map().unwrap();
+        assert_eq!(map[header::ACCEPT_LANGUAGE], "zh-CN");
+        assert_eq!(map["x-requested-with"], "fetch");
+
+        profile
+            .headers
+            .insert("X-Zse-96".to_owned(), "unsigned".to_owned());
+        assert!(matches!(
+            profile.header_map(),
+            Err(ProfileError::Reserved(_))
+        ));
+        profile.headers.clear();
+        profile.user_agent.clear();
+        assert!(matches!(
+            profile.header_map(),
+            Err(ProfileError::EmptyUserAgent)
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use reqwest::{NoProxy, Proxy};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProxyError {
+    #[error("invalid {scheme} proxy {url}")]
+    Url {
+        scheme: &'static str,
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("failed to build http client")]
+    Build(#[source] reqwest::Error),
+}
+
+/// proxies of outbound requests, `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY`
+/// are used when none is set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxyConfig {
+    /// proxy of every request, scheme specific ones take precedence
+    pub all: Option<String>,
+    pub http: Option<String>,
+    pub https: Option<String>,
+    /// comma separated hosts, domains and ip ranges connected directly, `NO_PROXY` by default
+    pub no_proxy: Option<String>,
+}
+impl ProxyConfig {
+    pub fn is_empty(&self) -> bool {
+        self.all.is_none() && self.http.is_none() && self.https.is_none()
+    }
+    /// proxies in the order they are tried
+    pub(super) fn proxies(&self) -> Result<Vec<Proxy>, ProxyError> {
+        let no_proxy = match &self.no_proxy {
+            Some(s) => NoProxy::from_string(s),
+            None => NoProxy::from_env(),
+        };
+        let schemes: [(_, _, fn(String) -> reqwest::Result<Proxy>); 3] = [
+            ("http", &self.http, Proxy::http),
+            ("https", &self.https, Proxy::https),
+            ("all", &self.all, Proxy::all),
+        ];
+        let mut ret = Vec::new();
+        for (scheme, url, new) in schemes {
+            if let Some(url) = url {
+                let proxy = new(url.clone()).map_err(|e| ProxyError::Url {
+                    scheme,
+                    url: url.clone(),
+                    source: e,
+                })?;
+                ret.push(proxy.no_proxy(no_proxy.clone()));
+            }
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxies() {
+        let config = ProxyConfig {
+            all: Some("socks5h://127.0.0.1:1080".to_owned()),
+            https: Some("http://127.0.0.1:8080".to_owned()),
+            no_proxy: Some("localhost,.example.com".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(config.proxies().unwrap().len(), 2);
+        assert!(ProxyConfig::default().proxies().unwrap().is_empty());
+        assert!(matches!(
+            ProxyConfig {
+                http: Some("ftp://127.0.0.1".to_owned()),
+                ..Default::default()
+            }
+            .proxies(),
+            Err(ProxyError::Url { scheme: "http", .. })
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+use reqwest::{Response, StatusCode};
+use std::time::{Duration, Instant};
+
+/// request rate limit, unlimited by default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimit {
+    /// sustained requests per second, unlimited when `None`
+    pub per_second: Option<f64>,
+    /// minimum delay between two requests
+    pub min_interval: Duration,
+}
+
+/// token bucket holding at most one second worth of requests
+#[derive(Debug)]
+pub(super) struct Limiter {
+    limit: RateLimit,
+    tokens: f64,
+    refilled: Option<Instant>,
+    /// earliest time the next request may be sent
+    next: Option<Instant>,
+}
+impl Limiter {
+    pub(super) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.per_second.map_or(0.0, |r| r.max(1.0)),
+            refilled: None,
+            next: None,
+        }
+    }
+    pub(super) fn limit(&self) -> RateLimit {
+        self.limit
+    }
+    /// take a slot for the next request, returns how long to wait before sending it
+    pub(super) fn reserve(&mut self, now: Instant) -> Duration {
+        let mut at = self.next.map_or(now, |n| n.max(now));
+        if let Some(rate) = self.limit.per_second.filter(|r| *r > 0.0) {
+            if let Some(last) = self.refilled {
+                self.tokens = (self.tokens
+                    + at.saturating_duration_since(last).as_secs_f64() * rate)
+                    .min(rate.max(1.0));
+            }
+            if self.tokens < 1.0 {
+                at += Duration::from_secs_f64((1.0 - self.tokens) / rate);
+                self.tokens = 1.0;
+            }
+            self.tokens -= 1.0;
+            self.refilled = Some(at);
+        }
+        self.next = Some(at + self.limit.min_interval);
+        at - now
+    }
+    /// hold back requests until `duration` after `now`
+    pub(super) fn back_off(&mut self, now: Instant, duration: Duration) {
+        let until = now + duration;
+        self.next = Some(self.next.map_or(until, |n| n.max(until)));
+    }
+}
+
+/// delay requested by a `429 Too Many Requests` response
+pub(super) fn retry_after(response: &Response) -> Option<Duration> {
+    if response.status() != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    web_dl_base::util::http::retry_after(response.headers())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket() {
+        let now = Instant::now();
+        let mut l = Limiter::new(RateLimit {
+            per_second: Some(2.0),
+            min_interval: Duration::ZERO,
+        });
+        assert_eq!(l.reserve(now), Duration::ZERO);
+        assert_eq!(l.reserve(now), Duration::ZERO);
+        assert_eq!(l.reserve(now), Duration::from_millis(500));
+        assert_eq!(l.reserve(now), Duration::from_millis(1000));
+    }
+    #[test]
+    fn min_interval_and_back_off() {
+        let now = Instant::now();
+        let mut l = Limiter::new(RateLimit {
+            per_second: None,
+            min_interval: Duration::from_secs(1),
+        });
+        assert_eq!(l.reserve(now), Duration::ZERO);
+        assert_eq!(l.reserve(now), Duration::from_secs(1));
+        l.back_off(now, Duration::from_secs(10));
+        assert_eq!(l.reserve(now), Duration::from_secs(10));
+    }
+}
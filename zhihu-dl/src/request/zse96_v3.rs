@@ -140,18 +140,34 @@ fn encode_zse96(d: &[u8; 16]) -> String {
 }
 
 pub struct Zse96V3;
+/// headers added when signing, lowercase
+pub(super) const SIGN_HEADERS: [&str; 2] = ["x-zse-93", "x-zse-96"];
+
+/// x-zse-96 signature of a request, `path` includes the query string if any
+///
+/// The signed string is `101_3_3.0+{path}+{d_c0}`, followed by `+{body}` for requests
+/// with a body.
+pub fn zse96_v3_sign(path: &str, cookie_d_c0: &str, body: Option<&[u8]>) -> String {
+    let mut dig = Md5::new_with_prefix("101_3_3.0+");
+    dig.update(path);
+    dig.update("+");
+    dig.update(cookie_d_c0);
+    if let Some(b) = body {
+        dig.update("+");
+        dig.update(b);
+    }
+    encode_zse96(&dig.finalize().into())
+}
 
 impl super::Signer for Zse96V3 {
     fn sign_request<U: IntoUrl>(client: &super::Client, method: Method, path: U) -> RequestBuilder {
-        let mut dig = Md5::new_with_prefix("101_3_3.0+");
         let url = path.into_url().unwrap();
-        dig.update(url.path());
-        if let Some(q) = url.query() {
-            dig.update("?");
-            dig.update(q);
-        }
-        dig.update("+");
-        dig.update(
+        let path = match url.query() {
+            Some(q) => format!("{}?{}", url.path(), q),
+            None => url.path().to_owned(),
+        };
+        let enc = zse96_v3_sign(
+            path.as_str(),
             client
                 .cookie_store
                 .lock()
@@ -159,8 +175,8 @@ impl super::Signer for Zse96V3 {
                 .get("zhihu.com", "/", "d_c0")
                 .unwrap()
                 .value(),
+            None,
         );
-        let enc = encode_zse96(&dig.finalize().into());
         log::debug!("request {} signature: {}", url, enc);
         client
             .http_client
@@ -172,7 +188,7 @@ impl super::Signer for Zse96V3 {
 
 #[cfg(test)]
 mod tests {
-    use super::encode_zse96;
+    use super::{encode_zse96, zse96_v3_sign};
     use hex_literal::hex;
 
     #[test]
@@ -182,4 +198,31 @@ mod tests {
             "2.0_Bj7+myTibavoPUJRzaV2S1uvi9kx+mqtQcQH9TxBRpiBkgNkW8z86R52P5jL3f=r"
         );
     }
+
+    /// regression snapshot of the current signing algorithm, computed offline rather than
+    /// captured from the site, so it detects unintended changes but not a wrong algorithm
+    #[test]
+    fn sign_snapshot() {
+        let d_c0 = "AEDQm1e1XxaPTuJwZ4EL3oUU6l5kk8dLm0w=|1678080000";
+        let vectors: [(&str, Option<&[u8]>, &str); 3] = [
+            (
+                "/api/v4/members/zhihu/answers?offset=0&limit=20",
+                None,
+                "2.0_3wafFsurczMi2KPvz4YFvYOZ9waf6vlzcRnkDjkVig1sgystXNSaihyJuYCcwRCG",
+            ),
+            (
+                "/api/v4/answers/2?include=content",
+                None,
+                "2.0_a8qqwKLarUjILUw32vGyGq9kxOJbcVayeDL+rjS=UxOIJcAHd3YMOt0whQfLs+PF",
+            ),
+            (
+                "/api/v4/members/zhihu/answers?offset=0&limit=20",
+                Some(b"{}"),
+                "2.0_UgebBg4Xp+Afe2ThI3HMF17KAIOU6wZntoN9lJ9ynY2CMji9MmM+ojVc7GJ0g=XJ",
+            ),
+        ];
+        for (path, body, expected) in vectors {
+            assert_eq!(zse96_v3_sign(path, d_c0, body), expected, "{}", path);
+        }
+    }
 }
@@ -1,9 +1,12 @@
 use crate::{
     item::{self},
     meta::Version,
+    progress::{ItemsProg, OtherJob},
+    raw_data::RAW_DATA,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::{BTreeMap, HashSet},
     fmt::Display,
     fs, io,
     marker::PhantomData,
@@ -17,11 +20,16 @@ pub enum FsErrorOp {
     CreateDir,
     CreateFile,
     OpenFile,
+    WriteFile,
     CanonicalizePath,
     SymLinkTo(PathBuf),
     RenameTo(PathBuf),
     OpenDir,
     GetDirEntry,
+    GetMetadata,
+    ReadLink,
+    RemoveFile,
+    RemoveDir,
 }
 impl Display for FsErrorOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -29,11 +37,16 @@ impl Display for FsErrorOp {
             FsErrorOp::CreateDir => f.write_str("create directory"),
             FsErrorOp::CreateFile => f.write_str("create file"),
             FsErrorOp::OpenFile => f.write_str("open file"),
+            FsErrorOp::WriteFile => f.write_str("write file"),
             FsErrorOp::CanonicalizePath => f.write_str("canonicalize path"),
             FsErrorOp::SymLinkTo(v) => write!(f, "create symbolic link to {} from", v.display()),
             FsErrorOp::RenameTo(t) => write!(f, "rename to {} from", t.display()),
             FsErrorOp::OpenDir => f.write_str("open directory"),
             FsErrorOp::GetDirEntry => f.write_str("get directory entry"),
+            FsErrorOp::GetMetadata => f.write_str("get metadata of"),
+            FsErrorOp::ReadLink => f.write_str("read link"),
+            FsErrorOp::RemoveFile => f.write_str("remove file"),
+            FsErrorOp::RemoveDir => f.write_str("remove directory"),
         }
     }
 }
@@ -55,12 +68,49 @@ pub enum StoreError {
         #[source]
         source: serde_yaml::Error,
     },
+    #[error("failed to load object {kind} {id}")]
+    LoadObject {
+        kind: &'static str,
+        id: String,
+        #[source]
+        source: Box<storable::Error>,
+    },
+    #[error("failed to store object")]
+    Object(#[source] Box<storable::Error>),
+    #[error("failed to store images")]
+    Media(#[source] Box<media::Error>),
+    #[error("store is opened read only")]
+    ReadOnly,
+    #[error(
+        "{kind} {id} is listed by {} container(s), delete them first: {}",
+        .links.len(),
+        .links[0].display()
+    )]
+    Referenced {
+        kind: &'static str,
+        id: String,
+        /// links to the object in container directories
+        links: Vec<PathBuf>,
+    },
+}
+impl From<storable::Error> for StoreError {
+    fn from(value: storable::Error) -> Self {
+        Self::Object(Box::new(value))
+    }
+}
+impl From<media::Error> for StoreError {
+    fn from(value: media::Error) -> Self {
+        Self::Media(Box::new(value))
+    }
 }
 
 pub(crate) mod info {
+    use crate::meta::Version;
     use crate::{
         element::author::UserId,
-        item::{AnswerId, ArticleId, CollectionId, ColumnId, CommentId, PinId, QuestionId},
+        item::{
+            AnswerId, ArticleId, CollectionId, ColumnId, CommentId, PinId, QuestionId, TopicId,
+        },
     };
     use serde::{Deserialize, Serialize};
     use std::collections::BTreeMap;
@@ -117,6 +167,15 @@ pub(crate) mod info {
         pub comment: bool,
     }
     #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+    pub struct Search {
+        pub item: bool,
+    }
+    #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+    pub struct Topic {
+        pub container: ItemInfo,
+        pub item: bool,
+    }
+    #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
     pub struct UserCollection {
         pub created: bool,
         pub liked: bool,
@@ -141,7 +200,42 @@ pub(crate) mod info {
         pub comment: BTreeMap<CommentId, Comment>,
         pub pin: BTreeMap<PinId, Pin>,
         pub question: BTreeMap<QuestionId, Question>,
+        /// ids of moved or merged objects to their canonical ids by type,
+        /// the alias directory links to the canonical one
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub redirect: BTreeMap<String, BTreeMap<String, String>>,
+        /// searches by query, they are containers only and not counted as objects
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub search: BTreeMap<String, Search>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub topic: BTreeMap<TopicId, Topic>,
         pub user: BTreeMap<UserId, User>,
+        /// oldest schema version among stored objects of each type
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub versions: BTreeMap<String, Version>,
+    }
+    impl Info {
+        /// number of objects of all kinds
+        pub fn count(&self) -> usize {
+            self.answer.len()
+                + self.article.len()
+                + self.collection.len()
+                + self.column.len()
+                + self.comment.len()
+                + self.pin.len()
+                + self.question.len()
+                + self.topic.len()
+                + self.user.len()
+        }
+        /// record that an object of `kind` was written with schema `version`
+        pub fn record_version(&mut self, kind: &str, version: Version) {
+            match self.versions.get_mut(kind) {
+                Some(v) => *v = (*v).min(version),
+                None => {
+                    self.versions.insert(kind.to_owned(), version);
+                }
+            }
+        }
     }
 }
 pub use info::Info as ObjectInfo;
@@ -170,17 +264,172 @@ fn store_yaml<V: Serialize, P: AsRef<Path>>(
     file: &'static str,
 ) -> Result<(), StoreError> {
     let path = path.as_ref().join(file);
-    serde_yaml::to_writer(
-        io::BufWriter::new(
-            fs::File::create(path.as_path()).map_err(|e| StoreError::Fs {
-                op: FsErrorOp::CreateFile,
-                path,
+    // write to a temporary file and rename it into place, so an interrupted write
+    // leaves the previous file intact
+    let tmp = path.with_file_name(format!(".{}.tmp", file));
+    let mut writer =
+        io::BufWriter::new(fs::File::create(tmp.as_path()).map_err(|e| StoreError::Fs {
+            op: FsErrorOp::CreateFile,
+            path: tmp.clone(),
+            source: e,
+        })?);
+    serde_yaml::to_writer(&mut writer, value).map_err(|e| StoreError::Yaml { file, source: e })?;
+    writer
+        .into_inner()
+        .map_err(io::IntoInnerError::into_error)
+        .and_then(|f| f.sync_all())
+        .map_err(|e| StoreError::Fs {
+            op: FsErrorOp::WriteFile,
+            path: tmp.clone(),
+            source: e,
+        })?;
+    fs::rename(&tmp, &path).map_err(|e| StoreError::Fs {
+        op: FsErrorOp::RenameTo(path),
+        path: tmp,
+        source: e,
+    })
+}
+
+fn create_image_root(root: &Path) -> Result<PathBuf, StoreError> {
+    let ret = root.with_file_name(IMAGES);
+    fs::create_dir_all(&ret).map_err(|e| StoreError::Fs {
+        op: FsErrorOp::CreateDir,
+        path: ret.clone(),
+        source: e,
+    })?;
+    Ok(ret)
+}
+
+/// placement of item directories in store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Layout {
+    /// length of the id prefix used to bucket item directories, two characters per level,
+    /// `0` puts items directly under the type directory
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub shard_prefix: usize,
+}
+fn is_zero(v: &usize) -> bool {
+    *v == 0
+}
+impl Layout {
+    fn item_path<I: HasId>(&self, id: I::Id<'_>, root: &Path) -> PathBuf {
+        self.object_path(I::TYPE, id.to_string(), root)
+    }
+    fn object_path(&self, kind: &str, id: String, root: &Path) -> PathBuf {
+        let mut ret = root.join(kind);
+        // pad short ids so that every item sits at the same depth
+        let prefix: Vec<char> = id
+            .chars()
+            .chain(std::iter::repeat('_'))
+            .take(self.shard_prefix)
+            .collect();
+        for c in prefix.chunks(2) {
+            ret.push(c.iter().collect::<String>());
+        }
+        ret.push(id);
+        ret
+    }
+}
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct VersionInfo {
+    #[serde(flatten)]
+    version: Version,
+    #[serde(flatten)]
+    layout: Layout,
+}
+
+/// relative symlink target from `link` to `target`, both inside `root`
+fn store_relative(root: &Path, link: &Path, target: &Path) -> PathBuf {
+    let mut ret = PathBuf::new();
+    for _ in link
+        .strip_prefix(root)
+        .unwrap()
+        .parent()
+        .unwrap()
+        .components()
+    {
+        ret.push("..");
+    }
+    ret.extend(target.strip_prefix(root).unwrap());
+    ret
+}
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut ret = PathBuf::new();
+    for c in path.components() {
+        match c {
+            std::path::Component::ParentDir => {
+                ret.pop();
+            }
+            std::path::Component::CurDir => (),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+/// collect symlinks under `path` pointing into `root`, as link and absolute target
+fn collect_links(
+    root: &Path,
+    path: &Path,
+    ret: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), StoreError> {
+    for ent in fs::read_dir(path).map_err(|e| StoreError::Fs {
+        op: FsErrorOp::OpenDir,
+        path: path.to_path_buf(),
+        source: e,
+    })? {
+        let ent = ent.map_err(|e| StoreError::Fs {
+            op: FsErrorOp::GetDirEntry,
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        let ty = ent.file_type().map_err(|e| StoreError::Fs {
+            op: FsErrorOp::GetMetadata,
+            path: ent.path(),
+            source: e,
+        })?;
+        let p = ent.path();
+        if ty.is_symlink() {
+            let target = fs::read_link(&p).map_err(|e| StoreError::Fs {
+                op: FsErrorOp::ReadLink,
+                path: p.clone(),
                 source: e,
-            })?,
-        ),
-        value,
-    )
-    .map_err(|e| StoreError::Yaml { file, source: e })
+            })?;
+            let target = normalize_path(&path.join(target));
+            if target.starts_with(root) {
+                ret.push((p, target));
+            }
+        } else if ty.is_dir() {
+            collect_links(root, &p, ret)?;
+        }
+    }
+    Ok(())
+}
+/// remove empty directories under and including `path`, returns whether `path` was removed
+fn remove_empty_dirs(path: &Path) -> Result<bool, StoreError> {
+    let mut empty = true;
+    for ent in fs::read_dir(path).map_err(|e| StoreError::Fs {
+        op: FsErrorOp::OpenDir,
+        path: path.to_path_buf(),
+        source: e,
+    })? {
+        let ent = ent.map_err(|e| StoreError::Fs {
+            op: FsErrorOp::GetDirEntry,
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        let is_dir = ent.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if !(is_dir && remove_empty_dirs(&ent.path())?) {
+            empty = false;
+        }
+    }
+    if empty {
+        fs::remove_dir(path).map_err(|e| StoreError::Fs {
+            op: FsErrorOp::RemoveDir,
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+    }
+    Ok(empty)
 }
 
 fn item_path<I: HasId, P: AsRef<Path>>(id: I::Id<'_>, path: P) -> PathBuf {
@@ -189,12 +438,24 @@ fn item_path<I: HasId, P: AsRef<Path>>(id: I::Id<'_>, path: P) -> PathBuf {
     path
 }
 pub trait BasicStoreItem: HasId + storable::Storable + media::StoreImage {
+    /// schema version objects of this type are written with
+    const VERSION: Version;
     fn in_store(id: Self::Id<'_>, store: &ObjectInfo) -> info::ItemInfo;
     fn add_info(id: Self::Id<'_>, info: info::ItemInfo, store: &mut ObjectInfo);
+    /// returns whether the object was recorded
+    fn remove_info(id: Self::Id<'_>, store: &mut ObjectInfo) -> bool;
+    /// repair an object written with schema `version` before [`Store::migrate`] rewrites it,
+    /// raw data is loaded when stored
+    #[allow(unused_variables)]
+    fn upgrade(&mut self, version: Version) {}
 }
 macro_rules! basic_store_item {
-    ($t:ty, $i:ident) => {
+    ($t:ty, $i:ident, $v:expr) => {
+        basic_store_item!($t, $i, $v;);
+    };
+    ($t:ty, $i:ident, $v:expr; $($upgrade:tt)*) => {
         impl BasicStoreItem for $t {
+            const VERSION: crate::meta::Version = $v;
             fn in_store(
                 id: Self::Id<'_>,
                 store: &crate::store::ObjectInfo,
@@ -208,18 +469,30 @@ macro_rules! basic_store_item {
             ) {
                 store.$i.entry(id).or_default().container = info;
             }
+            fn remove_info(id: Self::Id<'_>, store: &mut crate::store::ObjectInfo) -> bool {
+                store.$i.remove(&id).is_some()
+            }
+            $($upgrade)*
         }
     };
 }
 
-pub const VERSION: Version = Version { major: 1, minor: 1 };
+pub const VERSION: Version = Version { major: 1, minor: 2 };
 pub struct Store {
     version: Version,
+    layout: Layout,
     dirty: bool,
+    read_only: bool,
+    /// store raw api responses with items
+    keep_raw: bool,
     root: PathBuf,
+    image_root: PathBuf,
+    image_backend: Box<dyn media::ImageBackend>,
     pub(crate) objects: ObjectInfo,
 }
 const WEBSITE: &str = "zhihu.com";
+const IMAGES: &str = "images";
+const RESHARD_DIR: &str = ".reshard";
 const OBJECT_INFO: &str = "objects.yaml";
 const VERSION_FILE: &str = "version.yaml";
 
@@ -227,8 +500,6 @@ const VERSION_FILE: &str = "version.yaml";
 pub enum MigrateError {
     #[error("failed to open store")]
     OpenStore(#[source] StoreError),
-    #[error("unexpected store version {0}, expect {}", Version{major:1,minor:0})]
-    Version(Version),
     #[error("failed to load object {kind} {id}")]
     LoadObject {
         kind: &'static str,
@@ -243,11 +514,170 @@ pub enum MigrateError {
         #[source]
         source: media::Error,
     },
+    #[error("failed to upgrade object {kind} {id}")]
+    Upgrade {
+        kind: &'static str,
+        id: String,
+        #[source]
+        source: storable::Error,
+    },
+    #[error("failed to move item directories")]
+    Reshard(#[source] StoreError),
     #[error("failed to save store state")]
     SaveStore(#[source] StoreError),
 }
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StripReport {
+    /// items with raw data, removed unless in dry run
+    pub stripped: usize,
+    pub freed_bytes: u64,
+}
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GcReport {
+    /// images found in the image backend
+    pub scanned: usize,
+    /// unreferenced image files, removed unless in dry run
+    pub deleted: usize,
+    pub freed_bytes: u64,
+}
+
+#[derive(Debug, Error)]
+pub enum StoreInconsistency {
+    #[error("{kind} {id} is marked in store but failed to load")]
+    Object {
+        kind: &'static str,
+        id: String,
+        #[source]
+        source: Box<storable::Error>,
+    },
+    #[error("dangling link {} to {}", link.display(), target.display())]
+    DanglingLink { link: PathBuf, target: PathBuf },
+    #[error("image {image} of {kind} {id} is missing")]
+    Image {
+        kind: &'static str,
+        id: String,
+        image: String,
+        #[source]
+        source: Option<media::Error>,
+    },
+    #[error("failed to {op} {}", path.display())]
+    Fs {
+        op: FsErrorOp,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct StoreStats {
+    /// number of objects in store by kind
+    pub objects: Vec<(&'static str, usize)>,
+    pub images: usize,
+    pub image_bytes: u64,
+    /// size of store root, excluding images hard linked into items
+    pub store_bytes: u64,
+}
+
+/// sums file sizes of a directory tree, skipping symlinks and counting hard links once
+#[derive(Default)]
+struct DiskUsage {
+    files: usize,
+    bytes: u64,
+    #[cfg(unix)]
+    seen: HashSet<(u64, u64)>,
+}
+impl DiskUsage {
+    fn walk(&mut self, path: &Path) -> Result<(), StoreError> {
+        for ent in fs::read_dir(path).map_err(|e| StoreError::Fs {
+            op: FsErrorOp::OpenDir,
+            path: path.to_path_buf(),
+            source: e,
+        })? {
+            let ent = ent.map_err(|e| StoreError::Fs {
+                op: FsErrorOp::GetDirEntry,
+                path: path.to_path_buf(),
+                source: e,
+            })?;
+            let meta = ent.metadata().map_err(|e| StoreError::Fs {
+                op: FsErrorOp::GetMetadata,
+                path: ent.path(),
+                source: e,
+            })?;
+            if meta.is_dir() {
+                self.walk(&ent.path())?;
+            } else if meta.is_file() {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::MetadataExt;
+                    if !self.seen.insert((meta.dev(), meta.ino())) {
+                        continue;
+                    }
+                }
+                self.files += 1;
+                self.bytes += meta.len();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// evaluate `$body` for every object recorded in store, with `$t` bound to the item type
+macro_rules! for_each_object {
+    ($objects:expr, <$t:ident>($id:ident, $info:ident) => $body:block) => {
+        for (id, $info) in &$objects.answer {
+            type $t = item::Answer;
+            let $id = *id;
+            $body
+        }
+        for (id, $info) in &$objects.article {
+            type $t = item::Article;
+            let $id = *id;
+            $body
+        }
+        for (id, $info) in &$objects.collection {
+            type $t = item::Collection;
+            let $id = *id;
+            $body
+        }
+        for (id, $info) in &$objects.column {
+            type $t = item::Column;
+            let $id = item::column::ColumnRef(id.0.as_str());
+            $body
+        }
+        for (id, $info) in &$objects.comment {
+            type $t = item::Comment;
+            let $id = *id;
+            $body
+        }
+        for (id, $info) in &$objects.pin {
+            type $t = item::Pin;
+            let $id = *id;
+            $body
+        }
+        for (id, $info) in &$objects.question {
+            type $t = item::Question;
+            let $id = *id;
+            $body
+        }
+        for (id, $info) in &$objects.topic {
+            type $t = item::Topic;
+            let $id = *id;
+            $body
+        }
+        for (id, $info) in &$objects.user {
+            type $t = item::User;
+            let $id = item::user::StoreId(*id, "");
+            $body
+        }
+    };
+}
+
 impl Store {
     pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, StoreError> {
+        Self::create_with_layout(path, Layout::default())
+    }
+    pub fn create_with_layout<P: AsRef<Path>>(path: P, layout: Layout) -> Result<Self, StoreError> {
         let root = {
             fs::create_dir_all(path.as_ref()).map_err(|e| StoreError::Fs {
                 op: FsErrorOp::CreateDir,
@@ -266,107 +696,327 @@ impl Store {
             path: root.clone(),
             source: e,
         })?;
+        let image_root = create_image_root(&root)?;
         Ok(Self {
             version: {
-                store_yaml(&VERSION, &root, VERSION_FILE)?;
+                store_yaml(
+                    &VersionInfo {
+                        version: VERSION,
+                        layout,
+                    },
+                    &root,
+                    VERSION_FILE,
+                )?;
                 VERSION
             },
+            layout,
             dirty: false,
+            read_only: false,
+            keep_raw: true,
             objects: {
                 let ret = ObjectInfo::default();
                 store_yaml(&ret, root.as_path(), OBJECT_INFO)?;
                 ret
             },
             root,
+            image_backend: Box::new(media::FsBackend::new(image_root.as_path())),
+            image_root,
         })
     }
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StoreError> {
+        Self::open_impl(path.as_ref(), false)
+    }
+    /// open without writing anything, mutating operations fail with [`StoreError::ReadOnly`]
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self, StoreError> {
+        Self::open_impl(path.as_ref(), true)
+    }
+    fn open_impl(path: &Path, read_only: bool) -> Result<Self, StoreError> {
         let root = {
-            let path = path.as_ref().canonicalize().map_err(|e| StoreError::Fs {
+            let path = path.canonicalize().map_err(|e| StoreError::Fs {
                 op: FsErrorOp::CanonicalizePath,
-                path: path.as_ref().to_path_buf(),
+                path: path.to_path_buf(),
                 source: e,
             })?;
             path.join(WEBSITE)
         };
-        let version = load_yaml(&root, || Version { major: 0, minor: 0 }, VERSION_FILE)?;
+        let VersionInfo { version, layout } = load_yaml(
+            &root,
+            || VersionInfo {
+                version: Version { major: 0, minor: 0 },
+                layout: Layout::default(),
+            },
+            VERSION_FILE,
+        )?;
+        let image_root = if read_only {
+            root.with_file_name(IMAGES)
+        } else {
+            create_image_root(&root)?
+        };
         if !VERSION.is_compatible(version) {
             return Err(StoreError::Version(version));
         }
+        let mut objects: ObjectInfo = load_yaml(&root, ObjectInfo::default, OBJECT_INFO)?;
+        // objects stored before schema versions were tracked
+        let mut unknown = HashSet::new();
+        for_each_object!(objects, <I>(_id, info) => {
+            if info.container.in_store && !objects.versions.contains_key(I::TYPE) {
+                unknown.insert(I::TYPE);
+            }
+        });
+        for kind in unknown {
+            objects.record_version(kind, Version { major: 0, minor: 0 });
+        }
         Ok(Self {
             version,
-            objects: load_yaml(&root, ObjectInfo::default, OBJECT_INFO)?,
+            layout,
+            objects,
             dirty: false,
+            read_only,
+            keep_raw: true,
+            image_root: image_root.clone(),
+            image_backend: Box::new(media::FsBackend::new(image_root)),
             root,
         })
     }
 
     fn migrate_item<I: HasId + BasicStoreItem + media::StoreImage>(
         &self,
-        image_store: &PathBuf,
+        prog: &mut impl ItemsProg,
         id: I::Id<'_>,
     ) -> Result<(), MigrateError> {
+        prog.start_item(I::TYPE, id);
         let sp = self.store_path::<I>(id);
         let item = I::load(&sp, Default::default()).map_err(|e| MigrateError::LoadObject {
             kind: I::TYPE,
             id: id.to_string(),
             source: e,
         })?;
-        item.migrate(image_store, sp)
+        item.migrate(self.image_backend.as_ref(), sp)
             .map_err(|e| MigrateError::Image {
                 kind: I::TYPE,
                 id: id.to_string(),
                 source: e,
             })
     }
-    pub fn migrate<P: AsRef<Path>>(path: P) -> Result<(), MigrateError> {
-        let mut store = Self::open(path).map_err(MigrateError::OpenStore)?;
-        if store.version != (Version { major: 1, minor: 0 }) {
-            return Err(MigrateError::Version(store.version));
+    /// copy images kept only in the item directory into the image backend
+    fn import_images<I: BasicStoreItem>(
+        &self,
+        prog: &mut impl ItemsProg,
+        id: I::Id<'_>,
+    ) -> Result<(), MigrateError> {
+        prog.start_item(I::TYPE, id);
+        let sp = self.store_path::<I>(id);
+        let mut item = I::load(&sp, Default::default()).map_err(|e| MigrateError::LoadObject {
+            kind: I::TYPE,
+            id: id.to_string(),
+            source: e,
+        })?;
+        item.load_images(self.image_backend.as_ref(), &sp)
+            .and_then(|_| item.store_images(self.image_backend.as_ref(), &sp))
+            .map_err(|e| MigrateError::Image {
+                kind: I::TYPE,
+                id: id.to_string(),
+                source: e,
+            })
+    }
+    /// move the item directory from `staging` to its place in `layout`,
+    /// recording the path it had under store root in `moved`
+    fn move_item<I: HasId>(
+        &self,
+        layout: Layout,
+        staging: &Path,
+        id: I::Id<'_>,
+        prog: &mut impl ItemsProg,
+        moved: &mut std::collections::HashMap<PathBuf, PathBuf>,
+    ) -> Result<(), StoreError> {
+        self.move_object(layout, staging, I::TYPE, id.to_string(), prog, moved)
+    }
+    fn move_object(
+        &self,
+        layout: Layout,
+        staging: &Path,
+        kind: &str,
+        id: String,
+        prog: &mut impl ItemsProg,
+        moved: &mut std::collections::HashMap<PathBuf, PathBuf>,
+    ) -> Result<(), StoreError> {
+        let src = self.layout.object_path(kind, id.clone(), staging);
+        // alias links are moved as is and relinked with other links
+        if src.symlink_metadata().is_err() {
+            prog.skip_item();
+            return Ok(());
         }
-        let image_store = store.root.with_file_name("images");
-        for (id, info) in &store.objects.answer {
-            if info.container.in_store {
-                store.migrate_item::<item::Answer>(&image_store, *id)?;
+        prog.start_item(kind, id.as_str());
+        let dest = layout.object_path(kind, id.clone(), &self.root);
+        let parent = dest.parent().unwrap();
+        fs::create_dir_all(parent).map_err(|e| StoreError::Fs {
+            op: FsErrorOp::CreateDir,
+            path: parent.to_path_buf(),
+            source: e,
+        })?;
+        fs::rename(&src, &dest).map_err(|e| StoreError::Fs {
+            op: FsErrorOp::RenameTo(dest.clone()),
+            path: src,
+            source: e,
+        })?;
+        moved.insert(self.layout.object_path(kind, id, &self.root), dest);
+        Ok(())
+    }
+    /// move item directories into `layout`. links between items in store are recreated,
+    /// links from outside the store to item directories are left dangling
+    fn reshard(&mut self, layout: Layout, prog: &mut impl ItemsProg) -> Result<(), StoreError> {
+        let mut links = Vec::new();
+        collect_links(&self.root, &self.root, &mut links)?;
+        // move everything aside first, so old and new item directories never overlap
+        let staging = self.root.join(RESHARD_DIR);
+        fs::create_dir(&staging).map_err(|e| StoreError::Fs {
+            op: FsErrorOp::CreateDir,
+            path: staging.clone(),
+            source: e,
+        })?;
+        for ent in fs::read_dir(&self.root).map_err(|e| StoreError::Fs {
+            op: FsErrorOp::OpenDir,
+            path: self.root.clone(),
+            source: e,
+        })? {
+            let ent = ent.map_err(|e| StoreError::Fs {
+                op: FsErrorOp::GetDirEntry,
+                path: self.root.clone(),
+                source: e,
+            })?;
+            if ent.file_name() == RESHARD_DIR || !ent.path().is_dir() {
+                continue;
             }
+            let dest = staging.join(ent.file_name());
+            fs::rename(ent.path(), &dest).map_err(|e| StoreError::Fs {
+                op: FsErrorOp::RenameTo(dest),
+                path: ent.path(),
+                source: e,
+            })?;
         }
-        for (id, info) in &store.objects.article {
-            if info.container.in_store {
-                store.migrate_item::<item::Article>(&image_store, *id)?;
-            }
+        let mut moved = std::collections::HashMap::new();
+        for_each_object!(self.objects, <I>(id, _info) => {
+            self.move_item::<I>(layout, &staging, id, prog, &mut moved)?;
+        });
+        // searches are containers only, their directories are moved like items
+        for q in self.objects.search.keys() {
+            self.move_item::<item::Search>(
+                layout,
+                &staging,
+                item::search::Query(q.as_str()),
+                prog,
+                &mut moved,
+            )?;
         }
-        for (id, info) in &store.objects.collection {
-            if info.container.in_store {
-                store.migrate_item::<item::Collection>(&image_store, *id)?;
+        for (kind, aliases) in &self.objects.redirect {
+            for alias in aliases.keys() {
+                self.move_object(layout, &staging, kind, alias.clone(), prog, &mut moved)?;
             }
         }
-        for (id, info) in &store.objects.column {
-            if info.container.in_store {
-                store.migrate_item::<item::Column>(
-                    &image_store,
-                    item::column::ColumnRef(id.0.as_str()),
-                )?;
-            }
+        self.layout = layout;
+        self.dirty = true;
+
+        let relocate = |p: &Path| {
+            p.ancestors().find_map(|a| {
+                moved.get(a).map(|d| match p.strip_prefix(a).unwrap() {
+                    r if r.as_os_str().is_empty() => d.clone(),
+                    r => d.join(r),
+                })
+            })
+        };
+        for (link, target) in links {
+            let (Some(new_link), Some(new_target)) = (relocate(&link), relocate(&target)) else {
+                log::warn!("link {} is not relocated", link.display());
+                continue;
+            };
+            fs::remove_file(&new_link).map_err(|e| StoreError::Fs {
+                op: FsErrorOp::RemoveFile,
+                path: new_link.clone(),
+                source: e,
+            })?;
+            crate::util::relative_path::symlink(
+                store_relative(&self.root, &new_link, &new_target),
+                &new_link,
+            )
+            .map_err(|e| StoreError::Fs {
+                op: FsErrorOp::SymLinkTo(new_target),
+                path: new_link,
+                source: e,
+            })?;
         }
-        for (id, info) in &store.objects.comment {
-            if info.container.in_store {
-                store.migrate_item::<item::Comment>(&image_store, *id)?;
-            }
+        if !remove_empty_dirs(&staging)? {
+            log::warn!("unknown entries are left in {}", staging.display());
         }
-        for (id, info) in &store.objects.pin {
-            if info.container.in_store {
-                store.migrate_item::<item::Pin>(&image_store, *id)?;
-            }
+        Ok(())
+    }
+    /// upgrade store of version 1.0 or 1.1, and move item directories if `layout` differs
+    pub fn migrate<P: AsRef<Path>, J: OtherJob>(
+        path: P,
+        layout: Option<Layout>,
+        job: &J,
+    ) -> Result<(), MigrateError> {
+        let mut store = Self::open(path).map_err(MigrateError::OpenStore)?;
+        let layout = layout.filter(|l| *l != store.layout);
+        if store.version == (Version { major: 1, minor: 0 }) {
+            let mut prog = job.start_items(store.objects.count() as u64);
+            for_each_object!(store.objects, <I>(id, info) => {
+                if info.container.in_store {
+                    store.migrate_item::<I>(&mut prog, id)?;
+                } else {
+                    prog.skip_item();
+                }
+            });
+        } else if store.version == (Version { major: 1, minor: 1 }) {
+            let mut prog = job.start_items(store.objects.count() as u64);
+            for_each_object!(store.objects, <I>(id, info) => {
+                if info.container.in_store {
+                    store.import_images::<I>(&mut prog, id)?;
+                } else {
+                    prog.skip_item();
+                }
+            });
         }
-        for (id, info) in &store.objects.question {
-            if info.container.in_store {
-                store.migrate_item::<item::Question>(&image_store, *id)?;
+        let prog = job.start_items(store.objects.count() as u64);
+        let mut upgraded = Vec::new();
+        for_each_object!(store.objects, <I>(id, info) => {
+            if info.container.in_store
+                && store
+                    .objects
+                    .versions
+                    .get(I::TYPE)
+                    .is_some_and(|v| *v < I::VERSION)
+            {
+                // rewrite with the current schema version
+                prog.start_item(I::TYPE, id);
+                let sp = store.store_path::<I>(id);
+                let mut item =
+                    <I as storable::Storable>::load(&sp, storable::LoadOpt { load_raw: true })
+                        .map_err(|e| MigrateError::LoadObject {
+                            kind: I::TYPE,
+                            id: id.to_string(),
+                            source: e,
+                        })?;
+                item.upgrade(store.objects.versions[I::TYPE]);
+                storable::Storable::store(&item, &sp).map_err(|e| MigrateError::Upgrade {
+                    kind: I::TYPE,
+                    id: id.to_string(),
+                    source: e,
+                })?;
+                upgraded.push((I::TYPE, I::VERSION));
+            } else {
+                prog.skip_item();
             }
+        });
+        for (kind, version) in upgraded {
+            store.objects.versions.insert(kind.to_owned(), version);
+            store.dirty = true;
         }
-        for (id, info) in &store.objects.user {
-            if info.container.in_store {
-                store.migrate_item::<item::User>(&image_store, item::user::StoreId(*id, ""))?;
-            }
+        if let Some(l) = layout {
+            let mut prog = job.start_items(
+                (store.objects.count() + store.objects.search.len() + store.redirect_count())
+                    as u64,
+            );
+            store.reshard(l, &mut prog).map_err(MigrateError::Reshard)?;
         }
         store.version = VERSION;
         store.save().map_err(MigrateError::SaveStore)
@@ -375,11 +1025,315 @@ impl Store {
     pub fn is_dirty(&self) -> bool {
         self.dirty
     }
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+    /// without raw api responses items take less space, but they can't be updated
+    /// with conditional requests
+    pub fn set_keep_raw(&mut self, keep_raw: bool) {
+        self.keep_raw = keep_raw;
+    }
+    fn check_writable(&self) -> Result<(), StoreError> {
+        if self.read_only {
+            Err(StoreError::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
     pub fn root(&self) -> &PathBuf {
         &self.root
     }
+    /// info of stored objects
+    pub fn objects(&self) -> &ObjectInfo {
+        &self.objects
+    }
+    pub fn image_root(&self) -> &PathBuf {
+        &self.image_root
+    }
+    pub fn image_backend(&self) -> &dyn media::ImageBackend {
+        self.image_backend.as_ref()
+    }
+    /// replace the default filesystem image backend, use [`crate::Driver::set_image_backend`]
+    /// for the store of a driver so that images are no longer streamed into the old one
+    pub fn set_image_backend<B: media::ImageBackend + 'static>(&mut self, backend: B) {
+        self.image_backend = Box::new(backend);
+    }
+
+    fn collect_images<I: BasicStoreItem>(
+        &self,
+        id: I::Id<'_>,
+        refs: &mut HashSet<String>,
+    ) -> Result<(), StoreError> {
+        let item = I::load(self.store_path::<I>(id), Default::default()).map_err(|e| {
+            StoreError::LoadObject {
+                kind: I::TYPE,
+                id: id.to_string(),
+                source: Box::new(e),
+            }
+        })?;
+        item.visit_images(&mut |r| {
+            refs.insert(r.to_string());
+        });
+        Ok(())
+    }
+    /// names of image files referenced by objects in store
+    fn referenced_images(&self) -> Result<HashSet<String>, StoreError> {
+        let mut ret = HashSet::new();
+        for_each_object!(self.objects, <I>(id, info) => {
+            if info.container.in_store {
+                self.collect_images::<I>(id, &mut ret)?;
+            }
+        });
+        Ok(ret)
+    }
+    pub fn stats(&self) -> Result<StoreStats, StoreError> {
+        fn count<V>(objects: &std::collections::BTreeMap<impl Ord, V>, f: fn(&V) -> bool) -> usize {
+            objects.values().filter(|v| f(v)).count()
+        }
+        let mut usage = DiskUsage::default();
+        // read only stores are not given an image directory when opened
+        if self.image_root.exists() {
+            usage.walk(&self.image_root)?;
+        }
+        let (images, image_bytes) = (usage.files, usage.bytes);
+        usage.walk(&self.root)?;
+        Ok(StoreStats {
+            objects: vec![
+                (
+                    item::Answer::TYPE,
+                    count(&self.objects.answer, |v| v.container.in_store),
+                ),
+                (
+                    item::Article::TYPE,
+                    count(&self.objects.article, |v| v.container.in_store),
+                ),
+                (
+                    item::Collection::TYPE,
+                    count(&self.objects.collection, |v| v.container.in_store),
+                ),
+                (
+                    item::Column::TYPE,
+                    count(&self.objects.column, |v| v.container.in_store),
+                ),
+                (
+                    item::Comment::TYPE,
+                    count(&self.objects.comment, |v| v.container.in_store),
+                ),
+                (
+                    item::Pin::TYPE,
+                    count(&self.objects.pin, |v| v.container.in_store),
+                ),
+                (
+                    item::Question::TYPE,
+                    count(&self.objects.question, |v| v.container.in_store),
+                ),
+                (
+                    item::Topic::TYPE,
+                    count(&self.objects.topic, |v| v.container.in_store),
+                ),
+                (
+                    item::User::TYPE,
+                    count(&self.objects.user, |v| v.container.in_store),
+                ),
+            ],
+            images,
+            image_bytes,
+            store_bytes: usage.bytes - image_bytes,
+        })
+    }
+    /// remove object and its info directory, deleting an absent object is a no-op.
+    /// with `remove_images`, images only referenced by this object are removed as well.
+    /// objects listed by containers are kept and [`StoreError::Referenced`] is returned
+    pub fn delete_object<I: BasicStoreItem>(
+        &mut self,
+        id: I::Id<'_>,
+        remove_images: bool,
+    ) -> Result<bool, StoreError> {
+        self.check_writable()?;
+        let item = self.item_path::<I>(id);
+        let mut links = Vec::new();
+        collect_links(&self.root, &self.root, &mut links)?;
+        // redirect aliases link to objects as well, container links are under an item list
+        let in_container = |link: &Path| {
+            link.ancestors()
+                .skip(1)
+                .take_while(|a| a.starts_with(&self.root))
+                .any(|a| a.join(ITEM_LIST).exists())
+        };
+        let links: Vec<_> = links
+            .into_iter()
+            .filter_map(|(link, target)| (target == item && in_container(&link)).then_some(link))
+            .collect();
+        if !links.is_empty() {
+            return Err(StoreError::Referenced {
+                kind: I::TYPE,
+                id: id.to_string(),
+                links,
+            });
+        }
+        let path = self.store_path::<I>(id);
+        let mut images = HashSet::new();
+        if remove_images && path.exists() {
+            self.collect_images::<I>(id, &mut images)?;
+        }
+        if I::remove_info(id, &mut self.objects) {
+            self.dirty = true;
+        } else if !path.exists() {
+            return Ok(false);
+        }
+        if path.exists() {
+            fs::remove_dir_all(&path).map_err(|e| StoreError::Fs {
+                op: FsErrorOp::RemoveDir,
+                path: path.clone(),
+                source: e,
+            })?;
+        }
+        if !images.is_empty() {
+            let refs = self.referenced_images()?;
+            for i in images.difference(&refs) {
+                self.image_backend.remove(i)?;
+                log::debug!("removed image {} of {} {}", i, I::TYPE, id);
+            }
+        }
+        Ok(true)
+    }
+    /// remove images in the image backend not referenced by any object,
+    /// with `dry_run` nothing is removed and the report lists what would be
+    pub fn gc_images(&self, dry_run: bool) -> Result<GcReport, StoreError> {
+        if !dry_run {
+            self.check_writable()?;
+        }
+        let refs = self.referenced_images()?;
+        let mut ret = GcReport::default();
+        for (name, size) in self.image_backend.list()? {
+            ret.scanned += 1;
+            if refs.contains(&name) {
+                continue;
+            }
+            if !dry_run {
+                self.image_backend.remove(&name)?;
+            }
+            log::debug!("collected unreferenced image {}", name);
+            ret.deleted += 1;
+            ret.freed_bytes += size;
+        }
+        Ok(ret)
+    }
+    /// remove raw api responses stored with items, with `dry_run` nothing is removed
+    /// and the report lists what would be
+    pub fn strip_raw(&self, dry_run: bool) -> Result<StripReport, StoreError> {
+        if !dry_run {
+            self.check_writable()?;
+        }
+        let mut ret = StripReport::default();
+        for_each_object!(self.objects, <I>(id, info) => {
+            if info.container.in_store {
+                let path = self.store_path::<I>(id).join(RAW_DATA);
+                if path.is_dir() {
+                    let mut usage = DiskUsage::default();
+                    usage.walk(&path)?;
+                    if !dry_run {
+                        fs::remove_dir_all(&path).map_err(|e| StoreError::Fs {
+                            op: FsErrorOp::RemoveDir,
+                            path: path.clone(),
+                            source: e,
+                        })?;
+                    }
+                    log::debug!("stripped raw data of {} {}", I::TYPE, id);
+                    ret.stripped += 1;
+                    ret.freed_bytes += usage.bytes;
+                }
+            }
+        });
+        Ok(ret)
+    }
+
+    fn check_object<I: BasicStoreItem>(&self, id: I::Id<'_>, ret: &mut Vec<StoreInconsistency>) {
+        let item = match I::load(self.store_path::<I>(id), Default::default()) {
+            Ok(i) => i,
+            Err(e) => {
+                ret.push(StoreInconsistency::Object {
+                    kind: I::TYPE,
+                    id: id.to_string(),
+                    source: Box::new(e),
+                });
+                return;
+            }
+        };
+        item.visit_images(&mut |r| {
+            let source = match self.image_backend.exists(&r.hash, &r.extension) {
+                Ok(true) => return,
+                Ok(false) => None,
+                Err(e) => Some(e),
+            };
+            ret.push(StoreInconsistency::Image {
+                kind: I::TYPE,
+                id: id.to_string(),
+                image: r.to_string(),
+                source,
+            });
+        });
+    }
+    fn check_links(path: &Path, ret: &mut Vec<StoreInconsistency>) {
+        let dir = match fs::read_dir(path) {
+            Ok(d) => d,
+            Err(e) => {
+                ret.push(StoreInconsistency::Fs {
+                    op: FsErrorOp::OpenDir,
+                    path: path.to_path_buf(),
+                    source: e,
+                });
+                return;
+            }
+        };
+        for ent in dir {
+            let (ent, ty) = match ent.and_then(|e| e.file_type().map(|t| (e, t))) {
+                Ok(v) => v,
+                Err(e) => {
+                    ret.push(StoreInconsistency::Fs {
+                        op: FsErrorOp::GetDirEntry,
+                        path: path.to_path_buf(),
+                        source: e,
+                    });
+                    continue;
+                }
+            };
+            let p = ent.path();
+            if ty.is_symlink() {
+                if !p.exists() {
+                    ret.push(StoreInconsistency::DanglingLink {
+                        target: fs::read_link(&p).unwrap_or_default(),
+                        link: p,
+                    });
+                }
+            } else if ty.is_dir() {
+                Self::check_links(&p, ret);
+            }
+        }
+    }
+    /// verify that objects marked in store load, links in containers resolve
+    /// and referenced images exist
+    pub fn check(&self) -> Vec<StoreInconsistency> {
+        let mut ret = Vec::new();
+        for_each_object!(self.objects, <I>(id, info) => {
+            if info.container.in_store {
+                self.check_object::<I>(id, &mut ret);
+            }
+        });
+        Self::check_links(&self.root, &mut ret);
+        ret
+    }
+
     pub fn save(&mut self) -> Result<(), StoreError> {
-        store_yaml(&self.version, &self.root, VERSION_FILE)?;
+        self.check_writable()?;
+        store_yaml(
+            &VersionInfo {
+                version: self.version,
+                layout: self.layout,
+            },
+            &self.root,
+            VERSION_FILE,
+        )?;
         store_yaml(&self.objects, &self.root, OBJECT_INFO)?;
         self.dirty = false;
         Ok(())
@@ -391,7 +1345,10 @@ impl Store {
         ret
     }
     pub fn item_path<I: HasId>(&self, id: I::Id<'_>) -> PathBuf {
-        item_path::<I, _>(id, &self.root)
+        self.layout.item_path::<I>(id, &self.root)
+    }
+    pub fn layout(&self) -> Layout {
+        self.layout
     }
     pub fn container_store_path<IC: BasicStoreContainer<O, I>, O, I: HasId + 'static>(
         &self,
@@ -413,7 +1370,10 @@ impl Store {
         &mut self,
         object: &mut I,
     ) -> Result<(), media::Error> {
-        object.load_images(self.store_path::<I>(object.id()))
+        object.load_images(
+            self.image_backend.as_ref(),
+            self.store_path::<I>(object.id()),
+        )
     }
     pub fn get_container<O, I: HasId, IC: BasicStoreContainer<O, I>>(
         &self,
@@ -429,16 +1389,25 @@ impl Store {
     pub fn add_media<I: BasicStoreItem + media::StoreImage>(
         &mut self,
         data: &I,
-    ) -> Result<(), media::Error> {
-        data.store_images(self.store_path::<I>(data.id()))
+    ) -> Result<(), StoreError> {
+        self.check_writable()?;
+        data.store_images(self.image_backend.as_ref(), self.store_path::<I>(data.id()))
+            .map_err(StoreError::from)
     }
     pub fn add_object<I: BasicStoreItem>(
         &mut self,
         on_server: bool,
         object: &I,
-    ) -> Result<PathBuf, storable::Error> {
+    ) -> Result<PathBuf, StoreError> {
+        self.check_writable()?;
         let path = self.store_path::<I>(object.id());
-        object.store(&path)?;
+        object.store_with(
+            &path,
+            storable::StoreOpt {
+                store_raw: self.keep_raw,
+            },
+        )?;
+        self.objects.record_version(I::TYPE, I::VERSION);
         <I as StoreItem>::add_info(
             object.id(),
             info::ItemInfo {
@@ -449,10 +1418,70 @@ impl Store {
         );
         Ok(path)
     }
+    /// canonical id recorded for `id` moved or merged into another object
+    pub fn redirect<I: HasId>(&self, id: I::Id<'_>) -> Option<&str> {
+        self.objects
+            .redirect
+            .get(I::TYPE)
+            .and_then(|m| m.get(id.to_string().as_str()))
+            .map(String::as_str)
+    }
+    fn redirect_count(&self) -> usize {
+        self.objects.redirect.values().map(BTreeMap::len).sum()
+    }
+    /// record `from` as an alias of `to` and link its directory to the one of `to`,
+    /// a directory stored before `from` was redirected is kept
+    pub fn add_redirect<I: HasId>(
+        &mut self,
+        from: I::Id<'_>,
+        to: I::Id<'_>,
+    ) -> Result<(), StoreError> {
+        self.check_writable()?;
+        let link = self.item_path::<I>(from);
+        let target = self.item_path::<I>(to);
+        match link.symlink_metadata() {
+            Ok(m) if !m.is_symlink() => {
+                log::warn!("keep stored {} {} redirected to {}", I::TYPE, from, to)
+            }
+            m => {
+                if m.is_ok() {
+                    fs::remove_file(&link).map_err(|e| StoreError::Fs {
+                        op: FsErrorOp::RemoveFile,
+                        path: link.clone(),
+                        source: e,
+                    })?;
+                } else {
+                    let parent = link.parent().unwrap();
+                    fs::create_dir_all(parent).map_err(|e| StoreError::Fs {
+                        op: FsErrorOp::CreateDir,
+                        path: parent.to_path_buf(),
+                        source: e,
+                    })?;
+                }
+                crate::util::relative_path::symlink(
+                    store_relative(&self.root, &link, &target),
+                    &link,
+                )
+                .map_err(|e| StoreError::Fs {
+                    op: FsErrorOp::SymLinkTo(target),
+                    path: link,
+                    source: e,
+                })?;
+            }
+        }
+        self.objects
+            .redirect
+            .entry(I::TYPE.to_owned())
+            .or_default()
+            .insert(from.to_string(), to.to_string());
+        self.dirty = true;
+        Ok(())
+    }
     pub fn add_container<'a, 'b, IC: BasicStoreContainer<O, I>, O, I: HasId>(
         &'a mut self,
         id: IC::Id<'b>,
     ) -> Result<Container<'b, 'a, IC, O, I>, StoreError> {
+        self.check_writable()?;
         let path = self.container_store_path::<IC, O, I>(id);
         let item_list = if !path.exists() {
             fs::create_dir_all(&path).map_err(|e| StoreError::Fs {
@@ -484,18 +1513,14 @@ pub trait StoreItem: HasId {
     fn in_store(id: Self::Id<'_>, store: &Store) -> info::ItemInfo;
     fn add_info(id: Self::Id<'_>, info: info::ItemInfo, store: &mut Store);
     fn link_info<P: AsRef<Path>>(id: Self::Id<'_>, store: &Store, dest: P) -> Option<LinkInfo>;
-    fn add_media(&self, store: &mut Store) -> Result<(), media::Error>;
-    fn save_data(
-        &self,
-        on_server: bool,
-        store: &mut Store,
-    ) -> Result<Option<PathBuf>, storable::Error>;
+    fn add_media(&self, store: &mut Store) -> Result<(), StoreError>;
+    fn save_data(&self, on_server: bool, store: &mut Store) -> Result<Option<PathBuf>, StoreError>;
     fn save_data_link<P: AsRef<Path>>(
         &self,
         on_server: bool,
         store: &mut Store,
         dest: P,
-    ) -> Result<Option<LinkInfo>, storable::Error>;
+    ) -> Result<Option<LinkInfo>, StoreError>;
 }
 
 impl<I: BasicStoreItem> StoreItem for I {
@@ -512,14 +1537,10 @@ impl<I: BasicStoreItem> StoreItem for I {
         <Self as BasicStoreItem>::add_info(id, info, &mut store.objects);
         store.dirty = true;
     }
-    fn add_media(&self, store: &mut Store) -> Result<(), media::Error> {
+    fn add_media(&self, store: &mut Store) -> Result<(), StoreError> {
         store.add_media(self)
     }
-    fn save_data(
-        &self,
-        on_server: bool,
-        store: &mut Store,
-    ) -> Result<Option<PathBuf>, storable::Error> {
+    fn save_data(&self, on_server: bool, store: &mut Store) -> Result<Option<PathBuf>, StoreError> {
         Ok(Some(store.add_object(on_server, self)?))
     }
     fn save_data_link<P: AsRef<Path>>(
@@ -527,7 +1548,7 @@ impl<I: BasicStoreItem> StoreItem for I {
         on_server: bool,
         store: &mut Store,
         dest: P,
-    ) -> Result<Option<LinkInfo>, storable::Error> {
+    ) -> Result<Option<LinkInfo>, StoreError> {
         let source = store.add_object(on_server, self)?;
         Ok(Some(LinkInfo {
             source,
@@ -542,6 +1563,8 @@ pub trait ItemList<I: HasId + 'static>:
     fn insert(&mut self, id: I::Id<'_>);
     fn remove(&mut self, id: I::Id<'_>);
     fn set_item_info(&self, info: info::ItemInfo, store: &mut Store);
+    /// ids of listed items as displayed, items the list can't identify are left out
+    fn ids(&self) -> HashSet<String>;
 }
 macro_rules! item_list_btree {
     ($t:ty, $i:ty) => {
@@ -561,6 +1584,9 @@ macro_rules! item_list_btree {
                     <$t as crate::store::StoreItem>::add_info(*i, info, store);
                 }
             }
+            fn ids(&self) -> std::collections::HashSet<String> {
+                self.iter().map(|i| i.to_string()).collect()
+            }
         }
     };
 }
@@ -575,6 +1601,8 @@ pub trait BasicStoreContainer<O, I: HasId + 'static>: HasId {
 pub trait ContainerHandle<I: HasId> {
     fn link_item(&mut self, id: I::Id<'_>) -> Result<(), StoreError>;
     fn mark_missing(&mut self);
+    /// write the items linked so far without recording the container as stored
+    fn checkpoint(&mut self) -> Result<(), StoreError>;
     fn finish(self) -> Result<Option<PathBuf>, StoreError>;
 }
 pub trait StoreContainer<O, I: HasId>: HasId + 'static {
@@ -586,6 +1614,11 @@ pub trait StoreContainer<O, I: HasId>: HasId + 'static {
         id: Self::Id<'a>,
         store: &'b mut Store,
     ) -> Result<Self::Handle<'a, 'b>, StoreError>;
+    /// ids of items listed when the container was last stored, see [`ItemList::ids`]
+    #[allow(unused_variables)]
+    fn stored_ids(id: Self::Id<'_>, store: &Store) -> Result<HashSet<String>, StoreError> {
+        Ok(HashSet::new())
+    }
 }
 
 const ITEM_LIST: &str = "item_list.yaml";
@@ -607,6 +1640,25 @@ impl<'a, 'b, IC: 'a + BasicStoreContainer<O, I>, O, I: HasId + 'static>
         Ok(self.root)
     }
 }
+impl<'a, 'b, IC: 'a + BasicStoreContainer<O, I>, O, I: StoreItem + 'static>
+    Container<'a, 'b, IC, O, I>
+{
+    /// remove item from the container and its link, the item list is written on finish
+    pub fn unlink_item(&mut self, id: I::Id<'_>) -> Result<(), StoreError> {
+        self.item_list.remove(id);
+        self.absent_list.remove(id);
+        if let Some(v) = I::link_info(id, self.store, &self.root) {
+            if v.link.symlink_metadata().is_ok() {
+                fs::remove_file(&v.link).map_err(|e| StoreError::Fs {
+                    op: FsErrorOp::RemoveFile,
+                    path: v.link,
+                    source: e,
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
 impl<'a, 'b, IC: 'a + BasicStoreContainer<O, I>, O, I: StoreItem + 'static> ContainerHandle<I>
     for Container<'a, 'b, IC, O, I>
 {
@@ -627,18 +1679,7 @@ impl<'a, 'b, IC: 'a + BasicStoreContainer<O, I>, O, I: StoreItem + 'static> Cont
                     })?;
                 }
             }
-            let mut source = PathBuf::new();
-            for _ in v
-                .link
-                .strip_prefix(&self.store.root)
-                .unwrap()
-                .parent()
-                .unwrap()
-                .components()
-            {
-                source.push("..");
-            }
-            source.extend(v.source.strip_prefix(&self.store.root).unwrap());
+            let source = store_relative(&self.store.root, &v.link, &v.source);
             crate::util::relative_path::symlink(&source, &v.link).map_err(|e| StoreError::Fs {
                 op: FsErrorOp::SymLinkTo(v.source),
                 path: v.link,
@@ -657,6 +1698,9 @@ impl<'a, 'b, IC: 'a + BasicStoreContainer<O, I>, O, I: StoreItem + 'static> Cont
             self.store,
         );
     }
+    fn checkpoint(&mut self) -> Result<(), StoreError> {
+        store_yaml(&self.item_list, &self.root, ITEM_LIST)
+    }
     fn finish(self) -> Result<Option<PathBuf>, StoreError> {
         self.finish_container().map(Some)
     }
@@ -680,4 +1724,449 @@ where
     ) -> Result<Self::Handle<'a, 'b>, StoreError> {
         store.add_container::<IC, O, I>(id)
     }
+    fn stored_ids(id: Self::Id<'_>, store: &Store) -> Result<HashSet<String>, StoreError> {
+        store.get_container::<O, I, IC>(id).map(|l| l.ids())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let ret = std::env::temp_dir().join(format!("zhihu-dl-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&ret);
+        fs::create_dir_all(&ret).unwrap();
+        ret
+    }
+
+    fn image(data: &[u8]) -> media::ImageRef {
+        let hash = media::HashAlgo::default().digest(data);
+        media::ImageRef {
+            url: format!("https://pic1.zhimg.com/{}.png", hash),
+            hash,
+            extension: "png".to_string(),
+            original_hash: None,
+            animated: false,
+            mime: None,
+            data: Some(data.into()),
+        }
+    }
+    /// answer `id` referencing `images`, stored with its images
+    fn add_answer(store: &mut Store, id: u64, images: &[&media::ImageRef]) -> item::Answer {
+        use crate::item::Item;
+        let data: serde_json::Value =
+            serde_json::from_str(include_str!("item/fixtures/restricted_answer.json")).unwrap();
+        let mut ret = item::Answer::from_reply(
+            Deserialize::deserialize(&data).unwrap(),
+            crate::raw_data::RawData {
+                info: crate::raw_data::RawDataInfo {
+                    fetch_time: chrono::Utc::now(),
+                    container: crate::raw_data::Container::None,
+                    etag: None,
+                    last_modified: None,
+                },
+                data,
+            },
+        );
+        ret.info.id = item::AnswerId(id);
+        ret.content.info.images = images.iter().map(|i| (*i).clone()).collect();
+        store.add_object(true, &ret).unwrap();
+        store.add_media(&ret).unwrap();
+        ret
+    }
+
+    #[test]
+    fn interrupted_save() {
+        let dir = temp_dir("interrupted-save");
+        let mut store = Store::create(&dir).unwrap();
+        store
+            .objects
+            .answer
+            .insert(item::AnswerId(1), Default::default());
+        store.save().unwrap();
+        // a write killed before rename leaves only the temporary file behind
+        fs::write(
+            store.root().join(format!(".{}.tmp", OBJECT_INFO)),
+            b"answer:\n  2: {con",
+        )
+        .unwrap();
+        let store = Store::open(&dir).unwrap();
+        assert_eq!(
+            store.objects.answer.keys().copied().collect::<Vec<_>>(),
+            vec![item::AnswerId(1)]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn baseline_images() {
+        let dir = temp_dir("baseline-images");
+        let mut store = Store::create(&dir).unwrap();
+        let img = image(b"image");
+        add_answer(&mut store, 1, &[&img]);
+        store.save().unwrap();
+        // stores of version 1.1 keep images only in item directories
+        store.image_backend().remove(&img.to_string()).unwrap();
+        fs::write(store.root().join(VERSION_FILE), "major: 1\nminor: 1\n").unwrap();
+
+        let mut store = Store::open(&dir).unwrap();
+        let mut answer = store
+            .get_object::<item::Answer>(item::AnswerId(1), Default::default())
+            .unwrap();
+        store.get_media(&mut answer).unwrap();
+        assert_eq!(
+            answer.content.info.images[0].data.as_deref(),
+            Some(&b"image"[..])
+        );
+        assert_eq!(store.check().len(), 1);
+
+        Store::migrate(&dir, None, &crate::progress::silent::Silent).unwrap();
+        let store = Store::open(&dir).unwrap();
+        assert_eq!(store.version, VERSION);
+        assert!(store.image_root().join(img.to_string()).exists());
+        assert!(store.check().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn repair_times() {
+        let dir = temp_dir("repair-times");
+        let mut store = Store::create(&dir).unwrap();
+        let mut answer = add_answer(&mut store, 1, &[]);
+        let expect = (answer.info.created_time, answer.info.updated_time);
+        // answers of version 1.3 were stored with times 8 hours early
+        answer.info.created_time = answer.info.created_time - chrono::Duration::hours(8);
+        answer.info.updated_time = answer.info.updated_time - chrono::Duration::hours(8);
+        store.add_object(true, &answer).unwrap();
+        store
+            .objects
+            .versions
+            .insert("answer".to_owned(), Version { major: 1, minor: 3 });
+        store.save().unwrap();
+
+        Store::migrate(&dir, None, &crate::progress::silent::Silent).unwrap();
+        let mut store = Store::open(&dir).unwrap();
+        let answer = store
+            .get_object::<item::Answer>(item::AnswerId(1), Default::default())
+            .unwrap();
+        assert_eq!((answer.info.created_time, answer.info.updated_time), expect);
+        assert_eq!(
+            store.objects.versions.get("answer"),
+            Some(&<item::Answer as BasicStoreItem>::VERSION)
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reshard() {
+        let dir = temp_dir("reshard");
+        let mut store = Store::create(&dir).unwrap();
+        let info = info::ItemInfo {
+            in_store: true,
+            on_server: true,
+        };
+        store
+            .objects
+            .answer
+            .entry(item::AnswerId(123456))
+            .or_default()
+            .container = info;
+        store
+            .objects
+            .question
+            .entry(item::QuestionId(7))
+            .or_default()
+            .container = info;
+        let answer = store.store_path::<item::Answer>(item::AnswerId(123456));
+        fs::create_dir_all(&answer).unwrap();
+        fs::write(answer.join("data"), b"answer").unwrap();
+        let link = item_path::<item::Answer, _>(
+            item::AnswerId(123456),
+            store
+                .item_path::<item::Question>(item::QuestionId(7))
+                .join("answer"),
+        );
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        crate::util::relative_path::symlink(
+            store_relative(&store.root, &link, answer.parent().unwrap()),
+            &link,
+        )
+        .unwrap();
+
+        for (shard_prefix, expect) in [(4, "answer/12/34/123456"), (0, "answer/123456")] {
+            let layout = Layout { shard_prefix };
+            store
+                .reshard(layout, &mut crate::progress::silent::Silent)
+                .unwrap();
+            let answer = store.store_path::<item::Answer>(item::AnswerId(123456));
+            assert_eq!(answer, store.root.join(expect).join("info"));
+            assert_eq!(fs::read(answer.join("data")).unwrap(), b"answer");
+            let link = item_path::<item::Answer, _>(
+                item::AnswerId(123456),
+                store
+                    .item_path::<item::Question>(item::QuestionId(7))
+                    .join("answer"),
+            );
+            assert_eq!(
+                link.canonicalize().unwrap(),
+                answer.parent().unwrap().canonicalize().unwrap()
+            );
+            assert!(!store.root.join(RESHARD_DIR).exists());
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn redirect() {
+        let dir = temp_dir("redirect");
+        let mut store = Store::create(&dir).unwrap();
+        let canonical = store.store_path::<item::Question>(item::QuestionId(21));
+        fs::create_dir_all(&canonical).unwrap();
+        fs::write(canonical.join("data"), b"question").unwrap();
+        store
+            .add_redirect::<item::Question>(item::QuestionId(20), item::QuestionId(21))
+            .unwrap();
+        assert_eq!(
+            store.redirect::<item::Question>(item::QuestionId(20)),
+            Some("21")
+        );
+        let alias = store.store_path::<item::Question>(item::QuestionId(20));
+        assert_eq!(fs::read(alias.join("data")).unwrap(), b"question");
+        // relinking an alias replaces the previous link
+        store
+            .add_redirect::<item::Question>(item::QuestionId(20), item::QuestionId(21))
+            .unwrap();
+        assert!(store
+            .redirect::<item::Question>(item::QuestionId(21))
+            .is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn strip_raw() {
+        let dir = temp_dir("strip-raw");
+        let mut store = Store::create(&dir).unwrap();
+        store
+            .objects
+            .answer
+            .entry(item::AnswerId(1))
+            .or_default()
+            .container = info::ItemInfo {
+            in_store: true,
+            on_server: true,
+        };
+        let raw = store
+            .store_path::<item::Answer>(item::AnswerId(1))
+            .join(RAW_DATA);
+        fs::create_dir_all(&raw).unwrap();
+        fs::write(raw.join("data.json"), b"{}").unwrap();
+        let report = store.strip_raw(true).unwrap();
+        assert_eq!((report.stripped, report.freed_bytes), (1, 2));
+        assert!(raw.exists());
+        store.strip_raw(false).unwrap();
+        assert!(!raw.exists());
+        assert_eq!(store.strip_raw(false).unwrap().stripped, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn gc_images() {
+        let dir = temp_dir("gc-images");
+        let mut store = Store::create(&dir).unwrap();
+        let (kept, orphan) = (image(b"kept"), image(b"orphan"));
+        add_answer(&mut store, 1, &[&kept]);
+        store
+            .image_backend()
+            .put(&orphan.hash, &orphan.extension, b"orphan")
+            .unwrap();
+        // partial download of an image fetch still in progress
+        let temp = store.image_root().join(".download-1-0");
+        fs::write(&temp, b"partial").unwrap();
+        let exists = |i: &media::ImageRef| store.image_root().join(i.to_string()).exists();
+
+        let report = store.gc_images(true).unwrap();
+        assert_eq!(
+            (report.scanned, report.deleted, report.freed_bytes),
+            (2, 1, 6)
+        );
+        assert!(exists(&kept) && exists(&orphan));
+        let report = store.gc_images(false).unwrap();
+        assert_eq!((report.deleted, report.freed_bytes), (1, 6));
+        assert!(exists(&kept) && !exists(&orphan));
+        assert!(temp.exists());
+        assert_eq!(store.gc_images(false).unwrap().deleted, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn check() {
+        let dir = temp_dir("check");
+        let mut store = Store::create(&dir).unwrap();
+        let missing = image(b"missing");
+        add_answer(&mut store, 1, &[&missing]);
+        add_answer(&mut store, 2, &[]);
+        assert!(store.check().is_empty());
+
+        store.image_backend().remove(&missing.to_string()).unwrap();
+        store
+            .objects
+            .answer
+            .entry(item::AnswerId(3))
+            .or_default()
+            .container = info::ItemInfo {
+            in_store: true,
+            on_server: true,
+        };
+        let link = item_path::<item::Answer, _>(
+            item::AnswerId(4),
+            store.container_store_path::<item::Question, item::VoidOpt, item::Answer>(
+                item::QuestionId(7),
+            ),
+        );
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        crate::util::relative_path::symlink(
+            store_relative(
+                &store.root,
+                &link,
+                &store.item_path::<item::Answer>(item::AnswerId(4)),
+            ),
+            &link,
+        )
+        .unwrap();
+
+        let ret = store.check();
+        assert_eq!(ret.len(), 3, "{:?}", ret);
+        assert!(ret.iter().any(|i| matches!(
+            i,
+            StoreInconsistency::Image { id, image, source: None, .. }
+                if id == "1" && image == &missing.to_string()
+        )));
+        assert!(ret.iter().any(|i| matches!(
+            i,
+            StoreInconsistency::Object { id, .. } if id == "3"
+        )));
+        assert!(ret.iter().any(|i| matches!(
+            i,
+            StoreInconsistency::DanglingLink { link: l, .. } if l == &link
+        )));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stats_without_images() {
+        let dir = temp_dir("stats-without-images");
+        let mut store = Store::create(&dir).unwrap();
+        add_answer(&mut store, 1, &[]);
+        store.save().unwrap();
+        fs::remove_dir_all(store.image_root()).unwrap();
+
+        let store = Store::open_read_only(&dir).unwrap();
+        let stats = store.stats().unwrap();
+        assert_eq!((stats.images, stats.image_bytes), (0, 0));
+        assert!(stats.objects.contains(&(item::Answer::TYPE, 1)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn delete_object() {
+        let dir = temp_dir("delete-object");
+        let mut store = Store::create(&dir).unwrap();
+        let (own, shared) = (image(b"own"), image(b"shared"));
+        add_answer(&mut store, 1, &[&own, &shared]);
+        add_answer(&mut store, 2, &[&shared]);
+        let exists = |s: &Store, i: &media::ImageRef| s.image_root().join(i.to_string()).exists();
+
+        assert!(!store
+            .delete_object::<item::Answer>(item::AnswerId(3), true)
+            .unwrap());
+        assert!(store
+            .delete_object::<item::Answer>(item::AnswerId(1), true)
+            .unwrap());
+        assert!(!store.objects.answer.contains_key(&item::AnswerId(1)));
+        assert!(!store.store_path::<item::Answer>(item::AnswerId(1)).exists());
+        assert!(!exists(&store, &own));
+        assert!(exists(&store, &shared));
+        assert!(!store
+            .delete_object::<item::Answer>(item::AnswerId(1), true)
+            .unwrap());
+        // without remove_images the last reference is dropped and the image kept
+        assert!(store
+            .delete_object::<item::Answer>(item::AnswerId(2), false)
+            .unwrap());
+        assert!(exists(&store, &shared));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn delete_listed() {
+        let dir = temp_dir("delete-listed");
+        let mut store = Store::create(&dir).unwrap();
+        add_answer(&mut store, 1, &[]);
+        let question = item::QuestionId(7);
+        let mut container = store
+            .add_container::<item::Question, item::VoidOpt, item::Answer>(question)
+            .unwrap();
+        container.link_item(item::AnswerId(1)).unwrap();
+        container.finish().unwrap();
+
+        let link = item_path::<item::Answer, _>(
+            item::AnswerId(1),
+            store.container_store_path::<item::Question, item::VoidOpt, item::Answer>(question),
+        );
+        match store.delete_object::<item::Answer>(item::AnswerId(1), true) {
+            Err(StoreError::Referenced { links, .. }) => assert_eq!(links, [link]),
+            r => panic!("expect referenced error, got {:?}", r),
+        }
+        assert!(store.objects.answer.contains_key(&item::AnswerId(1)));
+        assert!(store.store_path::<item::Answer>(item::AnswerId(1)).exists());
+
+        // deleted once no longer listed
+        let mut container = store
+            .add_container::<item::Question, item::VoidOpt, item::Answer>(question)
+            .unwrap();
+        container.unlink_item(item::AnswerId(1)).unwrap();
+        container.finish().unwrap();
+        assert!(store
+            .delete_object::<item::Answer>(item::AnswerId(1), true)
+            .unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unlink_item() {
+        let dir = temp_dir("unlink-item");
+        let mut store = Store::create(&dir).unwrap();
+        add_answer(&mut store, 1, &[]);
+        add_answer(&mut store, 2, &[]);
+        let question = item::QuestionId(7);
+        let mut container = store
+            .add_container::<item::Question, item::VoidOpt, item::Answer>(question)
+            .unwrap();
+        container.link_item(item::AnswerId(1)).unwrap();
+        container.link_item(item::AnswerId(2)).unwrap();
+        container.finish().unwrap();
+        let root =
+            store.container_store_path::<item::Question, item::VoidOpt, item::Answer>(question);
+        let link = item_path::<item::Answer, _>(item::AnswerId(1), &root);
+        assert!(link.symlink_metadata().is_ok());
+
+        let mut container = store
+            .add_container::<item::Question, item::VoidOpt, item::Answer>(question)
+            .unwrap();
+        container.unlink_item(item::AnswerId(1)).unwrap();
+        // unlinking an item not in the container is a no-op
+        container.unlink_item(item::AnswerId(3)).unwrap();
+        container.finish().unwrap();
+        assert!(link.symlink_metadata().is_err());
+        assert!(item_path::<item::Answer, _>(item::AnswerId(2), &root).exists());
+        assert_eq!(
+            store
+                .get_container::<item::VoidOpt, item::Answer, item::Question>(question)
+                .unwrap()
+                .ids(),
+            HashSet::from(["2".to_string()])
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
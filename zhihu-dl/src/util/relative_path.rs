@@ -55,6 +55,13 @@ pub enum LinkError {
         #[source]
         source: io::Error,
     },
+    #[error("failed to copy {} to {}", from.display(), to.display())]
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+        #[source]
+        source: io::Error,
+    },
 }
 
 pub(crate) fn relative_path_to<P1, P2>(source: P1, path: P2) -> Option<PathBuf>
@@ -137,3 +144,47 @@ pub(crate) fn link_to_dest(
         source: e,
     })
 }
+
+/// hard link `from` to `to`, copies the file if `copy` is set or hard linking failed
+pub(crate) fn link_or_copy(copy: bool, from: &Path, to: &Path) -> Result<(), LinkError> {
+    if to.exists() {
+        fs::remove_file(to).map_err(|e| LinkError::Copy {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+            source: e,
+        })?;
+    }
+    if !copy && fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map(|_| ()).map_err(|e| LinkError::Copy {
+        from: from.to_path_buf(),
+        to: to.to_path_buf(),
+        source: e,
+    })
+}
+
+/// recursively copy the store directory `store_path` to `dest`
+pub(crate) fn export_to_dest(copy: bool, store_path: &Path, dest: &Path) -> Result<(), LinkError> {
+    let read_err = |e| LinkError::Copy {
+        from: store_path.to_path_buf(),
+        to: dest.to_path_buf(),
+        source: e,
+    };
+    if !dest.exists() {
+        fs::create_dir_all(dest).map_err(|e| LinkError::CreateDir {
+            dir: dest.to_path_buf(),
+            source: e,
+        })?;
+    }
+    for ent in fs::read_dir(store_path).map_err(read_err)? {
+        let from = ent.map_err(read_err)?.path();
+        let to = dest.join(from.file_name().unwrap());
+        if from.is_dir() {
+            export_to_dest(copy, &from, &to)?;
+        } else {
+            link_or_copy(copy, &from, &to)?;
+        }
+    }
+    Ok(())
+}